@@ -99,6 +99,112 @@ pub enum Commands {
         #[clap(long, short)]
         path: Option<String>,
     },
+    /// Manage the on-disk cache of eth_getLogs responses.
+    ///
+    /// This command removes cached logs so they are fetched from the network again.
+    ///
+    /// Example:
+    /// `rindexer cache prune` or `rindexer cache prune --network ethereum`
+    #[clap(name = "cache")]
+    Cache {
+        #[clap(subcommand)]
+        subcommand: CacheSubcommands,
+
+        /// optional - The path to run the command in, default will be where the command is run.
+        #[clap(long, short)]
+        path: Option<String>,
+    },
+    /// Inspect and re-drive the batches whose callback kept failing.
+    ///
+    /// Batches are dead lettered once `indexing.max_callback_attempts` is used up.
+    ///
+    /// Example:
+    /// `rindexer dead-letters list` or `rindexer dead-letters redrive --id <ID>`
+    #[clap(name = "dead-letters")]
+    DeadLetters {
+        #[clap(subcommand)]
+        subcommand: DeadLettersSubcommands,
+
+        /// optional - The path to run the command in, default will be where the command is run.
+        #[clap(long, short)]
+        path: Option<String>,
+    },
+    /// Re-index a block range of a contract.
+    ///
+    /// This command deletes the rows already indexed in the range and indexes it again without
+    /// moving the last synced block, a running indexer keeps going.
+    ///
+    /// Example:
+    /// `rindexer backfill --contract RocketPoolETH --network ethereum --from 18600000 --to
+    /// 18700000` or with `--event Transfer` to only re-index one event
+    #[clap(name = "backfill")]
+    Backfill {
+        /// The contract to re-index.
+        #[arg(long)]
+        contract: String,
+
+        /// optional - The event to re-index, default will re-index every event of the contract.
+        #[arg(long)]
+        event: Option<String>,
+
+        /// The network to re-index.
+        #[arg(long)]
+        network: String,
+
+        /// The block to re-index from (inclusive).
+        #[arg(long)]
+        from: u64,
+
+        /// The block to re-index to (inclusive).
+        #[arg(long)]
+        to: u64,
+
+        /// optional - The path to run the command in, default will be where the command is run.
+        #[clap(long, short)]
+        path: Option<String>,
+    },
+    /// Report the indexed block coverage of every event.
+    ///
+    /// This command shows the indexed blocks of each event and network and any gaps in them.
+    ///
+    /// Example:
+    /// `rindexer verify`
+    #[clap(name = "verify")]
+    Verify {
+        /// optional - The path to run the command in, default will be where the command is run.
+        #[clap(long, short)]
+        path: Option<String>,
+    },
+    /// Preview what `rindexer start` would do to the database.
+    ///
+    /// This command prints the table changes, checkpoints, index and relationship changes and the
+    /// block each event would resume from without changing anything.
+    ///
+    /// Example:
+    /// `rindexer plan`
+    #[clap(name = "plan")]
+    Plan {
+        /// optional - The path to run the command in, default will be where the command is run.
+        #[clap(long, short)]
+        path: Option<String>,
+    },
+    /// Rebuild the event tables of a contract from the raw log archive.
+    ///
+    /// This command decodes the logs kept in `rindexer_internal.raw_logs` with the current ABI
+    /// without any network access, it needs `raw_logs: true` on the postgres storage.
+    ///
+    /// Example:
+    /// `rindexer redecode --contract RocketPoolETH`
+    #[clap(name = "redecode")]
+    Redecode {
+        /// The contract to redecode.
+        #[arg(long)]
+        contract: String,
+
+        /// optional - The path to run the command in, default will be where the command is run.
+        #[clap(long, short)]
+        path: Option<String>,
+    },
     /// Use phantom events to add your own events to contracts
     ///
     /// This command helps you use phantom events within rindexer.
@@ -216,6 +322,45 @@ pub enum CodegenSubcommands {
     },
 }
 
+#[derive(Subcommand, Debug)]
+pub enum CacheSubcommands {
+    /// Deletes the cached eth_getLogs responses for every network or a single network.
+    ///
+    /// Example:
+    /// `rindexer cache prune` or `rindexer cache prune --network ethereum`
+    Prune {
+        /// optional - The network to prune, default will prune every network.
+        #[arg(long)]
+        network: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DeadLettersSubcommands {
+    /// Lists the dead letters with their error and log coordinates.
+    ///
+    /// Example:
+    /// `rindexer dead-letters list` or `rindexer dead-letters list --contract RocketPoolETH`
+    List {
+        /// optional - Only list the dead letters of this contract.
+        #[arg(long)]
+        contract: Option<String>,
+
+        /// optional - Only list the dead letters of this event.
+        #[arg(long)]
+        event: Option<String>,
+    },
+    /// Marks dead letters to be re-driven through the callback on the next `rindexer start`.
+    ///
+    /// Example:
+    /// `rindexer dead-letters redrive` or `rindexer dead-letters redrive --id <ID>`
+    Redrive {
+        /// optional - The dead letter to re-drive, default will re-drive every dead letter.
+        #[arg(long)]
+        id: Option<String>,
+    },
+}
+
 #[derive(Args, Debug)]
 pub struct PhantomBaseArgs {
     /// The name of the contract
@@ -177,6 +177,8 @@ pub async fn handle_add_contract_command(
                 None,
             )],
             abi: StringOrArray::Single(abi_path_relative),
+            preset: None,
+            track_balances: None,
             include_events: None,
             index_event_in_order: None,
             dependency_events: None,
@@ -184,6 +186,8 @@ pub async fn handle_add_contract_command(
             generate_csv: None,
             streams: None,
             chat: None,
+            include_transaction_fields: None,
+            include_function_calls: None,
         });
 
         write_manifest(&manifest, &rindexer_yaml_path).map_err(|e| {
@@ -0,0 +1,63 @@
+use std::{path::PathBuf, process::Command};
+
+use rindexer::{
+    indexer::backfill::BackfillDetails,
+    manifest::{
+        core::ProjectType,
+        yaml::{read_manifest, YAML_CONFIG_NAME},
+    },
+    start_backfill_no_code,
+};
+
+use crate::{
+    console::{print_error_message, print_success_message},
+    rindexer_yaml::validate_rindexer_yaml_exist,
+};
+
+pub async fn handle_backfill_command(
+    project_path: PathBuf,
+    details: BackfillDetails,
+) -> Result<(), Box<dyn std::error::Error>> {
+    validate_rindexer_yaml_exist(&project_path);
+
+    details.validate().inspect_err(|e| print_error_message(&e.to_string()))?;
+
+    let manifest_path = project_path.join(YAML_CONFIG_NAME);
+    let manifest = read_manifest(&manifest_path).map_err(|e| {
+        print_error_message(&format!("Could not read the rindexer.yaml file: {}", e));
+        e
+    })?;
+
+    match manifest.project_type {
+        ProjectType::Rust => {
+            // the handlers only exist in the rust project so it runs the backfill itself
+            let status = Command::new("cargo")
+                .arg("run")
+                .arg("--manifest-path")
+                .arg(project_path.join("Cargo.toml"))
+                .arg("--")
+                .args(details.to_args())
+                .status()
+                .map_err(|e| {
+                    print_error_message(&format!("Failed to execute cargo run: {}", e));
+                    e
+                })?;
+
+            if !status.success() {
+                let error = format!("cargo run failed with status: {:?}", status);
+                print_error_message(&error);
+                return Err(error.into());
+            }
+        }
+        ProjectType::NoCode => {
+            start_backfill_no_code(&manifest_path, details).await.map_err(|e| {
+                print_error_message(&format!("Error running the backfill: {}", e));
+                e
+            })?;
+        }
+    }
+
+    print_success_message("Backfill complete");
+
+    Ok(())
+}
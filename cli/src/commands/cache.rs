@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+
+use ethers::types::U256;
+use rindexer::{
+    log_cache::prune_log_cache,
+    manifest::yaml::{read_manifest, YAML_CONFIG_NAME},
+};
+
+use crate::console::{print_error_message, print_success_message};
+
+pub async fn handle_cache_prune_command(
+    project_path: PathBuf,
+    network: &Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let manifest = read_manifest(&project_path.join(YAML_CONFIG_NAME)).map_err(|e| {
+        print_error_message(&format!("Could read the rindexer.yaml please make sure you are running the command with rindexer.yaml in root: trace: {}", e));
+        e
+    })?;
+
+    let log_cache = match &manifest.storage.log_cache {
+        Some(log_cache) => log_cache,
+        None => {
+            print_success_message("No log cache configured. Nothing to prune.");
+            return Ok(());
+        }
+    };
+
+    // the cache is keyed by chain id so a renamed network still maps to its logs
+    let chain_id = match network {
+        Some(network) => match manifest.networks.iter().find(|n| &n.name == network) {
+            Some(network) => Some(U256::from(network.chain_id)),
+            None => {
+                let message = format!("Network {} not found in the rindexer.yaml", network);
+                print_error_message(&message);
+                return Err(message.into());
+            }
+        },
+        None => None,
+    };
+
+    prune_log_cache(&project_path.join(&log_cache.path), chain_id).await.map_err(|e| {
+        print_error_message(&format!("Could not prune the log cache: trace: {}", e));
+        e
+    })?;
+
+    match network {
+        Some(network) => {
+            print_success_message(&format!("Successfully pruned the log cache for {}.", network))
+        }
+        None => print_success_message("Successfully pruned the log cache."),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rindexer::{
+    indexer::dead_letters::DeadLetterStore,
+    manifest::{
+        core::Manifest,
+        yaml::{read_manifest, YAML_CONFIG_NAME},
+    },
+    PostgresClient,
+};
+
+use crate::console::{print_error_message, print_success_message};
+
+async fn dead_letter_store(
+    project_path: &Path,
+) -> Result<DeadLetterStore, Box<dyn std::error::Error>> {
+    let manifest: Manifest = read_manifest(&project_path.join(YAML_CONFIG_NAME)).map_err(|e| {
+        print_error_message(&format!("Could read the rindexer.yaml please make sure you are running the command with rindexer.yaml in root: trace: {}", e));
+        e
+    })?;
+
+    let database = if manifest.storage.postgres_enabled() {
+        let postgres_client = PostgresClient::new().await.map_err(|e| {
+            print_error_message(&format!("Could not connect to Postgres, make sure your connection string is mapping in the .env correctly: trace: {}", e));
+            e
+        })?;
+        Some(Arc::new(postgres_client))
+    } else {
+        None
+    };
+
+    Ok(DeadLetterStore::new(project_path, &manifest.name, &database))
+}
+
+pub async fn handle_dead_letters_list_command(
+    project_path: PathBuf,
+    contract: &Option<String>,
+    event: &Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let store = dead_letter_store(&project_path).await?;
+    let dead_letters = store.list().await.map_err(|e| {
+        print_error_message(&format!("Could not read the dead letters: trace: {}", e));
+        e
+    })?;
+
+    let dead_letters: Vec<_> = dead_letters
+        .into_iter()
+        .filter(|d| contract.as_ref().is_none_or(|contract| &d.contract_name == contract))
+        .filter(|d| event.as_ref().is_none_or(|event| &d.event_name == event))
+        .collect();
+
+    if dead_letters.is_empty() {
+        print_success_message("No dead letters found.");
+        return Ok(());
+    }
+
+    for dead_letter in &dead_letters {
+        println!(
+            "{}\n  blocks: {} - {}\n  attempts: {}\n  dead lettered at: {}\n  marked for redrive: {}\n  error: {}",
+            dead_letter.id,
+            dead_letter.from_block,
+            dead_letter.to_block,
+            dead_letter.attempts,
+            dead_letter.created_at.to_rfc3339(),
+            dead_letter.redrive,
+            dead_letter.error
+        );
+        for coordinates in dead_letter.log_coordinates() {
+            println!("  - {}", coordinates);
+        }
+    }
+
+    print_success_message(&format!("{} dead letters found.", dead_letters.len()));
+
+    Ok(())
+}
+
+pub async fn handle_dead_letters_redrive_command(
+    project_path: PathBuf,
+    id: &Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let store = dead_letter_store(&project_path).await?;
+    let marked = store.mark_for_redrive(id.as_deref()).await.map_err(|e| {
+        print_error_message(&format!("Could not mark the dead letters for redrive: trace: {}", e));
+        e
+    })?;
+
+    if marked == 0 {
+        match id {
+            Some(id) => print_error_message(&format!("Dead letter {} not found.", id)),
+            None => print_success_message("No dead letters found. Nothing to redrive."),
+        }
+        return Ok(());
+    }
+
+    print_success_message(&format!(
+        "{} dead letters marked for redrive, they will go through the callback again the next time you run `rindexer start`.",
+        marked
+    ));
+
+    Ok(())
+}
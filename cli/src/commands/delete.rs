@@ -1,9 +1,9 @@
 use std::path::PathBuf;
 
 use rindexer::{
-    drop_tables_for_indexer_sql,
+    drop_sqlite_tables_for_indexer_sql, drop_tables_for_indexer_sql,
     manifest::yaml::{read_manifest, YAML_CONFIG_NAME},
-    PostgresClient,
+    PostgresClient, SqliteClient,
 };
 use tokio::fs::remove_dir_all;
 
@@ -15,7 +15,7 @@ pub async fn handle_delete_command(
     project_path: PathBuf,
 ) -> Result<(), Box<dyn std::error::Error>> {
     print_warn_message(&format!(
-        "This will delete all data in the postgres database and csv files for the project at: {}",
+        "This will delete all data in the postgres database, sqlite database and csv files for the project at: {}",
         project_path.display()
     ));
     print_warn_message(
@@ -28,8 +28,9 @@ pub async fn handle_delete_command(
 
     let postgres_enabled = manifest.storage.postgres_enabled();
     let csv_enabled = manifest.storage.csv_enabled();
+    let sqlite_path = manifest.storage.sqlite_path(&project_path);
 
-    if !postgres_enabled && !csv_enabled {
+    if !postgres_enabled && !csv_enabled && sqlite_path.is_none() {
         print_success_message("No storage enabled. Nothing to delete.");
         return Ok(());
     }
@@ -59,6 +60,41 @@ pub async fn handle_delete_command(
         }
     }
 
+    if let Some(sqlite_path) = sqlite_path {
+        let sqlite_delete = prompt_for_input_list(
+            "Are you sure you wish to delete the sqlite data (it can not be reverted)?",
+            &["yes".to_string(), "no".to_string()],
+            None,
+        );
+
+        if sqlite_delete == "yes" {
+            // if the database does not exist we will just look like it cleared it
+            if sqlite_path.exists() {
+                let sqlite_client = SqliteClient::new(&sqlite_path).map_err(|e| {
+                    print_error_message(&format!(
+                        "Could not open the sqlite database at {}: trace: {}",
+                        sqlite_path.display(),
+                        e
+                    ));
+                    e
+                })?;
+                let sql = drop_sqlite_tables_for_indexer_sql(&project_path, &manifest.to_indexer());
+
+                sqlite_client.batch_execute(sql.as_str()).await.map_err(|e| {
+                    print_error_message(&format!(
+                        "Could not delete tables from sqlite: trace: {}",
+                        e
+                    ));
+                    e
+                })?;
+            }
+
+            print_success_message(
+                "\n\nSuccessfully deleted all data from the sqlite database.\n\n",
+            );
+        }
+    }
+
     if csv_enabled {
         let csv_delete = prompt_for_input_list(
             "Are you sure you wish to delete the csv data (it can not be reverted)?",
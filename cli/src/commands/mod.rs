@@ -1,8 +1,14 @@
 pub mod add;
+pub mod backfill;
+pub mod cache;
 pub mod codegen;
+pub mod dead_letters;
 pub mod delete;
 pub mod new;
 pub mod phantom;
+pub mod plan;
+pub mod redecode;
 pub mod start;
+pub mod verify;
 
 const BACKUP_ETHERSCAN_API_KEY: &str = "DHBPB1EJ84JMSWP7C86387NK7IIRRQJVV1";
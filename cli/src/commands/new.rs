@@ -12,7 +12,7 @@ use rindexer::{
     manifest::{
         contract::{Contract, ContractDetails},
         core::{Manifest, ProjectType},
-        network::Network,
+        network::{Network, NetworkRpc},
         storage::{CsvDetails, PostgresDetails, Storage},
         yaml::{write_manifest, YAML_CONFIG_NAME},
     },
@@ -137,10 +137,13 @@ pub fn handle_new_command(
         networks: vec![Network {
             name: "ethereum".to_string(),
             chain_id: 1,
-            rpc: "https://mainnet.gateway.tenderly.co".to_string(),
+            rpc: NetworkRpc::Single("https://mainnet.gateway.tenderly.co".to_string()),
+            ws_rpc: None,
             compute_units_per_second: None,
             max_block_range: None,
             disable_logs_bloom_checks: None,
+            reorg_safe_distance: None,
+            trace_method: None,
         }],
         contracts: vec![Contract {
             name: "RocketPoolETH".to_string(),
@@ -156,6 +159,8 @@ pub fn handle_new_command(
                 Some(U64::from(19000000)),
             )],
             abi: StringOrArray::Single(abi_example_path.display().to_string()),
+            preset: None,
+            track_balances: None,
             include_events: Some(vec!["Transfer".to_string(), "Approval".to_string()]),
             index_event_in_order: None,
             dependency_events: None,
@@ -163,7 +168,12 @@ pub fn handle_new_command(
             generate_csv: None,
             streams: None,
             chat: None,
+            include_transaction_fields: None,
+            include_function_calls: None,
         }],
+        block_handlers: None,
+        native_transfers: None,
+        entities: None,
         phantom: None,
         global: None,
         storage: Storage {
@@ -174,6 +184,8 @@ pub fn handle_new_command(
                     relationships: None,
                     indexes: None,
                     disable_create_tables: None,
+                    allow_destructive_migrations: None,
+                    raw_logs: None,
                 })
             } else {
                 None
@@ -187,8 +199,11 @@ pub fn handle_new_command(
             } else {
                 None
             },
+            sqlite: None,
+            log_cache: None,
         },
         graphql: None,
+        indexing: None,
     };
 
     // Write the rindexer.yaml file
@@ -11,7 +11,7 @@ use std::{
 use ethers::types::{Address, ValueOrArray, U64};
 use rindexer::{
     manifest::{
-        network::Network,
+        network::{Network, NetworkRpc},
         phantom::{Phantom, PhantomDyrpc, PhantomShadow},
         yaml::{read_manifest, read_manifest_raw, write_manifest, YAML_CONFIG_NAME},
     },
@@ -535,12 +535,13 @@ async fn handle_phantom_deploy(
 
                 if let Some(index) = network_index {
                     let net = &mut manifest.networks[index];
-                    net.rpc = rpc_url.to_string();
+                    net.rpc = NetworkRpc::Single(rpc_url.to_string());
                 } else {
                     manifest.networks.push(Network {
                         name: name.to_string(),
                         chain_id: network.unwrap().chain_id,
-                        rpc: rpc_url.to_string(),
+                        rpc: NetworkRpc::Single(rpc_url.to_string()),
+                        ws_rpc: None,
                         compute_units_per_second: None,
                         max_block_range: if phantom.dyrpc_enabled() {
                             Some(U64::from(20_000))
@@ -548,6 +549,8 @@ async fn handle_phantom_deploy(
                             Some(U64::from(2_000))
                         },
                         disable_logs_bloom_checks: None,
+                        reorg_safe_distance: None,
+                        trace_method: None,
                     });
                 }
 
@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+
+use rindexer::{
+    indexer::plan::{plan, PlannedStatement},
+    manifest::{
+        core::Manifest,
+        yaml::{read_manifest, YAML_CONFIG_NAME},
+    },
+};
+
+use crate::{
+    console::{print_error_message, print_success_message, print_warn_message},
+    rindexer_yaml::validate_rindexer_yaml_exist,
+};
+
+fn print_statements(title: &str, statements: &[PlannedStatement]) {
+    println!("{}", title);
+    if statements.is_empty() {
+        println!("  no changes\n");
+        return;
+    }
+
+    for statement in statements {
+        if statement.destructive {
+            print_warn_message(&format!("  - {} (destructive)", statement.description));
+        } else {
+            println!("  - {}", statement.description);
+        }
+        for line in statement.sql.lines() {
+            println!("      {}", line);
+        }
+    }
+    println!();
+}
+
+pub async fn handle_plan_command(project_path: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    validate_rindexer_yaml_exist(&project_path);
+
+    let manifest: Manifest = read_manifest(&project_path.join(YAML_CONFIG_NAME)).map_err(|e| {
+        print_error_message(&format!("Could read the rindexer.yaml please make sure you are running the command with rindexer.yaml in root: trace: {}", e));
+        e
+    })?;
+
+    let plan = plan(&project_path, &manifest).await.map_err(|e| {
+        print_error_message(&format!("Could not plan the changes: trace: {}", e));
+        e
+    })?;
+
+    if manifest.storage.postgres_enabled() {
+        print_statements("Tables", &plan.tables);
+        print_statements("Checkpoints", &plan.checkpoints);
+        print_statements("Dropped for the historic sync", &plan.dropped_for_historic_sync);
+        print_statements("Applied after the historic sync", &plan.applied_after_historic_sync);
+    }
+
+    println!("Resume blocks");
+    for resume_block in &plan.resume_blocks {
+        let resume_from = match (resume_block.resume_from, resume_block.last_synced_block) {
+            (Some(resume_from), Some(last_synced_block)) => {
+                format!("block {} (last synced {})", resume_from, last_synced_block)
+            }
+            (Some(resume_from), None) => format!("block {} (start block)", resume_from),
+            (None, _) => "the latest block".to_string(),
+        };
+        println!(
+            "  - {}::{} - {} resumes from {}",
+            resume_block.contract_name, resume_block.event_name, resume_block.network, resume_from
+        );
+    }
+    println!();
+
+    if plan.tables.iter().any(|statement| statement.destructive) &&
+        !manifest.storage.postgres_allow_destructive_migrations()
+    {
+        let error = "The plan has destructive table changes, `rindexer start` will refuse them unless `allow_destructive_migrations` is enabled.";
+        print_error_message(error);
+        return Err(error.into());
+    }
+
+    print_success_message("Nothing was changed, run `rindexer start` to apply the plan.");
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+
+use rindexer::{
+    indexer::raw_logs::redecode,
+    manifest::{
+        core::Manifest,
+        yaml::{read_manifest, YAML_CONFIG_NAME},
+    },
+    setup_info_logger,
+};
+
+use crate::{
+    console::{print_error_message, print_success_message, print_warn_message},
+    rindexer_yaml::validate_rindexer_yaml_exist,
+};
+
+pub async fn handle_redecode_command(
+    project_path: PathBuf,
+    contract_name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    validate_rindexer_yaml_exist(&project_path);
+
+    let manifest: Manifest = read_manifest(&project_path.join(YAML_CONFIG_NAME)).map_err(|e| {
+        print_error_message(&format!("Could read the rindexer.yaml please make sure you are running the command with rindexer.yaml in root: trace: {}", e));
+        e
+    })?;
+
+    setup_info_logger();
+
+    let redecoded = redecode(&project_path, &manifest, contract_name).await.map_err(|e| {
+        print_error_message(&format!("Could not redecode {}: trace: {}", contract_name, e));
+        e
+    })?;
+
+    for event in &redecoded {
+        println!(
+            "{}::{} - {}\n  {} rows from block {}",
+            contract_name, event.event_name, event.network, event.decoded, event.from_block
+        );
+        if event.undecodable > 0 {
+            print_warn_message(&format!(
+                "  {} archived logs did not match the event inputs in the ABI",
+                event.undecodable
+            ));
+        }
+    }
+
+    if redecoded.is_empty() {
+        print_warn_message(&format!("Nothing archived for {} to redecode.", contract_name));
+        return Ok(());
+    }
+
+    print_success_message(&format!("Redecoded {} from the raw log archive.", contract_name));
+
+    Ok(())
+}
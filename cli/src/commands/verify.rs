@@ -0,0 +1,95 @@
+use std::{path::PathBuf, sync::Arc};
+
+use rindexer::{
+    indexer::processed_ranges::ProcessedRangeStore,
+    manifest::{
+        core::Manifest,
+        yaml::{read_manifest, YAML_CONFIG_NAME},
+    },
+    ABIItem, PostgresClient,
+};
+
+use crate::{
+    console::{print_error_message, print_success_message, print_warn_message},
+    rindexer_yaml::validate_rindexer_yaml_exist,
+};
+
+pub async fn handle_verify_command(
+    project_path: PathBuf,
+) -> Result<(), Box<dyn std::error::Error>> {
+    validate_rindexer_yaml_exist(&project_path);
+
+    let manifest: Manifest = read_manifest(&project_path.join(YAML_CONFIG_NAME)).map_err(|e| {
+        print_error_message(&format!("Could read the rindexer.yaml please make sure you are running the command with rindexer.yaml in root: trace: {}", e));
+        e
+    })?;
+
+    let database = if manifest.storage.postgres_enabled() {
+        let postgres_client = PostgresClient::new().await.map_err(|e| {
+            print_error_message(&format!("Could not connect to Postgres, make sure your connection string is mapping in the .env correctly: trace: {}", e));
+            e
+        })?;
+        Some(Arc::new(postgres_client))
+    } else {
+        None
+    };
+
+    let store = ProcessedRangeStore::new(&project_path, &manifest.name, &database);
+    let mut total_gaps = 0;
+
+    for contract in &manifest.contracts {
+        let abi_items = ABIItem::read_abi_items(&project_path, contract)?;
+        let mut events = ABIItem::extract_event_names_and_signatures_from_abi(abi_items.clone())?;
+        if let Some(function_names) = &contract.include_function_calls {
+            events.extend(ABIItem::extract_function_calls_as_events_from_abi(
+                &abi_items,
+                function_names,
+            )?);
+        }
+
+        for event in &events {
+            for details in &contract.details {
+                let coverage = store
+                    .coverage(&contract.name, &event.name, &details.network, details.start_block)
+                    .await
+                    .map_err(|e| {
+                        print_error_message(&format!(
+                            "Could not read the processed ranges: trace: {}",
+                            e
+                        ));
+                        e
+                    })?;
+
+                let name = format!("{}::{} - {}", contract.name, event.name, details.network);
+                let Some(indexed_to) = coverage.indexed_to() else {
+                    println!("{}\n  not indexed yet", name);
+                    continue;
+                };
+
+                let from_block = coverage
+                    .start_block
+                    .or(coverage.ranges.first().map(|(from, _)| *from))
+                    .unwrap_or_default();
+                println!("{}\n  indexed blocks {} - {}", name, from_block, indexed_to);
+
+                for (from, to) in &coverage.gaps {
+                    print_warn_message(&format!("  gap: blocks {} - {}", from, to));
+                }
+                total_gaps += coverage.gaps.len();
+            }
+        }
+    }
+
+    if total_gaps > 0 {
+        let error = format!(
+            "{} gaps found, they are re-indexed the next time you run `rindexer start` or you can run `rindexer backfill` for the range.",
+            total_gaps
+        );
+        print_error_message(&error);
+        return Err(error.into());
+    }
+
+    print_success_message("No gaps found in the indexed blocks.");
+
+    Ok(())
+}
@@ -15,14 +15,27 @@ mod rindexer_yaml;
 use std::{path::PathBuf, str::FromStr, sync::Once};
 
 use clap::Parser;
-use rindexer::{load_env_from_project_path, manifest::core::ProjectType};
+use rindexer::{
+    indexer::backfill::BackfillDetails, load_env_from_project_path, manifest::core::ProjectType,
+};
 
 use crate::{
-    cli_interface::{AddSubcommands, Commands, NewSubcommands, CLI},
+    cli_interface::{
+        AddSubcommands, CacheSubcommands, Commands, DeadLettersSubcommands, NewSubcommands, CLI,
+    },
     commands::{
-        add::handle_add_contract_command, codegen::handle_codegen_command,
-        delete::handle_delete_command, new::handle_new_command, phantom::handle_phantom_commands,
+        add::handle_add_contract_command,
+        backfill::handle_backfill_command,
+        cache::handle_cache_prune_command,
+        codegen::handle_codegen_command,
+        dead_letters::{handle_dead_letters_list_command, handle_dead_letters_redrive_command},
+        delete::handle_delete_command,
+        new::handle_new_command,
+        phantom::handle_phantom_commands,
+        plan::handle_plan_command,
+        redecode::handle_redecode_command,
         start::start,
+        verify::handle_verify_command,
     },
     console::print_error_message,
 };
@@ -111,6 +124,59 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             load_env_from_project_path(&resolved_path);
             handle_delete_command(resolved_path).await
         }
+        Commands::Cache { subcommand, path } => {
+            let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
+            load_env_from_project_path(&resolved_path);
+
+            match subcommand {
+                CacheSubcommands::Prune { network } => {
+                    handle_cache_prune_command(resolved_path, network).await
+                }
+            }
+        }
+        Commands::DeadLetters { subcommand, path } => {
+            let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
+            load_env_from_project_path(&resolved_path);
+
+            match subcommand {
+                DeadLettersSubcommands::List { contract, event } => {
+                    handle_dead_letters_list_command(resolved_path, contract, event).await
+                }
+                DeadLettersSubcommands::Redrive { id } => {
+                    handle_dead_letters_redrive_command(resolved_path, id).await
+                }
+            }
+        }
+        Commands::Backfill { contract, event, network, from, to, path } => {
+            let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
+            load_env_from_project_path(&resolved_path);
+            handle_backfill_command(
+                resolved_path,
+                BackfillDetails {
+                    contract_name: contract.clone(),
+                    event_name: event.clone(),
+                    network: network.clone(),
+                    from_block: (*from).into(),
+                    to_block: (*to).into(),
+                },
+            )
+            .await
+        }
+        Commands::Verify { path } => {
+            let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
+            load_env_from_project_path(&resolved_path);
+            handle_verify_command(resolved_path).await
+        }
+        Commands::Plan { path } => {
+            let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
+            load_env_from_project_path(&resolved_path);
+            handle_plan_command(resolved_path).await
+        }
+        Commands::Redecode { contract, path } => {
+            let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
+            load_env_from_project_path(&resolved_path);
+            handle_redecode_command(resolved_path, contract).await
+        }
         Commands::Phantom { subcommand, path } => {
             let resolved_path = resolve_path(path).inspect_err(|e| print_error_message(e))?;
             load_env_from_project_path(&resolved_path);
@@ -8,11 +8,11 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     database::postgres::{
-        generate::solidity_type_to_db_type,
+        generate::{generate_transaction_fields_column_names, solidity_type_to_db_type},
         sql_type_wrapper::{solidity_type_to_ethereum_sql_type_wrapper, EthereumSqlTypeWrapper},
     },
     event::contract_setup::IndexingContractSetup,
-    helpers::camel_to_snake,
+    helpers::{camel_to_snake, to_pascal_case},
     manifest::contract::{Contract, ParseAbiError},
 };
 
@@ -30,6 +30,27 @@ pub struct ABIInput {
     pub components: Option<Vec<ABIInput>>,
 }
 
+/// The input function calls are indexed with holding the address which made the call.
+pub const FUNCTION_CALL_FROM_INPUT: &str = "call_from";
+
+/// The input function calls are indexed with holding the value sent with the call.
+pub const FUNCTION_CALL_VALUE_INPUT: &str = "call_value";
+
+/// The name of the event function calls are indexed as, the suffix keeps them apart from an
+/// event with the same name.
+pub fn function_call_event_name(function_name: &str) -> String {
+    format!("{}Call", to_pascal_case(function_name))
+}
+
+/// Function inputs are often unnamed, they still need a name for their column.
+pub fn function_call_input_name(name: &str, index: usize) -> String {
+    if name.is_empty() {
+        format!("param_{}", index)
+    } else {
+        name.to_string()
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ParamTypeError {
     #[error("tuple type specified but no components found")]
@@ -225,6 +246,52 @@ impl ABIItem {
         Ok(events)
     }
 
+    /// Function calls are indexed as an event with the caller and the value sent as indexed
+    /// inputs followed by the function inputs, so the call data decodes exactly like log data.
+    pub fn function_call_as_event(&self) -> ABIItem {
+        let mut inputs = vec![
+            ABIInput {
+                indexed: Some(true),
+                name: FUNCTION_CALL_FROM_INPUT.to_string(),
+                type_: "address".to_string(),
+                components: None,
+            },
+            ABIInput {
+                indexed: Some(true),
+                name: FUNCTION_CALL_VALUE_INPUT.to_string(),
+                type_: "uint256".to_string(),
+                components: None,
+            },
+        ];
+        inputs.extend(self.inputs.iter().enumerate().map(|(index, input)| ABIInput {
+            indexed: Some(false),
+            name: function_call_input_name(&input.name, index),
+            ..input.clone()
+        }));
+
+        ABIItem { inputs, name: function_call_event_name(&self.name), type_: "event".to_string() }
+    }
+
+    /// The events the included function calls are indexed as, only the first overload of a
+    /// function name is indexed.
+    pub fn extract_function_calls_as_events_from_abi(
+        abi_items: &[ABIItem],
+        function_names: &[String],
+    ) -> Result<Vec<EventInfo>, ParamTypeError> {
+        let mut events = Vec::new();
+        for function_name in function_names {
+            if let Some(item) = abi_items
+                .iter()
+                .find(|item| item.type_ == "function" && item.name == *function_name)
+            {
+                let event = item.function_call_as_event();
+                let signature = event.format_event_signature()?;
+                events.push(EventInfo::new(event, signature));
+            }
+        }
+        Ok(events)
+    }
+
     pub fn read_abi_items(
         project_path: &Path,
         contract: &Contract,
@@ -317,7 +384,7 @@ impl EventInfo {
         &self.struct_data
     }
 
-    pub fn csv_headers_for_event(&self) -> Vec<String> {
+    pub fn csv_headers_for_event(&self, include_transaction_fields: bool) -> Vec<String> {
         let mut headers: Vec<String> = ABIInput::generate_abi_name_properties(
             &self.inputs,
             &GenerateAbiPropertiesType::CsvHeaderNames,
@@ -334,6 +401,11 @@ impl EventInfo {
         headers.push(r#"network"#.to_string());
         headers.push(r#"tx_index"#.to_string());
         headers.push(r#"log_index"#.to_string());
+        headers.push(r#"block_timestamp"#.to_string());
+
+        if include_transaction_fields {
+            headers.extend(generate_transaction_fields_column_names());
+        }
 
         headers
     }
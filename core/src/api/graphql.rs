@@ -16,7 +16,8 @@ use tracing::{error, info};
 
 use crate::{
     database::postgres::{
-        client::connection_string, generate::generate_indexer_contract_schema_name,
+        client::connection_string, entity::generate_entities_schema_name,
+        generate::generate_indexer_contract_schema_name,
     },
     helpers::{kill_process_on_port, set_thread_no_logging},
     indexer::Indexer,
@@ -103,7 +104,7 @@ pub async fn start_graphql_server(
 ) -> Result<GraphQLServer, StartGraphqlServerError> {
     info!("Starting GraphQL server");
 
-    let schemas: Vec<String> = indexer
+    let mut schemas: Vec<String> = indexer
         .contracts
         .iter()
         .map(move |contract| {
@@ -113,6 +114,9 @@ pub async fn start_graphql_server(
             )
         })
         .collect();
+    if !indexer.entities.is_empty() {
+        schemas.push(generate_entities_schema_name(&indexer.name));
+    }
 
     let connection_string = connection_string()?;
     let port = settings.port;
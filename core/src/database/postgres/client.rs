@@ -1,17 +1,21 @@
-use std::{env, future::Future, time::Duration};
+use std::{env, future::Future, ops::Deref, sync::Arc, time::Duration};
 
-use bb8::{Pool, RunError};
+use bb8::{Pool, PooledConnection, RunError};
 use bb8_postgres::PostgresConnectionManager;
 use bytes::Buf;
 use dotenv::dotenv;
-use futures::pin_mut;
+use futures::{future::BoxFuture, pin_mut, FutureExt};
 use native_tls::TlsConnector;
 use postgres_native_tls::MakeTlsConnector;
-use tokio::{task, time::timeout};
+use tokio::{
+    sync::{Mutex, OwnedMutexGuard},
+    task,
+    time::timeout,
+};
 pub use tokio_postgres::types::{ToSql, Type as PgType};
 use tokio_postgres::{
-    binary_copy::BinaryCopyInWriter, config::SslMode, Config, CopyInSink, Error as PgError, Row,
-    Statement, ToStatement, Transaction as PgTransaction,
+    binary_copy::BinaryCopyInWriter, config::SslMode, Client, Config, CopyInSink, Error as PgError,
+    Row, Statement, ToStatement,
 };
 use tracing::{debug, error};
 
@@ -53,26 +57,139 @@ pub enum PostgresError {
 
     #[error("Connection pool error: {0}")]
     ConnectionPoolError(#[from] RunError<tokio_postgres::Error>),
+
+    #[error("The transaction has already been committed or rolled back")]
+    TransactionFinished,
+
+    #[error("The transaction committed but a task run after the commit failed: {0}")]
+    AfterCommitFailed(String),
 }
 
-pub struct PostgresTransaction<'a> {
-    pub transaction: PgTransaction<'a>,
+type PostgresConnectionManagerTls = PostgresConnectionManager<MakeTlsConnector>;
+type PostgresPooledConnection = PooledConnection<'static, PostgresConnectionManagerTls>;
+
+tokio::task_local! {
+    static CURRENT_TRANSACTION: PostgresTransaction;
 }
-impl<'a> PostgresTransaction<'a> {
+
+type AfterCommitTask = BoxFuture<'static, Result<(), String>>;
+
+/// A transaction on its own connection which can be shared, it is used by every
+/// `PostgresClient` call made inside `PostgresClient::in_transaction`.
+#[derive(Clone)]
+pub struct PostgresTransaction {
+    connection: Arc<Mutex<Option<PostgresPooledConnection>>>,
+    after_commit: Arc<std::sync::Mutex<Vec<AfterCommitTask>>>,
+    _rollback_on_drop: Arc<RollbackOnDrop>,
+}
+
+/// The connection goes back to the pool so it must never be left inside an open transaction.
+struct RollbackOnDrop(Arc<Mutex<Option<PostgresPooledConnection>>>);
+
+impl Drop for RollbackOnDrop {
+    fn drop(&mut self) {
+        let connection = self.0.try_lock().ok().and_then(|mut connection| connection.take());
+        if let (Some(connection), Ok(handle)) = (connection, tokio::runtime::Handle::try_current())
+        {
+            handle.spawn(async move {
+                if let Err(e) = connection.batch_execute("ROLLBACK").await {
+                    error!("Error rolling back dropped transaction: {}", e);
+                }
+            });
+        }
+    }
+}
+
+impl PostgresTransaction {
+    async fn begin(
+        pool: &Pool<PostgresConnectionManagerTls>,
+    ) -> Result<PostgresTransaction, PostgresError> {
+        let connection = pool.get_owned().await?;
+        connection.batch_execute("BEGIN").await?;
+
+        let connection = Arc::new(Mutex::new(Some(connection)));
+        Ok(PostgresTransaction {
+            connection: Arc::clone(&connection),
+            after_commit: Arc::new(std::sync::Mutex::new(vec![])),
+            _rollback_on_drop: Arc::new(RollbackOnDrop(connection)),
+        })
+    }
+
+    async fn connection(
+        &self,
+    ) -> Result<OwnedMutexGuard<Option<PostgresPooledConnection>>, PostgresError> {
+        let connection = Arc::clone(&self.connection).lock_owned().await;
+        if connection.is_none() {
+            return Err(PostgresError::TransactionFinished);
+        }
+        Ok(connection)
+    }
+
     pub async fn execute(
-        &mut self,
+        &self,
         query: &str,
         params: &[&(dyn ToSql + Sync)],
     ) -> Result<u64, PostgresError> {
-        self.transaction.execute(query, params).await.map_err(PostgresError::PgError)
+        let connection = ClientConnection::Transaction(self.connection().await?);
+        connection.execute(query, params).await.map_err(PostgresError::PgError)
+    }
+
+    pub async fn query(
+        &self,
+        query: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, PostgresError> {
+        let connection = ClientConnection::Transaction(self.connection().await?);
+        connection.query(query, params).await.map_err(PostgresError::PgError)
+    }
+
+    pub async fn batch_execute(&self, sql: &str) -> Result<(), PostgresError> {
+        let connection = ClientConnection::Transaction(self.connection().await?);
+        connection.batch_execute(sql).await.map_err(PostgresError::PgError)
+    }
+
+    async fn finish(&self, sql: &str) -> Result<(), PostgresError> {
+        let connection =
+            self.connection().await?.take().ok_or(PostgresError::TransactionFinished)?;
+        connection.batch_execute(sql).await.map_err(PostgresError::PgError)
+    }
+
+    pub async fn commit(&self) -> Result<(), PostgresError> {
+        self.finish("COMMIT").await
+    }
+
+    /// Runs the tasks queued with `PostgresClient::after_commit` in the order they were queued,
+    /// a rolled back transaction drops them without running them.
+    async fn run_after_commit(&self) -> Result<(), PostgresError> {
+        let tasks = std::mem::take(&mut *self.after_commit.lock().expect("lock poisoned"));
+        for task in tasks {
+            task.await.map_err(PostgresError::AfterCommitFailed)?;
+        }
+
+        Ok(())
     }
 
-    pub async fn commit(self) -> Result<(), PostgresError> {
-        self.transaction.commit().await.map_err(PostgresError::PgError)
+    pub async fn rollback(&self) -> Result<(), PostgresError> {
+        self.finish("ROLLBACK").await
     }
+}
 
-    pub async fn rollback(self) -> Result<(), PostgresError> {
-        self.transaction.rollback().await.map_err(PostgresError::PgError)
+/// A connection from the pool or the connection of the current transaction.
+enum ClientConnection<'a> {
+    Pool(PooledConnection<'a, PostgresConnectionManagerTls>),
+    Transaction(OwnedMutexGuard<Option<PostgresPooledConnection>>),
+}
+
+impl Deref for ClientConnection<'_> {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        match self {
+            ClientConnection::Pool(connection) => connection,
+            ClientConnection::Transaction(connection) => {
+                connection.as_ref().expect("transaction connection is checked when locked")
+            }
+        }
     }
 }
 
@@ -86,7 +203,7 @@ pub enum BulkInsertPostgresError {
 }
 
 pub struct PostgresClient {
-    pool: Pool<PostgresConnectionManager<MakeTlsConnector>>,
+    pool: Pool<PostgresConnectionManagerTls>,
 }
 
 impl PostgresClient {
@@ -156,8 +273,20 @@ impl PostgresClient {
         _new(false).await
     }
 
+    /// The connection of the transaction when called inside `in_transaction` otherwise a
+    /// connection from the pool.
+    async fn connection(&self) -> Result<ClientConnection<'_>, PostgresError> {
+        if let Ok(transaction) = CURRENT_TRANSACTION.try_with(|transaction| transaction.clone()) {
+            if let Ok(connection) = transaction.connection().await {
+                return Ok(ClientConnection::Transaction(connection));
+            }
+        }
+
+        Ok(ClientConnection::Pool(self.pool.get().await?))
+    }
+
     pub async fn batch_execute(&self, sql: &str) -> Result<(), PostgresError> {
-        let conn = self.pool.get().await?;
+        let conn = self.connection().await?;
         conn.batch_execute(sql).await.map_err(PostgresError::PgError)
     }
 
@@ -169,7 +298,7 @@ impl PostgresClient {
     where
         T: ?Sized + ToStatement,
     {
-        let conn = self.pool.get().await?;
+        let conn = self.connection().await?;
         conn.execute(query, params).await.map_err(PostgresError::PgError)
     }
 
@@ -178,10 +307,58 @@ impl PostgresClient {
         query: &str,
         parameter_types: &[PgType],
     ) -> Result<Statement, PostgresError> {
-        let conn = self.pool.get().await?;
+        let conn = self.connection().await?;
         conn.prepare_typed(query, parameter_types).await.map_err(PostgresError::PgError)
     }
 
+    /// True when called inside `in_transaction`, the calls then join its transaction.
+    pub fn is_in_transaction(&self) -> bool {
+        CURRENT_TRANSACTION.try_with(|_| ()).is_ok()
+    }
+
+    /// Runs `f` in a transaction which commits when it returns `Ok` and rolls back otherwise,
+    /// every `PostgresClient` call made while `f` runs joins the transaction.
+    pub async fn in_transaction<F, Fut, T, E>(&self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(PostgresTransaction) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: From<PostgresError>,
+    {
+        let transaction = PostgresTransaction::begin(&self.pool).await?;
+
+        match CURRENT_TRANSACTION.scope(transaction.clone(), f(transaction.clone())).await {
+            Ok(result) => {
+                transaction.commit().await?;
+                transaction.run_after_commit().await?;
+                Ok(result)
+            }
+            Err(e) => {
+                if let Err(rollback_error) = transaction.rollback().await {
+                    error!("Error rolling back transaction: {}", rollback_error);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Runs `task` once the current transaction commits, for work which can not be rolled back
+    /// like publishing to streams. Outside of a transaction it runs straight away. A failing task
+    /// fails `in_transaction` after the commit so the caller retries, which has to be safe.
+    pub async fn after_commit<F>(&self, task: F) -> Result<(), String>
+    where
+        F: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        match CURRENT_TRANSACTION.try_with(|transaction| Arc::clone(&transaction.after_commit)) {
+            Ok(after_commit) => {
+                after_commit.lock().expect("lock poisoned").push(task.boxed());
+                Ok(())
+            }
+            Err(_) => task.await,
+        }
+    }
+
+    /// Runs `query` in a transaction and hands its row count to `f`, the transaction commits
+    /// once `f` returns `Ok`.
     pub async fn with_transaction<F, Fut, T, Q>(
         &self,
         query: &Q,
@@ -193,16 +370,11 @@ impl PostgresClient {
         Fut: Future<Output = Result<T, PostgresError>> + Send,
         Q: ?Sized + ToStatement,
     {
-        let mut conn = self.pool.get().await.map_err(PostgresError::ConnectionPoolError)?;
-        let transaction = conn.transaction().await.map_err(PostgresError::PgError)?;
-
-        let count = transaction.execute(query, params).await.map_err(PostgresError::PgError)?;
-
-        let result = f(count).await?;
-
-        transaction.commit().await.map_err(PostgresError::PgError)?;
-
-        Ok(result)
+        self.in_transaction(|_| async {
+            let count = self.execute(query, params).await?;
+            f(count).await
+        })
+        .await
     }
 
     pub async fn query<T>(
@@ -213,7 +385,7 @@ impl PostgresClient {
     where
         T: ?Sized + ToStatement,
     {
-        let conn = self.pool.get().await?;
+        let conn = self.connection().await?;
         let rows = conn.query(query, params).await.map_err(PostgresError::PgError)?;
         Ok(rows)
     }
@@ -226,7 +398,7 @@ impl PostgresClient {
     where
         T: ?Sized + ToStatement,
     {
-        let conn = self.pool.get().await?;
+        let conn = self.connection().await?;
         let row = conn.query_one(query, params).await.map_err(PostgresError::PgError)?;
         Ok(row)
     }
@@ -239,7 +411,7 @@ impl PostgresClient {
     where
         T: ?Sized + ToStatement,
     {
-        let conn = self.pool.get().await?;
+        let conn = self.connection().await?;
         let row = conn.query_opt(query, params).await.map_err(PostgresError::PgError)?;
         Ok(row)
     }
@@ -252,19 +424,36 @@ impl PostgresClient {
     where
         T: ?Sized + ToStatement,
     {
-        let mut conn = self.pool.get().await?;
-        let transaction = conn.transaction().await.map_err(PostgresError::PgError)?;
+        let params_list: Vec<Vec<&(dyn ToSql + Sync)>> = params_list
+            .iter()
+            .map(|params| {
+                params.iter().map(|param| param.as_ref() as &(dyn ToSql + Sync)).collect()
+            })
+            .collect();
 
-        for params in params_list {
-            let params_refs: Vec<&(dyn ToSql + Sync)> =
-                params.iter().map(|param| param.as_ref() as &(dyn ToSql + Sync)).collect();
-            transaction.execute(query, &params_refs).await.map_err(PostgresError::PgError)?;
+        match self.connection().await? {
+            ClientConnection::Pool(mut conn) => {
+                let transaction = conn.transaction().await.map_err(PostgresError::PgError)?;
+
+                for params in &params_list {
+                    transaction.execute(query, params).await.map_err(PostgresError::PgError)?;
+                }
+
+                transaction.commit().await.map_err(PostgresError::PgError)?;
+            }
+            // already inside a transaction which commits the rows
+            conn => {
+                for params in &params_list {
+                    conn.execute(query, params).await.map_err(PostgresError::PgError)?;
+                }
+            }
         }
 
-        transaction.commit().await.map_err(PostgresError::PgError)?;
         Ok(())
     }
 
+    /// Starts a COPY on a connection from the pool, it does not join a transaction from
+    /// `in_transaction` use `bulk_insert_via_copy` for that.
     pub async fn copy_in<T, U>(&self, statement: &T) -> Result<CopyInSink<U>, PostgresError>
     where
         T: ?Sized + ToStatement,
@@ -275,6 +464,8 @@ impl PostgresClient {
         conn.copy_in(statement).await.map_err(PostgresError::PgError)
     }
 
+    /// COPY can not skip rows which already exist so the rows are copied into a staging table
+    /// and moved over with `ON CONFLICT DO NOTHING`, replaying a batch never duplicates rows.
     pub async fn bulk_insert_via_copy(
         &self,
         table_name: &str,
@@ -282,11 +473,60 @@ impl PostgresClient {
         column_types: &[PgType],
         data: &[Vec<EthereumSqlTypeWrapper>],
     ) -> Result<(), BulkInsertPostgresError> {
-        let stmt = format!(
-            "COPY {} ({}) FROM STDIN WITH (FORMAT binary)",
-            table_name,
-            generate_event_table_columns_names_sql(column_names),
-        );
+        self.bulk_insert_via_copy_returning(table_name, column_names, column_types, data, None)
+            .await
+            .map(|_| ())
+    }
+
+    async fn bulk_insert_via_copy_returning(
+        &self,
+        table_name: &str,
+        column_names: &[String],
+        column_types: &[PgType],
+        data: &[Vec<EthereumSqlTypeWrapper>],
+        returning: Option<&str>,
+    ) -> Result<Vec<Row>, BulkInsertPostgresError> {
+        // the staging table is dropped on commit so it needs a transaction to live in
+        if !self.is_in_transaction() {
+            return self
+                .in_transaction(|_| {
+                    self.bulk_insert_via_staging_table(
+                        table_name,
+                        column_names,
+                        column_types,
+                        data,
+                        returning,
+                    )
+                })
+                .await;
+        }
+
+        self.bulk_insert_via_staging_table(table_name, column_names, column_types, data, returning)
+            .await
+    }
+
+    async fn bulk_insert_via_staging_table(
+        &self,
+        table_name: &str,
+        column_names: &[String],
+        column_types: &[PgType],
+        data: &[Vec<EthereumSqlTypeWrapper>],
+        returning: Option<&str>,
+    ) -> Result<Vec<Row>, BulkInsertPostgresError> {
+        let staging_table_name = "rindexer_copy_staging";
+        let columns = generate_event_table_columns_names_sql(column_names);
+
+        // the connection is held until the COPY finishes as a transaction connection is shared
+        let conn = self.connection().await?;
+
+        conn.batch_execute(&format!(
+            "CREATE TEMP TABLE {} ON COMMIT DROP AS SELECT {} FROM {} WITH NO DATA",
+            staging_table_name, columns, table_name
+        ))
+        .await?;
+
+        let stmt =
+            format!("COPY {} ({}) FROM STDIN WITH (FORMAT binary)", staging_table_name, columns);
 
         debug!("Bulk insert statement: {}", stmt);
 
@@ -297,7 +537,7 @@ impl PostgresClient {
 
         //debug!("Prepared data: {:?}", prepared_data);
 
-        let sink = self.copy_in(&stmt).await?;
+        let sink = conn.copy_in(&stmt).await.map_err(PostgresError::PgError)?;
 
         let writer = BinaryCopyInWriter::new(sink, column_types);
         pin_mut!(writer);
@@ -308,40 +548,68 @@ impl PostgresClient {
 
         writer.finish().await?;
 
-        Ok(())
+        let insert = format!(
+            "INSERT INTO {} ({}) SELECT {} FROM {} ON CONFLICT DO NOTHING",
+            table_name, columns, columns, staging_table_name
+        );
+        let rows = match returning {
+            Some(returning) => {
+                conn.query(&format!("{} RETURNING {}", insert, returning), &[]).await?
+            }
+            None => {
+                conn.batch_execute(&insert).await?;
+                vec![]
+            }
+        };
+        conn.batch_execute(&format!("DROP TABLE {};", staging_table_name)).await?;
+
+        Ok(rows)
     }
 
-    pub async fn bulk_insert<'a>(
+    pub async fn bulk_insert(
         &self,
         table_name: &str,
         column_names: &[String],
-        bulk_data: &'a [Vec<EthereumSqlTypeWrapper>],
+        bulk_data: &[Vec<EthereumSqlTypeWrapper>],
     ) -> Result<u64, PostgresError> {
-        let total_columns = column_names.len();
+        let (query, params) = bulk_insert_query(table_name, column_names, bulk_data);
+        self.execute(&query, &params).await
+    }
 
-        let mut query = format!(
-            "INSERT INTO {} ({}) VALUES ",
-            table_name,
-            generate_event_table_columns_names_sql(column_names),
-        );
-        let mut params: Vec<&'a (dyn ToSql + Sync + 'a)> = Vec::new();
+    /// Inserts the rows like `insert_bulk` and returns the `tx_hash` and `log_index` of the rows
+    /// which were stored, rows which already existed (a replayed batch) are left out.
+    pub async fn insert_bulk_returning_log_keys(
+        &self,
+        table_name: &str,
+        columns: &[String],
+        postgres_bulk_data: &[Vec<EthereumSqlTypeWrapper>],
+    ) -> Result<Vec<(String, i32)>, String> {
+        if postgres_bulk_data.is_empty() {
+            return Ok(vec![]);
+        }
 
-        for (i, row) in bulk_data.iter().enumerate() {
-            if i > 0 {
-                query.push(',');
-            }
-            let mut placeholders = vec![];
-            for j in 0..total_columns {
-                placeholders.push(format!("${}", i * total_columns + j + 1));
-            }
-            query.push_str(&format!("({})", placeholders.join(",")));
+        let returning = "tx_hash, log_index";
+        let rows = if postgres_bulk_data.len() > 100 {
+            let column_types: Vec<PgType> =
+                postgres_bulk_data[0].iter().map(|param| param.to_type()).collect();
 
-            for param in row {
-                params.push(param as &'a (dyn ToSql + Sync + 'a));
-            }
-        }
+            self.bulk_insert_via_copy_returning(
+                table_name,
+                columns,
+                &column_types,
+                postgres_bulk_data,
+                Some(returning),
+            )
+            .await
+            .map_err(|e| e.to_string())?
+        } else {
+            let (query, params) = bulk_insert_query(table_name, columns, postgres_bulk_data);
+            self.query(&format!("{} RETURNING {}", query, returning), &params)
+                .await
+                .map_err(|e| e.to_string())?
+        };
 
-        self.execute(&query, &params).await
+        Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
     }
 
     /// This will use COPY to insert the data into the database
@@ -372,3 +640,39 @@ impl PostgresClient {
         }
     }
 }
+
+/// The multi row insert of `bulk_insert` with its parameters.
+fn bulk_insert_query<'a>(
+    table_name: &str,
+    column_names: &[String],
+    bulk_data: &'a [Vec<EthereumSqlTypeWrapper>],
+) -> (String, Vec<&'a (dyn ToSql + Sync + 'a)>) {
+    let total_columns = column_names.len();
+
+    let mut query = format!(
+        "INSERT INTO {} ({}) VALUES ",
+        table_name,
+        generate_event_table_columns_names_sql(column_names),
+    );
+    let mut params: Vec<&'a (dyn ToSql + Sync + 'a)> = Vec::new();
+
+    for (i, row) in bulk_data.iter().enumerate() {
+        if i > 0 {
+            query.push(',');
+        }
+        let mut placeholders = vec![];
+        for j in 0..total_columns {
+            placeholders.push(format!("${}", i * total_columns + j + 1));
+        }
+        query.push_str(&format!("({})", placeholders.join(",")));
+
+        for param in row {
+            params.push(param as &'a (dyn ToSql + Sync + 'a));
+        }
+    }
+
+    // rows which are already stored (a replayed batch) are skipped
+    query.push_str(" ON CONFLICT DO NOTHING");
+
+    (query, params)
+}
@@ -0,0 +1,887 @@
+use std::{collections::HashSet, path::Path};
+
+use ethers::{
+    abi::{LogParam, Token},
+    types::{Address, Bytes, U64},
+};
+use tokio_postgres::Row;
+
+use crate::{
+    abi::{ABIInput, ABIItem, ReadAbiError},
+    database::postgres::{
+        client::{PostgresClient, PostgresError, ToSql},
+        generate::generate_event_table_full_name,
+        migration::ExpectedTable,
+        sql_type_wrapper::EthereumSqlTypeWrapper,
+    },
+    event::callback_registry::TxInformation,
+    helpers::{camel_to_snake, u256_to_i256},
+    manifest::{
+        core::Manifest,
+        entity::{
+            entity_element_type, Entity, EntityColumn, EntityEventMapping, EntityOperationType,
+        },
+    },
+};
+
+/// The schema holding the entity tables of the indexer.
+pub fn generate_entities_schema_name(indexer_name: &str) -> String {
+    format!("{}_entities", camel_to_snake(indexer_name))
+}
+
+pub fn generate_entity_table_full_name(indexer_name: &str, entity_name: &str) -> String {
+    format!("{}.{}", generate_entities_schema_name(indexer_name), camel_to_snake(entity_name))
+}
+
+/// The entity tables keyed by network and the entity keys, numeric fields start at 0 so they
+/// can be added to straight away.
+pub fn expected_entity_tables(indexer_name: &str, entities: &[Entity]) -> Vec<ExpectedTable> {
+    entities
+        .iter()
+        .map(|entity| {
+            let mut columns = vec![("network".to_string(), "TEXT".to_string())];
+            columns.extend(entity.columns().map(|column| {
+                (column.name.clone(), column.postgres_type().unwrap_or("TEXT").to_string())
+            }));
+
+            let column_definitions = columns
+                .iter()
+                .map(|(name, data_type)| {
+                    let is_key =
+                        name == "network" || entity.keys.iter().any(|key| key.name == *name);
+                    if is_key {
+                        format!("\"{}\" {} NOT NULL", name, data_type)
+                    } else if data_type == "NUMERIC" {
+                        format!("\"{}\" NUMERIC NOT NULL DEFAULT 0", name)
+                    } else {
+                        format!("\"{}\" {}", name, data_type)
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+            let primary_key = std::iter::once("network")
+                .chain(entity.keys.iter().map(|key| key.name.as_str()))
+                .map(|name| format!("\"{}\"", name))
+                .collect::<Vec<String>>()
+                .join(", ");
+
+            let full_name = generate_entity_table_full_name(indexer_name, &entity.name);
+            ExpectedTable {
+                schema_name: generate_entities_schema_name(indexer_name),
+                table_name: camel_to_snake(&entity.name),
+                columns,
+                create_table_sql: format!(
+                    "CREATE TABLE IF NOT EXISTS {} ({}, PRIMARY KEY ({}));",
+                    full_name, column_definitions, primary_key
+                ),
+                log_identity_index: false,
+            }
+        })
+        .collect()
+}
+
+pub fn generate_entity_tables_sql(indexer_name: &str, entities: &[Entity]) -> String {
+    if entities.is_empty() {
+        return String::new();
+    }
+
+    let mut sql =
+        format!("CREATE SCHEMA IF NOT EXISTS {};", generate_entities_schema_name(indexer_name));
+    for table in expected_entity_tables(indexer_name, entities) {
+        sql.push_str(&table.create_table_sql);
+    }
+    sql
+}
+
+#[derive(Debug, Clone)]
+enum EntityValue {
+    Input(usize),
+    ContractAddress,
+    TxHash,
+    BlockNumber,
+}
+
+impl EntityValue {
+    fn new(value: &str, inputs: &[ABIInput]) -> Self {
+        match value {
+            "contract_address" => EntityValue::ContractAddress,
+            "tx_hash" => EntityValue::TxHash,
+            "block_number" => EntityValue::BlockNumber,
+            // the manifest validation makes sure the input exists
+            _ => EntityValue::Input(
+                inputs.iter().position(|input| input.name == value).unwrap_or_default(),
+            ),
+        }
+    }
+
+    /// The value for the log, arrays resolve to one value per element.
+    fn resolve(&self, log: &EntityLogValues) -> ResolvedValue {
+        match self {
+            EntityValue::Input(index) => {
+                log.inputs.get(*index).cloned().unwrap_or(ResolvedValue::Single(String::new()))
+            }
+            EntityValue::ContractAddress => ResolvedValue::Single(log.contract_address.clone()),
+            EntityValue::TxHash => ResolvedValue::Single(log.tx_hash.clone()),
+            EntityValue::BlockNumber => ResolvedValue::Single(log.block_number.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum ResolvedValue {
+    Single(String),
+    Array(Vec<String>),
+}
+
+impl ResolvedValue {
+    fn get(&self, element: usize) -> String {
+        match self {
+            ResolvedValue::Single(value) => value.clone(),
+            ResolvedValue::Array(values) => values[element].clone(),
+        }
+    }
+
+    fn from_json(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::String(value) => ResolvedValue::Single(value),
+            serde_json::Value::Array(values) => ResolvedValue::Array(
+                values.iter().map(|value| value.as_str().unwrap_or_default().to_string()).collect(),
+            ),
+            _ => ResolvedValue::Single(String::new()),
+        }
+    }
+}
+
+fn token_to_string(token: &Token) -> String {
+    match token {
+        Token::Address(address) => format!("{:?}", address),
+        Token::FixedBytes(bytes) | Token::Bytes(bytes) => Bytes::from(bytes.clone()).to_string(),
+        Token::Int(int) => u256_to_i256(*int).to_string(),
+        Token::Uint(uint) => uint.to_string(),
+        Token::Bool(b) => b.to_string(),
+        Token::String(s) => s.clone(),
+        // nested arrays and tuples are refused by the manifest validation
+        Token::FixedArray(_) | Token::Array(_) | Token::Tuple(_) => String::new(),
+    }
+}
+
+/// The values of a log the upserts read, from the decoded log while indexing or from the event
+/// row it was stored in when entities are reversed or rebuilt.
+#[derive(Debug, Clone)]
+pub struct EntityLogValues {
+    network: String,
+    contract_address: String,
+    tx_hash: String,
+    block_number: String,
+    inputs: Vec<ResolvedValue>,
+}
+
+impl EntityLogValues {
+    pub fn from_log(log_params: &[LogParam], tx_information: &TxInformation) -> Self {
+        EntityLogValues {
+            network: tx_information.network.clone(),
+            contract_address: format!("{:?}", tx_information.address),
+            tx_hash: format!("{:?}", tx_information.transaction_hash),
+            block_number: tx_information.block_number.to_string(),
+            inputs: log_params
+                .iter()
+                .map(|param| match &param.value {
+                    Token::Array(tokens) | Token::FixedArray(tokens) => {
+                        ResolvedValue::Array(tokens.iter().map(token_to_string).collect())
+                    }
+                    token => ResolvedValue::Single(token_to_string(token)),
+                })
+                .collect(),
+        }
+    }
+
+    /// Reads the columns selected by `event_row_values_sql` starting at the `first` column.
+    fn from_row(row: &Row, first: usize) -> Self {
+        let inputs = match row.get::<_, serde_json::Value>(first + 4) {
+            serde_json::Value::Array(values) => {
+                values.into_iter().map(ResolvedValue::from_json).collect()
+            }
+            _ => vec![],
+        };
+
+        EntityLogValues {
+            network: row.get(first),
+            contract_address: row.get(first + 1),
+            tx_hash: row.get(first + 2),
+            block_number: row.get(first + 3),
+            inputs,
+        }
+    }
+}
+
+/// The stored event row columns `EntityLogValues::from_row` reads, the inputs are turned into
+/// text the same way `token_to_string` formats a decoded log. Inputs an entity can not use are
+/// null.
+fn event_row_values_sql(inputs: &[ABIInput]) -> String {
+    let inputs_sql = inputs
+        .iter()
+        .map(|input| {
+            let column = format!("\"{}\"", camel_to_snake(&input.name));
+            let element_type = entity_element_type(&input.type_);
+            if input.components.is_some() ||
+                element_type.contains('[') ||
+                element_type.starts_with("tuple")
+            {
+                return "NULL".to_string();
+            }
+
+            match (element_type != input.type_, element_type.starts_with("bytes")) {
+                (false, false) => format!("to_jsonb({}::TEXT)", column),
+                (false, true) => format!("to_jsonb('0x' || COALESCE(encode({}, 'hex'), ''))", column),
+                (true, false) => format!("COALESCE(to_jsonb({}::TEXT[]), '[]')", column),
+                (true, true) => format!(
+                    "to_jsonb(ARRAY(SELECT '0x' || encode(element, 'hex') FROM unnest({}) AS element))",
+                    column
+                ),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    format!(
+        "network::TEXT, contract_address::TEXT, tx_hash::TEXT, block_number::TEXT, jsonb_build_array({})",
+        inputs_sql
+    )
+}
+
+/// The operation to apply to undo an operation, a `set` can not be undone without the value it
+/// replaced.
+fn reversed_operation(operation: EntityOperationType) -> Option<EntityOperationType> {
+    match operation {
+        EntityOperationType::Set => None,
+        EntityOperationType::Add => Some(EntityOperationType::Subtract),
+        EntityOperationType::Subtract => Some(EntityOperationType::Add),
+        EntityOperationType::Increment => Some(EntityOperationType::Decrement),
+        EntityOperationType::Decrement => Some(EntityOperationType::Increment),
+    }
+}
+
+/// The upsert of the operations, the parameters are the network, the keys and then the value
+/// of each operation which takes one.
+fn upsert_sql(
+    indexer_name: &str,
+    entity: &Entity,
+    operations: &[(&EntityColumn, EntityOperationType, bool)],
+) -> String {
+    let mut columns = vec!["\"network\"".to_string()];
+    let mut placeholders = vec!["$1::TEXT".to_string()];
+
+    for key in &entity.keys {
+        columns.push(format!("\"{}\"", key.name));
+        placeholders.push(format!(
+            "${}::TEXT::{}",
+            placeholders.len() + 1,
+            key.postgres_type().unwrap_or("TEXT")
+        ));
+    }
+
+    let mut parameters = placeholders.len();
+    let mut updates = vec![];
+    for (field, operation, takes_value) in operations {
+        let column = format!("\"{}\"", field.name);
+        let data_type = field.postgres_type().unwrap_or("TEXT");
+
+        let placeholder = if *takes_value {
+            parameters += 1;
+            format!("${}::TEXT::{}", parameters, data_type)
+        } else {
+            String::new()
+        };
+
+        let (inserted, update) = match operation {
+            EntityOperationType::Set => (placeholder, format!("EXCLUDED.{}", column)),
+            EntityOperationType::Add => {
+                (placeholder, format!("COALESCE(t.{}, 0) + EXCLUDED.{}", column, column))
+            }
+            EntityOperationType::Subtract => (
+                format!("-({})", placeholder),
+                format!("COALESCE(t.{}, 0) + EXCLUDED.{}", column, column),
+            ),
+            EntityOperationType::Increment => {
+                ("1".to_string(), format!("COALESCE(t.{}, 0) + 1", column))
+            }
+            EntityOperationType::Decrement => {
+                ("-1".to_string(), format!("COALESCE(t.{}, 0) - 1", column))
+            }
+        };
+
+        placeholders.push(inserted);
+        updates.push(format!("{} = {}", column, update));
+        columns.push(column);
+    }
+
+    let conflict_columns = columns[..entity.keys.len() + 1].join(", ");
+    let on_conflict = if updates.is_empty() {
+        "DO NOTHING".to_string()
+    } else {
+        format!("DO UPDATE SET {}", updates.join(", "))
+    };
+
+    format!(
+        "INSERT INTO {} AS t ({}) VALUES ({}) ON CONFLICT ({}) {};",
+        generate_entity_table_full_name(indexer_name, &entity.name),
+        columns.join(", "),
+        placeholders.join(", "),
+        conflict_columns,
+        on_conflict
+    )
+}
+
+async fn execute_upsert(
+    database: &PostgresClient,
+    sql: &str,
+    params: &[String],
+) -> Result<u64, PostgresError> {
+    let params: Vec<&(dyn ToSql + Sync)> =
+        params.iter().map(|param| param as &(dyn ToSql + Sync)).collect();
+    database.execute(sql, &params).await
+}
+
+/// The upsert an event applies to an entity, every value is sent as text and cast to the
+/// column type.
+#[derive(Debug, Clone)]
+pub struct EntityUpsert {
+    pub entity_name: String,
+    pub sql: String,
+    // undoes the upsert when the event row is removed, `None` when the entity has to be rebuilt
+    reverse_sql: Option<String>,
+    values: Vec<EntityValue>,
+    zero_address_checks: Vec<usize>,
+}
+
+impl EntityUpsert {
+    pub fn new(
+        indexer_name: &str,
+        entity: &Entity,
+        mapping: &EntityEventMapping,
+        inputs: &[ABIInput],
+    ) -> Self {
+        let mut values = vec![];
+        let mut zero_address_checks = vec![];
+
+        for key in &entity.keys {
+            if mapping.skip_zero_address.unwrap_or_default() && key.type_ == "address" {
+                zero_address_checks.push(values.len() + 1);
+            }
+            values.push(EntityValue::new(&mapping.keys[&key.name], inputs));
+        }
+
+        let mut operations = vec![];
+        for operation in &mapping.operations {
+            let Some(field) = entity.field(&operation.field) else {
+                continue;
+            };
+            if let Some(value) = &operation.value {
+                values.push(EntityValue::new(value, inputs));
+            }
+            operations.push((field, operation.operation, operation.value.is_some()));
+        }
+
+        let reversed_operations: Option<Vec<(&EntityColumn, EntityOperationType, bool)>> =
+            operations
+                .iter()
+                .map(|(field, operation, takes_value)| {
+                    reversed_operation(*operation)
+                        .map(|operation| (*field, operation, *takes_value))
+                })
+                .collect();
+
+        EntityUpsert {
+            entity_name: entity.name.clone(),
+            sql: upsert_sql(indexer_name, entity, &operations),
+            reverse_sql: reversed_operations
+                .filter(|operations| !operations.is_empty())
+                .map(|operations| upsert_sql(indexer_name, entity, &operations)),
+            values,
+            zero_address_checks,
+        }
+    }
+
+    /// The parameters of each upsert to run for a log starting with the network, a log with
+    /// array values runs one upsert per element.
+    pub fn params(&self, log: &EntityLogValues) -> Vec<Vec<String>> {
+        let resolved: Vec<ResolvedValue> =
+            self.values.iter().map(|value| value.resolve(log)).collect();
+        let elements = resolved
+            .iter()
+            .filter_map(|value| match value {
+                ResolvedValue::Array(values) => Some(values.len()),
+                ResolvedValue::Single(_) => None,
+            })
+            .min()
+            .unwrap_or(1);
+        let zero_address = format!("{:?}", Address::zero());
+
+        (0..elements)
+            .map(|element| {
+                let mut params = vec![log.network.clone()];
+                params.extend(resolved.iter().map(|value| value.get(element)));
+                params
+            })
+            .filter(|params| !self.zero_address_checks.iter().any(|i| params[*i] == zero_address))
+            .collect()
+    }
+
+    pub async fn execute(
+        &self,
+        database: &PostgresClient,
+        params: &[String],
+    ) -> Result<u64, PostgresError> {
+        execute_upsert(database, &self.sql, params).await
+    }
+}
+
+/// A log is stored once per network and a batch is for a single network, so the transaction
+/// hash and the log index identify a log of the batch.
+pub type EntityLogKey = (String, i32);
+
+pub fn entity_log_key(tx_information: &TxInformation) -> EntityLogKey {
+    (format!("{:?}", tx_information.transaction_hash), tx_information.log_index.as_u32() as i32)
+}
+
+/// The upserts to run for a batch in log order. Logs whose event row was already stored (a
+/// replayed batch) had their upserts applied when the row was first inserted so are skipped.
+pub fn entity_upserts_for_inserted_logs<'a>(
+    upserts: &'a [EntityUpsert],
+    logs: &[(EntityLogKey, EntityLogValues)],
+    inserted: &HashSet<EntityLogKey>,
+) -> Vec<(&'a EntityUpsert, Vec<String>)> {
+    logs.iter()
+        .filter(|(key, _)| inserted.contains(key))
+        .flat_map(|(_, log)| {
+            upserts.iter().flat_map(move |upsert| {
+                upsert.params(log).into_iter().map(move |params| (upsert, params))
+            })
+        })
+        .collect()
+}
+
+/// An entity mapping with the event table the mapped event is stored in.
+#[derive(Debug, Clone)]
+struct EntityEventTable {
+    table_name: String,
+    values_sql: String,
+    upsert: EntityUpsert,
+}
+
+#[derive(Debug, Clone)]
+struct IndexedEntity {
+    table_name: String,
+    mappings: Vec<EntityEventTable>,
+}
+
+const REBUILD_CURSOR_NAME: &str = "rindexer_entity_rebuild";
+const REBUILD_FETCH_SIZE: usize = 5000;
+
+/// The entities of an indexer with the event tables they are built from, keeps the entities in
+/// step with the event tables when indexed rows are removed or replaced.
+#[derive(Debug, Clone, Default)]
+pub struct IndexerEntities {
+    entities: Vec<IndexedEntity>,
+}
+
+impl IndexerEntities {
+    pub fn new(project_path: &Path, manifest: &Manifest) -> Result<Self, ReadAbiError> {
+        let mut entities = vec![];
+        for entity in manifest.all_entities() {
+            let mut mappings = vec![];
+            for mapping in &entity.events {
+                // the manifest validation makes sure the contract and event exist
+                let Some(contract) = manifest.contracts.iter().find(|c| c.name == mapping.contract)
+                else {
+                    continue;
+                };
+                let abi_items = ABIItem::read_abi_items(project_path, contract)?;
+                let Some(event) = abi_items
+                    .iter()
+                    .find(|item| item.type_ == "event" && item.name == mapping.event)
+                else {
+                    continue;
+                };
+
+                mappings.push(EntityEventTable {
+                    table_name: generate_event_table_full_name(
+                        &manifest.name,
+                        &contract.before_modify_name_if_filter_readonly(),
+                        &mapping.event,
+                    ),
+                    values_sql: event_row_values_sql(&event.inputs),
+                    upsert: EntityUpsert::new(&manifest.name, &entity, mapping, &event.inputs),
+                });
+            }
+
+            entities.push(IndexedEntity {
+                table_name: generate_entity_table_full_name(&manifest.name, &entity.name),
+                mappings,
+            });
+        }
+
+        Ok(IndexerEntities { entities })
+    }
+
+    /// The upserts of the entities for the rows of an event table, in the order the entities
+    /// and their events are defined.
+    pub fn upserts(&self, event_table_name: &str) -> Vec<EntityUpsert> {
+        self.mappings(event_table_name).map(|(_, mapping)| mapping.upsert.clone()).collect()
+    }
+
+    fn mappings<'a>(
+        &'a self,
+        event_table_name: &'a str,
+    ) -> impl Iterator<Item = (&'a IndexedEntity, &'a EntityEventTable)> + 'a {
+        self.entities.iter().flat_map(move |entity| {
+            entity
+                .mappings
+                .iter()
+                .filter(move |mapping| mapping.table_name == event_table_name)
+                .map(move |mapping| (entity, mapping))
+        })
+    }
+
+    /// Deletes the rows of an event table on a network from `from_block` up to `to_block`, or
+    /// every block after `from_block` when there is no `to_block`, and takes them back out of
+    /// the entities. Added values are reversed with the deleted rows, entities which `set` a
+    /// value from the event are rebuilt from the rows which are left.
+    pub async fn remove_event_rows(
+        &self,
+        database: &PostgresClient,
+        event_table_name: &str,
+        network: &str,
+        from_block: U64,
+        to_block: Option<U64>,
+    ) -> Result<u64, PostgresError> {
+        if self.mappings(event_table_name).next().is_none() {
+            let (query, from_block, to_block) =
+                delete_event_rows_query(event_table_name, from_block, to_block);
+            let mut params: Vec<&(dyn ToSql + Sync)> = vec![&network, &from_block];
+            if let Some(to_block) = &to_block {
+                params.push(to_block);
+            }
+            return database.execute(&query, &params).await;
+        }
+
+        if !database.is_in_transaction() {
+            return database
+                .in_transaction(|_| {
+                    self.remove_entity_event_rows(
+                        database,
+                        event_table_name,
+                        network,
+                        from_block,
+                        to_block,
+                    )
+                })
+                .await;
+        }
+
+        self.remove_entity_event_rows(database, event_table_name, network, from_block, to_block)
+            .await
+    }
+
+    async fn remove_entity_event_rows(
+        &self,
+        database: &PostgresClient,
+        event_table_name: &str,
+        network: &str,
+        from_block: U64,
+        to_block: Option<U64>,
+    ) -> Result<u64, PostgresError> {
+        let Some((_, first_mapping)) = self.mappings(event_table_name).next() else {
+            return Ok(0);
+        };
+
+        let (query, from_block, to_block) =
+            delete_event_rows_query(event_table_name, from_block, to_block);
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&network, &from_block];
+        if let Some(to_block) = &to_block {
+            params.push(to_block);
+        }
+        let rows = database
+            .query(&format!("{} RETURNING {}", query, first_mapping.values_sql), &params)
+            .await?;
+        let removed_logs: Vec<EntityLogValues> =
+            rows.iter().map(|row| EntityLogValues::from_row(row, 0)).collect();
+
+        for entity in &self.entities {
+            let mappings: Vec<&EntityEventTable> = entity
+                .mappings
+                .iter()
+                .filter(|mapping| mapping.table_name == event_table_name)
+                .collect();
+            if mappings.is_empty() {
+                continue;
+            }
+
+            if !mappings.iter().all(|mapping| mapping.upsert.reverse_sql.is_some()) {
+                self.rebuild_entity(database, entity, network).await?;
+                continue;
+            }
+
+            for log in &removed_logs {
+                for mapping in &mappings {
+                    let reverse_sql = mapping.upsert.reverse_sql.as_deref().unwrap_or_default();
+                    for params in mapping.upsert.params(log) {
+                        execute_upsert(database, reverse_sql, &params).await?;
+                    }
+                }
+            }
+        }
+
+        Ok(rows.len() as u64)
+    }
+
+    /// Rebuilds every entity built from any of the event tables on a network, used once the
+    /// rows of the event tables have been replaced.
+    pub async fn rebuild_entities_for_event_tables(
+        &self,
+        database: &PostgresClient,
+        event_table_names: &[String],
+        network: &str,
+    ) -> Result<(), PostgresError> {
+        let entities = self.entities.iter().filter(|entity| {
+            entity.mappings.iter().any(|mapping| event_table_names.contains(&mapping.table_name))
+        });
+
+        for entity in entities {
+            if database.is_in_transaction() {
+                self.rebuild_entity(database, entity, network).await?;
+            } else {
+                database.in_transaction(|_| self.rebuild_entity(database, entity, network)).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Clears the entity on the network and replays the stored event rows in the order they
+    /// were indexed, the rows are read through a cursor so large tables are not held in memory.
+    async fn rebuild_entity(
+        &self,
+        database: &PostgresClient,
+        entity: &IndexedEntity,
+        network: &str,
+    ) -> Result<(), PostgresError> {
+        database
+            .execute(&format!("DELETE FROM {} WHERE network = $1", entity.table_name), &[&network])
+            .await?;
+        if entity.mappings.is_empty() {
+            return Ok(());
+        }
+
+        // a cursor can not take parameters so the network is quoted into the query
+        let network_literal = format!("'{}'", network.replace('\'', "''"));
+        let event_rows = entity
+            .mappings
+            .iter()
+            .enumerate()
+            .map(|(index, mapping)| {
+                format!(
+                    "SELECT {} AS mapping, block_number AS position_block, log_index AS position_log, {} FROM {} WHERE network = {}",
+                    index, mapping.values_sql, mapping.table_name, network_literal
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(" UNION ALL ");
+        database
+            .batch_execute(&format!(
+                "DECLARE {} NO SCROLL CURSOR FOR SELECT * FROM ({}) AS event_rows ORDER BY position_block, position_log, mapping",
+                REBUILD_CURSOR_NAME, event_rows
+            ))
+            .await?;
+
+        loop {
+            let rows = database
+                .query(&format!("FETCH {} FROM {}", REBUILD_FETCH_SIZE, REBUILD_CURSOR_NAME), &[])
+                .await?;
+            for row in &rows {
+                let mapping = &entity.mappings[row.get::<_, i32>(0) as usize];
+                let log = EntityLogValues::from_row(row, 3);
+                for params in mapping.upsert.params(&log) {
+                    mapping.upsert.execute(database, &params).await?;
+                }
+            }
+
+            if rows.len() < REBUILD_FETCH_SIZE {
+                break;
+            }
+        }
+
+        database.batch_execute(&format!("CLOSE {}", REBUILD_CURSOR_NAME)).await
+    }
+}
+
+fn delete_event_rows_query(
+    event_table_name: &str,
+    from_block: U64,
+    to_block: Option<U64>,
+) -> (String, EthereumSqlTypeWrapper, Option<EthereumSqlTypeWrapper>) {
+    let mut query =
+        format!("DELETE FROM {} WHERE network = $1 AND block_number >= $2", event_table_name);
+    if to_block.is_some() {
+        query.push_str(" AND block_number <= $3");
+    }
+
+    (
+        query,
+        EthereumSqlTypeWrapper::U64BigInt(from_block),
+        to_block.map(EthereumSqlTypeWrapper::U64BigInt),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::manifest::entity::{EntityColumn, EntityOperation};
+
+    fn column(name: &str, type_: &str) -> EntityColumn {
+        EntityColumn { name: name.to_string(), type_: type_.to_string() }
+    }
+
+    #[test]
+    fn test_entity_upsert_sql() {
+        let entity = Entity {
+            name: "HolderBalance".to_string(),
+            keys: vec![column("holder", "address")],
+            fields: vec![column("balance", "uint256"), column("transfers", "uint256")],
+            events: vec![],
+        };
+        let mapping = EntityEventMapping {
+            contract: "RocketPoolETH".to_string(),
+            event: "Transfer".to_string(),
+            keys: HashMap::from([("holder".to_string(), "from".to_string())]),
+            operations: vec![
+                EntityOperation {
+                    field: "balance".to_string(),
+                    operation: EntityOperationType::Subtract,
+                    value: Some("value".to_string()),
+                },
+                EntityOperation {
+                    field: "transfers".to_string(),
+                    operation: EntityOperationType::Increment,
+                    value: None,
+                },
+            ],
+            skip_zero_address: Some(true),
+        };
+        let inputs: Vec<ABIInput> = ["from", "to", "value"]
+            .iter()
+            .map(|name| ABIInput {
+                indexed: None,
+                name: name.to_string(),
+                type_: "address".to_string(),
+                components: None,
+            })
+            .collect();
+
+        let upsert = EntityUpsert::new("RocketPool", &entity, &mapping, &inputs);
+
+        assert_eq!(
+            upsert.sql,
+            "INSERT INTO rocket_pool_entities.holder_balance AS t (\"network\", \"holder\", \"balance\", \"transfers\") \
+             VALUES ($1::TEXT, $2::TEXT::CHAR(42), -($3::TEXT::NUMERIC), 1) \
+             ON CONFLICT (\"network\", \"holder\") \
+             DO UPDATE SET \"balance\" = COALESCE(t.\"balance\", 0) + EXCLUDED.\"balance\", \"transfers\" = COALESCE(t.\"transfers\", 0) + 1;"
+        );
+        assert_eq!(
+            upsert.reverse_sql.as_deref(),
+            Some(
+                "INSERT INTO rocket_pool_entities.holder_balance AS t (\"network\", \"holder\", \"balance\", \"transfers\") \
+                 VALUES ($1::TEXT, $2::TEXT::CHAR(42), $3::TEXT::NUMERIC, -1) \
+                 ON CONFLICT (\"network\", \"holder\") \
+                 DO UPDATE SET \"balance\" = COALESCE(t.\"balance\", 0) + EXCLUDED.\"balance\", \"transfers\" = COALESCE(t.\"transfers\", 0) - 1;"
+            )
+        );
+        assert!(matches!(upsert.values[..], [EntityValue::Input(0), EntityValue::Input(2)]));
+        assert_eq!(upsert.zero_address_checks, vec![1]);
+
+        let table = &expected_entity_tables("RocketPool", &[entity])[0];
+        assert_eq!(
+            table.create_table_sql,
+            "CREATE TABLE IF NOT EXISTS rocket_pool_entities.holder_balance (\"network\" TEXT NOT NULL, \
+             \"holder\" CHAR(42) NOT NULL, \"balance\" NUMERIC NOT NULL DEFAULT 0, \
+             \"transfers\" NUMERIC NOT NULL DEFAULT 0, PRIMARY KEY (\"network\", \"holder\"));"
+        );
+    }
+
+    #[test]
+    fn test_replayed_batch_does_not_change_balances() {
+        let entity = Entity {
+            name: "HolderBalance".to_string(),
+            keys: vec![column("holder", "address")],
+            fields: vec![column("balance", "uint256")],
+            events: vec![],
+        };
+        let inputs: Vec<ABIInput> = [("from", "address"), ("to", "address"), ("value", "uint256")]
+            .iter()
+            .map(|(name, type_)| ABIInput {
+                indexed: None,
+                name: name.to_string(),
+                type_: type_.to_string(),
+                components: None,
+            })
+            .collect();
+        let upserts: Vec<EntityUpsert> =
+            [("from", EntityOperationType::Subtract), ("to", EntityOperationType::Add)]
+                .into_iter()
+                .map(|(holder, operation)| {
+                    let mapping = EntityEventMapping {
+                        contract: "RocketPoolETH".to_string(),
+                        event: "Transfer".to_string(),
+                        keys: HashMap::from([("holder".to_string(), holder.to_string())]),
+                        operations: vec![EntityOperation {
+                            field: "balance".to_string(),
+                            operation,
+                            value: Some("value".to_string()),
+                        }],
+                        skip_zero_address: Some(true),
+                    };
+                    EntityUpsert::new("RocketPool", &entity, &mapping, &inputs)
+                })
+                .collect();
+
+        let transfer = |log_index: i32, from: &str, to: &str, value: &str| {
+            let values = EntityLogValues {
+                network: "ethereum".to_string(),
+                contract_address: "0xae78736cd615f374d3085123a210448e74fc6393".to_string(),
+                tx_hash: format!("0x{:064x}", log_index),
+                block_number: "100".to_string(),
+                inputs: [from, to, value]
+                    .iter()
+                    .map(|value| ResolvedValue::Single(value.to_string()))
+                    .collect(),
+            };
+            ((values.tx_hash.clone(), log_index), values)
+        };
+        let zero_address = format!("{:?}", Address::zero());
+        let batch =
+            vec![transfer(0, &zero_address, "0x01", "10"), transfer(1, "0x01", "0x02", "4")];
+
+        // the event table skips stored rows like `ON CONFLICT DO NOTHING RETURNING`
+        let mut event_table: HashSet<EntityLogKey> = HashSet::new();
+        let mut balances: HashMap<String, i64> = HashMap::new();
+        let mut index_batch = |event_table: &mut HashSet<EntityLogKey>| {
+            let inserted: HashSet<EntityLogKey> = batch
+                .iter()
+                .map(|(key, _)| key.clone())
+                .filter(|key| event_table.insert(key.clone()))
+                .collect();
+            for (upsert, params) in entity_upserts_for_inserted_logs(&upserts, &batch, &inserted) {
+                let value: i64 = params[2].parse().unwrap();
+                let sign = if std::ptr::eq(upsert, &upserts[0]) { -1 } else { 1 };
+                *balances.entry(params[1].clone()).or_default() += sign * value;
+            }
+        };
+
+        index_batch(&mut event_table);
+        index_batch(&mut event_table);
+
+        assert_eq!(balances, HashMap::from([("0x01".to_string(), 6), ("0x02".to_string(), 4)]));
+    }
+}
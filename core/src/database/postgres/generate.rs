@@ -3,10 +3,17 @@ use std::path::Path;
 use tracing::{error, info};
 
 use crate::{
-    abi::{ABIInput, ABIItem, EventInfo, GenerateAbiPropertiesType, ParamTypeError, ReadAbiError},
+    abi::{
+        function_call_event_name, ABIInput, ABIItem, EventInfo, GenerateAbiPropertiesType,
+        ParamTypeError, ReadAbiError,
+    },
+    database::postgres::entity::{generate_entities_schema_name, generate_entity_tables_sql},
     helpers::camel_to_snake,
-    indexer::Indexer,
-    manifest::contract::Contract,
+    indexer::{
+        block_handlers::generate_block_handler_last_synced_table_name,
+        factory::generate_factory_children_table_names, Indexer,
+    },
+    manifest::{block_handler::NativeTransfers, contract::Contract},
     types::code::Code,
 };
 
@@ -25,7 +32,64 @@ fn generate_columns_names_only(inputs: &[ABIInput]) -> Vec<String> {
     generate_columns(inputs, &GenerateAbiPropertiesType::PostgresColumnsNamesOnly)
 }
 
-pub fn generate_column_names_only_with_base_properties(inputs: &[ABIInput]) -> Vec<String> {
+/// The columns added to event tables when a contract has `include_transaction_fields` enabled.
+const TRANSACTION_FIELDS_COLUMNS: [(&str, &str); 5] = [
+    ("tx_from", "CHAR(42)"),
+    ("tx_to", "CHAR(42)"),
+    ("tx_value", "VARCHAR(78)"),
+    ("tx_gas_used", "VARCHAR(78)"),
+    ("tx_effective_gas_price", "VARCHAR(78)"),
+];
+
+pub fn generate_transaction_fields_column_names() -> Vec<String> {
+    TRANSACTION_FIELDS_COLUMNS.iter().map(|(name, _)| name.to_string()).collect()
+}
+
+/// Every column of an event table with its type, the same columns `CREATE TABLE` creates.
+pub fn generate_event_table_columns_with_types(
+    inputs: &[ABIInput],
+    include_transaction_fields: bool,
+) -> Vec<(String, String)> {
+    let mut columns = vec![
+        ("rindexer_id".to_string(), "SERIAL".to_string()),
+        ("contract_address".to_string(), "CHAR(66)".to_string()),
+    ];
+    columns.extend(
+        ABIInput::generate_abi_name_properties(
+            inputs,
+            &GenerateAbiPropertiesType::PostgresColumnsNamesOnly,
+            None,
+        )
+        .into_iter()
+        .map(|property| (property.value, solidity_type_to_db_type(&property.abi_type))),
+    );
+    columns.extend(
+        [
+            ("tx_hash", "CHAR(66)"),
+            ("block_number", "BIGINT"),
+            ("block_hash", "CHAR(66)"),
+            ("network", "VARCHAR(50)"),
+            ("tx_index", "INTEGER"),
+            ("log_index", "INTEGER"),
+            ("block_timestamp", "TIMESTAMPTZ"),
+        ]
+        .iter()
+        .map(|(name, data_type)| (name.to_string(), data_type.to_string())),
+    );
+    if include_transaction_fields {
+        columns.extend(
+            TRANSACTION_FIELDS_COLUMNS
+                .iter()
+                .map(|(name, data_type)| (name.to_string(), data_type.to_string())),
+        );
+    }
+    columns
+}
+
+pub fn generate_column_names_only_with_base_properties(
+    inputs: &[ABIInput],
+    include_transaction_fields: bool,
+) -> Vec<String> {
     let mut column_names: Vec<String> = vec!["contract_address".to_string()];
     column_names.extend(generate_columns_names_only(inputs));
     column_names.extend(vec![
@@ -35,42 +99,72 @@ pub fn generate_column_names_only_with_base_properties(inputs: &[ABIInput]) -> V
         "network".to_string(),
         "tx_index".to_string(),
         "log_index".to_string(),
+        "block_timestamp".to_string(),
     ]);
+    if include_transaction_fields {
+        column_names.extend(generate_transaction_fields_column_names());
+    }
     column_names
 }
 
+pub fn generate_event_table_create_sql(table_name: &str, inputs: &[ABIInput]) -> String {
+    let event_columns = if inputs.is_empty() {
+        "".to_string()
+    } else {
+        generate_columns_with_data_types(inputs).join(", ") + ","
+    };
+
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} (\
+        rindexer_id SERIAL PRIMARY KEY NOT NULL, \
+        contract_address CHAR(66) NOT NULL, \
+        {} \
+        tx_hash CHAR(66) NOT NULL, \
+        block_number BIGINT NOT NULL, \
+        block_hash CHAR(66) NOT NULL, \
+        network VARCHAR(50) NOT NULL, \
+        tx_index INTEGER NOT NULL, \
+        log_index INTEGER NOT NULL, \
+        block_timestamp TIMESTAMPTZ\
+    );",
+        table_name, event_columns
+    )
+}
+
 fn generate_event_table_sql_with_comments(
     abi_inputs: &[EventInfo],
     contract_name: &str,
     schema_name: &str,
     apply_full_name_comment_for_events: Vec<String>,
+    include_transaction_fields: bool,
 ) -> String {
     abi_inputs
         .iter()
         .map(|event_info| {
             let table_name = format!("{}.{}", schema_name, camel_to_snake(&event_info.name));
             info!("Creating table if not exists: {}", table_name);
-            let event_columns = if event_info.inputs.is_empty() {
-                "".to_string()
-            } else {
-                generate_columns_with_data_types(&event_info.inputs).join(", ") + ","
-            };
+            let create_table_sql = generate_event_table_create_sql(&table_name, &event_info.inputs);
 
-            let create_table_sql = format!(
-                "CREATE TABLE IF NOT EXISTS {} (\
-                rindexer_id SERIAL PRIMARY KEY NOT NULL, \
-                contract_address CHAR(66) NOT NULL, \
-                {} \
-                tx_hash CHAR(66) NOT NULL, \
-                block_number NUMERIC NOT NULL, \
-                block_hash CHAR(66) NOT NULL, \
-                network VARCHAR(50) NOT NULL, \
-                tx_index NUMERIC NOT NULL, \
-                log_index VARCHAR(78) NOT NULL\
-            );",
-                table_name, event_columns
+            // tables created before block timestamps were indexed need the column added
+            let mut create_table_sql = format!(
+                "{}\nALTER TABLE {} ADD COLUMN IF NOT EXISTS block_timestamp TIMESTAMPTZ;",
+                create_table_sql, table_name
             );
 
+            if include_transaction_fields {
+                let transaction_fields_columns = TRANSACTION_FIELDS_COLUMNS
+                    .iter()
+                    .map(|(name, data_type)| {
+                        format!("ADD COLUMN IF NOT EXISTS {} {}", name, data_type)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                create_table_sql.push_str(&format!(
+                    "\nALTER TABLE {} {};",
+                    table_name, transaction_fields_columns
+                ));
+            }
+
             if !apply_full_name_comment_for_events.contains(&event_info.name) {
                 return create_table_sql;
             }
@@ -87,33 +181,44 @@ fn generate_event_table_sql_with_comments(
         .join("\n")
 }
 
+pub fn generate_internal_event_table_name(schema_name: &str, event_name: &str) -> String {
+    format!("rindexer_internal.{}_{}", schema_name, camel_to_snake(event_name))
+}
+
+pub fn generate_internal_event_table_create_sql(table_name: &str) -> String {
+    format!(
+        r#"CREATE TABLE IF NOT EXISTS {} ("network" TEXT PRIMARY KEY, "last_synced_block" NUMERIC);"#,
+        table_name
+    )
+}
+
+pub fn generate_internal_event_table_checkpoint_sql(table_name: &str, network: &str) -> String {
+    format!(
+        r#"INSERT INTO {} ("network", "last_synced_block") VALUES ('{}', 0) ON CONFLICT ("network") DO NOTHING;"#,
+        table_name, network
+    )
+}
+
 fn generate_internal_event_table_sql(
     abi_inputs: &[EventInfo],
     schema_name: &str,
     networks: Vec<&str>,
 ) -> String {
-    abi_inputs.iter().map(|event_info| {
-        let table_name = format!(
-            "rindexer_internal.{}_{}",
-            schema_name,
-            camel_to_snake(&event_info.name)
-        );
-
-        let create_table_query = format!(
-            r#"CREATE TABLE IF NOT EXISTS {} ("network" TEXT PRIMARY KEY, "last_synced_block" NUMERIC);"#,
-            table_name
-        );
+    abi_inputs
+        .iter()
+        .map(|event_info| {
+            let table_name = generate_internal_event_table_name(schema_name, &event_info.name);
 
-        let insert_queries = networks.iter().map(|network| {
-            format!(
-                r#"INSERT INTO {} ("network", "last_synced_block") VALUES ('{}', 0) ON CONFLICT ("network") DO NOTHING;"#,
-                table_name,
-                network
-            )
-        }).collect::<Vec<_>>().join("\n");
+            let insert_queries = networks
+                .iter()
+                .map(|network| generate_internal_event_table_checkpoint_sql(&table_name, network))
+                .collect::<Vec<_>>()
+                .join("\n");
 
-        format!("{}\n{}", create_table_query, insert_queries)
-    }).collect::<Vec<_>>().join("\n")
+            format!("{}\n{}", generate_internal_event_table_create_sql(&table_name), insert_queries)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -166,7 +271,14 @@ pub fn generate_tables_for_indexer_sql(
     for contract in &indexer.contracts {
         let contract_name = contract.before_modify_name_if_filter_readonly();
         let abi_items = ABIItem::read_abi_items(project_path, contract)?;
-        let event_names = ABIItem::extract_event_names_and_signatures_from_abi(abi_items)?;
+        let mut event_names =
+            ABIItem::extract_event_names_and_signatures_from_abi(abi_items.clone())?;
+        if let Some(function_names) = &contract.include_function_calls {
+            event_names.extend(ABIItem::extract_function_calls_as_events_from_abi(
+                &abi_items,
+                function_names,
+            )?);
+        }
         let schema_name = generate_indexer_contract_schema_name(&indexer.name, &contract_name);
         let networks: Vec<&str> = contract.details.iter().map(|d| d.network.as_str()).collect();
 
@@ -186,12 +298,17 @@ pub fn generate_tables_for_indexer_sql(
                 &contract.name,
                 &schema_name,
                 event_matching_name_on_other,
+                contract.include_transaction_fields.unwrap_or(false),
             ));
         }
         // we still need to create the internal tables for the contract
         sql.push_str(&generate_internal_event_table_sql(&event_names, &schema_name, networks));
     }
 
+    if !disable_event_tables {
+        sql.push_str(&generate_entity_tables_sql(&indexer.name, &indexer.entities));
+    }
+
     sql.push_str(&format!(
         r#"
         CREATE TABLE IF NOT EXISTS rindexer_internal.{indexer_name}_last_known_relationship_dropping_sql (
@@ -212,6 +329,64 @@ pub fn generate_tables_for_indexer_sql(
         indexer_name = camel_to_snake(&indexer.name)
     ));
 
+    sql.push_str(&format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {} (
+            network TEXT PRIMARY KEY,
+            block_range NUMERIC NOT NULL
+        );
+    "#,
+        generate_block_range_table_name(&indexer.name)
+    ));
+
+    sql.push_str(&format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {} (
+            id TEXT PRIMARY KEY,
+            contract_name TEXT NOT NULL,
+            event_name TEXT NOT NULL,
+            network TEXT NOT NULL,
+            from_block NUMERIC NOT NULL,
+            to_block NUMERIC NOT NULL,
+            error TEXT NOT NULL,
+            attempts INT NOT NULL,
+            logs JSONB NOT NULL,
+            redrive BOOLEAN NOT NULL DEFAULT FALSE,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+        );
+    "#,
+        generate_dead_letters_table_name(&indexer.name)
+    ));
+
+    sql.push_str(&format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {} (
+            contract_name TEXT NOT NULL,
+            event_name TEXT NOT NULL,
+            network TEXT NOT NULL,
+            from_block NUMERIC NOT NULL,
+            to_block NUMERIC NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS {}_processed_ranges_event_idx ON {} (contract_name, event_name, network);
+    "#,
+        generate_processed_ranges_table_name(&indexer.name),
+        camel_to_snake(&indexer.name),
+        generate_processed_ranges_table_name(&indexer.name)
+    ));
+
+    sql.push_str(&format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {} (
+            id SERIAL PRIMARY KEY,
+            change TEXT NOT NULL,
+            sql TEXT,
+            destructive BOOLEAN NOT NULL,
+            applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+        );
+    "#,
+        generate_schema_migrations_table_name(&indexer.name)
+    ));
+
     Ok(Code::new(sql))
 }
 
@@ -224,6 +399,25 @@ pub fn generate_event_table_full_name(
     format!("{}.{}", schema_name, camel_to_snake(event_name))
 }
 
+/// The block range learnt while syncing historic logs for each network.
+pub fn generate_block_range_table_name(indexer_name: &str) -> String {
+    format!("rindexer_internal.{}_block_range", camel_to_snake(indexer_name))
+}
+
+/// The batches whose callback kept failing, see `indexing.max_callback_attempts`.
+pub fn generate_dead_letters_table_name(indexer_name: &str) -> String {
+    format!("rindexer_internal.{}_dead_letters", camel_to_snake(indexer_name))
+}
+
+/// The block ranges indexed for each event and network, used to find gaps.
+pub fn generate_processed_ranges_table_name(indexer_name: &str) -> String {
+    format!("rindexer_internal.{}_processed_ranges", camel_to_snake(indexer_name))
+}
+
+pub fn generate_schema_migrations_table_name(indexer_name: &str) -> String {
+    format!("rindexer_internal.{}_schema_migrations", camel_to_snake(indexer_name))
+}
+
 pub fn generate_event_table_columns_names_sql(column_names: &[String]) -> String {
     column_names.iter().map(|name| format!("\"{}\"", name)).collect::<Vec<String>>().join(", ")
 }
@@ -238,12 +432,57 @@ pub fn drop_tables_for_indexer_sql(project_path: &Path, indexer: &Indexer) -> Co
         camel_to_snake(&indexer.name)
     );
     sql.push_str(format!("DROP TABLE IF EXISTS rindexer_internal.{}_last_known_relationship_dropping_sql CASCADE;", camel_to_snake(&indexer.name)).as_str());
+    sql.push_str(&format!(
+        "DROP TABLE IF EXISTS {} CASCADE;",
+        generate_block_range_table_name(&indexer.name)
+    ));
+    sql.push_str(&format!(
+        "DROP TABLE IF EXISTS {} CASCADE;",
+        generate_dead_letters_table_name(&indexer.name)
+    ));
+    sql.push_str(&format!(
+        "DROP TABLE IF EXISTS {} CASCADE;",
+        generate_processed_ranges_table_name(&indexer.name)
+    ));
+    sql.push_str(&format!(
+        "DROP TABLE IF EXISTS {} CASCADE;",
+        generate_schema_migrations_table_name(&indexer.name)
+    ));
+
+    sql.push_str(&format!(
+        "DROP SCHEMA IF EXISTS {} CASCADE;",
+        generate_entities_schema_name(&indexer.name)
+    ));
+
+    for block_handler in &indexer.block_handlers {
+        sql.push_str(&format!(
+            "DROP TABLE IF EXISTS {} CASCADE;",
+            generate_block_handler_last_synced_table_name(&indexer.name, &block_handler.name)
+        ));
+
+        // the built-in native transfers handler stores its rows in a schema of its own
+        if block_handler.name == NativeTransfers::HANDLER_NAME {
+            sql.push_str(&format!(
+                "DROP SCHEMA IF EXISTS {} CASCADE;",
+                generate_indexer_contract_schema_name(&indexer.name, NativeTransfers::HANDLER_NAME)
+            ));
+        }
+    }
 
     for contract in &indexer.contracts {
         let contract_name = contract.before_modify_name_if_filter_readonly();
         let schema_name = generate_indexer_contract_schema_name(&indexer.name, &contract_name);
         sql.push_str(format!("DROP SCHEMA IF EXISTS {} CASCADE;", schema_name).as_str());
 
+        if contract.details.iter().any(|details| details.factory.is_some()) {
+            let (children_table, last_synced_table) =
+                generate_factory_children_table_names(&indexer.name, &contract_name);
+            sql.push_str(&format!(
+                "DROP TABLE IF EXISTS {} CASCADE;DROP TABLE IF EXISTS {} CASCADE;",
+                children_table, last_synced_table
+            ));
+        }
+
         // drop last synced blocks for contracts
         let abi_items = ABIItem::read_abi_items(project_path, contract);
         if let Ok(abi_items) = abi_items {
@@ -254,6 +493,18 @@ pub fn drop_tables_for_indexer_sql(project_path: &Path, indexer: &Indexer) -> Co
                         .as_str(),
                 );
             }
+
+            for function_name in contract.include_function_calls.iter().flatten() {
+                let table_name = format!(
+                    "{}_{}",
+                    schema_name,
+                    camel_to_snake(&function_call_event_name(function_name))
+                );
+                sql.push_str(&format!(
+                    "DROP TABLE IF EXISTS rindexer_internal.{} CASCADE;",
+                    table_name
+                ));
+            }
         } else {
             error!(
                 "Could not read ABI items for contract moving on clearing the other data up: {}",
@@ -104,7 +104,7 @@ pub enum GetLastKnownIndexesDroppingSqlError {
     CouldNotParseIndexesToJson(#[from] serde_json::Error),
 }
 
-async fn get_last_known_indexes_dropping_sql(
+pub(crate) async fn get_last_known_indexes_dropping_sql(
     client: &PostgresClient,
     manifest_name: &str,
 ) -> Result<Vec<Code>, GetLastKnownIndexesDroppingSqlError> {
@@ -192,15 +192,14 @@ pub enum PrepareIndexesError {
     SaveIndexesError(#[from] PostgresError),
 }
 
-pub async fn prepare_indexes(
+/// The indexes the manifest describes, nothing is dropped or saved.
+pub fn build_indexes(
     project_path: &Path,
     manifest_name: &str,
     postgres_indexes: &PostgresIndexes,
     contracts: &[Contract],
 ) -> Result<Vec<PostgresIndexResult>, PrepareIndexesError> {
     let mut index_results: Vec<PostgresIndexResult> = vec![];
-    let mut dropping_sql: Vec<Code> = vec![];
-    let client = Arc::new(PostgresClient::new().await?);
 
     // global first
     if let Some(global_injected_parameters) = &postgres_indexes.global_injected_parameters {
@@ -220,7 +219,6 @@ pub async fn prepare_indexes(
                         db_table_name: db_table_name.clone(),
                         db_table_columns: vec![global_parameter_column_name.clone()],
                     };
-                    dropping_sql.push(index_result.drop_index_sql());
                     index_results.push(index_result);
                 }
             }
@@ -255,7 +253,6 @@ pub async fn prepare_indexes(
                                     db_table_name: db_table_name.clone(),
                                     db_table_columns: vec![injected_parameter.clone()],
                                 };
-                                dropping_sql.push(index_result.drop_index_sql());
                                 index_results.push(index_result);
                             }
                         }
@@ -275,7 +272,6 @@ pub async fn prepare_indexes(
                                     db_table_name: db_table_name.clone(),
                                     db_table_columns: vec![injected_parameter.clone()],
                                 };
-                                dropping_sql.push(index_result.drop_index_sql());
                                 index_results.push(index_result);
                             }
                         }
@@ -295,7 +291,6 @@ pub async fn prepare_indexes(
                                 db_table_name: db_table_name.clone(),
                                 db_table_columns,
                             };
-                            dropping_sql.push(index_result.drop_index_sql());
                             index_results.push(index_result);
                         }
                     }
@@ -304,6 +299,20 @@ pub async fn prepare_indexes(
         }
     }
 
+    Ok(index_results)
+}
+
+pub async fn prepare_indexes(
+    project_path: &Path,
+    manifest_name: &str,
+    postgres_indexes: &PostgresIndexes,
+    contracts: &[Contract],
+) -> Result<Vec<PostgresIndexResult>, PrepareIndexesError> {
+    let index_results = build_indexes(project_path, manifest_name, postgres_indexes, contracts)?;
+    let dropping_sql: Vec<Code> =
+        index_results.iter().map(|index_result| index_result.drop_index_sql()).collect();
+    let client = PostgresClient::new().await?;
+
     let indexes_dropping_sql_json = serde_json::to_string(
         &dropping_sql.iter().map(|code| code.as_str()).collect::<Vec<&str>>(),
     )?;
@@ -0,0 +1,559 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::Path,
+};
+
+use tracing::info;
+
+use crate::{
+    abi::{ABIItem, ParamTypeError, ReadAbiError},
+    database::postgres::{
+        client::{PostgresClient, PostgresError},
+        entity::expected_entity_tables,
+        generate::{
+            generate_event_table_columns_with_types, generate_event_table_create_sql,
+            generate_indexer_contract_schema_name, generate_schema_migrations_table_name,
+        },
+    },
+    helpers::camel_to_snake,
+    indexer::Indexer,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum SchemaMigrationError {
+    #[error("{0}")]
+    PostgresError(#[from] PostgresError),
+
+    #[error("Could not read ABI items: {0}")]
+    CouldNotReadAbiItems(#[from] ReadAbiError),
+
+    #[error("Could not get ABI param types: {0}")]
+    ParamTypeError(#[from] ParamTypeError),
+
+    #[error("Refusing destructive schema changes, set `allow_destructive_migrations: true` on the postgres storage to apply them:\n{0}")]
+    DestructiveChangesNotAllowed(String),
+}
+
+/// An event table as the ABI and manifest describe it.
+#[derive(Debug, Clone)]
+pub struct ExpectedTable {
+    pub schema_name: String,
+    pub table_name: String,
+    pub columns: Vec<(String, String)>,
+    pub create_table_sql: String,
+    /// Event tables store a log once per network which a unique index enforces.
+    pub log_identity_index: bool,
+}
+
+impl ExpectedTable {
+    pub fn full_name(&self) -> String {
+        format!("{}.{}", self.schema_name, self.table_name)
+    }
+
+    fn log_identity_index_name(&self) -> String {
+        format!("{}_log_identity_idx", self.table_name)
+    }
+}
+
+/// A difference between the expected event tables and the database.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaChange {
+    CreateTable {
+        table_name: String,
+        sql: String,
+    },
+    AddColumn {
+        table_name: String,
+        column_name: String,
+        data_type: String,
+    },
+    AlterColumnType {
+        table_name: String,
+        column_name: String,
+        from_type: String,
+        data_type: String,
+    },
+    DropColumn {
+        table_name: String,
+        column_name: String,
+    },
+    /// Rows stored more than once before the log identity index existed, the first stored row
+    /// is kept.
+    RemoveDuplicateLogs {
+        table_name: String,
+        duplicate_rows: u64,
+    },
+    AddLogIdentityIndex {
+        table_name: String,
+        index_name: String,
+    },
+}
+
+/// Base columns which used to be stored as text or numeric, the values always fit the new type so
+/// converting them is not destructive.
+const BASE_COLUMN_CONVERSIONS: [(&str, &str, &str); 3] = [
+    ("block_number", "numeric", "int8"),
+    ("tx_index", "numeric", "int4"),
+    ("log_index", "varchar", "int4"),
+];
+
+impl SchemaChange {
+    /// Destructive changes can lose data so they are only applied when explicitly allowed.
+    pub fn is_destructive(&self) -> bool {
+        match self {
+            SchemaChange::AlterColumnType { column_name, from_type, data_type, .. } => {
+                !BASE_COLUMN_CONVERSIONS.contains(&(
+                    column_name.as_str(),
+                    from_type.as_str(),
+                    udt_name(data_type).as_str(),
+                ))
+            }
+            SchemaChange::DropColumn { .. } | SchemaChange::RemoveDuplicateLogs { .. } => true,
+            SchemaChange::CreateTable { .. } |
+            SchemaChange::AddColumn { .. } |
+            SchemaChange::AddLogIdentityIndex { .. } => false,
+        }
+    }
+
+    pub fn sql(&self) -> String {
+        match self {
+            SchemaChange::CreateTable { sql, .. } => sql.clone(),
+            SchemaChange::AddColumn { table_name, column_name, data_type } => format!(
+                "ALTER TABLE {} ADD COLUMN IF NOT EXISTS \"{}\" {};",
+                table_name, column_name, data_type
+            ),
+            SchemaChange::AlterColumnType { table_name, column_name, data_type, .. } => format!(
+                "ALTER TABLE {} ALTER COLUMN \"{}\" TYPE {} USING \"{}\"::{};",
+                table_name, column_name, data_type, column_name, data_type
+            ),
+            SchemaChange::DropColumn { table_name, column_name } => {
+                format!("ALTER TABLE {} DROP COLUMN IF EXISTS \"{}\";", table_name, column_name)
+            }
+            SchemaChange::RemoveDuplicateLogs { table_name, .. } => format!(
+                "DELETE FROM {table_name} a USING {table_name} b WHERE a.rindexer_id > b.rindexer_id AND a.network = b.network AND a.tx_hash = b.tx_hash AND a.log_index = b.log_index;"
+            ),
+            SchemaChange::AddLogIdentityIndex { table_name, index_name } => format!(
+                "CREATE UNIQUE INDEX IF NOT EXISTS {} ON {} (network, tx_hash, log_index);",
+                index_name, table_name
+            ),
+        }
+    }
+}
+
+impl fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaChange::CreateTable { table_name, .. } => {
+                write!(f, "create table {}", table_name)
+            }
+            SchemaChange::AddColumn { table_name, column_name, data_type } => {
+                write!(f, "add column {}.{} {}", table_name, column_name, data_type)
+            }
+            SchemaChange::AlterColumnType { table_name, column_name, from_type, data_type } => {
+                write!(
+                    f,
+                    "change column type {}.{} from {} to {}",
+                    table_name, column_name, from_type, data_type
+                )
+            }
+            SchemaChange::DropColumn { table_name, column_name } => {
+                write!(f, "drop column {}.{}", table_name, column_name)
+            }
+            SchemaChange::RemoveDuplicateLogs { table_name, duplicate_rows } => {
+                write!(f, "remove {} duplicate rows from {}", duplicate_rows, table_name)
+            }
+            SchemaChange::AddLogIdentityIndex { table_name, index_name } => {
+                write!(
+                    f,
+                    "add unique index {} on {} (network, tx_hash, log_index)",
+                    index_name, table_name
+                )
+            }
+        }
+    }
+}
+
+/// The `udt_name` postgres reports in `information_schema` for a type used in a `CREATE TABLE`.
+fn udt_name(data_type: &str) -> String {
+    if let Some(element_type) = data_type.strip_suffix("[]") {
+        return format!("_{}", udt_name(element_type));
+    }
+
+    let base_type = data_type.split('(').next().unwrap_or(data_type).trim().to_uppercase();
+    match base_type.as_str() {
+        "CHAR" | "CHARACTER" => "bpchar".to_string(),
+        "VARCHAR" => "varchar".to_string(),
+        "BOOLEAN" => "bool".to_string(),
+        "SMALLINT" => "int2".to_string(),
+        "INTEGER" | "SERIAL" => "int4".to_string(),
+        "BIGINT" | "BIGSERIAL" => "int8".to_string(),
+        other => other.to_lowercase(),
+    }
+}
+
+/// The event and entity tables of the indexer, the same tables `generate_tables_for_indexer_sql`
+/// creates.
+pub fn expected_event_tables(
+    project_path: &Path,
+    indexer: &Indexer,
+) -> Result<Vec<ExpectedTable>, SchemaMigrationError> {
+    let mut tables = vec![];
+
+    for contract in &indexer.contracts {
+        let contract_name = contract.before_modify_name_if_filter_readonly();
+        let abi_items = ABIItem::read_abi_items(project_path, contract)?;
+        let mut event_names =
+            ABIItem::extract_event_names_and_signatures_from_abi(abi_items.clone())?;
+        if let Some(function_names) = &contract.include_function_calls {
+            event_names.extend(ABIItem::extract_function_calls_as_events_from_abi(
+                &abi_items,
+                function_names,
+            )?);
+        }
+        let schema_name = generate_indexer_contract_schema_name(&indexer.name, &contract_name);
+        let include_transaction_fields = contract.include_transaction_fields.unwrap_or(false);
+
+        tables.extend(event_names.iter().map(|event_info| {
+            let table_name = camel_to_snake(&event_info.name);
+            ExpectedTable {
+                create_table_sql: generate_event_table_create_sql(
+                    &format!("{}.{}", schema_name, table_name),
+                    &event_info.inputs,
+                ),
+                schema_name: schema_name.clone(),
+                table_name,
+                columns: generate_event_table_columns_with_types(
+                    &event_info.inputs,
+                    include_transaction_fields,
+                ),
+                log_identity_index: true,
+            }
+        }));
+    }
+
+    tables.extend(expected_entity_tables(&indexer.name, &indexer.entities));
+
+    Ok(tables)
+}
+
+/// Compares the expected tables to the columns in the database keyed by full table name with
+/// their `udt_name`, columns which only exist in the database are dropped. `existing_indexes`
+/// holds the full names of the indexes in the database.
+pub fn diff_schema(
+    expected_tables: &[ExpectedTable],
+    existing_tables: &HashMap<String, Vec<(String, String)>>,
+    existing_indexes: &HashSet<String>,
+) -> Vec<SchemaChange> {
+    let mut changes = vec![];
+
+    for table in expected_tables {
+        let table_name = table.full_name();
+        let add_log_identity_index = table.log_identity_index &&
+            !existing_indexes.contains(&format!(
+                "{}.{}",
+                table.schema_name,
+                table.log_identity_index_name()
+            ));
+        let Some(existing_columns) = existing_tables.get(&table_name) else {
+            changes.push(SchemaChange::CreateTable {
+                table_name: table_name.clone(),
+                sql: table.create_table_sql.clone(),
+            });
+            if table.log_identity_index {
+                changes.push(SchemaChange::AddLogIdentityIndex {
+                    table_name,
+                    index_name: table.log_identity_index_name(),
+                });
+            }
+            continue;
+        };
+
+        for (column_name, data_type) in &table.columns {
+            match existing_columns.iter().find(|(name, _)| name == column_name) {
+                None => changes.push(SchemaChange::AddColumn {
+                    table_name: table_name.clone(),
+                    column_name: column_name.clone(),
+                    data_type: data_type.clone(),
+                }),
+                Some((_, existing_type)) if *existing_type != udt_name(data_type) => {
+                    changes.push(SchemaChange::AlterColumnType {
+                        table_name: table_name.clone(),
+                        column_name: column_name.clone(),
+                        from_type: existing_type.clone(),
+                        data_type: data_type.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        for (column_name, _) in existing_columns {
+            if !table.columns.iter().any(|(name, _)| name == column_name) {
+                changes.push(SchemaChange::DropColumn {
+                    table_name: table_name.clone(),
+                    column_name: column_name.clone(),
+                });
+            }
+        }
+
+        // added after the column changes as `log_index` may still be converted
+        if add_log_identity_index {
+            changes.push(SchemaChange::AddLogIdentityIndex {
+                table_name,
+                index_name: table.log_identity_index_name(),
+            });
+        }
+    }
+
+    changes
+}
+
+async fn existing_event_tables(
+    client: &PostgresClient,
+    expected_tables: &[ExpectedTable],
+) -> Result<HashMap<String, Vec<(String, String)>>, PostgresError> {
+    let mut schema_names: Vec<String> =
+        expected_tables.iter().map(|table| table.schema_name.clone()).collect();
+    schema_names.dedup();
+
+    let rows = client
+        .query(
+            "SELECT table_schema::TEXT, table_name::TEXT, column_name::TEXT, udt_name::TEXT FROM information_schema.columns WHERE table_schema = ANY($1) ORDER BY ordinal_position",
+            &[&schema_names],
+        )
+        .await?;
+
+    let mut tables: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    for row in rows {
+        let schema_name: String = row.get(0);
+        let table_name: String = row.get(1);
+        tables
+            .entry(format!("{}.{}", schema_name, table_name))
+            .or_default()
+            .push((row.get(2), row.get(3)));
+    }
+
+    Ok(tables)
+}
+
+async fn existing_indexes(
+    client: &PostgresClient,
+    expected_tables: &[ExpectedTable],
+) -> Result<HashSet<String>, PostgresError> {
+    let mut schema_names: Vec<String> =
+        expected_tables.iter().map(|table| table.schema_name.clone()).collect();
+    schema_names.dedup();
+
+    let rows = client
+        .query(
+            "SELECT schemaname::TEXT || '.' || indexname::TEXT FROM pg_indexes WHERE schemaname = ANY($1)",
+            &[&schema_names],
+        )
+        .await?;
+
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// How many rows have to be removed from the table before the log identity index can be added.
+async fn count_duplicate_logs(
+    client: &PostgresClient,
+    table_name: &str,
+) -> Result<u64, PostgresError> {
+    let row = client
+        .query_one(
+            &format!(
+                "SELECT COALESCE(SUM(count - 1), 0)::BIGINT FROM (SELECT COUNT(*) AS count FROM {} GROUP BY network, tx_hash, log_index HAVING COUNT(*) > 1) duplicates",
+                table_name
+            ),
+            &[],
+        )
+        .await?;
+
+    Ok(row.get::<_, i64>(0) as u64)
+}
+
+/// The changes needed to bring the event tables in the database in line with the ABIs.
+pub async fn plan_schema_migrations(
+    client: &PostgresClient,
+    project_path: &Path,
+    indexer: &Indexer,
+) -> Result<Vec<SchemaChange>, SchemaMigrationError> {
+    let expected_tables = expected_event_tables(project_path, indexer)?;
+    let existing_tables = existing_event_tables(client, &expected_tables).await?;
+    let existing_indexes = existing_indexes(client, &expected_tables).await?;
+
+    // tables created before the log identity index can hold the same log more than once
+    let mut changes = vec![];
+    for change in diff_schema(&expected_tables, &existing_tables, &existing_indexes) {
+        if let SchemaChange::AddLogIdentityIndex { table_name, .. } = &change {
+            if existing_tables.contains_key(table_name) {
+                let duplicate_rows = count_duplicate_logs(client, table_name).await?;
+                if duplicate_rows > 0 {
+                    changes.push(SchemaChange::RemoveDuplicateLogs {
+                        table_name: table_name.clone(),
+                        duplicate_rows,
+                    });
+                }
+            }
+        }
+        changes.push(change);
+    }
+
+    Ok(changes)
+}
+
+/// Refuses the plan when it has destructive changes which are not allowed.
+pub fn check_destructive_changes(
+    changes: &[SchemaChange],
+    allow_destructive_migrations: bool,
+) -> Result<(), SchemaMigrationError> {
+    let destructive_changes: Vec<String> = changes
+        .iter()
+        .filter(|change| change.is_destructive())
+        .map(|change| format!("  - {}", change))
+        .collect();
+
+    if destructive_changes.is_empty() || allow_destructive_migrations {
+        return Ok(());
+    }
+
+    Err(SchemaMigrationError::DestructiveChangesNotAllowed(destructive_changes.join("\n")))
+}
+
+/// Applies the changes in one transaction and records each of them, runs after the tables are
+/// created so creating a new table again is a no-op.
+pub async fn apply_schema_migrations(
+    client: &PostgresClient,
+    indexer: &Indexer,
+    changes: &[SchemaChange],
+) -> Result<(), SchemaMigrationError> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let migrations_table_name = generate_schema_migrations_table_name(&indexer.name);
+
+    client
+        .in_transaction(|_| async {
+            for change in changes {
+                info!("Applying schema migration: {}", change);
+                let sql = change.sql();
+                if let SchemaChange::RemoveDuplicateLogs { table_name, .. } = change {
+                    let removed = client.execute(&sql, &[]).await?;
+                    info!("Removed {} duplicate rows from {}", removed, table_name);
+                } else {
+                    client.batch_execute(&sql).await?;
+                }
+
+                client
+                    .execute(
+                        &format!(
+                            "INSERT INTO {} (change, sql, destructive) VALUES ($1, $2, $3)",
+                            migrations_table_name
+                        ),
+                        &[&change.to_string(), &sql, &change.is_destructive()],
+                    )
+                    .await?;
+            }
+
+            Ok::<(), PostgresError>(())
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_schema() {
+        let expected_tables = vec![
+            ExpectedTable {
+                schema_name: "indexer_contract".to_string(),
+                table_name: "transfer".to_string(),
+                columns: vec![
+                    ("rindexer_id".to_string(), "SERIAL".to_string()),
+                    ("from".to_string(), "CHAR(42)".to_string()),
+                    ("value".to_string(), "VARCHAR(78)".to_string()),
+                    ("ids".to_string(), "NUMERIC[]".to_string()),
+                    ("memo".to_string(), "TEXT".to_string()),
+                ],
+                create_table_sql: String::new(),
+                log_identity_index: true,
+            },
+            ExpectedTable {
+                schema_name: "indexer_contract".to_string(),
+                table_name: "approval".to_string(),
+                columns: vec![("rindexer_id".to_string(), "SERIAL".to_string())],
+                create_table_sql: "CREATE TABLE indexer_contract.approval ()".to_string(),
+                log_identity_index: false,
+            },
+        ];
+        let existing_tables = HashMap::from([(
+            "indexer_contract.transfer".to_string(),
+            vec![
+                ("rindexer_id".to_string(), "int4".to_string()),
+                ("from".to_string(), "bpchar".to_string()),
+                ("value".to_string(), "numeric".to_string()),
+                ("ids".to_string(), "_numeric".to_string()),
+                ("old".to_string(), "text".to_string()),
+            ],
+        )]);
+
+        let changes = diff_schema(&expected_tables, &existing_tables, &HashSet::new());
+        assert_eq!(
+            changes,
+            vec![
+                SchemaChange::AlterColumnType {
+                    table_name: "indexer_contract.transfer".to_string(),
+                    column_name: "value".to_string(),
+                    from_type: "numeric".to_string(),
+                    data_type: "VARCHAR(78)".to_string(),
+                },
+                SchemaChange::AddColumn {
+                    table_name: "indexer_contract.transfer".to_string(),
+                    column_name: "memo".to_string(),
+                    data_type: "TEXT".to_string(),
+                },
+                SchemaChange::DropColumn {
+                    table_name: "indexer_contract.transfer".to_string(),
+                    column_name: "old".to_string(),
+                },
+                SchemaChange::AddLogIdentityIndex {
+                    table_name: "indexer_contract.transfer".to_string(),
+                    index_name: "transfer_log_identity_idx".to_string(),
+                },
+                SchemaChange::CreateTable {
+                    table_name: "indexer_contract.approval".to_string(),
+                    sql: "CREATE TABLE indexer_contract.approval ()".to_string(),
+                },
+            ]
+        );
+
+        assert!(check_destructive_changes(&changes, false).is_err());
+        assert!(check_destructive_changes(&changes, true).is_ok());
+        assert!(check_destructive_changes(&changes[1..2], false).is_ok());
+
+        let base_column_conversion = SchemaChange::AlterColumnType {
+            table_name: "indexer_contract.transfer".to_string(),
+            column_name: "log_index".to_string(),
+            from_type: "varchar".to_string(),
+            data_type: "INTEGER".to_string(),
+        };
+        assert!(!base_column_conversion.is_destructive());
+
+        let existing_indexes =
+            HashSet::from(["indexer_contract.transfer_log_identity_idx".to_string()]);
+        assert!(!diff_schema(&expected_tables, &existing_tables, &existing_indexes)
+            .iter()
+            .any(|change| matches!(change, SchemaChange::AddLogIdentityIndex { .. })));
+        assert!(SchemaChange::RemoveDuplicateLogs {
+            table_name: "indexer_contract.transfer".to_string(),
+            duplicate_rows: 2,
+        }
+        .is_destructive());
+    }
+}
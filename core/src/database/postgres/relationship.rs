@@ -246,6 +246,15 @@ impl Relationship {
         )
     }
 
+    /// The sql `apply` runs once the historic indexing is done.
+    pub fn apply_sql(&self) -> Vec<Code> {
+        vec![
+            self.apply_unique_construct_sql(),
+            self.apply_foreign_key_construct_sql(),
+            self.apply_index_sql(),
+        ]
+    }
+
     pub async fn apply(&self, client: &PostgresClient) -> Result<(), PostgresError> {
         // apply on its own as it's in a DO block
         client.execute(self.apply_unique_construct_sql().as_str(), &[]).await?;
@@ -299,7 +308,7 @@ pub enum GetLastKnownRelationshipsDroppingSqlError {
     CouldNotParseRelationshipToJson(#[from] serde_json::Error),
 }
 
-async fn get_last_known_relationships_dropping_sql(
+pub(crate) async fn get_last_known_relationships_dropping_sql(
     client: &PostgresClient,
     manifest_name: &str,
 ) -> Result<Vec<Code>, GetLastKnownRelationshipsDroppingSqlError> {
@@ -353,14 +362,14 @@ pub async fn drop_last_known_relationships(
     Ok(())
 }
 
-pub async fn create_relationships(
+/// The relationships the manifest describes, nothing is dropped or saved.
+pub fn build_relationships(
     project_path: &Path,
     manifest_name: &str,
     contracts: &[Contract],
     foreign_keys: &[ForeignKeys],
 ) -> Result<Vec<Relationship>, CreateRelationshipError> {
     let mut relationships = vec![];
-    let mut dropping_sql: Vec<Code> = vec![];
     for foreign_key in foreign_keys {
         let contract = contracts.iter().find(|c| c.name == foreign_key.contract_name);
 
@@ -440,14 +449,27 @@ pub async fn create_relationships(
                         },
                     };
 
-                    let sql = relationship.drop_sql().await?;
-                    dropping_sql.extend(sql);
                     relationships.push(relationship);
                 }
             }
         }
     }
 
+    Ok(relationships)
+}
+
+pub async fn create_relationships(
+    project_path: &Path,
+    manifest_name: &str,
+    contracts: &[Contract],
+    foreign_keys: &[ForeignKeys],
+) -> Result<Vec<Relationship>, CreateRelationshipError> {
+    let relationships = build_relationships(project_path, manifest_name, contracts, foreign_keys)?;
+    let mut dropping_sql: Vec<Code> = vec![];
+    for relationship in &relationships {
+        dropping_sql.extend(relationship.drop_sql().await?);
+    }
+
     let relationships_dropping_sql_json = serde_json::to_string(
         &dropping_sql.iter().map(|code| code.as_str()).collect::<Vec<&str>>(),
     )?;
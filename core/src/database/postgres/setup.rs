@@ -6,8 +6,13 @@ use crate::{
     database::postgres::{
         client::{PostgresClient, PostgresConnectionError, PostgresError},
         generate::{generate_tables_for_indexer_sql, GenerateTablesForIndexerSqlError},
+        migration::{
+            apply_schema_migrations, check_destructive_changes, plan_schema_migrations,
+            SchemaMigrationError,
+        },
     },
     drop_tables_for_indexer_sql,
+    indexer::raw_logs::generate_raw_logs_table_sql,
     manifest::core::Manifest,
 };
 
@@ -21,6 +26,9 @@ pub enum SetupPostgresError {
 
     #[error("Error creating tables for indexer: {0}")]
     GeneratingTables(#[from] GenerateTablesForIndexerSqlError),
+
+    #[error("Error migrating tables for indexer: {0}")]
+    SchemaMigration(#[from] SchemaMigrationError),
 }
 
 pub async fn setup_postgres(
@@ -42,16 +50,25 @@ pub async fn setup_postgres(
         info!("Dropped all data for {}", manifest.name);
     }
 
+    let indexer = manifest.to_indexer();
+
+    // planned before the tables are created so new tables show up in the migrations
+    let schema_changes = if disable_event_tables {
+        vec![]
+    } else {
+        plan_schema_migrations(&client, project_path, &indexer).await?
+    };
+    check_destructive_changes(
+        &schema_changes,
+        manifest.storage.postgres_allow_destructive_migrations(),
+    )?;
+
     if !disable_event_tables {
         info!("Creating tables for {}", manifest.name);
     } else {
         info!("Creating internal rindexer tables for {}", manifest.name);
     }
-    let sql = generate_tables_for_indexer_sql(
-        project_path,
-        &manifest.to_indexer(),
-        disable_event_tables,
-    )?;
+    let sql = generate_tables_for_indexer_sql(project_path, &indexer, disable_event_tables)?;
     debug!("{}", sql);
     client.batch_execute(sql.as_str()).await?;
     if !disable_event_tables {
@@ -60,5 +77,11 @@ pub async fn setup_postgres(
         info!("Created internal rindexer tables for {}", manifest.name);
     }
 
+    apply_schema_migrations(&client, &indexer, &schema_changes).await?;
+
+    if manifest.storage.postgres_raw_logs_enabled() {
+        client.batch_execute(&generate_raw_logs_table_sql()).await?;
+    }
+
     Ok(client)
 }
@@ -13,7 +13,10 @@ use serde_json::{json, Value};
 use tokio_postgres::types::{to_sql_checked, IsNull, ToSql, Type as PgType};
 use tracing::error;
 
-use crate::{abi::ABIInput, event::callback_registry::TxInformation};
+use crate::{
+    abi::ABIInput,
+    event::callback_registry::{TransactionFields, TxInformation},
+};
 
 #[derive(Debug, Clone)]
 pub enum EthereumSqlTypeWrapper {
@@ -41,6 +44,7 @@ pub enum EthereumSqlTypeWrapper {
 
     // 64-bit integers
     U64(U64),
+    U64BigInt(U64),
     I64(i64),
     VecU64(Vec<U64>),
     VecI64(Vec<i64>),
@@ -104,6 +108,7 @@ pub enum EthereumSqlTypeWrapper {
     VecBytes(Vec<Bytes>),
 
     DateTime(DateTime<Utc>),
+    DateTimeNullable(Option<DateTime<Utc>>),
 
     JSONB(Value),
 }
@@ -135,6 +140,7 @@ impl EthereumSqlTypeWrapper {
 
             // 64-bit integers
             EthereumSqlTypeWrapper::U64(_) => "U64",
+            EthereumSqlTypeWrapper::U64BigInt(_) => "U64BigInt",
             EthereumSqlTypeWrapper::I64(_) => "I64",
             EthereumSqlTypeWrapper::VecU64(_) => "VecU64",
             EthereumSqlTypeWrapper::VecI64(_) => "VecI64",
@@ -198,6 +204,7 @@ impl EthereumSqlTypeWrapper {
             EthereumSqlTypeWrapper::VecBytes(_) => "VecBytes",
 
             EthereumSqlTypeWrapper::DateTime(_) => "DateTime",
+            EthereumSqlTypeWrapper::DateTimeNullable(_) => "DateTimeNullable",
 
             EthereumSqlTypeWrapper::JSONB(_) => "JSONB",
         }
@@ -229,6 +236,7 @@ impl EthereumSqlTypeWrapper {
 
             // 64-bit integers
             EthereumSqlTypeWrapper::U64(_) => PgType::INT8,
+            EthereumSqlTypeWrapper::U64BigInt(_) => PgType::INT8,
             EthereumSqlTypeWrapper::I64(_) => PgType::INT8,
             EthereumSqlTypeWrapper::VecU64(_) => PgType::INT8_ARRAY,
             EthereumSqlTypeWrapper::VecI64(_) => PgType::INT8_ARRAY,
@@ -301,7 +309,9 @@ impl EthereumSqlTypeWrapper {
             EthereumSqlTypeWrapper::VecBytes(_) => PgType::BYTEA_ARRAY,
 
             // DateTime
-            EthereumSqlTypeWrapper::DateTime(_) => PgType::TIMESTAMPTZ,
+            EthereumSqlTypeWrapper::DateTime(_) | EthereumSqlTypeWrapper::DateTimeNullable(_) => {
+                PgType::TIMESTAMPTZ
+            }
 
             EthereumSqlTypeWrapper::JSONB(_) => PgType::JSONB,
         }
@@ -319,6 +329,10 @@ impl ToSql for EthereumSqlTypeWrapper {
                 let value = value.to_string();
                 Decimal::to_sql(&value.parse::<Decimal>()?, ty, out)
             }
+            EthereumSqlTypeWrapper::U64BigInt(value) => {
+                let int_value: i64 = value.as_u64() as i64;
+                int_value.to_sql(ty, out)
+            }
             EthereumSqlTypeWrapper::I64(value) => value.to_sql(ty, out),
             EthereumSqlTypeWrapper::VecU64(values) => serialize_vec_decimal(values, ty, out),
             EthereumSqlTypeWrapper::VecI64(values) => {
@@ -702,6 +716,7 @@ impl ToSql for EthereumSqlTypeWrapper {
                 }
             }
             EthereumSqlTypeWrapper::DateTime(value) => value.to_sql(ty, out),
+            EthereumSqlTypeWrapper::DateTimeNullable(value) => value.to_sql(ty, out),
             EthereumSqlTypeWrapper::JSONB(value) => value.to_sql(ty, out),
         }
     }
@@ -849,6 +864,21 @@ pub fn solidity_type_to_ethereum_sql_type_wrapper(
     }
 }
 
+/// Maps the transaction fields to the wrappers for their columns, the order matches
+/// `generate_transaction_fields_column_names`.
+pub fn map_transaction_fields_to_ethereum_wrapper(
+    transaction_fields: Option<&TransactionFields>,
+) -> Vec<EthereumSqlTypeWrapper> {
+    let transaction_fields = transaction_fields.cloned().unwrap_or_default();
+    vec![
+        EthereumSqlTypeWrapper::Address(transaction_fields.from),
+        EthereumSqlTypeWrapper::AddressNullable(transaction_fields.to.unwrap_or_default()),
+        EthereumSqlTypeWrapper::U256(transaction_fields.value),
+        EthereumSqlTypeWrapper::U256(transaction_fields.gas_used),
+        EthereumSqlTypeWrapper::U256(transaction_fields.effective_gas_price),
+    ]
+}
+
 pub fn map_log_params_to_ethereum_wrapper(
     abi_inputs: &[ABIInput],
     params: &[LogParam],
@@ -1299,6 +1329,7 @@ pub fn map_ethereum_wrapper_to_json(
             } else {
                 let value = match wrapper {
                     EthereumSqlTypeWrapper::U64(u) => json!(u),
+                    EthereumSqlTypeWrapper::U64BigInt(u) => json!(u),
                     EthereumSqlTypeWrapper::VecU64(u64s) => json!(u64s),
                     EthereumSqlTypeWrapper::I64(i) => json!(i),
                     EthereumSqlTypeWrapper::VecI64(i64s) => json!(i64s),
@@ -1382,6 +1413,9 @@ pub fn map_ethereum_wrapper_to_json(
                     EthereumSqlTypeWrapper::DateTime(date_time) => {
                         json!(date_time.to_rfc3339())
                     }
+                    EthereumSqlTypeWrapper::DateTimeNullable(date_time) => {
+                        json!(date_time.map(|date_time| date_time.to_rfc3339()))
+                    }
                     EthereumSqlTypeWrapper::JSONB(json) => json.clone(),
                 };
                 result.insert(abi_input.name.clone(), value);
@@ -0,0 +1,129 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use rusqlite::{params_from_iter, types::FromSql, Connection, OptionalExtension};
+use tokio::task;
+
+use crate::database::postgres::{
+    generate::generate_event_table_columns_names_sql, sql_type_wrapper::EthereumSqlTypeWrapper,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum SqliteConnectionError {
+    #[error("Could not create the directory for the sqlite database: {0}")]
+    CouldNotCreateDirectory(#[from] std::io::Error),
+
+    #[error("Could not open the sqlite database: {0}")]
+    CouldNotOpenDatabase(#[from] rusqlite::Error),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SqliteError {
+    #[error("SqliteError {0}")]
+    SqliteError(#[from] rusqlite::Error),
+
+    #[error("The sqlite task failed: {0}")]
+    TaskFailed(#[from] task::JoinError),
+}
+
+/// A single connection to the sqlite file, sqlite only allows one writer at a time so the
+/// calls are queued on the connection and run on the blocking thread pool.
+#[derive(Clone)]
+pub struct SqliteClient {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteClient {
+    pub fn new(path: &Path) -> Result<Self, SqliteConnectionError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let connection = Connection::open(path)?;
+        // WAL lets readers query the file while rindexer writes to it
+        connection.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+        connection.busy_timeout(Duration::from_secs(30))?;
+
+        Ok(SqliteClient { connection: Arc::new(Mutex::new(connection)) })
+    }
+
+    async fn run<F, T>(&self, f: F) -> Result<T, SqliteError>
+    where
+        F: FnOnce(&mut Connection) -> Result<T, rusqlite::Error> + Send + 'static,
+        T: Send + 'static,
+    {
+        let connection = Arc::clone(&self.connection);
+        let result = task::spawn_blocking(move || {
+            let mut connection = connection.lock().unwrap_or_else(|e| e.into_inner());
+            f(&mut connection)
+        })
+        .await?;
+
+        Ok(result?)
+    }
+
+    pub async fn batch_execute(&self, sql: &str) -> Result<(), SqliteError> {
+        let sql = sql.to_string();
+        self.run(move |connection| connection.execute_batch(&sql)).await
+    }
+
+    pub async fn execute(
+        &self,
+        sql: &str,
+        params: Vec<EthereumSqlTypeWrapper>,
+    ) -> Result<usize, SqliteError> {
+        let sql = sql.to_string();
+        self.run(move |connection| connection.execute(&sql, params_from_iter(params))).await
+    }
+
+    /// The first column of the first row, `None` when the query returns no rows.
+    pub async fn query_one_or_none<T>(
+        &self,
+        sql: &str,
+        params: Vec<EthereumSqlTypeWrapper>,
+    ) -> Result<Option<T>, SqliteError>
+    where
+        T: FromSql + Send + 'static,
+    {
+        let sql = sql.to_string();
+        self.run(move |connection| {
+            connection.query_row(&sql, params_from_iter(params), |row| row.get(0)).optional()
+        })
+        .await
+    }
+
+    /// Inserts the rows in one transaction, rows which are already stored (a replayed batch)
+    /// are skipped.
+    pub async fn insert_bulk(
+        &self,
+        table_name: &str,
+        column_names: &[String],
+        bulk_data: Vec<Vec<EthereumSqlTypeWrapper>>,
+    ) -> Result<(), SqliteError> {
+        if bulk_data.is_empty() {
+            return Ok(());
+        }
+
+        let sql = format!(
+            "INSERT OR IGNORE INTO \"{}\" ({}) VALUES ({})",
+            table_name,
+            generate_event_table_columns_names_sql(column_names),
+            (1..=column_names.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ")
+        );
+
+        self.run(move |connection| {
+            let transaction = connection.transaction()?;
+            {
+                let mut statement = transaction.prepare_cached(&sql)?;
+                for row in &bulk_data {
+                    statement.execute(params_from_iter(row))?;
+                }
+            }
+            transaction.commit()
+        })
+        .await
+    }
+}
@@ -0,0 +1,215 @@
+use std::path::Path;
+
+use tracing::{error, info};
+
+use crate::{
+    abi::{ABIInput, ABIItem},
+    database::postgres::generate::{
+        generate_event_table_columns_with_types, generate_indexer_contract_schema_name,
+        GenerateTablesForIndexerSqlError,
+    },
+    helpers::camel_to_snake,
+    indexer::Indexer,
+    manifest::contract::Contract,
+    types::code::Code,
+};
+
+/// SQLite has no schemas so the event tables are named like the postgres schema and table
+/// joined together.
+pub fn generate_sqlite_event_table_name(
+    indexer_name: &str,
+    contract_name: &str,
+    event_name: &str,
+) -> String {
+    format!(
+        "{}_{}",
+        generate_indexer_contract_schema_name(indexer_name, contract_name),
+        camel_to_snake(event_name)
+    )
+}
+
+/// The last synced block of each event table and network.
+pub fn generate_sqlite_last_synced_blocks_table_name(indexer_name: &str) -> String {
+    format!("rindexer_internal_{}_last_synced_blocks", camel_to_snake(indexer_name))
+}
+
+/// The SQLite type of a postgres column type. Numbers which can be wider than 64 bits are
+/// stored as TEXT as SQLite would turn them into a lossy REAL, arrays are stored as JSON text.
+pub fn postgres_type_to_sqlite_type(data_type: &str) -> &'static str {
+    if data_type.ends_with("[]") {
+        return "TEXT";
+    }
+
+    match data_type {
+        "SERIAL" | "SMALLINT" | "INTEGER" | "BIGINT" | "BOOLEAN" => "INTEGER",
+        "BYTEA" => "BLOB",
+        _ => "TEXT",
+    }
+}
+
+pub fn generate_sqlite_event_table_create_sql(
+    table_name: &str,
+    inputs: &[ABIInput],
+    include_transaction_fields: bool,
+) -> String {
+    let not_null = [
+        "contract_address",
+        "tx_hash",
+        "block_number",
+        "block_hash",
+        "network",
+        "tx_index",
+        "log_index",
+    ];
+
+    let columns = generate_event_table_columns_with_types(inputs, include_transaction_fields)
+        .into_iter()
+        .map(|(name, data_type)| {
+            if name == "rindexer_id" {
+                format!("\"{}\" INTEGER PRIMARY KEY", name)
+            } else if not_null.contains(&name.as_str()) {
+                format!("\"{}\" {} NOT NULL", name, postgres_type_to_sqlite_type(&data_type))
+            } else {
+                format!("\"{}\" {}", name, postgres_type_to_sqlite_type(&data_type))
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    // a log is stored once per network so replaying a batch can not duplicate it
+    format!(
+        "CREATE TABLE IF NOT EXISTS \"{table_name}\" ({columns});\
+         CREATE UNIQUE INDEX IF NOT EXISTS \"{table_name}_log_identity_idx\" ON \"{table_name}\" (network, tx_hash, log_index);"
+    )
+}
+
+struct SqliteEventTable {
+    table_name: String,
+    inputs: Vec<ABIInput>,
+    include_transaction_fields: bool,
+    networks: Vec<String>,
+}
+
+fn sqlite_event_tables_for_contract(
+    project_path: &Path,
+    indexer: &Indexer,
+    contract: &Contract,
+) -> Result<Vec<SqliteEventTable>, GenerateTablesForIndexerSqlError> {
+    let contract_name = contract.before_modify_name_if_filter_readonly();
+    let abi_items = ABIItem::read_abi_items(project_path, contract)?;
+    let mut event_names = ABIItem::extract_event_names_and_signatures_from_abi(abi_items.clone())?;
+    if let Some(function_names) = &contract.include_function_calls {
+        event_names.extend(ABIItem::extract_function_calls_as_events_from_abi(
+            &abi_items,
+            function_names,
+        )?);
+    }
+
+    Ok(event_names
+        .into_iter()
+        .map(|event_info| SqliteEventTable {
+            table_name: generate_sqlite_event_table_name(
+                &indexer.name,
+                &contract_name,
+                &event_info.name,
+            ),
+            inputs: event_info.inputs,
+            include_transaction_fields: contract.include_transaction_fields.unwrap_or_default(),
+            networks: contract.details.iter().map(|d| d.network.clone()).collect(),
+        })
+        .collect())
+}
+
+pub fn generate_sqlite_tables_for_indexer_sql(
+    project_path: &Path,
+    indexer: &Indexer,
+) -> Result<Code, GenerateTablesForIndexerSqlError> {
+    let last_synced_blocks_table = generate_sqlite_last_synced_blocks_table_name(&indexer.name);
+    let mut sql = format!(
+        "CREATE TABLE IF NOT EXISTS \"{}\" (\
+         event_table TEXT NOT NULL, \
+         network TEXT NOT NULL, \
+         last_synced_block INTEGER NOT NULL, \
+         PRIMARY KEY (event_table, network)\
+        );",
+        last_synced_blocks_table
+    );
+
+    for contract in &indexer.contracts {
+        for table in sqlite_event_tables_for_contract(project_path, indexer, contract)? {
+            info!("Creating sqlite table if not exists: {}", table.table_name);
+            sql.push_str(&generate_sqlite_event_table_create_sql(
+                &table.table_name,
+                &table.inputs,
+                table.include_transaction_fields,
+            ));
+            for network in &table.networks {
+                sql.push_str(&format!(
+                    "INSERT OR IGNORE INTO \"{}\" (event_table, network, last_synced_block) VALUES ('{}', '{}', 0);",
+                    last_synced_blocks_table, table.table_name, network
+                ));
+            }
+        }
+    }
+
+    Ok(Code::new(sql))
+}
+
+pub fn drop_sqlite_tables_for_indexer_sql(project_path: &Path, indexer: &Indexer) -> Code {
+    let mut sql = format!(
+        "DROP TABLE IF EXISTS \"{}\";",
+        generate_sqlite_last_synced_blocks_table_name(&indexer.name)
+    );
+
+    for contract in &indexer.contracts {
+        match sqlite_event_tables_for_contract(project_path, indexer, contract) {
+            Ok(tables) => {
+                for table in tables {
+                    sql.push_str(&format!("DROP TABLE IF EXISTS \"{}\";", table.table_name));
+                }
+            }
+            Err(e) => {
+                error!(
+                    "Could not read ABI items for contract moving on clearing the other data up: {} - {}",
+                    contract.name, e
+                );
+            }
+        }
+    }
+
+    Code::new(sql)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_sqlite_event_table_create_sql() {
+        let inputs: Vec<ABIInput> = [("from", "address"), ("value", "uint256"), ("data", "bytes")]
+            .iter()
+            .map(|(name, type_)| ABIInput {
+                indexed: None,
+                name: name.to_string(),
+                type_: type_.to_string(),
+                components: None,
+            })
+            .collect();
+
+        let table_name =
+            generate_sqlite_event_table_name("RocketPool", "RocketPoolETH", "Transfer");
+        assert_eq!(table_name, "rocket_pool_rocket_pool_eth_transfer");
+
+        assert_eq!(
+            generate_sqlite_event_table_create_sql(&table_name, &inputs, false),
+            "CREATE TABLE IF NOT EXISTS \"rocket_pool_rocket_pool_eth_transfer\" (\
+             \"rindexer_id\" INTEGER PRIMARY KEY, \"contract_address\" TEXT NOT NULL, \
+             \"from\" TEXT, \"value\" TEXT, \"data\" BLOB, \"tx_hash\" TEXT NOT NULL, \
+             \"block_number\" INTEGER NOT NULL, \"block_hash\" TEXT NOT NULL, \
+             \"network\" TEXT NOT NULL, \"tx_index\" INTEGER NOT NULL, \
+             \"log_index\" INTEGER NOT NULL, \"block_timestamp\" TEXT);\
+             CREATE UNIQUE INDEX IF NOT EXISTS \"rocket_pool_rocket_pool_eth_transfer_log_identity_idx\" \
+             ON \"rocket_pool_rocket_pool_eth_transfer\" (network, tx_hash, log_index);"
+        );
+    }
+}
@@ -0,0 +1,4 @@
+pub mod client;
+pub mod generate;
+pub mod setup;
+pub mod sql_type_wrapper;
@@ -0,0 +1,43 @@
+use std::path::Path;
+
+use tracing::{debug, info};
+
+use crate::{
+    database::{
+        postgres::generate::GenerateTablesForIndexerSqlError,
+        sqlite::{
+            client::{SqliteClient, SqliteConnectionError, SqliteError},
+            generate::generate_sqlite_tables_for_indexer_sql,
+        },
+    },
+    manifest::core::Manifest,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum SetupSqliteError {
+    #[error("{0}")]
+    SqliteConnection(#[from] SqliteConnectionError),
+
+    #[error("{0}")]
+    SqliteError(#[from] SqliteError),
+
+    #[error("Error creating sqlite tables for indexer: {0}")]
+    GeneratingTables(#[from] GenerateTablesForIndexerSqlError),
+}
+
+pub async fn setup_sqlite(
+    project_path: &Path,
+    manifest: &Manifest,
+    path: &Path,
+) -> Result<SqliteClient, SetupSqliteError> {
+    info!("Setting up sqlite at {}", path.display());
+    let client = SqliteClient::new(path)?;
+
+    info!("Creating sqlite tables for {}", manifest.name);
+    let sql = generate_sqlite_tables_for_indexer_sql(project_path, &manifest.to_indexer())?;
+    debug!("{}", sql);
+    client.batch_execute(sql.as_str()).await?;
+    info!("Created sqlite tables for {}", manifest.name);
+
+    Ok(client)
+}
@@ -0,0 +1,230 @@
+use ethers::types::{I256, U256};
+use rusqlite::types::{ToSql, ToSqlOutput, Value};
+use serde_json::json;
+
+use crate::database::postgres::sql_type_wrapper::EthereumSqlTypeWrapper;
+
+fn integer(value: impl Into<i64>) -> Value {
+    Value::Integer(value.into())
+}
+
+fn text(value: impl ToString) -> Value {
+    Value::Text(value.to_string())
+}
+
+/// Arrays have no SQLite type so they are stored as a JSON array, empty arrays are NULL like
+/// in postgres.
+fn json_array<T>(values: &[T], to_json: impl Fn(&T) -> serde_json::Value) -> Value {
+    if values.is_empty() {
+        return Value::Null;
+    }
+    Value::Text(serde_json::Value::Array(values.iter().map(to_json).collect()).to_string())
+}
+
+fn u256_bytes(value: &U256) -> Vec<u8> {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    bytes.to_vec()
+}
+
+fn i256_bytes(value: &I256) -> Vec<u8> {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    bytes.to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+impl EthereumSqlTypeWrapper {
+    /// The value stored in SQLite, integers which do not fit in 64 bits are kept as decimal
+    /// text so no precision is lost.
+    pub fn to_sqlite_value(&self) -> Value {
+        match self {
+            EthereumSqlTypeWrapper::Bool(value) => integer(*value),
+            EthereumSqlTypeWrapper::VecBool(values) => json_array(values, |v| json!(v)),
+
+            EthereumSqlTypeWrapper::U8(value) => integer(*value),
+            EthereumSqlTypeWrapper::I8(value) => integer(*value),
+            EthereumSqlTypeWrapper::VecU8(values) => json_array(values, |v| json!(v)),
+            EthereumSqlTypeWrapper::VecI8(values) => json_array(values, |v| json!(v)),
+
+            EthereumSqlTypeWrapper::U16(value) => integer(*value),
+            EthereumSqlTypeWrapper::I16(value) => integer(*value),
+            EthereumSqlTypeWrapper::VecU16(values) => json_array(values, |v| json!(v)),
+            EthereumSqlTypeWrapper::VecI16(values) => json_array(values, |v| json!(v)),
+
+            EthereumSqlTypeWrapper::U32(value) => integer(*value),
+            EthereumSqlTypeWrapper::I32(value) => integer(*value),
+            EthereumSqlTypeWrapper::VecU32(values) => json_array(values, |v| json!(v)),
+            EthereumSqlTypeWrapper::VecI32(values) => json_array(values, |v| json!(v)),
+
+            EthereumSqlTypeWrapper::U64(value) | EthereumSqlTypeWrapper::U64BigInt(value) => {
+                match i64::try_from(value.as_u64()) {
+                    Ok(value) => integer(value),
+                    Err(_) => text(value),
+                }
+            }
+            EthereumSqlTypeWrapper::I64(value) => integer(*value),
+            EthereumSqlTypeWrapper::VecU64(values) => json_array(values, |v| json!(v.to_string())),
+            EthereumSqlTypeWrapper::VecI64(values) => json_array(values, |v| json!(v)),
+
+            EthereumSqlTypeWrapper::U128(value) => text(value),
+            EthereumSqlTypeWrapper::I128(value) => text(value),
+            EthereumSqlTypeWrapper::VecU128(values) => json_array(values, |v| json!(v.to_string())),
+            EthereumSqlTypeWrapper::VecI128(values) => json_array(values, |v| json!(v.to_string())),
+
+            EthereumSqlTypeWrapper::U256(value) => text(value),
+            EthereumSqlTypeWrapper::U256Nullable(value) => {
+                if value.is_zero() {
+                    Value::Null
+                } else {
+                    text(value)
+                }
+            }
+            EthereumSqlTypeWrapper::U256Bytes(value) => Value::Blob(u256_bytes(value)),
+            EthereumSqlTypeWrapper::U256BytesNullable(value) => {
+                if value.is_zero() {
+                    Value::Null
+                } else {
+                    Value::Blob(u256_bytes(value))
+                }
+            }
+            EthereumSqlTypeWrapper::I256(value) => text(value),
+            EthereumSqlTypeWrapper::I256Nullable(value) => {
+                if value.is_zero() {
+                    Value::Null
+                } else {
+                    text(value)
+                }
+            }
+            EthereumSqlTypeWrapper::I256Bytes(value) => Value::Blob(i256_bytes(value)),
+            EthereumSqlTypeWrapper::I256BytesNullable(value) => {
+                if value.is_zero() {
+                    Value::Null
+                } else {
+                    Value::Blob(i256_bytes(value))
+                }
+            }
+            EthereumSqlTypeWrapper::VecU256(values) => json_array(values, |v| json!(v.to_string())),
+            EthereumSqlTypeWrapper::VecU256Bytes(values) => {
+                json_array(values, |v| json!(hex(&u256_bytes(v))))
+            }
+            EthereumSqlTypeWrapper::VecI256(values) => json_array(values, |v| json!(v.to_string())),
+            EthereumSqlTypeWrapper::VecI256Bytes(values) => {
+                json_array(values, |v| json!(hex(&i256_bytes(v))))
+            }
+
+            EthereumSqlTypeWrapper::U512(value) => text(value),
+            EthereumSqlTypeWrapper::VecU512(values) => json_array(values, |v| json!(v.to_string())),
+
+            EthereumSqlTypeWrapper::H128(value) => text(format!("{:?}", value)),
+            EthereumSqlTypeWrapper::H160(value) => text(format!("{:?}", value)),
+            EthereumSqlTypeWrapper::H256(value) => text(format!("{:?}", value)),
+            EthereumSqlTypeWrapper::H256Bytes(value) => Value::Blob(value.as_bytes().to_vec()),
+            EthereumSqlTypeWrapper::H512(value) => text(format!("{:?}", value)),
+            EthereumSqlTypeWrapper::VecH128(values) => {
+                json_array(values, |v| json!(format!("{:?}", v)))
+            }
+            EthereumSqlTypeWrapper::VecH160(values) => {
+                json_array(values, |v| json!(format!("{:?}", v)))
+            }
+            EthereumSqlTypeWrapper::VecH256(values) |
+            EthereumSqlTypeWrapper::VecH256Bytes(values) => {
+                json_array(values, |v| json!(format!("{:?}", v)))
+            }
+            EthereumSqlTypeWrapper::VecH512(values) => {
+                json_array(values, |v| json!(format!("{:?}", v)))
+            }
+
+            EthereumSqlTypeWrapper::Address(value) => text(format!("{:?}", value)),
+            EthereumSqlTypeWrapper::AddressNullable(value) => {
+                if value.is_zero() {
+                    Value::Null
+                } else {
+                    text(format!("{:?}", value))
+                }
+            }
+            EthereumSqlTypeWrapper::AddressBytes(value) => Value::Blob(value.as_bytes().to_vec()),
+            EthereumSqlTypeWrapper::AddressBytesNullable(value) => {
+                if value.is_zero() {
+                    Value::Null
+                } else {
+                    Value::Blob(value.as_bytes().to_vec())
+                }
+            }
+            EthereumSqlTypeWrapper::VecAddress(values) |
+            EthereumSqlTypeWrapper::VecAddressBytes(values) => {
+                json_array(values, |v| json!(format!("{:?}", v)))
+            }
+
+            EthereumSqlTypeWrapper::String(value) |
+            EthereumSqlTypeWrapper::StringVarchar(value) |
+            EthereumSqlTypeWrapper::StringChar(value) => text(value),
+            EthereumSqlTypeWrapper::StringNullable(value) |
+            EthereumSqlTypeWrapper::StringVarcharNullable(value) |
+            EthereumSqlTypeWrapper::StringCharNullable(value) => {
+                if value.is_empty() {
+                    Value::Null
+                } else {
+                    text(value)
+                }
+            }
+            EthereumSqlTypeWrapper::VecString(values) |
+            EthereumSqlTypeWrapper::VecStringVarchar(values) |
+            EthereumSqlTypeWrapper::VecStringChar(values) => json_array(values, |v| json!(v)),
+            EthereumSqlTypeWrapper::Bytes(value) => Value::Blob(value.to_vec()),
+            EthereumSqlTypeWrapper::BytesNullable(value) => {
+                if value.is_empty() {
+                    Value::Null
+                } else {
+                    Value::Blob(value.to_vec())
+                }
+            }
+            EthereumSqlTypeWrapper::VecBytes(values) => json_array(values, |v| json!(hex(v))),
+
+            EthereumSqlTypeWrapper::DateTime(value) => text(value.to_rfc3339()),
+            EthereumSqlTypeWrapper::DateTimeNullable(value) => {
+                value.map_or(Value::Null, |value| text(value.to_rfc3339()))
+            }
+
+            EthereumSqlTypeWrapper::JSONB(value) => text(value),
+        }
+    }
+}
+
+impl ToSql for EthereumSqlTypeWrapper {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(self.to_sqlite_value()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::{Address, U64};
+
+    use super::*;
+
+    #[test]
+    fn test_to_sqlite_value() {
+        assert_eq!(EthereumSqlTypeWrapper::Bool(true).to_sqlite_value(), Value::Integer(1));
+        assert_eq!(
+            EthereumSqlTypeWrapper::U64(U64::from(u64::MAX)).to_sqlite_value(),
+            Value::Text(u64::MAX.to_string())
+        );
+        assert_eq!(
+            EthereumSqlTypeWrapper::U256(U256::exp10(30)).to_sqlite_value(),
+            Value::Text(format!("1{}", "0".repeat(30)))
+        );
+        assert_eq!(
+            EthereumSqlTypeWrapper::AddressNullable(Address::zero()).to_sqlite_value(),
+            Value::Null
+        );
+        assert_eq!(
+            EthereumSqlTypeWrapper::VecU256(vec![U256::one(), U256::from(2)]).to_sqlite_value(),
+            Value::Text(r#"["1","2"]"#.to_string())
+        );
+        assert_eq!(EthereumSqlTypeWrapper::VecAddress(vec![]).to_sqlite_value(), Value::Null);
+    }
+}
@@ -0,0 +1,120 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use ethers::types::{Block, Transaction, H256};
+use futures::future::BoxFuture;
+use tokio::time::sleep;
+use tracing::{debug, error, info};
+
+use crate::{
+    event::callback_registry::{CallbackTransaction, EventCallbackResult},
+    is_running,
+};
+
+#[derive(Debug, Clone)]
+pub struct BlockResult {
+    pub network: String,
+    pub block: Block<H256>,
+    // only populated when the block handler has `include_transactions` enabled
+    pub transactions: Vec<Transaction>,
+}
+
+impl BlockResult {
+    pub fn block_timestamp_to_datetime(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(i64::try_from(self.block.timestamp.low_u64()).ok()?, 0)
+    }
+}
+
+pub type BlockCallbackType =
+    Arc<dyn Fn(Vec<BlockResult>) -> BoxFuture<'static, EventCallbackResult<()>> + Send + Sync>;
+
+pub struct BlockCallbackRegistryInformation {
+    /// Matches the name of the block handler in the manifest.
+    pub name: String,
+    pub callback: BlockCallbackType,
+}
+
+impl Clone for BlockCallbackRegistryInformation {
+    fn clone(&self) -> Self {
+        BlockCallbackRegistryInformation {
+            name: self.name.clone(),
+            callback: Arc::clone(&self.callback),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct BlockCallbackRegistry {
+    pub handlers: Vec<BlockCallbackRegistryInformation>,
+}
+
+impl BlockCallbackRegistry {
+    pub fn new() -> Self {
+        BlockCallbackRegistry { handlers: Vec::new() }
+    }
+
+    pub fn find_handler(&self, name: &str) -> Option<&BlockCallbackRegistryInformation> {
+        self.handlers.iter().find(|h| h.name == name)
+    }
+
+    pub fn register_block_handler(&mut self, handler: BlockCallbackRegistryInformation) {
+        self.handlers.push(handler);
+    }
+
+    /// Calls the block handler retrying failures with a capped backoff, with a transaction each
+    /// attempt runs inside it together with its `before_commit`.
+    pub async fn trigger_block_handler(
+        &self,
+        name: &str,
+        data: Vec<BlockResult>,
+        transaction: Option<CallbackTransaction<'_>>,
+    ) {
+        let mut attempts = 0;
+        let mut delay = Duration::from_millis(100);
+
+        if let Some(handler_information) = self.find_handler(name) {
+            debug!("{} - Pushed {} blocks", name, data.len());
+
+            loop {
+                if !is_running() {
+                    info!("Detected shutdown, stopping block handler trigger");
+                    break;
+                }
+
+                let callback = (handler_information.callback)(data.clone());
+                let result = match &transaction {
+                    Some(transaction) => transaction.run(callback).await,
+                    None => callback.await,
+                };
+
+                match result {
+                    Ok(_) => {
+                        debug!("Block processing succeeded for block handler: {}", name);
+                        break;
+                    }
+                    Err(e) => {
+                        if !is_running() {
+                            info!("Detected shutdown, stopping block handler trigger");
+                            break;
+                        }
+                        attempts += 1;
+                        error!(
+                            "{} Block processing failed. Retrying... (attempt {}). Error: {}",
+                            name, attempts, e
+                        );
+
+                        delay = (delay * 2).min(Duration::from_secs(15));
+
+                        sleep(delay).await;
+                    }
+                }
+            }
+        } else {
+            error!("BlockCallbackRegistry: No block handler found for name: {}", name);
+        }
+    }
+
+    pub fn complete(&self) -> Arc<Self> {
+        Arc::new(self.clone())
+    }
+}
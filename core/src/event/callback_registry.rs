@@ -1,5 +1,6 @@
 use std::{any::Any, sync::Arc, time::Duration};
 
+use chrono::{DateTime, Utc};
 use ethers::{
     addressbook::Address,
     contract::LogMeta,
@@ -11,10 +12,12 @@ use tokio::time::sleep;
 use tracing::{debug, error, info};
 
 use crate::{
+    database::postgres::client::PostgresError,
     event::contract_setup::{ContractInformation, NetworkContract},
     indexer::start::ProcessedNetworkContract,
     is_running,
     provider::WrappedLog,
+    PostgresClient,
 };
 
 pub type Decoder = Arc<dyn Fn(Vec<H256>, Bytes) -> Arc<dyn Any + Send + Sync> + Send + Sync>;
@@ -35,6 +38,41 @@ pub struct TxInformation {
     pub transaction_hash: H256,
     pub log_index: U256,
     pub transaction_index: U64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction_fields: Option<TransactionFields>,
+}
+
+/// Fields from the transaction and its receipt, only populated when the contract has
+/// `include_transaction_fields` enabled.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct TransactionFields {
+    pub from: Address,
+    pub to: Option<Address>,
+    pub value: U256,
+    pub gas_used: U256,
+    pub effective_gas_price: U256,
+}
+
+impl TransactionFields {
+    /// The csv values for the transaction fields, the order matches the column names.
+    pub fn csv_values(&self) -> Vec<String> {
+        vec![
+            format!("{:?}", self.from),
+            self.to.map(|to| format!("{:?}", to)).unwrap_or_default(),
+            self.value.to_string(),
+            self.gas_used.to_string(),
+            self.effective_gas_price.to_string(),
+        ]
+    }
+}
+
+impl TxInformation {
+    /// The block timestamp as a UTC date time, `None` if it could not be resolved.
+    pub fn block_timestamp_to_datetime(&self) -> Option<DateTime<Utc>> {
+        self.block_timestamp.and_then(|timestamp| {
+            DateTime::from_timestamp(i64::try_from(timestamp.low_u64()).ok()?, 0)
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -72,6 +110,7 @@ impl EventResult {
                 transaction_hash: log_meta.transaction_hash,
                 transaction_index: log_meta.transaction_index,
                 log_index: log_meta.log_index,
+                transaction_fields: None,
             },
             found_in_request: LogFoundInRequest { from_block: start_block, to_block: end_block },
         }
@@ -88,6 +127,8 @@ pub struct EventCallbackRegistryInformation {
     pub topic_id: H256,
     pub event_name: String,
     pub index_event_in_order: bool,
+    /// Set when the event is a function call indexed from traces, holds the function selector.
+    pub function_call_selector: Option<[u8; 4]>,
     pub contract: ContractInformation,
     pub callback: EventCallbackType,
 }
@@ -106,12 +147,44 @@ impl Clone for EventCallbackRegistryInformation {
             topic_id: self.topic_id,
             event_name: self.event_name.clone(),
             index_event_in_order: self.index_event_in_order,
+            function_call_selector: self.function_call_selector,
             contract: self.contract.clone(),
             callback: Arc::clone(&self.callback),
         }
     }
 }
 
+#[derive(thiserror::Error, Debug)]
+enum CallbackTransactionError {
+    #[error("{0}")]
+    Callback(String),
+
+    #[error("Could not commit the transaction: {0}")]
+    PostgresError(#[from] PostgresError),
+}
+
+/// Runs every attempt of a callback in its own postgres transaction, `before_commit` runs inside
+/// the same transaction so its writes are committed together with the writes of the callback.
+pub struct CallbackTransaction<'a> {
+    pub database: &'a PostgresClient,
+    pub before_commit: &'a (dyn Fn() -> BoxFuture<'a, EventCallbackResult<()>> + Send + Sync),
+}
+
+impl CallbackTransaction<'_> {
+    pub(crate) async fn run(
+        &self,
+        callback: BoxFuture<'static, EventCallbackResult<()>>,
+    ) -> EventCallbackResult<()> {
+        self.database
+            .in_transaction(|_| async {
+                callback.await.map_err(CallbackTransactionError::Callback)?;
+                (self.before_commit)().await.map_err(CallbackTransactionError::Callback)
+            })
+            .await
+            .map_err(|e: CallbackTransactionError| e.to_string())
+    }
+}
+
 #[derive(Clone)]
 pub struct EventCallbackRegistry {
     pub events: Vec<EventCallbackRegistryInformation>,
@@ -136,7 +209,15 @@ impl EventCallbackRegistry {
         self.events.push(event);
     }
 
-    pub async fn trigger_event(&self, id: &String, data: Vec<EventResult>) {
+    /// Calls the event callback retrying failures with a capped backoff, when `max_attempts` is
+    /// set it gives up after that many attempts and returns the last error.
+    pub async fn trigger_event(
+        &self,
+        id: &String,
+        data: &[EventResult],
+        max_attempts: Option<u32>,
+        transaction: Option<CallbackTransaction<'_>>,
+    ) -> EventCallbackResult<()> {
         let mut attempts = 0;
         let mut delay = Duration::from_millis(100);
 
@@ -149,7 +230,13 @@ impl EventCallbackRegistry {
                     break;
                 }
 
-                match (event_information.callback)(data.clone()).await {
+                let callback = (event_information.callback)(data.to_vec());
+                let result = match &transaction {
+                    Some(transaction) => transaction.run(callback).await,
+                    None => callback.await,
+                };
+
+                match result {
                     Ok(_) => {
                         debug!(
                             "Event processing succeeded for id: {} - topic_id: {}",
@@ -163,6 +250,13 @@ impl EventCallbackRegistry {
                             break;
                         }
                         attempts += 1;
+                        if max_attempts.is_some_and(|max_attempts| attempts >= max_attempts) {
+                            error!(
+                                "{} Event processing failed - id: {} - topic_id: {}. Giving up after {} attempts. Error: {}",
+                                event_information.info_log_name(), id, event_information.topic_id, attempts, e
+                            );
+                            return Err(e);
+                        }
                         error!(
                             "{} Event processing failed - id: {} - topic_id: {}. Retrying... (attempt {}). Error: {}",
                             event_information.info_log_name(), id, event_information.topic_id, attempts, e
@@ -177,6 +271,8 @@ impl EventCallbackRegistry {
         } else {
             error!("EventCallbackRegistry: No event found for id: {}", id);
         }
+
+        Ok(())
     }
 
     pub fn complete(&self) -> Arc<Self> {
@@ -1,19 +1,35 @@
 use std::{path::PathBuf, sync::Arc};
 
 use ethers::prelude::{H256, U64};
+use futures::FutureExt;
 use tokio::sync::{Mutex, Semaphore};
 
 use crate::{
+    database::{postgres::entity::IndexerEntities, sqlite::client::SqliteClient},
     event::{
-        callback_registry::{EventCallbackRegistry, EventResult},
+        callback_registry::{
+            CallbackTransaction, EventCallbackRegistry, EventCallbackResult, EventResult,
+        },
         contract_setup::NetworkContract,
         BuildRindexerFilterError, RindexerEventFilter,
     },
-    indexer::IndexingEventsProgressState,
-    manifest::storage::CsvDetails,
+    indexer::{
+        dead_letters::{
+            store_dead_letter, DeadLetter, DeadLetterError, DeadLetterPolicy, DeadLetterStore,
+        },
+        factory::FactoryChildren,
+        function_calls::FunctionCallTracer,
+        last_synced::update_last_synced_block_in_database,
+        processed_ranges::ProcessedRangeStore,
+        raw_logs::store_raw_logs,
+        IndexingEventsProgressState,
+    },
+    is_running,
+    manifest::{network::ReorgSafeDistance, storage::CsvDetails},
     PostgresClient,
 };
 
+#[derive(Clone)]
 pub struct EventProcessingConfig {
     pub id: String,
     pub project_path: PathBuf,
@@ -29,11 +45,25 @@ pub struct EventProcessingConfig {
     pub registry: Arc<EventCallbackRegistry>,
     pub progress: Arc<Mutex<IndexingEventsProgressState>>,
     pub database: Option<Arc<PostgresClient>>,
+    pub sqlite: Option<Arc<SqliteClient>>,
     pub csv_details: Option<CsvDetails>,
     pub stream_last_synced_block_file_path: Option<String>,
     pub index_event_in_order: bool,
     pub live_indexing: bool,
-    pub indexing_distance_from_head: U64,
+    pub reorg_safe_distance: ReorgSafeDistance,
+    pub factory_children: Option<Arc<FactoryChildren>>,
+    pub include_transaction_fields: bool,
+    pub function_call: Option<Arc<FunctionCallTracer>>,
+    // how many fetched batches can wait to be indexed before fetching pauses
+    pub prefetch_depth: usize,
+    pub dead_letters: Option<DeadLetterPolicy>,
+    // false for backfills so the checkpoint of a running indexer is left alone
+    pub update_last_synced_block: bool,
+    pub processed_ranges: Arc<ProcessedRangeStore>,
+    // keeps the fetched logs in `rindexer_internal.raw_logs` so they can be redecoded
+    pub archive_raw_logs: bool,
+    // takes removed rows back out of the entities built from them
+    pub entities: Arc<IndexerEntities>,
 }
 
 impl EventProcessingConfig {
@@ -47,7 +77,122 @@ impl EventProcessingConfig {
         )
     }
 
-    pub async fn trigger_event(&self, fn_data: Vec<EventResult>) {
-        self.registry.trigger_event(&self.id, fn_data).await;
+    /// Writes the last synced block, the processed range and the raw logs inside the transaction
+    /// of the callback so they can never get ahead of the indexed rows.
+    async fn commit_progress(
+        &self,
+        database: &PostgresClient,
+        results: &[EventResult],
+        from_block: U64,
+        to_block: U64,
+    ) -> EventCallbackResult<()> {
+        if self.archive_raw_logs && !results.is_empty() {
+            store_raw_logs(
+                database,
+                &self.indexer_name,
+                &self.contract_name,
+                &self.network_contract.network,
+                results,
+            )
+            .await
+            .map_err(|e| format!("Error archiving raw logs: {}", e))?;
+        }
+
+        if self.update_last_synced_block {
+            update_last_synced_block_in_database(self, database, to_block)
+                .await
+                .map_err(|e| format!("Error updating last synced block: {}", e))?;
+        }
+
+        self.processed_ranges
+            .record(
+                &self.contract_name,
+                &self.event_name,
+                &self.network_contract.network,
+                from_block,
+                to_block,
+            )
+            .await
+            .map_err(|e| format!("Error recording processed blocks: {}", e))
+    }
+
+    /// Triggers the callback, with postgres the rows, last synced block and processed range are
+    /// committed in one transaction. Returns true if the progress was committed with the rows.
+    pub async fn trigger_event(
+        &self,
+        fn_data: Vec<EventResult>,
+        from_block: U64,
+        to_block: U64,
+    ) -> bool {
+        match self.run_callback(&fn_data, from_block, to_block, None).await {
+            Ok(()) => self.database.is_some(),
+            Err(e) => {
+                self.store_dead_letter(&fn_data, e).await;
+                false
+            }
+        }
+    }
+
+    /// Runs a dead letter through the callback again, with postgres it is removed in the
+    /// transaction of the callback otherwise once the callback succeeded. A batch which fails
+    /// again is dead lettered again and one cut short by a shutdown is left in the store.
+    pub async fn trigger_redrive(
+        &self,
+        fn_data: Vec<EventResult>,
+        dead_letter: &DeadLetter,
+        store: &DeadLetterStore,
+    ) -> Result<(), DeadLetterError> {
+        let redrive = Some((store, dead_letter.id.as_str()));
+        match self
+            .run_callback(&fn_data, dead_letter.from_block, dead_letter.to_block, redrive)
+            .await
+        {
+            // the registry also returns once it sees a shutdown without running the callback
+            Ok(()) if self.database.is_none() && is_running() => {
+                store.remove(&dead_letter.id).await
+            }
+            Ok(()) => Ok(()),
+            Err(e) => {
+                self.store_dead_letter(&fn_data, e).await;
+                Ok(())
+            }
+        }
+    }
+
+    async fn run_callback(
+        &self,
+        fn_data: &[EventResult],
+        from_block: U64,
+        to_block: U64,
+        redrive: Option<(&DeadLetterStore, &str)>,
+    ) -> EventCallbackResult<()> {
+        let max_attempts = self.dead_letters.as_ref().map(|policy| policy.max_attempts);
+        let database = self.database.as_deref();
+        let before_commit = || match database {
+            Some(database) => async move {
+                self.commit_progress(database, fn_data, from_block, to_block).await?;
+                if let Some((store, id)) = redrive {
+                    store
+                        .remove(id)
+                        .await
+                        .map_err(|e| format!("Error removing the re-driven dead letter: {}", e))?;
+                }
+                Ok(())
+            }
+            .boxed(),
+            None => async { Ok(()) }.boxed(),
+        };
+        let transaction = database
+            .map(|database| CallbackTransaction { database, before_commit: &before_commit });
+
+        self.registry.trigger_event(&self.id, fn_data, max_attempts, transaction).await
+    }
+
+    async fn store_dead_letter(&self, fn_data: &[EventResult], error: String) {
+        // only called once the max attempts are used up so indexing can move on
+        if let Some(policy) = &self.dead_letters {
+            let dead_letter = DeadLetter::new(self, fn_data, error, policy.max_attempts);
+            store_dead_letter(&policy.store, dead_letter).await;
+        }
     }
 }
@@ -9,7 +9,10 @@ use serde::{Deserialize, Serialize};
 use crate::{
     event::callback_registry::Decoder,
     generate_random_id,
-    manifest::contract::{Contract, EventInputIndexedFilters},
+    manifest::{
+        contract::{Contract, EventInputIndexedFilters},
+        network::ReorgSafeDistance,
+    },
     provider::{CreateNetworkProvider, JsonRpcCachedProvider},
     types::single_or_array::StringOrArray,
 };
@@ -24,6 +27,7 @@ pub struct NetworkContract {
     pub start_block: Option<U64>,
     pub end_block: Option<U64>,
     pub disable_logs_bloom_checks: bool,
+    pub reorg_safe_distance: Option<ReorgSafeDistance>,
 }
 
 impl NetworkContract {
@@ -42,6 +46,7 @@ pub struct ContractInformation {
     pub details: Vec<NetworkContract>,
     pub abi: StringOrArray,
     pub reorg_safe_distance: bool,
+    pub include_transaction_fields: bool,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -76,6 +81,7 @@ impl ContractInformation {
                         start_block: c.start_block,
                         end_block: c.end_block,
                         disable_logs_bloom_checks: provider.disable_logs_bloom_checks,
+                        reorg_safe_distance: provider.reorg_safe_distance,
                     });
                 }
             }
@@ -86,6 +92,7 @@ impl ContractInformation {
             details,
             abi: contract.abi.clone(),
             reorg_safe_distance: contract.reorg_safe_distance.unwrap_or_default(),
+            include_transaction_fields: contract.include_transaction_fields.unwrap_or_default(),
         })
     }
 }
@@ -105,13 +112,11 @@ pub struct AddressDetails {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FactoryDetails {
-    pub address: String,
+    pub address: ValueOrArray<Address>,
 
-    #[serde(rename = "eventName")]
     pub event_name: String,
 
-    #[serde(rename = "parameterName")]
-    pub parameter_name: String,
+    pub input_name: String,
 
     pub abi: String,
 }
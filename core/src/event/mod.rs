@@ -1,3 +1,4 @@
+pub mod block_callback_registry;
 pub mod callback_registry;
 
 pub mod config;
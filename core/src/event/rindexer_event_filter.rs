@@ -84,20 +84,11 @@ impl RindexerEventFilter {
                     Filter::new().topic0(*topic_id).from_block(current_block).to_block(next_block),
                 )),
             },
-            IndexingContractSetup::Factory(factory) => {
-                let address = factory
-                    .address
-                    .parse::<Address>()
-                    .map_err(|_| BuildRindexerFilterError::AddressInvalidFormat)?;
-
-                Ok(RindexerEventFilter::from_filter(
-                    Filter::new()
-                        .address(address)
-                        .topic0(*topic_id)
-                        .from_block(current_block)
-                        .to_block(next_block),
-                ))
-            }
+            // children are only known at runtime so the logs are matched against the discovered
+            // children after they are fetched
+            IndexingContractSetup::Factory(_) => Ok(RindexerEventFilter::from_filter(
+                Filter::new().topic0(*topic_id).from_block(current_block).to_block(next_block),
+            )),
         }
     }
 
@@ -123,6 +114,12 @@ impl RindexerEventFilter {
         self
     }
 
+    /// Matches any of the topic ids so many events can be fetched with one filter.
+    pub fn set_topic_ids(mut self, topic_ids: Vec<H256>) -> Self {
+        self.filter = self.filter.topic0(topic_ids);
+        self
+    }
+
     pub fn contract_address(&self) -> Option<ValueOrArray<Address>> {
         self.filter.address.clone()
     }
@@ -0,0 +1,69 @@
+use crate::{helpers::camel_to_snake, manifest::block_handler::BlockHandler, types::code::Code};
+
+fn generate_block_handler_code(block_handler: &BlockHandler) -> Code {
+    Code::new(format!(
+        r#"
+        async fn {fn_name}_block_handler(registry: &mut BlockCallbackRegistry) {{
+            registry.register_block_handler(BlockCallbackRegistryInformation {{
+                name: "{name}".to_string(),
+                callback: Arc::new(move |results| {{
+                    async move {{
+                        if results.is_empty() {{
+                            return Ok(());
+                        }}
+
+                        rindexer_info!(
+                            "{name} - {{}} - {{}} - blocks: {{}} - {{}}",
+                            results[0].network,
+                            "HANDLED".green(),
+                            results[0].block.number.unwrap_or_default(),
+                            results[results.len() - 1].block.number.unwrap_or_default()
+                        );
+
+                        Ok(())
+                    }}
+                    .boxed()
+                }}),
+            }});
+        }}
+        "#,
+        fn_name = camel_to_snake(&block_handler.name),
+        name = block_handler.name,
+    ))
+}
+
+pub fn generate_block_handlers(block_handlers: &[BlockHandler]) -> Code {
+    let mut code = Code::new(
+        r#"
+        use std::sync::Arc;
+
+        use rindexer::{
+            event::block_callback_registry::{BlockCallbackRegistry, BlockCallbackRegistryInformation},
+            rindexer_info, FutureExt, RindexerColorize,
+        };
+        "#
+        .to_string(),
+    );
+
+    for block_handler in block_handlers {
+        code.push_str(&generate_block_handler_code(block_handler));
+    }
+
+    let register_calls = block_handlers
+        .iter()
+        .map(|block_handler| {
+            format!("{}_block_handler(registry).await;", camel_to_snake(&block_handler.name))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    code.push_str(&Code::new(format!(
+        r#"
+        pub async fn block_handlers(registry: &mut BlockCallbackRegistry) {{
+            {register_calls}
+        }}
+        "#
+    )));
+
+    code
+}
@@ -6,6 +6,7 @@ use std::{
 use ethers::contract::Abigen;
 
 use super::{
+    block_handlers_bindings::generate_block_handlers,
     context_bindings::generate_context_code,
     events_bindings::{
         abigen_contract_file_name, abigen_contract_name, generate_event_bindings,
@@ -246,7 +247,9 @@ pub fn generate_rindexer_handlers(
             handlers.push_str(
                 r#"
         use std::path::PathBuf;
-        use rindexer::event::callback_registry::EventCallbackRegistry;
+        use rindexer::event::{
+            block_callback_registry::BlockCallbackRegistry, callback_registry::EventCallbackRegistry,
+        };
         
         pub async fn register_all_handlers(manifest_path: &PathBuf) -> EventCallbackRegistry {
              let mut registry = EventCallbackRegistry::new();
@@ -286,6 +289,42 @@ pub fn generate_rindexer_handlers(
 
             handlers.push_str("registry");
             handlers.push('}');
+
+            handlers.push_str(
+                r#"
+        pub async fn register_all_block_handlers() -> BlockCallbackRegistry {
+        "#,
+            );
+            match manifest
+                .block_handlers
+                .as_ref()
+                .filter(|block_handlers| !block_handlers.is_empty())
+            {
+                Some(block_handlers) => {
+                    let indexer_name = camel_to_snake(&manifest.name);
+                    handlers.insert_str(
+                        0,
+                        &format!(r#"use super::{indexer_name}::block_handlers::block_handlers;"#),
+                    );
+                    handlers.push_str(
+                        r#"
+             let mut registry = BlockCallbackRegistry::new();
+             block_handlers(&mut registry).await;
+             registry
+        "#,
+                    );
+
+                    write_file(
+                        &generate_file_location(
+                            &output,
+                            &format!("indexers/{}/block_handlers", indexer_name),
+                        ),
+                        generate_block_handlers(block_handlers).as_str(),
+                    )?;
+                }
+                None => handlers.push_str("BlockCallbackRegistry::new()"),
+            }
+            handlers.push('}');
             write_file(&generate_file_location(&output, "indexers/all_handlers"), &handlers)
                 .map_err(GenerateRindexerHandlersError::CouldNotWriteEventHandlersCode)?;
 
@@ -392,9 +431,12 @@ serde = {{ version = "1.0.194", features = ["derive"] }}
     let main_code = r#"
             use std::env;
 
-            use self::rindexer_lib::indexers::all_handlers::register_all_handlers;
+            use self::rindexer_lib::indexers::all_handlers::{
+                register_all_block_handlers, register_all_handlers,
+            };
             use rindexer::{
-                start_rindexer, GraphqlOverrideSettings, IndexingDetails, StartDetails,
+                indexer::backfill::BackfillDetails, start_backfill, start_rindexer,
+                BackfillStartDetails, GraphqlOverrideSettings, IndexingDetails, StartDetails,
             };
 
             mod rindexer_lib;
@@ -403,6 +445,29 @@ serde = {{ version = "1.0.194", features = ["derive"] }}
             async fn main() {
                 let args: Vec<String> = env::args().collect();
 
+                if let Some(backfill) = BackfillDetails::from_args(&args) {
+                    let result = match (backfill, env::current_dir()) {
+                        (Ok(backfill), Ok(path)) => {
+                            let manifest_path = path.join("rindexer.yaml");
+                            start_backfill(BackfillStartDetails {
+                                manifest_path: &manifest_path,
+                                registry: register_all_handlers(&manifest_path).await,
+                                backfill,
+                            })
+                            .await
+                            .map_err(|e| e.to_string())
+                        }
+                        (Err(e), _) => Err(e.to_string()),
+                        (_, Err(e)) => Err(e.to_string()),
+                    };
+
+                    if let Err(e) = result {
+                        eprintln!("Error running backfill: {}", e);
+                        std::process::exit(1);
+                    }
+                    return;
+                }
+
                 let mut enable_graphql = false;
                 let mut enable_indexer = false;
                 
@@ -443,6 +508,7 @@ serde = {{ version = "1.0.194", features = ["derive"] }}
                             indexing_details: if enable_indexer {
                                 Some(IndexingDetails {
                                     registry: register_all_handlers(&manifest_path).await,
+                                    block_registry: register_all_block_handlers().await,
                                 })
                             } else {
                                 None
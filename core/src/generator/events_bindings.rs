@@ -195,8 +195,11 @@ fn generate_csv_instance(
 
     let csv_path_str = csv_path.to_str().expect("Failed to convert csv path to string");
     let csv_path = event_info.create_csv_file_for_event(project_path, contract, csv_path_str)?;
-    let headers: Vec<String> =
-        event_info.csv_headers_for_event().iter().map(|h| format!("\"{}\"", h)).collect();
+    let headers: Vec<String> = event_info
+        .csv_headers_for_event(contract.include_transaction_fields.unwrap_or_default())
+        .iter()
+        .map(|h| format!("\"{}\"", h))
+        .collect();
 
     let headers_with_into: Vec<String> = headers.iter().map(|h| format!("{}.into()", h)).collect();
 
@@ -613,10 +616,16 @@ fn generate_event_bindings_code(
                                                         .iter()
                                                         .find(|n| n.name == c.network)
                                                         .map_or(false, |n| n.disable_logs_bloom_checks.unwrap_or_default()),
+                            reorg_safe_distance: rindexer_yaml
+                                                        .networks
+                                                        .iter()
+                                                        .find(|n| n.name == c.network)
+                                                        .and_then(|n| n.reorg_safe_distance),
                         }})
                         .collect(),
                     abi: contract_details.abi,
                     reorg_safe_distance: contract_details.reorg_safe_distance.unwrap_or_default(),
+                    include_transaction_fields: contract_details.include_transaction_fields.unwrap_or_default(),
                 }};
 
                 let callback: Arc<dyn Fn(Vec<EventResult>) -> BoxFuture<'static, EventCallbackResult<()>> + Send + Sync> = match self {{
@@ -628,6 +637,7 @@ fn generate_event_bindings_code(
                     indexer_name: "{indexer_name}".to_string(),
                     event_name: event_name.to_string(),
                     index_event_in_order,
+                    function_call_selector: None,
                     topic_id: topic_id.parse::<H256>().unwrap(),
                     contract,
                     callback,
@@ -647,8 +657,12 @@ fn generate_event_bindings_code(
         abigen_name = abigen_contract_name(contract),
         structs = generate_structs(project_path, contract)?,
         event_type_name = &event_type_name,
-        event_context_database =
-            if storage.postgres_enabled() { "pub database: Arc<PostgresClient>," } else { "" },
+        event_context_database = if storage.postgres_enabled() {
+            r#"/// Writes made while a handler runs join the transaction of the batch.
+                pub database: Arc<PostgresClient>,"#
+        } else {
+            ""
+        },
         event_context_csv =
             if storage.csv_enabled() { "pub csv: Arc<AsyncCsvAppender>," } else { "" },
         event_callback_structs =
@@ -784,9 +798,19 @@ pub fn generate_event_handlers(
             csv_data.push_str(r#"result.tx_information.block_hash.to_string(),"#);
             csv_data.push_str(r#"result.tx_information.network.to_string(),"#);
             csv_data.push_str(r#"result.tx_information.transaction_index.to_string(),"#);
-            csv_data.push_str(r#"result.tx_information.log_index.to_string()"#);
+            csv_data.push_str(r#"result.tx_information.log_index.to_string(),"#);
+            csv_data.push_str(
+                r#"result.tx_information.block_timestamp_to_datetime().map(|date_time| date_time.to_rfc3339()).unwrap_or_default()"#,
+            );
 
-            csv_write = format!(r#"csv_bulk_data.push(vec![{csv_data}]);"#, csv_data = csv_data,);
+            csv_write = if contract.include_transaction_fields.unwrap_or_default() {
+                format!(
+                    r#"csv_bulk_data.push([vec![{csv_data}], result.tx_information.transaction_fields.clone().unwrap_or_default().csv_values()].concat());"#,
+                    csv_data = csv_data,
+                )
+            } else {
+                format!(r#"csv_bulk_data.push(vec![{csv_data}]);"#, csv_data = csv_data,)
+            };
 
             if storage.postgres_disable_create_tables() {
                 csv_write = format!(
@@ -842,14 +866,26 @@ pub fn generate_event_handlers(
             }
 
             data.push_str("EthereumSqlTypeWrapper::H256(result.tx_information.transaction_hash),");
-            data.push_str("EthereumSqlTypeWrapper::U64(result.tx_information.block_number),");
+            data.push_str("EthereumSqlTypeWrapper::U64BigInt(result.tx_information.block_number),");
             data.push_str("EthereumSqlTypeWrapper::H256(result.tx_information.block_hash),");
             data.push_str(
                 "EthereumSqlTypeWrapper::String(result.tx_information.network.to_string()),",
             );
-            data.push_str("EthereumSqlTypeWrapper::U64(result.tx_information.transaction_index),");
-            data.push_str("EthereumSqlTypeWrapper::U256(result.tx_information.log_index)");
-            data.push_str("];");
+            data.push_str(
+                "EthereumSqlTypeWrapper::U32(result.tx_information.transaction_index.as_u32()),",
+            );
+            data.push_str("EthereumSqlTypeWrapper::U32(result.tx_information.log_index.as_u32()),");
+            data.push_str(
+                "EthereumSqlTypeWrapper::DateTimeNullable(result.tx_information.block_timestamp_to_datetime())",
+            );
+            data.push(']');
+            if contract.include_transaction_fields.unwrap_or_default() {
+                data = format!(
+                    "[{}, rindexer::map_transaction_fields_to_ethereum_wrapper(result.tx_information.transaction_fields.as_ref())].concat()",
+                    data
+                );
+            }
+            data.push(';');
 
             postgres_write = format!(
                 r#"
@@ -907,11 +943,14 @@ pub fn generate_event_handlers(
                     generate_event_table_full_name(indexer_name, &contract.name, &event.name),
                 handler_name = event.name,
                 event_type_name = event_type_name,
-                columns_names = generate_column_names_only_with_base_properties(&event.inputs)
-                    .iter()
-                    .map(|item| format!("\"{}\".to_string()", item))
-                    .collect::<Vec<String>>()
-                    .join(", "),
+                columns_names = generate_column_names_only_with_base_properties(
+                    &event.inputs,
+                    contract.include_transaction_fields.unwrap_or_default()
+                )
+                .iter()
+                .map(|item| format!("\"{}\".to_string()", item))
+                .collect::<Vec<String>>()
+                .join(", "),
                 data = data,
                 csv_write = csv_write,
                 csv_bulk_data = if storage.csv_enabled() {
@@ -1,5 +1,6 @@
 pub mod build;
 
+mod block_handlers_bindings;
 mod context_bindings;
 mod database_bindings;
 mod docker;
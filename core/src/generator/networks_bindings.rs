@@ -1,4 +1,7 @@
-use crate::{manifest::network::Network, types::code::Code};
+use crate::{
+    manifest::network::{Network, NetworkRpc, ReorgSafeDistance},
+    types::code::Code,
+};
 
 fn network_provider_name(network: &Network) -> String {
     network_provider_name_from_name(&network.name)
@@ -12,28 +15,78 @@ pub fn network_provider_fn_name(network: &Network) -> String {
     format!("get_{fn_name}", fn_name = network_provider_name(network).to_lowercase())
 }
 
+fn option_code<T: std::fmt::Display>(value: Option<T>, wrap: fn(T) -> String) -> String {
+    value.map_or_else(|| "None".to_string(), |value| format!("Some({})", wrap(value)))
+}
+
 fn generate_network_lazy_provider_code(network: &Network) -> Code {
+    let ws_rpc_url = option_code(network.ws_rpc.as_ref(), |ws_rpc| {
+        format!(r#"&public_read_env_value("{ws_rpc}").unwrap_or("{ws_rpc}".to_string())"#)
+    });
+    let compute_units_per_second =
+        option_code(network.compute_units_per_second, |value| value.to_string());
+    let max_block_range =
+        option_code(network.max_block_range, |value| format!("U64::from({})", value));
+    let reorg_safe_distance = option_code(network.reorg_safe_distance, |value| match value {
+        ReorgSafeDistance::Blocks(blocks) => {
+            format!("rindexer::manifest::network::ReorgSafeDistance::Blocks(U64::from({}))", blocks)
+        }
+        ReorgSafeDistance::Latest => {
+            "rindexer::manifest::network::ReorgSafeDistance::Latest".to_string()
+        }
+        ReorgSafeDistance::Safe => {
+            "rindexer::manifest::network::ReorgSafeDistance::Safe".to_string()
+        }
+        ReorgSafeDistance::Finalized => {
+            "rindexer::manifest::network::ReorgSafeDistance::Finalized".to_string()
+        }
+    });
+
+    let client = match &network.rpc {
+        NetworkRpc::Single(rpc) => format!(
+            r#"{client_fn}(&public_read_env_value("{network_url}").unwrap_or("{network_url}".to_string()), {ws_rpc_url}, {compute_units_per_second}, {max_block_range}, {reorg_safe_distance} {placeholder_headers})"#,
+            network_url = rpc,
+            client_fn =
+                if rpc.contains("shadow") { "create_shadow_client" } else { "create_client" },
+            placeholder_headers = if rpc.contains("shadow") { "" } else { ", HeaderMap::new()" },
+        ),
+        NetworkRpc::Multiple(endpoints) => {
+            let endpoints = endpoints
+                .iter()
+                .map(|endpoint| {
+                    format!(
+                        r#"rindexer::manifest::network::RpcEndpointConfig {{
+                            url: public_read_env_value("{url}").unwrap_or("{url}".to_string()),
+                            priority: {priority},
+                            weight: {weight},
+                            compute_units_per_second: {compute_units_per_second},
+                            max_block_range: {max_block_range},
+                        }}"#,
+                        url = endpoint.url,
+                        priority = option_code(endpoint.priority, |value| value.to_string()),
+                        weight = option_code(endpoint.weight, |value| value.to_string()),
+                        compute_units_per_second =
+                            option_code(endpoint.compute_units_per_second, |value| value
+                                .to_string()),
+                        max_block_range = option_code(endpoint.max_block_range, |value| {
+                            format!("U64::from({})", value)
+                        }),
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            format!(
+                r#"rindexer::provider::create_client_with_endpoints(&[{endpoints}], {ws_rpc_url}, {compute_units_per_second}, {max_block_range}, {reorg_safe_distance}, HeaderMap::new())"#
+            )
+        }
+    };
+
     Code::new(format!(
         r#"
-            static ref {network_name}: Arc<JsonRpcCachedProvider> = {client_fn}(&public_read_env_value("{network_url}").unwrap_or("{network_url}".to_string()), {compute_units_per_second}, {max_block_range} {placeholder_headers}).expect("Error creating provider");
+            static ref {network_name}: Arc<JsonRpcCachedProvider> = {client}.expect("Error creating provider");
         "#,
         network_name = network_provider_name(network),
-        network_url = network.rpc,
-        compute_units_per_second =
-            if let Some(compute_units_per_second) = network.compute_units_per_second {
-                format!("Some({})", compute_units_per_second)
-            } else {
-                "None".to_string()
-            },
-        max_block_range = if let Some(max_block_range) = network.max_block_range {
-            format!("Some(U64::from({}))", max_block_range)
-        } else {
-            "None".to_string()
-        },
-        client_fn =
-            if network.rpc.contains("shadow") { "create_shadow_client" } else { "create_client" },
-        placeholder_headers =
-            if network.rpc.contains("shadow") { "" } else { ", HeaderMap::new()" },
     ))
 }
 
@@ -92,6 +145,7 @@ pub fn generate_networks_code(networks: &[Network]) -> Code {
             use ethers::types::U64;
             use rindexer::{
                 lazy_static,
+                manifest::network::ReorgSafeDistance,
                 provider::{create_client, JsonRpcCachedProvider, RetryClientError},
                 public_read_env_value, HeaderMap,
             };
@@ -100,15 +154,17 @@ pub fn generate_networks_code(networks: &[Network]) -> Code {
             #[allow(dead_code)]
             fn create_shadow_client(
                 rpc_url: &str,
+                ws_rpc_url: Option<&str>,
                 compute_units_per_second: Option<u64>,
                 max_block_range: Option<U64>,
+                reorg_safe_distance: Option<ReorgSafeDistance>,
             ) -> Result<Arc<JsonRpcCachedProvider>, RetryClientError> {
                 let mut header = HeaderMap::new();
                 header.insert(
                     "X-SHADOW-API-KEY",
                     public_read_env_value("RINDEXER_PHANTOM_API_KEY").unwrap().parse().unwrap(),
                 );
-                create_client(rpc_url, compute_units_per_second, max_block_range, header)
+                create_client(rpc_url, ws_rpc_url, compute_units_per_second, max_block_range, reorg_safe_distance, header)
             }
 
             lazy_static! {
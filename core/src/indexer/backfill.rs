@@ -0,0 +1,311 @@
+use std::{path::Path, sync::Arc};
+
+use ethers::types::U64;
+use tracing::info;
+
+use crate::{
+    abi::ReadAbiError,
+    database::postgres::{
+        client::{PostgresConnectionError, PostgresError},
+        entity::IndexerEntities,
+        generate::generate_event_table_full_name,
+    },
+    event::callback_registry::{EventCallbackRegistry, EventCallbackRegistryInformation},
+    helpers::get_full_path,
+    indexer::{
+        reorg::csv_file_path_for_event,
+        start::{start_backfill_indexing, StartIndexingError},
+    },
+    manifest::core::Manifest,
+    simple_file_formatters::csv::remove_csv_rows_in_block_range,
+    PostgresClient,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum BackfillError {
+    #[error("Missing --{0} for backfill")]
+    MissingArgument(String),
+
+    #[error("Invalid block number for --{0}: {1}")]
+    InvalidBlockNumber(String, String),
+
+    #[error("Backfill from block {0} is higher than the to block {1}")]
+    InvalidBlockRange(U64, U64),
+
+    #[error("Contract {0} not found in the registered handlers")]
+    ContractNotFound(String),
+
+    #[error("Event {0} not found for contract {1}")]
+    EventNotFound(String, String),
+
+    #[error("Contract {0} is not indexed on network {1}")]
+    NetworkNotFound(String, String),
+
+    #[error("{0}")]
+    PostgresConnectionError(#[from] PostgresConnectionError),
+
+    #[error("Could not remove the indexed rows: {0}")]
+    PostgresError(#[from] PostgresError),
+
+    #[error("Could not read the ABIs of the entities: {0}")]
+    EntitiesAbiError(#[from] ReadAbiError),
+
+    #[error("Could not remove the indexed csv rows: {0}")]
+    CsvError(#[from] csv::Error),
+
+    #[error("Could not index the backfill range: {0}")]
+    StartIndexingError(#[from] StartIndexingError),
+}
+
+/// A block range of a contract to re-index, every event of the contract when no event is passed.
+#[derive(Debug, Clone)]
+pub struct BackfillDetails {
+    pub contract_name: String,
+    pub event_name: Option<String>,
+    pub network: String,
+    pub from_block: U64,
+    pub to_block: U64,
+}
+
+fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    let prefix = format!("--{}=", name);
+    args.iter().find_map(|arg| arg.strip_prefix(&prefix))
+}
+
+fn block_arg(args: &[String], name: &str) -> Result<U64, BackfillError> {
+    let value =
+        arg_value(args, name).ok_or_else(|| BackfillError::MissingArgument(name.to_string()))?;
+    U64::from_dec_str(value)
+        .map_err(|_| BackfillError::InvalidBlockNumber(name.to_string(), value.to_string()))
+}
+
+impl BackfillDetails {
+    /// Reads the backfill from the args a rust project is started with, `None` when the args do
+    /// not contain `--backfill`.
+    pub fn from_args(args: &[String]) -> Option<Result<Self, BackfillError>> {
+        if !args.iter().any(|arg| arg == "--backfill") {
+            return None;
+        }
+
+        let details = Self::parse_args(args).and_then(|details| {
+            details.validate()?;
+            Ok(details)
+        });
+
+        Some(details)
+    }
+
+    fn parse_args(args: &[String]) -> Result<Self, BackfillError> {
+        let required = |name: &str| {
+            arg_value(args, name)
+                .map(|value| value.to_string())
+                .ok_or_else(|| BackfillError::MissingArgument(name.to_string()))
+        };
+
+        Ok(BackfillDetails {
+            contract_name: required("contract")?,
+            event_name: arg_value(args, "event").map(|value| value.to_string()),
+            network: required("network")?,
+            from_block: block_arg(args, "from")?,
+            to_block: block_arg(args, "to")?,
+        })
+    }
+
+    /// The args to start a rust project with so it runs this backfill.
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "--backfill".to_string(),
+            format!("--contract={}", self.contract_name),
+            format!("--network={}", self.network),
+            format!("--from={}", self.from_block),
+            format!("--to={}", self.to_block),
+        ];
+        if let Some(event_name) = &self.event_name {
+            args.push(format!("--event={}", event_name));
+        }
+        args
+    }
+
+    pub fn validate(&self) -> Result<(), BackfillError> {
+        if self.from_block > self.to_block {
+            return Err(BackfillError::InvalidBlockRange(self.from_block, self.to_block));
+        }
+
+        Ok(())
+    }
+
+    /// Narrows the registry down to the backfill events with only the backfill network and range.
+    fn backfill_registry(
+        &self,
+        registry: EventCallbackRegistry,
+    ) -> Result<EventCallbackRegistry, BackfillError> {
+        let contract_events: Vec<EventCallbackRegistryInformation> = registry
+            .events
+            .into_iter()
+            .filter(|event| event.contract.name == self.contract_name)
+            .collect();
+
+        if contract_events.is_empty() {
+            return Err(BackfillError::ContractNotFound(self.contract_name.clone()));
+        }
+
+        let mut events: Vec<EventCallbackRegistryInformation> = contract_events
+            .into_iter()
+            .filter(|event| self.event_name.as_ref().is_none_or(|name| &event.event_name == name))
+            .collect();
+
+        if let (true, Some(event_name)) = (events.is_empty(), &self.event_name) {
+            return Err(BackfillError::EventNotFound(
+                event_name.clone(),
+                self.contract_name.clone(),
+            ));
+        }
+
+        for event in events.iter_mut() {
+            event.contract.details.retain(|details| details.network == self.network);
+            if event.contract.details.is_empty() {
+                return Err(BackfillError::NetworkNotFound(
+                    self.contract_name.clone(),
+                    self.network.clone(),
+                ));
+            }
+
+            for details in event.contract.details.iter_mut() {
+                details.start_block = Some(self.from_block);
+                details.end_block = Some(self.to_block);
+            }
+        }
+
+        Ok(EventCallbackRegistry { events })
+    }
+}
+
+/// Removes what is already indexed for the backfill range so re-indexing does not duplicate it.
+async fn remove_indexed_range(
+    manifest: &Manifest,
+    project_path: &Path,
+    registry: &EventCallbackRegistry,
+    details: &BackfillDetails,
+) -> Result<(), BackfillError> {
+    let database =
+        if manifest.storage.postgres_enabled() { Some(PostgresClient::new().await?) } else { None };
+    let entities = IndexerEntities::new(project_path, manifest)?;
+
+    for event in &registry.events {
+        if let Some(database) = &database {
+            let table_name = generate_event_table_full_name(
+                &event.indexer_name,
+                &event.contract.name,
+                &event.event_name,
+            );
+            // the entities are taken back out so re-indexing the range does not count it twice
+            let deleted = entities
+                .remove_event_rows(
+                    database,
+                    &table_name,
+                    &details.network,
+                    details.from_block,
+                    Some(details.to_block),
+                )
+                .await?;
+            info!(
+                "{} - Removed {} rows to backfill from {}",
+                event.info_log_name(),
+                deleted,
+                table_name
+            );
+        }
+
+        if let Some(csv_details) = manifest
+            .storage
+            .csv
+            .as_ref()
+            .filter(|_| manifest.contract_csv_enabled(&event.contract.name))
+        {
+            if let Ok(full_path) = get_full_path(project_path, &csv_details.path) {
+                let csv_file =
+                    csv_file_path_for_event(&full_path, &event.contract.name, &event.event_name);
+                if Path::new(&csv_file).exists() {
+                    remove_csv_rows_in_block_range(
+                        &csv_file,
+                        &details.network,
+                        details.from_block,
+                        Some(details.to_block),
+                    )
+                    .await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Re-indexes a block range of a contract, the rows already indexed in the range are removed
+/// first and the last synced block is left alone so a running indexer can keep going.
+pub async fn backfill(
+    manifest: &Manifest,
+    project_path: &Path,
+    registry: EventCallbackRegistry,
+    details: &BackfillDetails,
+) -> Result<(), BackfillError> {
+    details.validate()?;
+    let registry = details.backfill_registry(registry)?;
+
+    info!(
+        "Backfilling {} on {} - blocks {} - {} - events: {}",
+        details.contract_name,
+        details.network,
+        details.from_block,
+        details.to_block,
+        registry
+            .events
+            .iter()
+            .map(|event| event.event_name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    remove_indexed_range(manifest, project_path, &registry, details).await?;
+    start_backfill_indexing(manifest, project_path, Arc::new(registry)).await?;
+
+    info!("Backfill complete");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backfill_details_args_round_trip() {
+        let args: Vec<String> = [
+            "rindexer_rust_playground",
+            "--backfill",
+            "--contract=RocketPoolETH",
+            "--network=ethereum",
+            "--from=100",
+            "--to=200",
+            "--event=Transfer",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+
+        let details = BackfillDetails::from_args(&args).unwrap().unwrap();
+        assert_eq!(details.contract_name, "RocketPoolETH");
+        assert_eq!(details.event_name.as_deref(), Some("Transfer"));
+        assert_eq!(details.from_block, U64::from(100));
+        assert_eq!(details.to_block, U64::from(200));
+        assert_eq!(details.to_args(), args[1..].to_vec());
+
+        assert!(BackfillDetails::from_args(&args[2..]).is_none());
+
+        let reversed = [&args[..4], &["--from=300".to_string(), "--to=200".to_string()]].concat();
+        assert!(matches!(
+            BackfillDetails::from_args(&reversed),
+            Some(Err(BackfillError::InvalidBlockRange(_, _)))
+        ));
+    }
+}
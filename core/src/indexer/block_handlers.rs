@@ -0,0 +1,418 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ethers::{prelude::ProviderError, types::U64};
+use futures::{future::try_join_all, FutureExt};
+use rust_decimal::Decimal;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info};
+
+use crate::{
+    database::postgres::client::PostgresConnectionError,
+    event::{
+        block_callback_registry::{BlockCallbackRegistry, BlockResult},
+        callback_registry::CallbackTransaction,
+    },
+    helpers::camel_to_snake,
+    indexer::{
+        reorg::{reorg_safe_distance_for_chain, safe_block_number},
+        IndexingEventProgressStatus,
+    },
+    is_running,
+    manifest::{block_handler::BlockHandler, core::Manifest, network::ReorgSafeDistance},
+    provider::{CreateNetworkProvider, JsonRpcCachedProvider, RetryClientError},
+    EthereumSqlTypeWrapper, PostgresClient,
+};
+
+/// How many blocks are fetched at the same time and handed to the handler in one call.
+const BLOCK_HANDLER_BATCH_SIZE: usize = 50;
+
+#[derive(thiserror::Error, Debug)]
+pub enum BlockHandlerError {
+    #[error("Block handler {0} is in the manifest but no handler is registered for it")]
+    HandlerNotRegistered(String),
+
+    #[error("Network {0} for block handler {1} not found in networks")]
+    NetworkNotFound(String, String),
+
+    #[error("Could not create the network providers: {0}")]
+    RetryClientError(#[from] RetryClientError),
+
+    #[error("{0}")]
+    PostgresConnectionError(#[from] PostgresConnectionError),
+
+    #[error("Could not load the last synced block: {0}")]
+    CouldNotLoad(String),
+
+    #[error("Could not store the last synced block: {0}")]
+    CouldNotStore(String),
+
+    #[error("Could not fetch blocks: {0}")]
+    ProviderError(#[from] ProviderError),
+}
+
+pub fn generate_block_handler_last_synced_table_name(
+    indexer_name: &str,
+    handler_name: &str,
+) -> String {
+    format!(
+        "rindexer_internal.{}_{}_block_last_synced",
+        camel_to_snake(indexer_name),
+        camel_to_snake(handler_name)
+    )
+}
+
+enum BlockHandlerCheckpointStore {
+    Postgres { database: Arc<PostgresClient>, table: String },
+    File(PathBuf),
+}
+
+/// The last block a block handler has processed on a network so it resumes where it left off.
+struct BlockHandlerCheckpoint {
+    network: String,
+    store: BlockHandlerCheckpointStore,
+}
+
+impl BlockHandlerCheckpoint {
+    async fn load(
+        project_path: &Path,
+        indexer_name: &str,
+        handler_name: &str,
+        network: &str,
+        database: &Option<Arc<PostgresClient>>,
+    ) -> Result<BlockHandlerCheckpoint, BlockHandlerError> {
+        let store = match database {
+            Some(database) => {
+                let table =
+                    generate_block_handler_last_synced_table_name(indexer_name, handler_name);
+                database
+                    .batch_execute(&format!(
+                        r#"
+                        CREATE SCHEMA IF NOT EXISTS rindexer_internal;
+                        CREATE TABLE IF NOT EXISTS {table} ("network" TEXT PRIMARY KEY, "last_synced_block" NUMERIC);
+                        "#
+                    ))
+                    .await
+                    .map_err(|e| BlockHandlerError::CouldNotLoad(e.to_string()))?;
+
+                BlockHandlerCheckpointStore::Postgres { database: Arc::clone(database), table }
+            }
+            None => BlockHandlerCheckpointStore::File(
+                project_path
+                    .join(".rindexer")
+                    .join("blocks")
+                    .join(format!("{}-{}.txt", handler_name, network).to_lowercase()),
+            ),
+        };
+
+        Ok(BlockHandlerCheckpoint { network: network.to_string(), store })
+    }
+
+    /// The database the checkpoint is stored in, the handler writes are committed with it.
+    fn database(&self) -> Option<&PostgresClient> {
+        match &self.store {
+            BlockHandlerCheckpointStore::Postgres { database, .. } => Some(database),
+            BlockHandlerCheckpointStore::File(_) => None,
+        }
+    }
+
+    async fn last_synced_block(&self) -> Result<Option<U64>, BlockHandlerError> {
+        match &self.store {
+            BlockHandlerCheckpointStore::Postgres { database, table } => Ok(database
+                .query_one_or_none(
+                    &format!("SELECT last_synced_block FROM {} WHERE network = $1", table),
+                    &[&self.network],
+                )
+                .await
+                .map_err(|e| BlockHandlerError::CouldNotLoad(e.to_string()))?
+                .and_then(|row| {
+                    let value: Decimal = row.get("last_synced_block");
+                    U64::from_dec_str(&value.to_string()).ok()
+                })),
+            BlockHandlerCheckpointStore::File(path) => {
+                if !path.exists() {
+                    return Ok(None);
+                }
+
+                let contents = tokio::fs::read_to_string(path)
+                    .await
+                    .map_err(|e| BlockHandlerError::CouldNotLoad(e.to_string()))?;
+                U64::from_dec_str(contents.trim())
+                    .map(Some)
+                    .map_err(|e| BlockHandlerError::CouldNotLoad(e.to_string()))
+            }
+        }
+    }
+
+    async fn update(&self, to_block: U64) -> Result<(), BlockHandlerError> {
+        match &self.store {
+            BlockHandlerCheckpointStore::Postgres { database, table } => {
+                database
+                    .execute(
+                        &format!(
+                            r#"INSERT INTO {} ("network", "last_synced_block") VALUES ($1, $2) ON CONFLICT ("network") DO UPDATE SET "last_synced_block" = EXCLUDED."last_synced_block""#,
+                            table
+                        ),
+                        &[&self.network, &EthereumSqlTypeWrapper::U64(to_block)],
+                    )
+                    .await
+                    .map_err(|e| BlockHandlerError::CouldNotStore(e.to_string()))?;
+
+                Ok(())
+            }
+            BlockHandlerCheckpointStore::File(path) => {
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent)
+                        .await
+                        .map_err(|e| BlockHandlerError::CouldNotStore(e.to_string()))?;
+                }
+
+                // write then rename so a crash never leaves a half written checkpoint behind
+                let temp_path = path.with_extension("txt.tmp");
+                tokio::fs::write(&temp_path, to_block.to_string())
+                    .await
+                    .map_err(|e| BlockHandlerError::CouldNotStore(e.to_string()))?;
+                tokio::fs::rename(&temp_path, path)
+                    .await
+                    .map_err(|e| BlockHandlerError::CouldNotStore(e.to_string()))
+            }
+        }
+    }
+}
+
+/// Runs a block handler on a single network, historic blocks are processed first and then new
+/// blocks as they are produced until the end block if one is set.
+struct BlockHandlerIndexer {
+    name: String,
+    info_log_name: String,
+    network: String,
+    cached_provider: Arc<JsonRpcCachedProvider>,
+    interval: u64,
+    include_transactions: bool,
+    reorg_safe_distance: ReorgSafeDistance,
+    end_block: Option<U64>,
+    checkpoint: BlockHandlerCheckpoint,
+    registry: Arc<BlockCallbackRegistry>,
+}
+
+impl BlockHandlerIndexer {
+    async fn run(self, mut from_block: U64) {
+        let mut new_block_listener = self.cached_provider.new_block_listener();
+
+        loop {
+            match self.safe_to_block().await {
+                Ok(to_block) if from_block <= to_block => {
+                    if let Err(e) = self.process_range(&mut from_block, to_block).await {
+                        error!("{} - Error processing blocks: {}", self.info_log_name, e);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!(
+                        "{} - Error getting the block to index up to: {}",
+                        self.info_log_name, e
+                    );
+                }
+            }
+
+            if self.end_block.is_some_and(|end_block| from_block > end_block) {
+                info!(
+                    "{} - {} - Finished indexing blocks",
+                    self.info_log_name,
+                    IndexingEventProgressStatus::Completed.log()
+                );
+                return;
+            }
+
+            new_block_listener.wait().await;
+        }
+    }
+
+    async fn safe_to_block(&self) -> Result<U64, ProviderError> {
+        let latest_block = self.cached_provider.get_block_number().await?;
+        let safe_block =
+            safe_block_number(&self.cached_provider, &self.reorg_safe_distance, latest_block)
+                .await?;
+
+        Ok(self.end_block.map_or(safe_block, |end_block| end_block.min(safe_block)))
+    }
+
+    /// Hands the blocks in the range which match the interval to the handler, `from_block` is
+    /// moved along as batches complete so a failure resumes after the last handled batch. With
+    /// postgres each batch is committed together with the checkpoint.
+    async fn process_range(
+        &self,
+        from_block: &mut U64,
+        to_block: U64,
+    ) -> Result<(), BlockHandlerError> {
+        let block_numbers: Vec<U64> = (from_block.as_u64()..=to_block.as_u64())
+            .filter(|block_number| block_number % self.interval == 0)
+            .map(U64::from)
+            .collect();
+
+        for batch in block_numbers.chunks(BLOCK_HANDLER_BATCH_SIZE) {
+            let blocks = try_join_all(batch.iter().map(|block_number| async move {
+                self.cached_provider
+                    .get_block_with_transactions(*block_number, self.include_transactions)
+                    .await?
+                    .ok_or_else(|| {
+                        ProviderError::CustomError(format!(
+                            "RPC did not return block {}",
+                            block_number
+                        ))
+                    })
+            }))
+            .await?;
+
+            let results: Vec<BlockResult> = blocks
+                .into_iter()
+                .map(|(block, transactions)| BlockResult {
+                    network: self.network.clone(),
+                    block,
+                    transactions,
+                })
+                .collect();
+
+            // with postgres the checkpoint is committed in the transaction of the handler writes
+            let last_block = *batch.last().expect("batch is never empty");
+            let before_commit = || {
+                async move { self.checkpoint.update(last_block).await.map_err(|e| e.to_string()) }
+                    .boxed()
+            };
+            let transaction = self
+                .checkpoint
+                .database()
+                .map(|database| CallbackTransaction { database, before_commit: &before_commit });
+            let in_transaction = transaction.is_some();
+
+            self.registry.trigger_block_handler(&self.name, results, transaction).await;
+
+            // the registry also returns once it sees a shutdown without running the handler
+            if !is_running() {
+                return Ok(());
+            }
+            if !in_transaction {
+                self.checkpoint.update(last_block).await?;
+            }
+            *from_block = last_block + 1;
+
+            info!(
+                "{} - {} - {} blocks - blocks: {} - {}",
+                self.info_log_name,
+                IndexingEventProgressStatus::Syncing.log(),
+                batch.len(),
+                batch[0],
+                last_block
+            );
+        }
+
+        // blocks skipped by the interval still count as synced
+        self.checkpoint.update(to_block).await?;
+        debug!("{} - Processed blocks up to {}", self.info_log_name, to_block);
+        *from_block = to_block + 1;
+
+        Ok(())
+    }
+}
+
+async fn reorg_safe_distance_for_block_handler(
+    block_handler: &BlockHandler,
+    network_provider: &CreateNetworkProvider,
+) -> Result<ReorgSafeDistance, ProviderError> {
+    match network_provider.reorg_safe_distance {
+        Some(reorg_safe_distance) => Ok(reorg_safe_distance),
+        None if block_handler.reorg_safe_distance.unwrap_or_default() => {
+            let chain_id = network_provider.client.get_chain_id().await?;
+            Ok(ReorgSafeDistance::Blocks(reorg_safe_distance_for_chain(&chain_id)))
+        }
+        None => Ok(ReorgSafeDistance::Latest),
+    }
+}
+
+/// Starts every block handler in the manifest on each of its networks, the handlers run in the
+/// background and the returned handles complete once all of them reached their end block.
+pub async fn start_block_handlers(
+    manifest: &Manifest,
+    project_path: &Path,
+    registry: Arc<BlockCallbackRegistry>,
+) -> Result<Vec<JoinHandle<()>>, BlockHandlerError> {
+    let block_handlers = manifest.all_block_handlers();
+    if block_handlers.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let network_providers = CreateNetworkProvider::create(manifest)?;
+    let database = if manifest.storage.postgres_enabled() {
+        Some(Arc::new(PostgresClient::new().await?))
+    } else {
+        None
+    };
+
+    let mut handles = vec![];
+    for block_handler in block_handlers {
+        if registry.find_handler(&block_handler.name).is_none() {
+            return Err(BlockHandlerError::HandlerNotRegistered(block_handler.name.clone()));
+        }
+
+        for details in &block_handler.details {
+            let network_provider = network_providers
+                .iter()
+                .find(|provider| provider.network_name == details.network)
+                .ok_or_else(|| {
+                    BlockHandlerError::NetworkNotFound(
+                        details.network.clone(),
+                        block_handler.name.clone(),
+                    )
+                })?;
+
+            let checkpoint = BlockHandlerCheckpoint::load(
+                project_path,
+                &manifest.name,
+                &block_handler.name,
+                &details.network,
+                &database,
+            )
+            .await?;
+
+            let info_log_name = format!("{}::{}", block_handler.name, details.network);
+            let start_block = match checkpoint.last_synced_block().await? {
+                Some(last_synced_block) => {
+                    info!(
+                        "{} Found last synced block number - {:?} rindexer will start up from {:?}",
+                        info_log_name,
+                        last_synced_block,
+                        last_synced_block + 1
+                    );
+                    last_synced_block + 1
+                }
+                None => match details.start_block {
+                    Some(start_block) => start_block,
+                    None => network_provider.client.get_block_number().await?,
+                },
+            };
+
+            let indexer = BlockHandlerIndexer {
+                name: block_handler.name.clone(),
+                info_log_name,
+                network: details.network.clone(),
+                cached_provider: Arc::clone(&network_provider.client),
+                interval: block_handler.interval(),
+                include_transactions: block_handler.include_transactions.unwrap_or_default(),
+                reorg_safe_distance: reorg_safe_distance_for_block_handler(
+                    &block_handler,
+                    network_provider,
+                )
+                .await?,
+                end_block: details.end_block,
+                checkpoint,
+                registry: Arc::clone(&registry),
+            };
+
+            handles.push(tokio::spawn(indexer.run(start_block)));
+        }
+    }
+
+    Ok(handles)
+}
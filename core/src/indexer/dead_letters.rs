@@ -0,0 +1,495 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use ethers::types::U64;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio_postgres::Row;
+use tracing::{error, info, warn};
+
+use crate::{
+    database::postgres::{client::PostgresError, generate::generate_dead_letters_table_name},
+    event::{callback_registry::EventResult, config::EventProcessingConfig},
+    helpers::camel_to_snake,
+    indexer::process::event_results_for_logs,
+    is_running,
+    provider::WrappedLog,
+    EthereumSqlTypeWrapper, PostgresClient,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum DeadLetterError {
+    #[error("{0}")]
+    PostgresError(#[from] PostgresError),
+
+    #[error("Could not read or write the dead letters file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Could not serialize the dead letter: {0}")]
+    Serde(#[from] serde_json::Error),
+}
+
+/// A batch of logs whose callback kept failing, stored so indexing can move on and the batch
+/// can be re-driven later.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeadLetter {
+    pub id: String,
+    pub contract_name: String,
+    pub event_name: String,
+    pub network: String,
+    pub from_block: U64,
+    pub to_block: U64,
+    pub error: String,
+    pub attempts: u32,
+    pub logs: Vec<WrappedLog>,
+    #[serde(default)]
+    pub redrive: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl DeadLetter {
+    pub fn new(
+        config: &EventProcessingConfig,
+        results: &[EventResult],
+        error: String,
+        attempts: u32,
+    ) -> Self {
+        let from_block = results.first().map(|r| r.found_in_request.from_block).unwrap_or_default();
+        let to_block = results.first().map(|r| r.found_in_request.to_block).unwrap_or_default();
+
+        DeadLetter {
+            id: format!(
+                "{}:{}:{}:{}-{}",
+                config.contract_name,
+                config.event_name,
+                config.network_contract.network,
+                from_block,
+                to_block
+            ),
+            contract_name: config.contract_name.clone(),
+            event_name: config.event_name.clone(),
+            network: config.network_contract.network.clone(),
+            from_block,
+            to_block,
+            error,
+            attempts,
+            logs: results
+                .iter()
+                .map(|result| WrappedLog {
+                    inner: result.log.clone(),
+                    block_timestamp: result.tx_information.block_timestamp,
+                })
+                .collect(),
+            redrive: false,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// The block number, transaction hash and log index of every log in the batch.
+    pub fn log_coordinates(&self) -> Vec<String> {
+        self.logs
+            .iter()
+            .map(|log| {
+                format!(
+                    "block {} - tx {:?} - log index {}",
+                    log.inner.block_number.unwrap_or_default(),
+                    log.inner.transaction_hash.unwrap_or_default(),
+                    log.inner.log_index.unwrap_or_default()
+                )
+            })
+            .collect()
+    }
+
+    fn is_for(&self, config: &EventProcessingConfig) -> bool {
+        self.contract_name == config.contract_name &&
+            self.event_name == config.event_name &&
+            self.network == config.network_contract.network
+    }
+}
+
+/// When set failing callbacks are given up on after `max_attempts` and written to the store.
+#[derive(Clone)]
+pub struct DeadLetterPolicy {
+    pub max_attempts: u32,
+    pub store: Arc<DeadLetterStore>,
+}
+
+enum DeadLetterStoreKind {
+    Postgres { database: Arc<PostgresClient>, table: String },
+    File { path: PathBuf, lock: Mutex<()> },
+}
+
+/// Dead letters live in postgres when it is enabled otherwise in a JSONL file in the project.
+pub struct DeadLetterStore {
+    kind: DeadLetterStoreKind,
+}
+
+impl DeadLetterStore {
+    pub fn new(
+        project_path: &Path,
+        indexer_name: &str,
+        database: &Option<Arc<PostgresClient>>,
+    ) -> Self {
+        let kind = match database {
+            Some(database) => DeadLetterStoreKind::Postgres {
+                database: Arc::clone(database),
+                table: generate_dead_letters_table_name(indexer_name),
+            },
+            None => DeadLetterStoreKind::File {
+                path: project_path
+                    .join(".rindexer")
+                    .join("dead-letters")
+                    .join(format!("{}.jsonl", camel_to_snake(indexer_name))),
+                lock: Mutex::new(()),
+            },
+        };
+
+        DeadLetterStore { kind }
+    }
+
+    pub async fn insert(&self, dead_letter: &DeadLetter) -> Result<(), DeadLetterError> {
+        match &self.kind {
+            DeadLetterStoreKind::Postgres { database, table } => {
+                database
+                    .execute(
+                        &format!(
+                            r#"INSERT INTO {} ("id", "contract_name", "event_name", "network", "from_block", "to_block", "error", "attempts", "logs", "redrive", "created_at")
+                            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                            ON CONFLICT ("id") DO UPDATE SET "error" = EXCLUDED."error", "attempts" = EXCLUDED."attempts", "logs" = EXCLUDED."logs", "redrive" = EXCLUDED."redrive", "created_at" = EXCLUDED."created_at""#,
+                            table
+                        ),
+                        &[
+                            &dead_letter.id,
+                            &dead_letter.contract_name,
+                            &dead_letter.event_name,
+                            &dead_letter.network,
+                            &EthereumSqlTypeWrapper::U64(dead_letter.from_block),
+                            &EthereumSqlTypeWrapper::U64(dead_letter.to_block),
+                            &dead_letter.error,
+                            &(dead_letter.attempts as i32),
+                            &serde_json::to_value(&dead_letter.logs)?,
+                            &dead_letter.redrive,
+                            &dead_letter.created_at,
+                        ],
+                    )
+                    .await?;
+                Ok(())
+            }
+            DeadLetterStoreKind::File { path, lock } => {
+                let _guard = lock.lock().await;
+                let mut dead_letters = read_dead_letters_file(path).await?;
+                dead_letters.retain(|existing| existing.id != dead_letter.id);
+                dead_letters.push(dead_letter.clone());
+                write_dead_letters_file(path, &dead_letters).await
+            }
+        }
+    }
+
+    pub async fn list(&self) -> Result<Vec<DeadLetter>, DeadLetterError> {
+        match &self.kind {
+            DeadLetterStoreKind::Postgres { database, table } => {
+                let rows = database
+                    .query(
+                        &format!(
+                            r#"SELECT "id", "contract_name", "event_name", "network", "from_block", "to_block", "error", "attempts", "logs", "redrive", "created_at" FROM {} ORDER BY "created_at""#,
+                            table
+                        ),
+                        &[],
+                    )
+                    .await?;
+                rows.iter().map(dead_letter_from_row).collect()
+            }
+            DeadLetterStoreKind::File { path, lock } => {
+                let _guard = lock.lock().await;
+                read_dead_letters_file(path).await
+            }
+        }
+    }
+
+    /// Marks a dead letter, or every dead letter when no id is passed, to be re-driven the next
+    /// time the indexer starts. Returns how many were marked.
+    pub async fn mark_for_redrive(&self, id: Option<&str>) -> Result<usize, DeadLetterError> {
+        match &self.kind {
+            DeadLetterStoreKind::Postgres { database, table } => {
+                let marked = match id {
+                    Some(id) => {
+                        database
+                            .execute(
+                                &format!(
+                                    r#"UPDATE {} SET "redrive" = TRUE WHERE "id" = $1"#,
+                                    table
+                                ),
+                                &[&id],
+                            )
+                            .await?
+                    }
+                    None => {
+                        database
+                            .execute(&format!(r#"UPDATE {} SET "redrive" = TRUE"#, table), &[])
+                            .await?
+                    }
+                };
+                Ok(marked as usize)
+            }
+            DeadLetterStoreKind::File { path, lock } => {
+                let _guard = lock.lock().await;
+                let mut dead_letters = read_dead_letters_file(path).await?;
+                let mut marked = 0;
+                for dead_letter in
+                    dead_letters.iter_mut().filter(|d| id.is_none_or(|id| d.id == id))
+                {
+                    dead_letter.redrive = true;
+                    marked += 1;
+                }
+                write_dead_letters_file(path, &dead_letters).await?;
+                Ok(marked)
+            }
+        }
+    }
+
+    /// Returns the dead letters of the event which are marked to be re-driven, they stay in the
+    /// store until `remove` is called once their batch went through.
+    async fn redrives(
+        &self,
+        config: &EventProcessingConfig,
+    ) -> Result<Vec<DeadLetter>, DeadLetterError> {
+        match &self.kind {
+            DeadLetterStoreKind::Postgres { database, table } => {
+                let rows = database
+                    .query(
+                        &format!(
+                            r#"SELECT "id", "contract_name", "event_name", "network", "from_block", "to_block", "error", "attempts", "logs", "redrive", "created_at" FROM {}
+                            WHERE "redrive" = TRUE AND "contract_name" = $1 AND "event_name" = $2 AND "network" = $3 ORDER BY "from_block""#,
+                            table
+                        ),
+                        &[
+                            &config.contract_name,
+                            &config.event_name,
+                            &config.network_contract.network,
+                        ],
+                    )
+                    .await?;
+                rows.iter().map(dead_letter_from_row).collect()
+            }
+            DeadLetterStoreKind::File { path, lock } => {
+                let _guard = lock.lock().await;
+                let mut redrives = read_dead_letters_file(path).await?;
+                redrives.retain(|dead_letter| dead_letter.redrive && dead_letter.is_for(config));
+                redrives.sort_by_key(|dead_letter| dead_letter.from_block);
+                Ok(redrives)
+            }
+        }
+    }
+
+    /// Removes a dead letter, with postgres inside `in_transaction` it is removed in that
+    /// transaction.
+    pub async fn remove(&self, id: &str) -> Result<(), DeadLetterError> {
+        match &self.kind {
+            DeadLetterStoreKind::Postgres { database, table } => {
+                database
+                    .execute(&format!(r#"DELETE FROM {} WHERE "id" = $1"#, table), &[&id])
+                    .await?;
+                Ok(())
+            }
+            DeadLetterStoreKind::File { path, lock } => {
+                let _guard = lock.lock().await;
+                let mut dead_letters = read_dead_letters_file(path).await?;
+                let count = dead_letters.len();
+                dead_letters.retain(|dead_letter| dead_letter.id != id);
+                if dead_letters.len() != count {
+                    write_dead_letters_file(path, &dead_letters).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn dead_letter_from_row(row: &Row) -> Result<DeadLetter, DeadLetterError> {
+    let from_block: Decimal = row.get("from_block");
+    let to_block: Decimal = row.get("to_block");
+    let attempts: i32 = row.get("attempts");
+    let logs: serde_json::Value = row.get("logs");
+
+    Ok(DeadLetter {
+        id: row.get("id"),
+        contract_name: row.get("contract_name"),
+        event_name: row.get("event_name"),
+        network: row.get("network"),
+        from_block: U64::from_dec_str(&from_block.to_string()).unwrap_or_default(),
+        to_block: U64::from_dec_str(&to_block.to_string()).unwrap_or_default(),
+        error: row.get("error"),
+        attempts: attempts as u32,
+        logs: serde_json::from_value(logs)?,
+        redrive: row.get("redrive"),
+        created_at: row.get("created_at"),
+    })
+}
+
+async fn read_dead_letters_file(path: &Path) -> Result<Vec<DeadLetter>, DeadLetterError> {
+    let contents = match tokio::fs::read_to_string(path).await {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(DeadLetterError::from))
+        .collect()
+}
+
+async fn write_dead_letters_file(
+    path: &Path,
+    dead_letters: &[DeadLetter],
+) -> Result<(), DeadLetterError> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let mut contents = String::new();
+    for dead_letter in dead_letters {
+        contents.push_str(&serde_json::to_string(dead_letter)?);
+        contents.push('\n');
+    }
+
+    // write then rename so a crash never leaves a half written file behind
+    let temp_path = path.with_extension("jsonl.tmp");
+    tokio::fs::write(&temp_path, contents).await?;
+    tokio::fs::rename(&temp_path, path).await?;
+    Ok(())
+}
+
+/// Stores the batch as a dead letter, retrying until it is stored as dropping it would lose the
+/// logs for good.
+pub async fn store_dead_letter(store: &DeadLetterStore, dead_letter: DeadLetter) {
+    let mut delay = Duration::from_millis(100);
+
+    loop {
+        match store.insert(&dead_letter).await {
+            Ok(_) => {
+                warn!(
+                    "{}::{} - Dead lettered {} logs in blocks {} - {} after {} attempts - inspect with `rindexer dead-letters list`",
+                    dead_letter.contract_name,
+                    dead_letter.event_name,
+                    dead_letter.logs.len(),
+                    dead_letter.from_block,
+                    dead_letter.to_block,
+                    dead_letter.attempts
+                );
+                return;
+            }
+            Err(e) => {
+                if !is_running() {
+                    return;
+                }
+                error!(
+                    "{}::{} - Could not store the dead letter, retrying: {}",
+                    dead_letter.contract_name, dead_letter.event_name, e
+                );
+                delay = (delay * 2).min(Duration::from_secs(15));
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+}
+
+/// Runs the dead letters of the event marked with `rindexer dead-letters redrive` through the
+/// callback again, each is only removed from the store once its batch went through.
+pub async fn redrive_dead_letters(
+    config: &EventProcessingConfig,
+    store: &DeadLetterStore,
+) -> Result<(), DeadLetterError> {
+    let dead_letters = store.redrives(config).await?;
+    for dead_letter in dead_letters {
+        if !is_running() {
+            break;
+        }
+
+        info!(
+            "{} - Re-driving dead letter {} with {} logs",
+            config.info_log_name,
+            dead_letter.id,
+            dead_letter.logs.len()
+        );
+
+        let results = match event_results_for_logs(
+            config,
+            dead_letter.logs.clone(),
+            dead_letter.from_block,
+            dead_letter.to_block,
+        )
+        .await
+        {
+            Ok(results) => results,
+            Err(e) => {
+                // left marked in the store so the next start tries again
+                error!(
+                    "{} - Could not re-drive dead letter {}: {}",
+                    config.info_log_name, dead_letter.id, e
+                );
+                continue;
+            }
+        };
+
+        config.trigger_redrive(results, &dead_letter, store).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn dead_letter(from_block: u64, error: &str) -> DeadLetter {
+        DeadLetter {
+            id: format!("RocketPoolETH:Transfer:ethereum:{}-{}", from_block, from_block + 10),
+            contract_name: "RocketPoolETH".to_string(),
+            event_name: "Transfer".to_string(),
+            network: "ethereum".to_string(),
+            from_block: U64::from(from_block),
+            to_block: U64::from(from_block + 10),
+            error: error.to_string(),
+            attempts: 3,
+            logs: vec![WrappedLog::default()],
+            redrive: false,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_dead_letter_store() {
+        let project = tempdir().unwrap();
+        let store = DeadLetterStore::new(project.path(), "RocketPoolIndexer", &None);
+
+        store.insert(&dead_letter(100, "first")).await.unwrap();
+        store.insert(&dead_letter(200, "second")).await.unwrap();
+        // the same batch failing again replaces the previous dead letter
+        store.insert(&dead_letter(100, "again")).await.unwrap();
+
+        let dead_letters = store.list().await.unwrap();
+        assert_eq!(dead_letters.len(), 2);
+        assert!(dead_letters.iter().any(|d| d.error == "again"));
+
+        let id = dead_letters[0].id.clone();
+        assert_eq!(store.mark_for_redrive(Some(&id)).await.unwrap(), 1);
+        assert_eq!(store.mark_for_redrive(Some("missing")).await.unwrap(), 0);
+
+        let dead_letters = store.list().await.unwrap();
+        assert!(dead_letters.iter().all(|d| d.redrive == (d.id == id)));
+
+        store.remove(&id).await.unwrap();
+        let dead_letters = store.list().await.unwrap();
+        assert_eq!(dead_letters.len(), 1);
+        assert!(dead_letters.iter().all(|d| d.id != id));
+    }
+}
@@ -0,0 +1,497 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ethers::{
+    abi::{Abi, Event, RawLog, Token},
+    middleware::MiddlewareError,
+    prelude::ProviderError,
+    types::{Address, ValueOrArray, U64},
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{debug, info};
+
+use crate::{
+    event::{
+        contract_setup::{AddressDetails, FactoryDetails, IndexingContractSetup},
+        BuildRindexerFilterError, RindexerEventFilter,
+    },
+    helpers::{camel_to_snake, get_full_path},
+    provider::{JsonRpcCachedProvider, WrappedLog},
+    EthereumSqlTypeWrapper, PostgresClient,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum FactoryChildrenError {
+    #[error("Could not read factory ABI {0}: {1}")]
+    CouldNotReadAbi(String, String),
+
+    #[error("Factory event {0} not found in factory ABI")]
+    EventNotFoundInAbi(String),
+
+    #[error("Factory event {0} does not have an address input named {1}")]
+    InputNotFoundInEvent(String, String),
+
+    #[error("Could not load the discovered factory children: {0}")]
+    CouldNotLoad(String),
+
+    #[error("Could not store the discovered factory children: {0}")]
+    CouldNotStore(String),
+
+    #[error("Could not fetch factory logs: {0}")]
+    ProviderError(#[from] ProviderError),
+
+    #[error("Could not build the factory logs filter: {0}")]
+    BuildFilterError(#[from] BuildRindexerFilterError),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FactoryChild {
+    address: Address,
+    block_number: U64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FactoryChildrenFile {
+    last_synced_block: Option<U64>,
+    children: Vec<FactoryChild>,
+}
+
+enum FactoryChildrenStore {
+    Postgres { database: Arc<PostgresClient>, children_table: String, last_synced_table: String },
+    File(PathBuf),
+}
+
+/// The child contracts a factory has created on a network, discovered from the factory creation
+/// event and shared by every event of the child contract so discovery only happens once.
+pub struct FactoryChildren {
+    network: String,
+    contract_name: String,
+    factory_address: ValueOrArray<Address>,
+    event: Event,
+    input_name: String,
+    start_block: U64,
+    // the block each child was created in so a reorg can forget children created after the fork
+    children: RwLock<HashMap<Address, U64>>,
+    discovered_up_to: Mutex<Option<U64>>,
+    store: FactoryChildrenStore,
+}
+
+pub fn generate_factory_children_table_names(
+    indexer_name: &str,
+    contract_name: &str,
+) -> (String, String) {
+    let prefix = format!(
+        "rindexer_internal.{}_{}",
+        camel_to_snake(indexer_name),
+        camel_to_snake(contract_name)
+    );
+    (format!("{}_factory_children", prefix), format!("{}_factory_last_synced", prefix))
+}
+
+pub fn read_factory_event(
+    project_path: &Path,
+    factory: &FactoryDetails,
+) -> Result<Event, FactoryChildrenError> {
+    let abi_error = |e: String| FactoryChildrenError::CouldNotReadAbi(factory.abi.clone(), e);
+
+    let full_path =
+        get_full_path(project_path, &factory.abi).map_err(|e| abi_error(e.to_string()))?;
+    let abi_str = std::fs::read_to_string(full_path).map_err(|e| abi_error(e.to_string()))?;
+    let abi: Abi = serde_json::from_str(&abi_str).map_err(|e| abi_error(e.to_string()))?;
+
+    let event = abi
+        .event(&factory.event_name)
+        .map_err(|_| FactoryChildrenError::EventNotFoundInAbi(factory.event_name.clone()))?
+        .clone();
+
+    if !event.inputs.iter().any(|input| {
+        input.name == factory.input_name && input.kind == ethers::abi::ParamType::Address
+    }) {
+        return Err(FactoryChildrenError::InputNotFoundInEvent(
+            factory.event_name.clone(),
+            factory.input_name.clone(),
+        ));
+    }
+
+    Ok(event)
+}
+
+impl FactoryChildren {
+    #[allow(clippy::too_many_arguments)]
+    pub async fn load(
+        project_path: &Path,
+        indexer_name: &str,
+        contract_name: &str,
+        network: &str,
+        factory: &FactoryDetails,
+        start_block: U64,
+        database: &Option<Arc<PostgresClient>>,
+    ) -> Result<Arc<FactoryChildren>, FactoryChildrenError> {
+        let event = read_factory_event(project_path, factory)?;
+
+        let store = match database {
+            Some(database) => {
+                let (children_table, last_synced_table) =
+                    generate_factory_children_table_names(indexer_name, contract_name);
+                database
+                    .batch_execute(&format!(
+                        r#"
+                        CREATE SCHEMA IF NOT EXISTS rindexer_internal;
+                        CREATE TABLE IF NOT EXISTS {children_table} ("network" TEXT NOT NULL, "address" CHAR(42) NOT NULL, "block_number" NUMERIC NOT NULL, PRIMARY KEY ("network", "address"));
+                        CREATE TABLE IF NOT EXISTS {last_synced_table} ("network" TEXT PRIMARY KEY, "last_synced_block" NUMERIC);
+                        "#
+                    ))
+                    .await
+                    .map_err(|e| FactoryChildrenError::CouldNotLoad(e.to_string()))?;
+
+                FactoryChildrenStore::Postgres {
+                    database: Arc::clone(database),
+                    children_table,
+                    last_synced_table,
+                }
+            }
+            None => FactoryChildrenStore::File(
+                project_path
+                    .join(".rindexer")
+                    .join("factory")
+                    .join(format!("{}-{}.json", contract_name, network).to_lowercase()),
+            ),
+        };
+
+        let (children, discovered_up_to) = Self::load_from_store(&store, network).await?;
+
+        info!(
+            "{} - network {} - loaded {} discovered factory children",
+            contract_name,
+            network,
+            children.len()
+        );
+
+        Ok(Arc::new(FactoryChildren {
+            network: network.to_string(),
+            contract_name: contract_name.to_string(),
+            factory_address: factory.address.clone(),
+            event,
+            input_name: factory.input_name.clone(),
+            start_block,
+            children: RwLock::new(children),
+            discovered_up_to: Mutex::new(discovered_up_to),
+            store,
+        }))
+    }
+
+    async fn load_from_store(
+        store: &FactoryChildrenStore,
+        network: &str,
+    ) -> Result<(HashMap<Address, U64>, Option<U64>), FactoryChildrenError> {
+        match store {
+            FactoryChildrenStore::Postgres { database, children_table, last_synced_table } => {
+                let rows = database
+                    .query(
+                        &format!(
+                            "SELECT address, block_number FROM {} WHERE network = $1",
+                            children_table
+                        ),
+                        &[&network],
+                    )
+                    .await
+                    .map_err(|e| FactoryChildrenError::CouldNotLoad(e.to_string()))?;
+
+                let children = rows
+                    .iter()
+                    .filter_map(|row| {
+                        let address = row.get::<_, String>("address").parse::<Address>().ok()?;
+                        let block_number: Decimal = row.get("block_number");
+                        Some((address, U64::from_dec_str(&block_number.to_string()).ok()?))
+                    })
+                    .collect();
+
+                let last_synced_block = database
+                    .query_one_or_none(
+                        &format!(
+                            "SELECT last_synced_block FROM {} WHERE network = $1",
+                            last_synced_table
+                        ),
+                        &[&network],
+                    )
+                    .await
+                    .map_err(|e| FactoryChildrenError::CouldNotLoad(e.to_string()))?
+                    .and_then(|row| {
+                        let value: Decimal = row.get("last_synced_block");
+                        U64::from_dec_str(&value.to_string()).ok()
+                    });
+
+                Ok((children, last_synced_block))
+            }
+            FactoryChildrenStore::File(path) => {
+                if !path.exists() {
+                    return Ok((HashMap::new(), None));
+                }
+
+                let contents = tokio::fs::read_to_string(path)
+                    .await
+                    .map_err(|e| FactoryChildrenError::CouldNotLoad(e.to_string()))?;
+                let file: FactoryChildrenFile = serde_json::from_str(&contents)
+                    .map_err(|e| FactoryChildrenError::CouldNotLoad(e.to_string()))?;
+
+                Ok((
+                    file.children
+                        .into_iter()
+                        .map(|child| (child.address, child.block_number))
+                        .collect(),
+                    file.last_synced_block,
+                ))
+            }
+        }
+    }
+
+    /// Stores the children discovered up to `to_block`, with postgres the children and the
+    /// checkpoint are written in one transaction so a crash never moves one without the other.
+    async fn store(
+        &self,
+        new_children: &[(Address, U64)],
+        to_block: U64,
+    ) -> Result<(), FactoryChildrenError> {
+        match &self.store {
+            FactoryChildrenStore::Postgres { database, children_table, last_synced_table } => {
+                let addresses: Vec<String> =
+                    new_children.iter().map(|(address, _)| format!("{:?}", address)).collect();
+                let block_numbers: Vec<String> =
+                    new_children.iter().map(|(_, block_number)| block_number.to_string()).collect();
+
+                database
+                    .in_transaction(|_| async {
+                        if !new_children.is_empty() {
+                            database
+                                .execute(
+                                    &format!(
+                                        r#"INSERT INTO {} ("network", "address", "block_number") SELECT $1, "address", "block_number"::NUMERIC FROM UNNEST($2::TEXT[], $3::TEXT[]) AS children("address", "block_number") ON CONFLICT DO NOTHING"#,
+                                        children_table
+                                    ),
+                                    &[&self.network, &addresses, &block_numbers],
+                                )
+                                .await?;
+                        }
+
+                        database
+                            .execute(
+                                &format!(
+                                    r#"INSERT INTO {} ("network", "last_synced_block") VALUES ($1, $2) ON CONFLICT ("network") DO UPDATE SET "last_synced_block" = EXCLUDED."last_synced_block""#,
+                                    last_synced_table
+                                ),
+                                &[&self.network, &EthereumSqlTypeWrapper::U64(to_block)],
+                            )
+                            .await
+                    })
+                    .await
+                    .map_err(|e| FactoryChildrenError::CouldNotStore(e.to_string()))?;
+
+                Ok(())
+            }
+            FactoryChildrenStore::File(path) => self.write_file(path, to_block).await,
+        }
+    }
+
+    async fn write_file(&self, path: &Path, to_block: U64) -> Result<(), FactoryChildrenError> {
+        let file = FactoryChildrenFile {
+            last_synced_block: Some(to_block),
+            children: self
+                .children
+                .read()
+                .await
+                .iter()
+                .map(|(address, block_number)| FactoryChild {
+                    address: *address,
+                    block_number: *block_number,
+                })
+                .collect(),
+        };
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| FactoryChildrenError::CouldNotStore(e.to_string()))?;
+        }
+
+        let contents = serde_json::to_string(&file)
+            .map_err(|e| FactoryChildrenError::CouldNotStore(e.to_string()))?;
+
+        // write then rename so a crash never leaves a half written file behind
+        let temp_path = path.with_extension("json.tmp");
+        tokio::fs::write(&temp_path, contents)
+            .await
+            .map_err(|e| FactoryChildrenError::CouldNotStore(e.to_string()))?;
+        tokio::fs::rename(&temp_path, path)
+            .await
+            .map_err(|e| FactoryChildrenError::CouldNotStore(e.to_string()))
+    }
+
+    /// Forgets the children created after `fork_block` after a chain reorg and moves discovery
+    /// back to the fork so the canonical factory logs are read again.
+    pub async fn rollback_to_block(&self, fork_block: U64) -> Result<(), FactoryChildrenError> {
+        let mut discovered_up_to = self.discovered_up_to.lock().await;
+        let Some(discovered) = *discovered_up_to else {
+            return Ok(());
+        };
+        if discovered <= fork_block {
+            return Ok(());
+        }
+
+        let removed = {
+            let mut children = self.children.write().await;
+            let before = children.len();
+            children.retain(|_, block_number| *block_number <= fork_block);
+            before - children.len()
+        };
+        *discovered_up_to = Some(fork_block);
+
+        match &self.store {
+            FactoryChildrenStore::Postgres { database, children_table, last_synced_table } => {
+                database
+                    .in_transaction(|_| async {
+                        database
+                            .execute(
+                                &format!(
+                                    r#"DELETE FROM {} WHERE "network" = $1 AND "block_number" > $2"#,
+                                    children_table
+                                ),
+                                &[&self.network, &EthereumSqlTypeWrapper::U64(fork_block)],
+                            )
+                            .await?;
+
+                        database
+                            .execute(
+                                &format!(
+                                    r#"UPDATE {} SET "last_synced_block" = $2 WHERE "network" = $1"#,
+                                    last_synced_table
+                                ),
+                                &[&self.network, &EthereumSqlTypeWrapper::U64(fork_block)],
+                            )
+                            .await
+                    })
+                    .await
+                    .map_err(|e| FactoryChildrenError::CouldNotStore(e.to_string()))?;
+            }
+            FactoryChildrenStore::File(path) => self.write_file(path, fork_block).await?,
+        }
+
+        info!(
+            "{} - network {} - removed {} factory children created after block {}",
+            self.contract_name, self.network, removed, fork_block
+        );
+
+        Ok(())
+    }
+
+    fn decode_child_address(&self, log: &WrappedLog) -> Option<Address> {
+        let raw_log = RawLog { topics: log.inner.topics.clone(), data: log.inner.data.to_vec() };
+        let parsed = self.event.parse_log(raw_log).ok()?;
+
+        parsed.params.into_iter().find(|param| param.name == self.input_name).and_then(|param| {
+            match param.value {
+                Token::Address(address) => Some(address),
+                _ => None,
+            }
+        })
+    }
+
+    /// Makes sure every child created up to and including `to_block` is known, the factory logs
+    /// are fetched in ranges the rpc accepts and stored as they are discovered.
+    async fn discover_up_to(
+        &self,
+        cached_provider: &Arc<JsonRpcCachedProvider>,
+        to_block: U64,
+    ) -> Result<(), FactoryChildrenError> {
+        let mut discovered_up_to = self.discovered_up_to.lock().await;
+
+        let mut from_block = match *discovered_up_to {
+            Some(discovered_up_to) if discovered_up_to >= to_block => return Ok(()),
+            Some(discovered_up_to) => std::cmp::max(discovered_up_to + 1, self.start_block),
+            None => self.start_block,
+        };
+
+        let setup = IndexingContractSetup::Address(AddressDetails {
+            address: self.factory_address.clone(),
+            indexed_filters: None,
+        });
+
+        while from_block <= to_block {
+            let mut range_to_block = match cached_provider.max_block_range() {
+                Some(max_block_range) => std::cmp::min(from_block + max_block_range, to_block),
+                None => to_block,
+            };
+
+            let logs = loop {
+                let filter = RindexerEventFilter::new(
+                    &self.event.signature(),
+                    &self.event.name,
+                    &setup,
+                    from_block,
+                    range_to_block,
+                )?;
+
+                match cached_provider.get_logs(&filter).await {
+                    Ok(logs) => break logs,
+                    // most rpc errors on eth_getLogs are the range being too big so keep halving
+                    Err(e) if e.as_error_response().is_some() && range_to_block > from_block => {
+                        debug!(
+                            "{} - network {} - retrying factory logs with a smaller range: {}",
+                            self.contract_name, self.network, e
+                        );
+                        range_to_block = from_block + (range_to_block - from_block) / 2;
+                    }
+                    Err(e) => return Err(FactoryChildrenError::ProviderError(e)),
+                }
+            };
+
+            let new_children: Vec<(Address, U64)> = logs
+                .iter()
+                .filter_map(|log| {
+                    let block_number = log.inner.block_number?;
+                    self.decode_child_address(log).map(|address| (address, block_number))
+                })
+                .collect();
+
+            if !new_children.is_empty() {
+                let mut children = self.children.write().await;
+                for (address, block_number) in &new_children {
+                    children.entry(*address).or_insert(*block_number);
+                }
+                info!(
+                    "{} - network {} - discovered {} new factory children between blocks {} - {} ({} total)",
+                    self.contract_name,
+                    self.network,
+                    new_children.len(),
+                    from_block,
+                    range_to_block,
+                    children.len()
+                );
+            }
+
+            self.store(&new_children, range_to_block).await?;
+            *discovered_up_to = Some(range_to_block);
+            from_block = range_to_block + 1;
+        }
+
+        Ok(())
+    }
+
+    /// Drops any logs which were not emitted by a known child, discovering children up to the
+    /// block the logs were fetched to first so children created in the same range are included.
+    pub async fn filter_logs(
+        &self,
+        cached_provider: &Arc<JsonRpcCachedProvider>,
+        logs: Vec<WrappedLog>,
+        to_block: U64,
+    ) -> Result<Vec<WrappedLog>, FactoryChildrenError> {
+        self.discover_up_to(cached_provider, to_block).await?;
+
+        let children = self.children.read().await;
+        Ok(logs.into_iter().filter(|log| children.contains_key(&log.inner.address)).collect())
+    }
+}
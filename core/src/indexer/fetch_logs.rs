@@ -1,145 +1,330 @@
-use std::{error::Error, str::FromStr, sync::Arc, time::Duration};
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
 
 use ethers::{
     addressbook::Address,
     middleware::MiddlewareError,
-    prelude::{BlockNumber, JsonRpcError, ValueOrArray, H256, U64},
+    prelude::{Block, BlockNumber, JsonRpcError, ValueOrArray, H256, U64},
+    providers::ProviderError,
 };
 use regex::Regex;
+use rust_decimal::Decimal;
 use tokio::{
     sync::{mpsc, Semaphore},
     time::Instant,
 };
-use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::wrappers::ReceiverStream;
 use tracing::{debug, error, info, warn};
 
 use crate::{
+    database::postgres::generate::generate_block_range_table_name,
     event::{config::EventProcessingConfig, RindexerEventFilter},
-    indexer::{log_helpers::is_relevant_block, IndexingEventProgressStatus},
+    indexer::{
+        factory::{FactoryChildren, FactoryChildrenError},
+        function_calls::FunctionCallTracer,
+        log_helpers::is_relevant_block,
+        reorg::{block_hashes, safe_block_number, BlockHashTracker},
+        IndexingEventProgressStatus,
+    },
+    manifest::network::ReorgSafeDistance,
     provider::{JsonRpcCachedProvider, WrappedLog},
+    EthereumSqlTypeWrapper, PostgresClient,
 };
 
 pub struct FetchLogsResult {
     pub logs: Vec<WrappedLog>,
     pub from_block: U64,
     pub to_block: U64,
+    // This is only populated when a chain reorg was detected while live indexing, anything
+    // indexed above this block must be rolled back before these logs are processed
+    pub rollback_to_block: Option<U64>,
 }
 
+type LogsQueueItem = Result<FetchLogsResult, Box<dyn Error + Send>>;
+
 pub fn fetch_logs_stream(
     config: Arc<EventProcessingConfig>,
     force_no_live_indexing: bool,
-) -> impl tokio_stream::Stream<Item = Result<FetchLogsResult, Box<dyn Error + Send>>> + Send + Unpin
-{
-    let (tx, rx) = mpsc::unbounded_channel();
+) -> impl tokio_stream::Stream<Item = LogsQueueItem> + Send + Unpin {
+    let (tx, rx) = mpsc::channel(config.prefetch_depth);
 
     let initial_filter = config.to_event_filter().unwrap();
-    let contract_address = initial_filter.contract_address();
+    let topic_ids = vec![config.topic_id];
+    let info_log_name = config.info_log_name.clone();
+
+    tokio::spawn(fetch_logs(
+        config,
+        initial_filter,
+        topic_ids,
+        info_log_name,
+        force_no_live_indexing,
+        tx,
+    ));
+
+    ReceiverStream::new(rx)
+}
+
+pub type FetchLogsStream = ReceiverStream<LogsQueueItem>;
+
+/// Fetches the logs of every event of a contract on a network with one `eth_getLogs` per block
+/// range and splits them out into a stream per event, in the same order as the configs.
+pub fn fetch_contract_logs_streams(
+    configs: &[Arc<EventProcessingConfig>],
+    force_no_live_indexing: bool,
+) -> Vec<FetchLogsStream> {
+    let prefetch_depth = configs[0].prefetch_depth;
+    let (tx, mut rx) = mpsc::channel(prefetch_depth);
+
+    // every event shares the contract and network so the first config drives the fetching
+    let lead_config = Arc::clone(&configs[0]);
+    let topic_ids: Vec<H256> = configs.iter().map(|config| config.topic_id).collect();
+    let from_block = configs.iter().map(|config| config.start_block).min().unwrap_or_default();
+    let to_block = configs.iter().map(|config| config.end_block).max().unwrap_or_default();
+    let initial_filter = lead_config
+        .to_event_filter()
+        .unwrap()
+        .set_topic_ids(topic_ids.clone())
+        .set_from_block(from_block)
+        .set_to_block(to_block);
+    let info_log_name =
+        format!("{}::{}", lead_config.contract_name, lead_config.network_contract.network);
+
+    tokio::spawn(fetch_logs(
+        lead_config,
+        initial_filter,
+        topic_ids,
+        info_log_name,
+        force_no_live_indexing,
+        tx,
+    ));
+
+    let (event_txs, streams): (Vec<_>, Vec<_>) = configs
+        .iter()
+        .map(|_| {
+            let (event_tx, event_rx) = mpsc::channel(prefetch_depth);
+            (event_tx, ReceiverStream::new(event_rx))
+        })
+        .unzip();
+    let events: Vec<(H256, U64)> =
+        configs.iter().map(|config| (config.topic_id, config.start_block)).collect();
 
     tokio::spawn(async move {
-        let snapshot_to_block = initial_filter.get_to_block();
-        let from_block = initial_filter.get_from_block();
-        let mut current_filter = initial_filter;
-
-        // add any max block range limitation before we start processing
-        let mut max_block_range_limitation =
-            config.network_contract.cached_provider.max_block_range;
-        if max_block_range_limitation.is_some() {
-            current_filter = current_filter.set_to_block(calculate_process_historic_log_to_block(
-                &from_block,
-                &snapshot_to_block,
-                &max_block_range_limitation,
-            ));
-            warn!(
-                "{} - {} - max block range limitation of {} blocks applied - block range indexing will be slower then RPC providers supplying the optimal ranges - https://rindexer.xyz/docs/references/rpc-node-providers#rpc-node-providers",
-                config.info_log_name,
-                IndexingEventProgressStatus::Syncing.log(),
-                max_block_range_limitation.unwrap()
-            );
+        while let Some(result) = rx.recv().await {
+            for ((topic_id, start_block), event_tx) in events.iter().zip(&event_txs) {
+                let event_result = match &result {
+                    Ok(result) => match logs_for_event(result, topic_id, *start_block) {
+                        Some(event_result) => Ok(event_result),
+                        None => continue,
+                    },
+                    Err(e) => Err(Box::new(ProviderError::CustomError(e.to_string()))
+                        as Box<dyn Error + Send>),
+                };
+                // waits while the event is behind which in turn pauses the shared fetching
+                let _ = event_tx.send(event_result).await;
+            }
+
+            // every event stopped consuming so stop fetching
+            if event_txs.iter().all(|event_tx| event_tx.is_closed()) {
+                break;
+            }
         }
-        while current_filter.get_from_block() <= snapshot_to_block {
-            let semaphore_client = Arc::clone(&config.semaphore);
-            let permit = semaphore_client.acquire_owned().await;
-
-            match permit {
-                Ok(permit) => {
-                    let result = fetch_historic_logs_stream(
-                        &config.network_contract.cached_provider,
-                        &tx,
-                        &config.topic_id,
-                        current_filter.clone(),
-                        max_block_range_limitation,
-                        snapshot_to_block,
-                        &config.info_log_name,
-                    )
-                    .await;
-
-                    drop(permit);
-
-                    // slow indexing warn user
-                    if let Some(range) = max_block_range_limitation {
-                        warn!(
-                            "{} - RPC PROVIDER IS SLOW - Slow indexing mode enabled, max block range limitation: {} blocks - we advise using a faster provider who can predict the next block ranges.",
-                            &config.info_log_name,
+    });
+
+    streams
+}
+
+/// Picks the logs of one event out of the logs fetched for every event of the contract, anything
+/// before the block the event resumes from has already been indexed.
+fn logs_for_event(
+    result: &FetchLogsResult,
+    topic_id: &H256,
+    start_block: U64,
+) -> Option<FetchLogsResult> {
+    if result.to_block < start_block && result.rollback_to_block.is_none() {
+        return None;
+    }
+
+    let logs = result
+        .logs
+        .iter()
+        .filter(|log| {
+            log.inner.topics.first() == Some(topic_id) &&
+                log.inner.block_number.is_some_and(|block_number| block_number >= start_block)
+        })
+        .cloned()
+        .collect();
+
+    Some(FetchLogsResult {
+        logs,
+        from_block: result.from_block.max(start_block),
+        to_block: result.to_block,
+        rollback_to_block: result.rollback_to_block,
+    })
+}
+
+async fn fetch_logs(
+    config: Arc<EventProcessingConfig>,
+    initial_filter: RindexerEventFilter,
+    topic_ids: Vec<H256>,
+    info_log_name: String,
+    force_no_live_indexing: bool,
+    tx: mpsc::Sender<LogsQueueItem>,
+) {
+    let contract_address = initial_filter.contract_address();
+
+    let snapshot_to_block = initial_filter.get_to_block();
+    let from_block = initial_filter.get_from_block();
+    let mut current_filter = initial_filter;
+
+    // add any max block range limitation before we start processing
+    let configured_max_block_range = config.network_contract.cached_provider.max_block_range();
+    if let Some(max_block_range) = configured_max_block_range {
+        warn!(
+            "{} - {} - max block range limitation of {} blocks applied - block range indexing will be slower then RPC providers supplying the optimal ranges - https://rindexer.xyz/docs/references/rpc-node-providers#rpc-node-providers",
+            &info_log_name,
+            IndexingEventProgressStatus::Syncing.log(),
+            max_block_range
+        );
+    }
+
+    // start from the block range learnt on a previous run so it does not have to be relearnt
+    let block_range_store = BlockRangeStore::new(
+        &config.project_path,
+        &config.indexer_name,
+        &config.network_contract.network,
+        &config.database,
+    );
+    let mut block_range = AdaptiveBlockRange::new(
+        configured_max_block_range,
+        block_range_store.load(&info_log_name).await,
+    );
+    if block_range.limitation().is_some() {
+        current_filter = current_filter.set_to_block(calculate_process_historic_log_to_block(
+            &from_block,
+            &snapshot_to_block,
+            &block_range.limitation(),
+        ));
+    }
+
+    while current_filter.get_from_block() <= snapshot_to_block {
+        // wait for room in the queue before fetching so a slow consumer is not handed more logs
+        let Some(queue_slot) = reserve_queue_slot(&tx, &info_log_name).await else {
+            error!(
+                "{} - {} - Failed to send logs to stream consumer!",
+                &info_log_name,
+                IndexingEventProgressStatus::Syncing.log()
+            );
+            break;
+        };
+
+        let semaphore_client = Arc::clone(&config.semaphore);
+        let permit = semaphore_client.acquire_owned().await;
+
+        match permit {
+            Ok(permit) => {
+                let previous_block_range = block_range.limitation();
+                let result = fetch_historic_logs_stream(
+                    &config.network_contract.cached_provider,
+                    queue_slot,
+                    &topic_ids,
+                    &config.factory_children,
+                    &config.function_call,
+                    current_filter.clone(),
+                    &mut block_range,
+                    snapshot_to_block,
+                    &info_log_name,
+                )
+                .await;
+
+                drop(permit);
+
+                let adjusted_block_range = block_range.limitation();
+                if adjusted_block_range != previous_block_range {
+                    if let Some(range) = adjusted_block_range {
+                        debug!(
+                            "{} - {} - Block range adjusted to {} blocks",
+                            &info_log_name,
+                            IndexingEventProgressStatus::Syncing.log(),
                             range
                         );
-                    }
-
-                    if let Some(result) = result {
-                        current_filter = result.next;
-                        max_block_range_limitation = result.max_block_range_limitation;
-                    } else {
-                        break;
+                        block_range_store.save(range, &info_log_name).await;
                     }
                 }
-                Err(e) => {
-                    error!(
-                        "{} - {} - Semaphore error: {}",
-                        &config.info_log_name,
-                        IndexingEventProgressStatus::Syncing.log(),
-                        e
-                    );
-                    continue;
+
+                if let Some(result) = result {
+                    current_filter = result.next;
+                } else {
+                    break;
                 }
             }
+            Err(e) => {
+                error!(
+                    "{} - {} - Semaphore error: {}",
+                    &info_log_name,
+                    IndexingEventProgressStatus::Syncing.log(),
+                    e
+                );
+                continue;
+            }
         }
+    }
 
-        info!(
-            "{} - {} - Finished indexing historic events",
-            &config.info_log_name,
-            IndexingEventProgressStatus::Completed.log()
-        );
+    info!(
+        "{} - {} - Finished indexing historic events",
+        &info_log_name,
+        IndexingEventProgressStatus::Completed.log()
+    );
 
-        // Live indexing mode
-        if config.live_indexing && !force_no_live_indexing {
-            live_indexing_stream(
-                &config.network_contract.cached_provider,
-                &tx,
-                &contract_address,
-                &config.topic_id,
-                &config.indexing_distance_from_head,
-                current_filter,
-                &config.info_log_name,
-                &config.semaphore,
-                config.network_contract.disable_logs_bloom_checks,
-            )
-            .await;
-        }
-    });
+    // Live indexing mode
+    if config.live_indexing && !force_no_live_indexing {
+        live_indexing_stream(
+            &config.network_contract.cached_provider,
+            &tx,
+            &contract_address,
+            &topic_ids,
+            &config.factory_children,
+            &config.function_call,
+            &config.reorg_safe_distance,
+            current_filter,
+            &info_log_name,
+            &config.semaphore,
+            // function calls are not in the logs bloom so every block has to be traced
+            config.network_contract.disable_logs_bloom_checks || config.function_call.is_some(),
+        )
+        .await;
+    }
+}
 
-    UnboundedReceiverStream::new(rx)
+/// Function calls are not emitted as logs so they come from the traces instead.
+pub async fn get_logs_or_function_calls(
+    cached_provider: &Arc<JsonRpcCachedProvider>,
+    function_call: &Option<Arc<FunctionCallTracer>>,
+    filter: &RindexerEventFilter,
+) -> Result<Vec<WrappedLog>, ProviderError> {
+    match function_call {
+        Some(function_call) => function_call.get_calls(cached_provider, filter).await,
+        None => cached_provider.get_logs(filter).await,
+    }
 }
 
 struct ProcessHistoricLogsStreamResult {
     pub next: RindexerEventFilter,
-    pub max_block_range_limitation: Option<U64>,
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn fetch_historic_logs_stream(
     cached_provider: &Arc<JsonRpcCachedProvider>,
-    tx: &mpsc::UnboundedSender<Result<FetchLogsResult, Box<dyn Error + Send>>>,
-    topic_id: &H256,
+    queue_slot: mpsc::Permit<'_, LogsQueueItem>,
+    topic_ids: &[H256],
+    factory_children: &Option<Arc<FactoryChildren>>,
+    function_call: &Option<Arc<FunctionCallTracer>>,
     current_filter: RindexerEventFilter,
-    max_block_range_limitation: Option<U64>,
+    block_range: &mut AdaptiveBlockRange,
     snapshot_to_block: U64,
     info_log_name: &str,
 ) -> Option<ProcessHistoricLogsStreamResult> {
@@ -164,7 +349,6 @@ async fn fetch_historic_logs_stream(
 
         return Some(ProcessHistoricLogsStreamResult {
             next: current_filter.set_from_block(to_block),
-            max_block_range_limitation,
         });
     }
 
@@ -175,13 +359,51 @@ async fn fetch_historic_logs_stream(
         current_filter
     );
 
-    match cached_provider.get_logs(&current_filter).await {
+    let started = Instant::now();
+    match get_logs_or_function_calls(cached_provider, function_call, &current_filter).await {
         Ok(logs) => {
+            block_range.on_response(to_block - from_block, logs.len(), started.elapsed());
+
+            let logs = match filter_factory_children_logs(
+                factory_children,
+                cached_provider,
+                logs,
+                to_block,
+            )
+            .await
+            {
+                Ok(logs) => logs,
+                Err(err) => {
+                    error!(
+                        "{} - {} - Error discovering factory children: {}",
+                        info_log_name,
+                        IndexingEventProgressStatus::Syncing.log(),
+                        err
+                    );
+                    queue_slot.send(Err(Box::new(err)));
+                    return None;
+                }
+            };
+
+            let logs = match cached_provider.populate_block_timestamps(logs).await {
+                Ok(logs) => logs,
+                Err(err) => {
+                    error!(
+                        "{} - {} - Error resolving block timestamps: {}",
+                        info_log_name,
+                        IndexingEventProgressStatus::Syncing.log(),
+                        err
+                    );
+                    queue_slot.send(Err(Box::new(err)));
+                    return None;
+                }
+            };
+
             debug!(
-                "{} - {} - topic_id {}, Logs: {} from {} to {}",
+                "{} - {} - topic_ids {:?}, Logs: {} from {} to {}",
                 info_log_name,
                 IndexingEventProgressStatus::Syncing.log(),
-                topic_id,
+                topic_ids,
                 logs.len(),
                 from_block,
                 to_block
@@ -200,14 +422,12 @@ async fn fetch_historic_logs_stream(
             // clone here over the full logs way less overhead
             let last_log = logs.last().cloned();
 
-            if tx.send(Ok(FetchLogsResult { logs, from_block, to_block })).is_err() {
-                error!(
-                    "{} - {} - Failed to send logs to stream consumer!",
-                    IndexingEventProgressStatus::Syncing.log(),
-                    info_log_name
-                );
-                return None;
-            }
+            queue_slot.send(Ok(FetchLogsResult {
+                logs,
+                from_block,
+                to_block,
+                rollback_to_block: None,
+            }));
 
             if logs_empty {
                 info!(
@@ -221,7 +441,7 @@ async fn fetch_historic_logs_stream(
                     let new_to_block = calculate_process_historic_log_to_block(
                         &next_from_block,
                         &snapshot_to_block,
-                        &max_block_range_limitation,
+                        &block_range.limitation(),
                     );
 
                     debug!(
@@ -236,7 +456,6 @@ async fn fetch_historic_logs_stream(
                         next: current_filter
                             .set_from_block(next_from_block)
                             .set_to_block(new_to_block),
-                        max_block_range_limitation,
                     })
                 };
             }
@@ -259,7 +478,7 @@ async fn fetch_historic_logs_stream(
                     let new_to_block = calculate_process_historic_log_to_block(
                         &next_from_block,
                         &snapshot_to_block,
-                        &max_block_range_limitation,
+                        &block_range.limitation(),
                     );
 
                     debug!(
@@ -274,7 +493,6 @@ async fn fetch_historic_logs_stream(
                         next: current_filter
                             .set_from_block(next_from_block)
                             .set_to_block(new_to_block),
-                        max_block_range_limitation,
                     })
                 };
             }
@@ -290,11 +508,17 @@ async fn fetch_historic_logs_stream(
                         IndexingEventProgressStatus::Syncing.log(),
                         retry_result
                     );
+                    if block_range.on_retry(&retry_result, from_block) {
+                        warn!(
+                            "{} - RPC PROVIDER IS SLOW - Slow indexing mode enabled, max block range limitation: {} blocks - we advise using a faster provider who can predict the next block ranges.",
+                            info_log_name,
+                            retry_result.max_block_range.unwrap_or_default()
+                        );
+                    }
                     return Some(ProcessHistoricLogsStreamResult {
                         next: current_filter
                             .set_from_block(retry_result.from)
                             .set_to_block(retry_result.to),
-                        max_block_range_limitation: retry_result.max_block_range,
                     });
                 }
             }
@@ -306,7 +530,7 @@ async fn fetch_historic_logs_stream(
                 err
             );
 
-            let _ = tx.send(Err(Box::new(err)));
+            queue_slot.send(Err(Box::new(err)));
             return None;
         }
     }
@@ -319,10 +543,12 @@ async fn fetch_historic_logs_stream(
 #[allow(clippy::too_many_arguments)]
 async fn live_indexing_stream(
     cached_provider: &Arc<JsonRpcCachedProvider>,
-    tx: &mpsc::UnboundedSender<Result<FetchLogsResult, Box<dyn Error + Send>>>,
+    tx: &mpsc::Sender<LogsQueueItem>,
     contract_address: &Option<ValueOrArray<Address>>,
-    topic_id: &H256,
-    reorg_safe_distance: &U64,
+    topic_ids: &[H256],
+    factory_children: &Option<Arc<FactoryChildren>>,
+    function_call: &Option<Arc<FunctionCallTracer>>,
+    reorg_safe_distance: &ReorgSafeDistance,
     mut current_filter: RindexerEventFilter,
     info_log_name: &str,
     semaphore: &Arc<Semaphore>,
@@ -330,18 +556,50 @@ async fn live_indexing_stream(
 ) {
     let mut last_seen_block_number = U64::from(0);
 
+    // remembers recent block hashes so chain reorgs can be detected and rolled back
+    let mut block_hash_tracker = BlockHashTracker::new();
+    let mut pending_rollback_to_block: Option<U64> = None;
+
+    // blocks skipped by the logs bloom check are reported with the next batch so the
+    // processed ranges do not get a hole for every skipped block
+    let mut skipped_from_block: Option<U64> = None;
+
     // this is used for less busy chains to make sure they know rindexer is still alive
     let mut last_no_new_block_log_time = Instant::now();
     let log_no_new_block_interval = Duration::from_secs(300);
 
+    // wakes up on new heads when the network has a websocket rpc otherwise polls
+    let mut new_block_listener = cached_provider.new_block_listener();
+
     loop {
-        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        new_block_listener.wait().await;
 
         let latest_block = cached_provider.get_latest_block().await;
         match latest_block {
             Ok(latest_block) => {
                 if let Some(latest_block) = latest_block {
                     if let Some(latest_block_number) = latest_block.number {
+                        if let Some(fork_block) = detect_reorg(
+                            cached_provider,
+                            &mut block_hash_tracker,
+                            &latest_block,
+                            info_log_name,
+                        )
+                        .await
+                        {
+                            // only need to roll back if we already indexed blocks after the fork
+                            if fork_block + 1 < current_filter.get_from_block() {
+                                pending_rollback_to_block = Some(
+                                    pending_rollback_to_block
+                                        .map_or(fork_block, |pending| pending.min(fork_block)),
+                                );
+                                current_filter = current_filter.set_from_block(fork_block + 1);
+                                last_seen_block_number = fork_block;
+                                skipped_from_block =
+                                    skipped_from_block.filter(|block| *block <= fork_block);
+                            }
+                        }
+
                         if last_seen_block_number == latest_block_number {
                             debug!(
                                 "{} - {} - No new blocks to process...",
@@ -367,7 +625,25 @@ async fn live_indexing_stream(
                             last_seen_block_number
                         );
 
-                        let safe_block_number = latest_block_number - reorg_safe_distance;
+                        let safe_block_number = match safe_block_number(
+                            cached_provider,
+                            reorg_safe_distance,
+                            latest_block_number,
+                        )
+                        .await
+                        {
+                            Ok(safe_block_number) => safe_block_number,
+                            Err(e) => {
+                                error!(
+                                    "{} - {} - Error getting the {} block to index up to: {}",
+                                    info_log_name,
+                                    IndexingEventProgressStatus::Live.log(),
+                                    reorg_safe_distance,
+                                    e
+                                );
+                                continue;
+                            }
+                        };
                         let from_block = current_filter.get_from_block();
                         // check reorg distance and skip if not safe
                         if from_block > safe_block_number {
@@ -383,8 +659,9 @@ async fn live_indexing_stream(
 
                         let to_block = safe_block_number;
                         if from_block == to_block &&
+                            pending_rollback_to_block.is_none() &&
                             !disable_logs_bloom_checks &&
-                            !is_relevant_block(contract_address, topic_id, &latest_block)
+                            !is_relevant_block(contract_address, topic_ids, &latest_block)
                         {
                             debug!(
                                 "{} - {} - Skipping block {} as it's not relevant",
@@ -400,6 +677,7 @@ async fn live_indexing_stream(
                             );
                             current_filter = current_filter.set_from_block(to_block + 1);
                             last_seen_block_number = to_block;
+                            skipped_from_block.get_or_insert(from_block);
                             continue;
                         }
 
@@ -412,17 +690,69 @@ async fn live_indexing_stream(
                             current_filter
                         );
 
+                        let Some(queue_slot) = reserve_queue_slot(tx, info_log_name).await else {
+                            error!(
+                                "{} - {} - Failed to send logs to stream consumer!",
+                                info_log_name,
+                                IndexingEventProgressStatus::Live.log()
+                            );
+                            break;
+                        };
+
                         let semaphore_client = Arc::clone(semaphore);
                         let permit = semaphore_client.acquire_owned().await;
 
                         if let Ok(permit) = permit {
-                            match cached_provider.get_logs(&current_filter).await {
+                            match get_logs_or_function_calls(
+                                cached_provider,
+                                function_call,
+                                &current_filter,
+                            )
+                            .await
+                            {
                                 Ok(logs) => {
+                                    let logs = match filter_factory_children_logs(
+                                        factory_children,
+                                        cached_provider,
+                                        logs,
+                                        to_block,
+                                    )
+                                    .await
+                                    {
+                                        Ok(logs) => logs,
+                                        Err(err) => {
+                                            error!(
+                                                "{} - {} - Error discovering factory children: {}",
+                                                info_log_name,
+                                                IndexingEventProgressStatus::Live.log(),
+                                                err
+                                            );
+                                            drop(permit);
+                                            continue;
+                                        }
+                                    };
+
+                                    let logs =
+                                        match cached_provider.populate_block_timestamps(logs).await
+                                        {
+                                            Ok(logs) => logs,
+                                            Err(err) => {
+                                                error!(
+                                                "{} - {} - Error resolving block timestamps: {}",
+                                                info_log_name,
+                                                IndexingEventProgressStatus::Live.log(),
+                                                err
+                                            );
+                                                drop(permit);
+                                                continue;
+                                            }
+                                        };
+
                                     debug!(
-                                        "{} - {} - Live topic_id {}, Logs: {} from {} to {}",
+                                        "{} - {} - Live topic_ids {:?}, Logs: {} from {} to {}",
                                         info_log_name,
                                         IndexingEventProgressStatus::Live.log(),
-                                        topic_id,
+                                        topic_ids,
                                         logs.len(),
                                         from_block,
                                         to_block
@@ -443,18 +773,12 @@ async fn live_indexing_stream(
                                     // clone here over the full logs way less overhead
                                     let last_log = logs.last().cloned();
 
-                                    if tx
-                                        .send(Ok(FetchLogsResult { logs, from_block, to_block }))
-                                        .is_err()
-                                    {
-                                        error!(
-                                            "{} - {} - Failed to send logs to stream consumer!",
-                                            info_log_name,
-                                            IndexingEventProgressStatus::Live.log()
-                                        );
-                                        drop(permit);
-                                        break;
-                                    }
+                                    queue_slot.send(Ok(FetchLogsResult {
+                                        logs,
+                                        from_block: skipped_from_block.take().unwrap_or(from_block),
+                                        to_block,
+                                        rollback_to_block: pending_rollback_to_block.take(),
+                                    }));
 
                                     if logs_empty {
                                         current_filter =
@@ -509,14 +833,111 @@ async fn live_indexing_stream(
     }
 }
 
+/// Waits for a free slot in the logs queue, fetching pauses here while decoding and storage catch
+/// up. Returns `None` once the consumer has stopped.
+async fn reserve_queue_slot<'a>(
+    tx: &'a mpsc::Sender<LogsQueueItem>,
+    info_log_name: &str,
+) -> Option<mpsc::Permit<'a, LogsQueueItem>> {
+    let queued = tx.max_capacity() - tx.capacity();
+    if queued == tx.max_capacity() {
+        info!(
+            "{} - Log queue full ({}/{} batches) - fetching paused until indexing catches up",
+            info_log_name,
+            queued,
+            tx.max_capacity()
+        );
+    } else {
+        debug!("{} - Log queue depth {}/{} batches", info_log_name, queued, tx.max_capacity());
+    }
+
+    tx.reserve().await.ok()
+}
+
+/// For factory contracts only the logs emitted by a discovered child are kept.
+async fn filter_factory_children_logs(
+    factory_children: &Option<Arc<FactoryChildren>>,
+    cached_provider: &Arc<JsonRpcCachedProvider>,
+    logs: Vec<WrappedLog>,
+    to_block: U64,
+) -> Result<Vec<WrappedLog>, FactoryChildrenError> {
+    match factory_children {
+        Some(factory_children) => {
+            factory_children.filter_logs(cached_provider, logs, to_block).await
+        }
+        None => Ok(logs),
+    }
+}
+
+/// Checks the latest block still builds on the blocks we have seen before, if not it returns the
+/// block number the chain forked from.
+pub(crate) async fn detect_reorg(
+    cached_provider: &Arc<JsonRpcCachedProvider>,
+    block_hash_tracker: &mut BlockHashTracker,
+    latest_block: &Block<H256>,
+    info_log_name: &str,
+) -> Option<U64> {
+    let (block_number, block_hash, parent_hash) = block_hashes(latest_block)?;
+
+    let mut reorged = block_hash_tracker.is_reorged(block_number, block_hash, parent_hash);
+
+    // blocks got skipped between polls so make sure the last block we saw is still canonical
+    let blocks_skipped = block_hash_tracker
+        .latest()
+        .is_some_and(|(last_tracked_number, _)| last_tracked_number + 1 < block_number);
+    if !reorged && blocks_skipped {
+        match block_hash_tracker.latest_is_canonical(cached_provider).await {
+            Ok(is_canonical) => reorged = !is_canonical,
+            Err(e) => {
+                error!(
+                    "{} - {} - Error checking for chain reorg: {}",
+                    info_log_name,
+                    IndexingEventProgressStatus::Live.log(),
+                    e
+                );
+                return None;
+            }
+        }
+    }
+
+    let mut fork_block = None;
+    if reorged {
+        match block_hash_tracker.find_fork_point(cached_provider).await {
+            Ok(fork) => {
+                warn!(
+                    "{} - {} - Chain reorg detected at block {} - forked after block {:?}",
+                    info_log_name,
+                    IndexingEventProgressStatus::Live.log(),
+                    block_number,
+                    fork
+                );
+                fork_block = fork;
+            }
+            Err(e) => {
+                error!(
+                    "{} - {} - Error finding chain reorg fork point, will try again: {}",
+                    info_log_name,
+                    IndexingEventProgressStatus::Live.log(),
+                    e
+                );
+                return None;
+            }
+        }
+    }
+
+    block_hash_tracker.record(block_number, block_hash);
+
+    fork_block
+}
+
 #[derive(Debug)]
 struct RetryWithBlockRangeResult {
     from: BlockNumber,
     to: BlockNumber,
     // This is only populated if you are using an RPC provider
-    // who doesn't give block ranges, this tends to be providers
-    // which are a lot slower than others, expect these providers
-    // to be slow
+    // who has a fixed max block range and doesn't give block ranges,
+    // this tends to be providers which are a lot slower than others,
+    // expect these providers to be slow
     max_block_range: Option<U64>,
 }
 
@@ -525,6 +946,33 @@ fn retry_with_block_range(
     error: &JsonRpcError,
     from_block: U64,
     to_block: U64,
+) -> Option<RetryWithBlockRangeResult> {
+    if let Some(retry) = provider_block_range_limit(error, from_block) {
+        return Some(retry);
+    }
+
+    // Fallback range
+    if to_block > from_block {
+        let fallback_range = (to_block - from_block) / 2;
+        return Some(RetryWithBlockRangeResult {
+            from: BlockNumber::from(from_block),
+            to: BlockNumber::from(from_block + fallback_range),
+            max_block_range: None,
+        });
+    }
+
+    None
+}
+
+/// The block range limit of an `eth_getLogs` error if it is one of the known provider range or
+/// result size limits, the inner `Option` is the max block range when the provider states it.
+pub(crate) fn provider_block_range_limit_from_error(error: &JsonRpcError) -> Option<Option<U64>> {
+    provider_block_range_limit(error, U64::zero()).map(|retry| retry.max_block_range)
+}
+
+fn provider_block_range_limit(
+    error: &JsonRpcError,
+    from_block: U64,
 ) -> Option<RetryWithBlockRangeResult> {
     let error_message = &error.message;
     // some providers put the data in the data field
@@ -612,17 +1060,161 @@ fn retry_with_block_range(
         });
     }
 
-    // Fallback range
-    if to_block > from_block {
-        let fallback_range = (to_block - from_block) / 2;
-        return Some(RetryWithBlockRangeResult {
-            from: BlockNumber::from(from_block),
-            to: BlockNumber::from(from_block + fallback_range),
-            max_block_range: Some(fallback_range),
-        });
+    None
+}
+
+/// Responses quicker than this which also have few logs let the block range grow.
+const FAST_RESPONSE: Duration = Duration::from_secs(2);
+const SMALL_RESPONSE_LOGS: usize = 1_000;
+/// Responses with more logs than this shrink the block range.
+const LARGE_RESPONSE_LOGS: usize = 10_000;
+
+/// Learns the block range to request for historic logs, it grows after fast and small responses
+/// and shrinks on errors or large payloads.
+#[derive(Debug)]
+struct AdaptiveBlockRange {
+    // `None` means there is no limitation and the whole remaining range is requested
+    range: Option<U64>,
+    // the range can never grow past the configured `max_block_range` or a provider limit
+    ceiling: Option<U64>,
+}
+
+impl AdaptiveBlockRange {
+    fn new(max_block_range: Option<U64>, learnt_block_range: Option<U64>) -> Self {
+        let range = match (learnt_block_range, max_block_range) {
+            (Some(learnt), Some(max)) => Some(learnt.min(max)),
+            (learnt, max) => learnt.or(max),
+        };
+
+        AdaptiveBlockRange { range, ceiling: max_block_range }
     }
 
-    None
+    fn limitation(&self) -> Option<U64> {
+        self.range
+    }
+
+    fn on_response(&mut self, requested_range: U64, logs: usize, elapsed: Duration) {
+        if logs > LARGE_RESPONSE_LOGS {
+            self.range = Some((requested_range / 2).max(U64::one()));
+            return;
+        }
+
+        if let Some(range) = self.range {
+            // only grow once the full range has been requested, the end of the sync is smaller
+            if logs < SMALL_RESPONSE_LOGS && elapsed < FAST_RESPONSE && requested_range >= range {
+                let grown = range * 2;
+                self.range = Some(self.ceiling.map_or(grown, |ceiling| grown.min(ceiling)));
+            }
+        }
+    }
+
+    /// Shrinks the range to the retry range, returns true when a new provider limit was found.
+    fn on_retry(&mut self, retry: &RetryWithBlockRangeResult, from_block: U64) -> bool {
+        if let Some(to) = retry.to.as_number() {
+            let from = retry.from.as_number().unwrap_or(from_block);
+            self.range = Some(to.saturating_sub(from).max(U64::one()));
+        }
+
+        match retry.max_block_range {
+            Some(max_block_range) if self.ceiling.is_none_or(|c| max_block_range < c) => {
+                self.ceiling = Some(max_block_range);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+enum BlockRangeStoreKind {
+    Postgres { database: Arc<PostgresClient>, table: String },
+    File(PathBuf),
+}
+
+/// Persists the learnt block range per network so a restart does not relearn it.
+struct BlockRangeStore {
+    network: String,
+    kind: BlockRangeStoreKind,
+}
+
+impl BlockRangeStore {
+    fn new(
+        project_path: &Path,
+        indexer_name: &str,
+        network: &str,
+        database: &Option<Arc<PostgresClient>>,
+    ) -> Self {
+        let kind = match database {
+            Some(database) => BlockRangeStoreKind::Postgres {
+                database: Arc::clone(database),
+                table: generate_block_range_table_name(indexer_name),
+            },
+            None => BlockRangeStoreKind::File(
+                project_path
+                    .join(".rindexer")
+                    .join("block-ranges")
+                    .join(format!("{}.txt", network.to_lowercase())),
+            ),
+        };
+
+        BlockRangeStore { network: network.to_string(), kind }
+    }
+
+    async fn load(&self, info_log_name: &str) -> Option<U64> {
+        let result = match &self.kind {
+            BlockRangeStoreKind::Postgres { database, table } => database
+                .query_one_or_none(
+                    &format!("SELECT block_range FROM {} WHERE network = $1", table),
+                    &[&self.network],
+                )
+                .await
+                .map(|row| {
+                    row.and_then(|row| {
+                        let value: Decimal = row.get("block_range");
+                        U64::from_dec_str(&value.to_string()).ok()
+                    })
+                })
+                .map_err(|e| e.to_string()),
+            BlockRangeStoreKind::File(path) => match tokio::fs::read_to_string(path).await {
+                Ok(contents) => Ok(U64::from_dec_str(contents.trim()).ok()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.to_string()),
+            },
+        };
+
+        result.unwrap_or_else(|e| {
+            warn!("{} - Could not load the learnt block range: {}", info_log_name, e);
+            None
+        })
+    }
+
+    async fn save(&self, block_range: U64, info_log_name: &str) {
+        let result = match &self.kind {
+            BlockRangeStoreKind::Postgres { database, table } => database
+                .execute(
+                    &format!(
+                        r#"INSERT INTO {} ("network", "block_range") VALUES ($1, $2) ON CONFLICT ("network") DO UPDATE SET "block_range" = EXCLUDED."block_range""#,
+                        table
+                    ),
+                    &[&self.network, &EthereumSqlTypeWrapper::U64(block_range)],
+                )
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            BlockRangeStoreKind::File(path) => {
+                let write = async {
+                    if let Some(parent) = path.parent() {
+                        tokio::fs::create_dir_all(parent).await?;
+                    }
+                    tokio::fs::write(path, block_range.to_string()).await
+                };
+                write.await.map_err(|e| e.to_string())
+            }
+        };
+
+        if let Err(e) = result {
+            warn!("{} - Could not store the learnt block range: {}", info_log_name, e);
+        }
+    }
 }
 
 fn calculate_process_historic_log_to_block(
@@ -641,3 +1233,65 @@ fn calculate_process_historic_log_to_block(
         *snapshot_to_block
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_block_range() {
+        let mut block_range = AdaptiveBlockRange::new(Some(U64::from(4000)), Some(U64::from(1000)));
+        assert_eq!(block_range.limitation(), Some(U64::from(1000)));
+
+        // fast small responses grow up to the configured max block range
+        block_range.on_response(U64::from(1000), 10, Duration::from_millis(100));
+        assert_eq!(block_range.limitation(), Some(U64::from(2000)));
+        block_range.on_response(U64::from(2000), 10, Duration::from_millis(100));
+        block_range.on_response(U64::from(4000), 10, Duration::from_millis(100));
+        assert_eq!(block_range.limitation(), Some(U64::from(4000)));
+
+        // large payloads shrink it
+        block_range.on_response(U64::from(4000), LARGE_RESPONSE_LOGS + 1, FAST_RESPONSE);
+        assert_eq!(block_range.limitation(), Some(U64::from(2000)));
+
+        // provider limits become the new ceiling
+        let retry = RetryWithBlockRangeResult {
+            from: BlockNumber::from(100),
+            to: BlockNumber::from(600),
+            max_block_range: Some(U64::from(500)),
+        };
+        assert!(block_range.on_retry(&retry, U64::from(100)));
+        block_range.on_response(U64::from(500), 10, Duration::from_millis(100));
+        assert_eq!(block_range.limitation(), Some(U64::from(500)));
+    }
+
+    #[test]
+    fn test_logs_for_event() {
+        let transfer = H256::from_low_u64_be(1);
+        let approval = H256::from_low_u64_be(2);
+        let log = |topic_id: H256, block_number: u64| WrappedLog {
+            inner: ethers::types::Log {
+                topics: vec![topic_id],
+                block_number: Some(U64::from(block_number)),
+                ..Default::default()
+            },
+            block_timestamp: None,
+        };
+        let result = FetchLogsResult {
+            logs: vec![log(transfer, 10), log(approval, 11), log(transfer, 20)],
+            from_block: U64::from(10),
+            to_block: U64::from(30),
+            rollback_to_block: None,
+        };
+
+        let transfers = logs_for_event(&result, &transfer, U64::from(15)).unwrap();
+        assert_eq!(transfers.logs.len(), 1);
+        assert_eq!(transfers.from_block, U64::from(15));
+
+        let approvals = logs_for_event(&result, &approval, U64::from(10)).unwrap();
+        assert_eq!(approvals.logs.len(), 1);
+
+        // the event resumes after this range so nothing is sent
+        assert!(logs_for_event(&result, &approval, U64::from(31)).is_none());
+    }
+}
@@ -0,0 +1,356 @@
+use std::{collections::HashMap, sync::Arc};
+
+use ethers::{
+    abi::{Event, EventParam, Function, ParamType},
+    prelude::ValueOrArray,
+    providers::ProviderError,
+    types::{Action, Address, BigEndianHash, Bytes, CallType, Log, TraceFilter, H256, U256, U64},
+};
+use futures::future::try_join_all;
+
+use crate::{
+    abi::{
+        function_call_event_name, function_call_input_name, FUNCTION_CALL_FROM_INPUT,
+        FUNCTION_CALL_VALUE_INPUT,
+    },
+    event::RindexerEventFilter,
+    manifest::network::TraceMethod,
+    provider::{CallTracerFrame, JsonRpcCachedProvider, WrappedLog},
+};
+
+/// How many blocks are traced at the same time when using `debug_traceBlockByNumber`.
+const DEBUG_TRACE_BATCH_SIZE: usize = 10;
+
+/// The event a function call is indexed as, the caller and value are indexed inputs followed
+/// by the function inputs so the call data decodes as the log data.
+pub fn function_call_event(function: &Function) -> Event {
+    let mut inputs = vec![
+        EventParam {
+            name: FUNCTION_CALL_FROM_INPUT.to_string(),
+            kind: ParamType::Address,
+            indexed: true,
+        },
+        EventParam {
+            name: FUNCTION_CALL_VALUE_INPUT.to_string(),
+            kind: ParamType::Uint(256),
+            indexed: true,
+        },
+    ];
+    inputs.extend(function.inputs.iter().enumerate().map(|(index, input)| EventParam {
+        name: function_call_input_name(&input.name, index),
+        kind: input.kind.clone(),
+        indexed: false,
+    }));
+
+    Event { name: function_call_event_name(&function.name), inputs, anonymous: false }
+}
+
+/// A successful call to the contract function found in the traces.
+struct TracedCall {
+    from: Address,
+    to: Address,
+    value: U256,
+    input: Bytes,
+    block_number: U64,
+    block_hash: H256,
+    transaction_hash: H256,
+    transaction_index: U64,
+    // position of the call in the transaction traces, unique within the transaction
+    trace_index: usize,
+}
+
+/// Traces the calls made to a contract and turns every successful call to the function into a
+/// log of its function call event, so calls go through the same processing as events.
+#[derive(Debug, Clone)]
+pub struct FunctionCallTracer {
+    selector: [u8; 4],
+    topic_id: H256,
+    trace_method: TraceMethod,
+}
+
+impl FunctionCallTracer {
+    pub fn new(selector: [u8; 4], topic_id: H256, trace_method: TraceMethod) -> Self {
+        Self { selector, topic_id, trace_method }
+    }
+
+    pub async fn get_calls(
+        &self,
+        cached_provider: &Arc<JsonRpcCachedProvider>,
+        filter: &RindexerEventFilter,
+    ) -> Result<Vec<WrappedLog>, ProviderError> {
+        let addresses = filter.contract_address().map(|address| match address {
+            ValueOrArray::Value(address) => vec![address],
+            ValueOrArray::Array(addresses) => addresses,
+        });
+
+        let calls = match self.trace_method {
+            TraceMethod::TraceFilter => {
+                self.trace_filter_calls(cached_provider, filter, addresses).await?
+            }
+            TraceMethod::DebugTraceBlockByNumber => {
+                self.debug_trace_calls(cached_provider, filter, addresses).await?
+            }
+        };
+
+        Ok(calls.into_iter().map(|call| self.to_log(call)).collect())
+    }
+
+    fn is_function_call(
+        &self,
+        to: &Address,
+        input: &Bytes,
+        addresses: &Option<Vec<Address>>,
+    ) -> bool {
+        input.len() >= 4 &&
+            input[..4] == self.selector &&
+            addresses.as_ref().is_none_or(|addresses| addresses.contains(to))
+    }
+
+    async fn trace_filter_calls(
+        &self,
+        cached_provider: &Arc<JsonRpcCachedProvider>,
+        filter: &RindexerEventFilter,
+        addresses: Option<Vec<Address>>,
+    ) -> Result<Vec<TracedCall>, ProviderError> {
+        let mut trace_filter = TraceFilter::default()
+            .from_block(filter.get_from_block())
+            .to_block(filter.get_to_block());
+        if let Some(addresses) = &addresses {
+            trace_filter = trace_filter.to_address(addresses.clone());
+        }
+
+        let traces = cached_provider.trace_filter(&trace_filter).await?;
+
+        let mut calls = vec![];
+        // trace index and the reverted trace addresses for each transaction, traces come back
+        // depth first so a reverted parent is always seen before its children
+        let mut transactions: HashMap<H256, (usize, Vec<Vec<usize>>)> = HashMap::new();
+        for trace in traces {
+            let Some(transaction_hash) = trace.transaction_hash else {
+                continue;
+            };
+
+            let (trace_index, reverted) = transactions.entry(transaction_hash).or_default();
+            let index = *trace_index;
+            *trace_index += 1;
+
+            if trace.error.is_some() {
+                reverted.push(trace.trace_address.clone());
+                continue;
+            }
+            if reverted.iter().any(|reverted| trace.trace_address.starts_with(reverted)) {
+                continue;
+            }
+
+            if let Action::Call(call) = trace.action {
+                if call.call_type == CallType::Call &&
+                    self.is_function_call(&call.to, &call.input, &addresses)
+                {
+                    calls.push(TracedCall {
+                        from: call.from,
+                        to: call.to,
+                        value: call.value,
+                        input: call.input,
+                        block_number: U64::from(trace.block_number),
+                        block_hash: trace.block_hash,
+                        transaction_hash,
+                        transaction_index: U64::from(
+                            trace.transaction_position.unwrap_or_default(),
+                        ),
+                        trace_index: index,
+                    });
+                }
+            }
+        }
+
+        Ok(calls)
+    }
+
+    async fn debug_trace_calls(
+        &self,
+        cached_provider: &Arc<JsonRpcCachedProvider>,
+        filter: &RindexerEventFilter,
+        addresses: Option<Vec<Address>>,
+    ) -> Result<Vec<TracedCall>, ProviderError> {
+        let block_numbers: Vec<U64> = (filter.get_from_block().as_u64()..=
+            filter.get_to_block().as_u64())
+            .map(U64::from)
+            .collect();
+
+        let mut calls = vec![];
+        for batch in block_numbers.chunks(DEBUG_TRACE_BATCH_SIZE) {
+            let blocks = try_join_all(batch.iter().map(|block_number| {
+                self.debug_trace_block(cached_provider, *block_number, &addresses)
+            }))
+            .await?;
+            calls.extend(blocks.into_iter().flatten());
+        }
+
+        Ok(calls)
+    }
+
+    async fn debug_trace_block(
+        &self,
+        cached_provider: &Arc<JsonRpcCachedProvider>,
+        block_number: U64,
+        addresses: &Option<Vec<Address>>,
+    ) -> Result<Vec<TracedCall>, ProviderError> {
+        let traces = cached_provider.debug_trace_block_calls(block_number).await?;
+
+        // (transaction index, transaction hash if returned, trace index, frame)
+        let mut matches = vec![];
+        for (transaction_index, trace) in traces.iter().enumerate() {
+            let mut trace_index = 0;
+            self.collect_frames(trace.frame(), &mut trace_index, addresses, &mut |index, frame| {
+                matches.push((transaction_index, trace.transaction_hash(), index, frame.clone()))
+            });
+        }
+
+        if matches.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // the block is needed for its hash and for the transaction hashes older geth versions
+        // do not return
+        let block = cached_provider.get_block_by_number(block_number).await?.ok_or_else(|| {
+            ProviderError::CustomError(format!(
+                "RPC did not return block {} to index function calls",
+                block_number
+            ))
+        })?;
+        let block_hash = block.hash.ok_or_else(|| {
+            ProviderError::CustomError(format!("Block {} has no hash", block_number))
+        })?;
+
+        matches
+            .into_iter()
+            .map(|(transaction_index, transaction_hash, trace_index, frame)| {
+                let transaction_hash = transaction_hash
+                    .or_else(|| block.transactions.get(transaction_index).copied())
+                    .ok_or_else(|| {
+                        ProviderError::CustomError(format!(
+                            "Could not find transaction {} in block {}",
+                            transaction_index, block_number
+                        ))
+                    })?;
+
+                Ok(TracedCall {
+                    from: frame.from,
+                    to: frame.to.unwrap_or_default(),
+                    value: frame.value.unwrap_or_default(),
+                    input: frame.input,
+                    block_number,
+                    block_hash,
+                    transaction_hash,
+                    transaction_index: U64::from(transaction_index),
+                    trace_index,
+                })
+            })
+            .collect()
+    }
+
+    /// Walks the call frames depth first, anything under a reverted frame is skipped as its
+    /// state changes never happened.
+    fn collect_frames<'a>(
+        &self,
+        frame: &'a CallTracerFrame,
+        trace_index: &mut usize,
+        addresses: &Option<Vec<Address>>,
+        on_match: &mut impl FnMut(usize, &'a CallTracerFrame),
+    ) {
+        let index = *trace_index;
+        *trace_index += 1;
+
+        if frame.error.is_some() {
+            // the skipped frames still count towards the trace index to keep it stable
+            *trace_index += count_frames(frame) - 1;
+            return;
+        }
+
+        if frame.call_type.eq_ignore_ascii_case("call") {
+            if let Some(to) = &frame.to {
+                if self.is_function_call(to, &frame.input, addresses) {
+                    on_match(index, frame);
+                }
+            }
+        }
+
+        for call in frame.calls.iter().flatten() {
+            self.collect_frames(call, trace_index, addresses, on_match);
+        }
+    }
+
+    fn to_log(&self, call: TracedCall) -> WrappedLog {
+        WrappedLog {
+            inner: Log {
+                address: call.to,
+                topics: vec![self.topic_id, H256::from(call.from), H256::from_uint(&call.value)],
+                data: Bytes::from(call.input[4..].to_vec()),
+                block_hash: Some(call.block_hash),
+                block_number: Some(call.block_number),
+                transaction_hash: Some(call.transaction_hash),
+                transaction_index: Some(call.transaction_index),
+                log_index: Some(U256::from(call.trace_index)),
+                ..Default::default()
+            },
+            block_timestamp: None,
+        }
+    }
+}
+
+fn count_frames(frame: &CallTracerFrame) -> usize {
+    1 + frame.calls.iter().flatten().map(count_frames).sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(
+        to: Address,
+        input: &[u8],
+        error: Option<&str>,
+        calls: Vec<CallTracerFrame>,
+    ) -> CallTracerFrame {
+        CallTracerFrame {
+            call_type: "CALL".to_string(),
+            from: Address::zero(),
+            to: Some(to),
+            value: None,
+            input: Bytes::from(input.to_vec()),
+            error: error.map(|e| e.to_string()),
+            calls: Some(calls),
+        }
+    }
+
+    #[test]
+    fn test_collect_frames_skips_reverted_calls() {
+        let contract = Address::from_low_u64_be(1);
+        let selector = [1, 2, 3, 4];
+        let tracer = FunctionCallTracer::new(selector, H256::zero(), TraceMethod::TraceFilter);
+
+        let root = frame(
+            Address::from_low_u64_be(2),
+            &[9, 9, 9, 9],
+            None,
+            vec![
+                frame(
+                    contract,
+                    &selector,
+                    Some("execution reverted"),
+                    vec![frame(contract, &selector, None, vec![])],
+                ),
+                frame(contract, &selector, None, vec![]),
+            ],
+        );
+
+        let mut trace_index = 0;
+        let mut matched = vec![];
+        tracer.collect_frames(&root, &mut trace_index, &Some(vec![contract]), &mut |index, _| {
+            matched.push(index)
+        });
+
+        assert_eq!(matched, vec![3]);
+        assert_eq!(trace_index, 4);
+    }
+}
@@ -10,8 +10,18 @@ use tokio::{
 use tracing::error;
 
 use crate::{
+    database::{
+        postgres::client::PostgresError,
+        sqlite::{
+            client::{SqliteClient, SqliteError},
+            generate::{
+                generate_sqlite_event_table_name, generate_sqlite_last_synced_blocks_table_name,
+            },
+        },
+    },
     event::config::EventProcessingConfig,
     helpers::{camel_to_snake, get_full_path},
+    indexer::processed_ranges::record_processed_range,
     manifest::{storage::CsvDetails, stream::StreamsConfig},
     EthereumSqlTypeWrapper, PostgresClient,
 };
@@ -68,6 +78,7 @@ fn build_last_synced_block_number_file(
 pub struct SyncConfig<'a> {
     pub project_path: &'a Path,
     pub database: &'a Option<Arc<PostgresClient>>,
+    pub sqlite: &'a Option<Arc<SqliteClient>>,
     pub csv_details: &'a Option<CsvDetails>,
     pub stream_details: &'a Option<&'a StreamsConfig>,
     pub contract_csv_enabled: bool,
@@ -77,7 +88,88 @@ pub struct SyncConfig<'a> {
     pub network: &'a str,
 }
 
+async fn get_last_synced_block_number_sqlite(
+    sqlite: &SqliteClient,
+    indexer_name: &str,
+    contract_name: &str,
+    event_name: &str,
+    network: &str,
+) -> Result<Option<U64>, SqliteError> {
+    let query = format!(
+        "SELECT last_synced_block FROM \"{}\" WHERE event_table = ?1 AND network = ?2",
+        generate_sqlite_last_synced_blocks_table_name(indexer_name)
+    );
+    let result: Option<i64> = sqlite
+        .query_one_or_none(
+            &query,
+            vec![
+                EthereumSqlTypeWrapper::String(generate_sqlite_event_table_name(
+                    indexer_name,
+                    contract_name,
+                    event_name,
+                )),
+                EthereumSqlTypeWrapper::String(network.to_string()),
+            ],
+        )
+        .await?;
+
+    Ok(result.filter(|value| *value > 0).map(|value| U64::from(value as u64)))
+}
+
+/// Writes the last synced block to the sqlite checkpoint table, `only_forward` ignores a block
+/// lower than the current one.
+async fn set_last_synced_block_in_sqlite(
+    config: &EventProcessingConfig,
+    sqlite: &SqliteClient,
+    to_block: U64,
+    only_forward: bool,
+) -> Result<usize, SqliteError> {
+    let mut query = format!(
+        "UPDATE \"{}\" SET last_synced_block = ?1 WHERE event_table = ?2 AND network = ?3",
+        generate_sqlite_last_synced_blocks_table_name(&config.indexer_name)
+    );
+    if only_forward {
+        query.push_str(" AND ?1 > last_synced_block");
+    }
+
+    sqlite
+        .execute(
+            &query,
+            vec![
+                EthereumSqlTypeWrapper::U64(to_block),
+                EthereumSqlTypeWrapper::String(generate_sqlite_event_table_name(
+                    &config.indexer_name,
+                    &config.contract_name,
+                    &config.event_name,
+                )),
+                EthereumSqlTypeWrapper::String(config.network_contract.network.clone()),
+            ],
+        )
+        .await
+}
+
 pub async fn get_last_synced_block_number(config: SyncConfig<'_>) -> Option<U64> {
+    // SQLite keeps the last synced block next to the indexed rows when postgres is not enabled
+    if config.database.is_none() {
+        if let Some(sqlite) = config.sqlite {
+            return match get_last_synced_block_number_sqlite(
+                sqlite,
+                config.indexer_name,
+                config.contract_name,
+                config.event_name,
+                config.network,
+            )
+            .await
+            {
+                Ok(result) => result,
+                Err(e) => {
+                    error!("Error fetching last synced block from sqlite: {:?}", e);
+                    None
+                }
+            };
+        }
+    }
+
     // Check CSV file for last seen block as no database enabled
     if config.database.is_none() && config.contract_csv_enabled {
         if let Some(csv_details) = config.csv_details {
@@ -203,21 +295,97 @@ async fn update_last_synced_block_number_for_file(
         if let Some(last_block_value) = last_block { to_block > last_block_value } else { true };
 
     if last_block.is_none() || to_block_higher_then_last_block {
-        let temp_file_path = format!("{}.tmp", file_path);
+        write_last_synced_block_number_file(&file_path, to_block).await?;
+    }
 
-        let mut file = File::create(&temp_file_path).await?;
-        file.write_all(to_block.to_string().as_bytes()).await?;
-        file.sync_all().await?;
+    Ok(())
+}
 
-        fs::rename(temp_file_path, file_path).await?;
-    }
+async fn write_last_synced_block_number_file(
+    file_path: &str,
+    to_block: U64,
+) -> Result<(), UpdateLastSyncedBlockNumberFile> {
+    let temp_file_path = format!("{}.tmp", file_path);
+
+    let mut file = File::create(&temp_file_path).await?;
+    file.write_all(to_block.to_string().as_bytes()).await?;
+    file.sync_all().await?;
+
+    fs::rename(temp_file_path, file_path).await?;
 
     Ok(())
 }
 
+/// Moves the last synced block forward, a lower block than the current one is ignored.
+pub(crate) async fn update_last_synced_block_in_database(
+    config: &EventProcessingConfig,
+    database: &PostgresClient,
+    to_block: U64,
+) -> Result<u64, PostgresError> {
+    database
+        .execute(
+            &format!(
+                "UPDATE rindexer_internal.{}_{}_{} SET last_synced_block = $1 WHERE network = $2 AND $1 > last_synced_block",
+                camel_to_snake(&config.indexer_name),
+                camel_to_snake(&config.contract_name),
+                camel_to_snake(&config.event_name)
+            ),
+            &[&EthereumSqlTypeWrapper::U64(to_block), &config.network_contract.network],
+        )
+        .await
+}
+
+async fn update_last_synced_block(config: &Arc<EventProcessingConfig>, to_block: U64) {
+    if let Some(database) = &config.database {
+        if let Err(e) = update_last_synced_block_in_database(config, database, to_block).await {
+            error!("Error updating last synced block: {:?}", e);
+        }
+    } else if let Some(sqlite) = &config.sqlite {
+        if let Err(e) = set_last_synced_block_in_sqlite(config, sqlite, to_block, true).await {
+            error!("Error updating last synced block in sqlite: {:?}", e);
+        }
+    } else if let Some(csv_details) = &config.csv_details {
+        if let Err(e) = update_last_synced_block_number_for_file(
+            config,
+            &get_full_path(&config.project_path, &csv_details.path).unwrap_or_else(|_| {
+                panic!("failed to get full path {}", config.project_path.display())
+            }),
+            to_block,
+        )
+        .await
+        {
+            error!(
+                "Error updating last synced block to CSV - path - {} error - {:?}",
+                csv_details.path, e
+            );
+        }
+    } else if let Some(stream_last_synced_block_file_path) =
+        &config.stream_last_synced_block_file_path
+    {
+        if let Err(e) = update_last_synced_block_number_for_file(
+            config,
+            &config
+                .project_path
+                .join(stream_last_synced_block_file_path)
+                .canonicalize()
+                .expect("Failed to canonicalize path"),
+            to_block,
+        )
+        .await
+        {
+            error!(
+                "Error updating last synced block to stream - path - {} error - {:?}",
+                stream_last_synced_block_file_path, e
+            );
+        }
+    }
+}
+
 pub fn update_progress_and_last_synced_task(
     config: Arc<EventProcessingConfig>,
+    from_block: U64,
     to_block: U64,
+    progress_committed: bool,
     on_complete: impl FnOnce() + Send + 'static,
 ) {
     tokio::spawn(async move {
@@ -231,61 +399,68 @@ pub fn update_progress_and_last_synced_task(
             error!("Error updating last synced block: {:?}", e);
         }
 
-        if let Some(database) = &config.database {
-            let result = database
-                .execute(
-                    &format!(
-                        "UPDATE rindexer_internal.{}_{}_{} SET last_synced_block = $1 WHERE network = $2 AND $1 > last_synced_block",
-                        camel_to_snake(&config.indexer_name),
-                        camel_to_snake(&config.contract_name),
-                        camel_to_snake(&config.event_name)
-                    ),
-                    &[
-                        &EthereumSqlTypeWrapper::U64(to_block),
-                        &config.network_contract.network,
-                    ],
-                )
-                .await;
-
-            if let Err(e) = result {
-                error!("Error updating last synced block: {:?}", e);
+        // with postgres they are committed in the same transaction as the indexed rows
+        if !progress_committed {
+            // backfills re-index an old range so the last synced block stays where it is
+            if config.update_last_synced_block {
+                update_last_synced_block(&config, to_block).await;
             }
-        } else if let Some(csv_details) = &config.csv_details {
-            if let Err(e) = update_last_synced_block_number_for_file(
-                &config,
-                &get_full_path(&config.project_path, &csv_details.path).unwrap_or_else(|_| {
-                    panic!("failed to get full path {}", config.project_path.display())
-                }),
-                to_block,
+
+            // recorded after the checkpoint so a crash in between shows up as a gap to re-index
+            record_processed_range(&config, from_block, to_block).await;
+        }
+
+        on_complete();
+    });
+}
+
+/// Moves the last synced block backwards, unlike `update_progress_and_last_synced_task` this
+/// will always write the block even if it is lower than the current one (used for reorgs).
+pub async fn rewind_last_synced_block(config: &Arc<EventProcessingConfig>, to_block: U64) {
+    if let Some(database) = &config.database {
+        let result = database
+            .execute(
+                &format!(
+                    "UPDATE rindexer_internal.{}_{}_{} SET last_synced_block = $1 WHERE network = $2",
+                    camel_to_snake(&config.indexer_name),
+                    camel_to_snake(&config.contract_name),
+                    camel_to_snake(&config.event_name)
+                ),
+                &[&EthereumSqlTypeWrapper::U64(to_block), &config.network_contract.network],
             )
-            .await
-            {
-                error!(
-                    "Error updating last synced block to CSV - path - {} error - {:?}",
-                    csv_details.path, e
-                );
-            }
+            .await;
+
+        if let Err(e) = result {
+            error!("Error rewinding last synced block: {:?}", e);
+        }
+    } else if let Some(sqlite) = &config.sqlite {
+        if let Err(e) = set_last_synced_block_in_sqlite(config, sqlite, to_block, false).await {
+            error!("Error rewinding last synced block in sqlite: {:?}", e);
+        }
+    } else {
+        let full_path = if let Some(csv_details) = &config.csv_details {
+            get_full_path(&config.project_path, &csv_details.path).ok()
         } else if let Some(stream_last_synced_block_file_path) =
             &config.stream_last_synced_block_file_path
         {
-            if let Err(e) = update_last_synced_block_number_for_file(
-                &config,
-                &config
-                    .project_path
-                    .join(stream_last_synced_block_file_path)
-                    .canonicalize()
-                    .expect("Failed to canonicalize path"),
-                to_block,
-            )
-            .await
-            {
+            config.project_path.join(stream_last_synced_block_file_path).canonicalize().ok()
+        } else {
+            None
+        };
+
+        if let Some(full_path) = full_path {
+            let file_path = build_last_synced_block_number_file(
+                &full_path,
+                &config.contract_name,
+                &config.network_contract.network,
+                &config.event_name,
+            );
+            if let Err(e) = write_last_synced_block_number_file(&file_path, to_block).await {
                 error!(
-                    "Error updating last synced block to stream - path - {} error - {:?}",
-                    stream_last_synced_block_file_path, e
+                    "Error rewinding last synced block file - path - {} error - {:?}",
+                    file_path, e
                 );
             }
         }
-
-        on_complete();
-    });
+    }
 }
@@ -96,7 +96,7 @@ pub fn topic_in_bloom(topic_id: H256, logs_bloom: Bloom) -> bool {
 
 pub fn is_relevant_block(
     contract_address: &Option<ValueOrArray<Address>>,
-    topic_id: &H256,
+    topic_ids: &[H256],
     latest_block: &Block<H256>,
 ) -> bool {
     match latest_block.logs_bloom {
@@ -117,7 +117,7 @@ pub fn is_relevant_block(
                 }
             }
 
-            if !topic_in_bloom(*topic_id, logs_bloom) {
+            if topic_ids.iter().all(|topic_id| !topic_in_bloom(*topic_id, logs_bloom)) {
                 return false;
             }
 
@@ -4,24 +4,39 @@ mod progress;
 pub use progress::{IndexingEventProgressStatus, IndexingEventsProgressState};
 use serde::{Deserialize, Serialize};
 
+pub mod backfill;
+pub mod block_handlers;
 mod log_helpers;
 pub use log_helpers::parse_topic;
+pub mod dead_letters;
 mod dependency;
 pub use dependency::ContractEventDependenciesMapFromRelationshipsError;
-mod fetch_logs;
-mod last_synced;
+pub mod factory;
+pub(crate) mod fetch_logs;
+pub mod function_calls;
+pub(crate) mod last_synced;
+pub mod native_transfers;
 pub mod no_code;
-mod reorg;
+pub mod plan;
+pub mod processed_ranges;
+pub mod raw_logs;
+pub(crate) mod reorg;
 pub mod start;
 pub mod task_tracker;
 
 pub use dependency::{ContractEventDependencies, EventDependencies, EventsDependencyTree};
 
-use crate::manifest::contract::Contract;
+use crate::manifest::{block_handler::BlockHandler, contract::Contract, entity::Entity};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Indexer {
     pub name: String,
 
     pub contracts: Vec<Contract>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entities: Vec<Entity>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub block_handlers: Vec<BlockHandler>,
 }
@@ -0,0 +1,299 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ethers::types::{Address, Transaction, U64};
+use futures::FutureExt;
+use tracing::{error, info};
+
+use crate::{
+    database::postgres::{
+        client::PostgresConnectionError,
+        generate::{generate_event_table_full_name, generate_indexer_contract_schema_name},
+    },
+    event::block_callback_registry::{BlockCallbackRegistryInformation, BlockResult},
+    manifest::{block_handler::NativeTransfers, core::Manifest},
+    provider::{CreateNetworkProvider, JsonRpcCachedProvider, RetryClientError},
+    AsyncCsvAppender, EthereumSqlTypeWrapper, PostgresClient,
+};
+
+const NATIVE_TRANSFER_TABLE_NAME: &str = "NativeTransfer";
+
+const NATIVE_TRANSFER_COLUMNS: [(&str, &str); 9] = [
+    ("from", "CHAR(42) NOT NULL"),
+    ("to", "CHAR(42) NOT NULL"),
+    ("value", "VARCHAR(78) NOT NULL"),
+    ("tx_hash", "CHAR(66) NOT NULL"),
+    ("block_number", "BIGINT NOT NULL"),
+    ("block_hash", "CHAR(66) NOT NULL"),
+    ("network", "VARCHAR(50) NOT NULL"),
+    ("tx_index", "INTEGER NOT NULL"),
+    ("block_timestamp", "TIMESTAMPTZ"),
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum NativeTransfersError {
+    #[error("{0}")]
+    PostgresConnectionError(#[from] PostgresConnectionError),
+
+    #[error("Could not create the native transfers table: {0}")]
+    CouldNotCreateTable(String),
+
+    #[error("Could not create the network providers: {0}")]
+    RetryClientError(#[from] RetryClientError),
+
+    #[error("Could not create the native transfers csv file: {0}")]
+    CouldNotCreateCsv(#[from] std::io::Error),
+
+    #[error("Could not append headers to csv: {0}")]
+    CsvHeadersAppendError(#[from] csv::Error),
+}
+
+struct NativeTransfersParams {
+    addresses: Vec<Address>,
+    providers: HashMap<String, Arc<JsonRpcCachedProvider>>,
+    postgres: Option<Arc<PostgresClient>>,
+    postgres_table_name: String,
+    csv: Option<AsyncCsvAppender>,
+}
+
+fn native_transfer_column_names() -> Vec<String> {
+    NATIVE_TRANSFER_COLUMNS.iter().map(|(name, _)| name.to_string()).collect()
+}
+
+/// A transaction is a native transfer for the configured addresses when it moves value to or
+/// from one of them.
+fn is_native_transfer(transaction: &Transaction, addresses: &[Address]) -> bool {
+    !transaction.value.is_zero() &&
+        (addresses.contains(&transaction.from) ||
+            transaction.to.is_some_and(|to| addresses.contains(&to)))
+}
+
+async fn handle_native_transfers(
+    params: &NativeTransfersParams,
+    results: Vec<BlockResult>,
+) -> Result<(), String> {
+    let transfers: Vec<(&BlockResult, &Transaction)> = results
+        .iter()
+        .flat_map(|result| {
+            result
+                .transactions
+                .iter()
+                .filter(|transaction| is_native_transfer(transaction, &params.addresses))
+                .map(move |transaction| (result, transaction))
+        })
+        .collect();
+
+    if transfers.is_empty() {
+        return Ok(());
+    }
+
+    // the value only moved if the transaction succeeded
+    let network = &transfers[0].0.network;
+    let provider = params
+        .providers
+        .get(network)
+        .ok_or_else(|| format!("No provider found for network {}", network))?;
+    let transaction_hashes: Vec<_> =
+        transfers.iter().map(|(_, transaction)| transaction.hash).collect();
+    let receipts =
+        provider.get_transaction_receipts(&transaction_hashes).await.map_err(|e| e.to_string())?;
+
+    let mut postgres_bulk_data: Vec<Vec<EthereumSqlTypeWrapper>> = vec![];
+    let mut csv_bulk_data: Vec<Vec<String>> = vec![];
+    for (result, transaction) in transfers {
+        let succeeded = receipts
+            .get(&transaction.hash)
+            .is_none_or(|receipt| receipt.status != Some(U64::zero()));
+        if !succeeded {
+            continue;
+        }
+
+        let to = transaction.to.unwrap_or_default();
+        let block_number = result.block.number.unwrap_or_default();
+        let block_hash = result.block.hash.unwrap_or_default();
+        let transaction_index = transaction.transaction_index.unwrap_or_default();
+        let block_timestamp = result.block_timestamp_to_datetime();
+
+        if params.postgres.is_some() {
+            postgres_bulk_data.push(vec![
+                EthereumSqlTypeWrapper::Address(transaction.from),
+                EthereumSqlTypeWrapper::Address(to),
+                EthereumSqlTypeWrapper::U256(transaction.value),
+                EthereumSqlTypeWrapper::H256(transaction.hash),
+                EthereumSqlTypeWrapper::U64BigInt(block_number),
+                EthereumSqlTypeWrapper::H256(block_hash),
+                EthereumSqlTypeWrapper::String(result.network.clone()),
+                EthereumSqlTypeWrapper::U32(transaction_index.as_u32()),
+                EthereumSqlTypeWrapper::DateTimeNullable(block_timestamp),
+            ]);
+        }
+
+        if params.csv.is_some() {
+            csv_bulk_data.push(vec![
+                format!("{:?}", transaction.from),
+                format!("{:?}", to),
+                transaction.value.to_string(),
+                format!("{:?}", transaction.hash),
+                block_number.to_string(),
+                format!("{:?}", block_hash),
+                result.network.clone(),
+                transaction_index.to_string(),
+                block_timestamp.map(|timestamp| timestamp.to_rfc3339()).unwrap_or_default(),
+            ]);
+        }
+    }
+
+    let indexed_count = postgres_bulk_data.len().max(csv_bulk_data.len());
+
+    if let Some(postgres) = &params.postgres {
+        postgres
+            .insert_bulk(
+                &params.postgres_table_name,
+                &native_transfer_column_names(),
+                &postgres_bulk_data,
+            )
+            .await
+            .map_err(|e| {
+                error!("NativeTransfers - Error performing bulk insert: {}", e);
+                e
+            })?;
+    }
+
+    if let Some(csv) = &params.csv {
+        if !csv_bulk_data.is_empty() {
+            csv.append_bulk(csv_bulk_data).await.map_err(|e| e.to_string())?;
+        }
+    }
+
+    info!("NativeTransfers - network {} - {} native transfers indexed", network, indexed_count);
+
+    Ok(())
+}
+
+async fn create_native_transfers_table(
+    postgres: &PostgresClient,
+    indexer_name: &str,
+    table_name: &str,
+) -> Result<(), NativeTransfersError> {
+    let columns = NATIVE_TRANSFER_COLUMNS
+        .iter()
+        .map(|(name, data_type)| format!("\"{}\" {}", name, data_type))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // a replayed batch is skipped by the unique transfer key when it is inserted again
+    let schema_name =
+        generate_indexer_contract_schema_name(indexer_name, NativeTransfers::HANDLER_NAME);
+    postgres
+        .batch_execute(&format!(
+            r#"CREATE SCHEMA IF NOT EXISTS {schema_name};
+            CREATE TABLE IF NOT EXISTS {table_name} (rindexer_id SERIAL PRIMARY KEY NOT NULL, {columns});
+            CREATE UNIQUE INDEX IF NOT EXISTS native_transfer_identity ON {table_name} ("network", "tx_hash", "tx_index");"#
+        ))
+        .await
+        .map_err(|e| NativeTransfersError::CouldNotCreateTable(e.to_string()))
+}
+
+async fn create_native_transfers_csv(
+    project_path: &Path,
+    csv_path: &str,
+) -> Result<AsyncCsvAppender, NativeTransfersError> {
+    let csv_folder = project_path
+        .join(csv_path.strip_prefix("./").unwrap_or(csv_path))
+        .join(NativeTransfers::HANDLER_NAME);
+    tokio::fs::create_dir_all(&csv_folder).await?;
+
+    let csv_file: PathBuf = csv_folder.join("native_transfers.csv");
+    let csv_appender = AsyncCsvAppender::new(&csv_file.display().to_string());
+    if !csv_file.exists() {
+        csv_appender.append_header(native_transfer_column_names()).await?;
+    }
+
+    Ok(csv_appender)
+}
+
+/// The built-in block handler which stores native value transfers to or from the configured
+/// addresses, transfers made by contracts inside a transaction are not included.
+pub async fn native_transfers_block_handler(
+    project_path: &Path,
+    manifest: &Manifest,
+    native_transfers: &NativeTransfers,
+) -> Result<BlockCallbackRegistryInformation, NativeTransfersError> {
+    let postgres_table_name = generate_event_table_full_name(
+        &manifest.name,
+        NativeTransfers::HANDLER_NAME,
+        NATIVE_TRANSFER_TABLE_NAME,
+    );
+
+    let postgres = if manifest.storage.postgres_enabled() {
+        let postgres = PostgresClient::new().await?;
+        create_native_transfers_table(&postgres, &manifest.name, &postgres_table_name).await?;
+        Some(Arc::new(postgres))
+    } else {
+        None
+    };
+
+    let csv = match &manifest.storage.csv {
+        Some(csv_details)
+            if manifest.storage.csv_enabled() && native_transfers.generate_csv.unwrap_or(true) =>
+        {
+            Some(create_native_transfers_csv(project_path, &csv_details.path).await?)
+        }
+        _ => None,
+    };
+
+    let providers = CreateNetworkProvider::create(manifest)?
+        .into_iter()
+        .map(|provider| (provider.network_name, provider.client))
+        .collect();
+
+    let params = Arc::new(NativeTransfersParams {
+        addresses: native_transfers.addresses.clone(),
+        providers,
+        postgres,
+        postgres_table_name,
+        csv,
+    });
+
+    Ok(BlockCallbackRegistryInformation {
+        name: NativeTransfers::HANDLER_NAME.to_string(),
+        callback: Arc::new(move |results| {
+            let params = Arc::clone(&params);
+            async move { handle_native_transfers(&params, results).await }.boxed()
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::U256;
+
+    use super::*;
+
+    #[test]
+    fn test_is_native_transfer() {
+        let treasury = Address::from_low_u64_be(1);
+        let other = Address::from_low_u64_be(2);
+        let addresses = vec![treasury];
+
+        let incoming = Transaction {
+            from: other,
+            to: Some(treasury),
+            value: U256::from(1),
+            ..Default::default()
+        };
+        assert!(is_native_transfer(&incoming, &addresses));
+
+        let outgoing = Transaction { from: treasury, to: Some(other), ..incoming.clone() };
+        assert!(is_native_transfer(&outgoing, &addresses));
+
+        let no_value = Transaction { value: U256::zero(), ..incoming.clone() };
+        assert!(!is_native_transfer(&no_value, &addresses));
+
+        let unrelated = Transaction { from: other, to: Some(other), ..incoming };
+        assert!(!is_native_transfer(&unrelated, &addresses));
+    }
+}
@@ -1,30 +1,49 @@
 use std::{
+    collections::HashSet,
     io,
     path::{Path, PathBuf},
     sync::Arc,
 };
 
 use colored::Colorize;
-use ethers::abi::{Abi, Contract as EthersContract, Event};
+use ethers::{
+    abi::{Abi, Contract as EthersContract, Event},
+    types::U64,
+};
 use serde_json::Value;
 use tokio_postgres::types::Type as PgType;
 use tracing::{debug, error, info, warn};
 
 use crate::{
-    abi::{ABIItem, CreateCsvFileForEvent, EventInfo, ParamTypeError, ReadAbiError},
+    abi::{
+        function_call_event_name, ABIItem, CreateCsvFileForEvent, EventInfo, ParamTypeError,
+        ReadAbiError,
+    },
     chat::ChatClients,
-    database::postgres::{
-        client::PostgresClient,
-        generate::{
-            generate_column_names_only_with_base_properties, generate_event_table_full_name,
+    database::{
+        postgres::{
+            client::PostgresClient,
+            entity::{
+                entity_log_key, entity_upserts_for_inserted_logs, EntityLogKey, EntityLogValues,
+                EntityUpsert, IndexerEntities,
+            },
+            generate::{
+                generate_column_names_only_with_base_properties, generate_event_table_full_name,
+            },
+            setup::{setup_postgres, SetupPostgresError},
+            sql_type_wrapper::{
+                map_ethereum_wrapper_to_json, map_log_params_to_ethereum_wrapper,
+                map_transaction_fields_to_ethereum_wrapper, EthereumSqlTypeWrapper,
+            },
         },
-        setup::{setup_postgres, SetupPostgresError},
-        sql_type_wrapper::{
-            map_ethereum_wrapper_to_json, map_log_params_to_ethereum_wrapper,
-            EthereumSqlTypeWrapper,
+        sqlite::{
+            client::SqliteClient,
+            generate::generate_sqlite_event_table_name,
+            setup::{setup_sqlite, SetupSqliteError},
         },
     },
     event::{
+        block_callback_registry::BlockCallbackRegistry,
         callback_registry::{
             noop_decoder, EventCallbackRegistry, EventCallbackRegistryInformation,
             EventCallbackType, TxInformation,
@@ -33,7 +52,10 @@ use crate::{
         EventMessage,
     },
     generate_random_id,
-    indexer::log_helpers::{map_log_params_to_raw_values, parse_log},
+    indexer::{
+        function_calls::function_call_event,
+        log_helpers::{map_log_params_to_raw_values, parse_log},
+    },
     manifest::{
         contract::ParseAbiError,
         core::Manifest,
@@ -56,6 +78,9 @@ pub enum SetupNoCodeError {
     #[error("Could not setup postgres: {0}")]
     SetupPostgresError(#[from] SetupPostgresError),
 
+    #[error("Could not setup sqlite: {0}")]
+    SetupSqliteError(#[from] SetupSqliteError),
+
     #[error("{0}")]
     RetryClientError(#[from] RetryClientError),
 
@@ -85,6 +110,11 @@ pub async fn setup_no_code(
                 postgres = Some(Arc::new(setup_postgres(project_path, &manifest).await?));
             }
 
+            let mut sqlite: Option<Arc<SqliteClient>> = None;
+            if let Some(sqlite_path) = manifest.storage.sqlite_path(project_path) {
+                sqlite = Some(Arc::new(setup_sqlite(project_path, &manifest, &sqlite_path).await?));
+            }
+
             if !details.indexing_details.enabled {
                 return Ok(StartDetails {
                     manifest_path: details.manifest_path,
@@ -104,7 +134,8 @@ pub async fn setup_no_code(
             );
 
             let events =
-                process_events(project_path, &mut manifest, postgres, &network_providers).await?;
+                process_events(project_path, &mut manifest, postgres, sqlite, &network_providers)
+                    .await?;
 
             let registry = EventCallbackRegistry { events };
             info!(
@@ -119,7 +150,10 @@ pub async fn setup_no_code(
 
             Ok(StartDetails {
                 manifest_path: details.manifest_path,
-                indexing_details: Some(IndexingDetails { registry }),
+                indexing_details: Some(IndexingDetails {
+                    registry,
+                    block_registry: BlockCallbackRegistry::new(),
+                }),
                 graphql_details: details.graphql_details,
             })
         }
@@ -138,10 +172,89 @@ struct NoCodeCallbackParams {
     postgres: Option<Arc<PostgresClient>>,
     postgres_event_table_name: String,
     postgres_column_names: Vec<String>,
+    entity_upserts: Vec<EntityUpsert>,
+    sqlite: Option<Arc<SqliteClient>>,
+    sqlite_event_table_name: String,
+    include_transaction_fields: bool,
     streams_clients: Arc<Option<StreamsClients>>,
     chat_clients: Arc<Option<ChatClients>>,
 }
 
+/// Sends the events of a batch to the streams and chat bots of the contract.
+async fn publish_event_message(
+    params: Arc<NoCodeCallbackParams>,
+    event_message: EventMessage,
+    from_block: U64,
+    to_block: U64,
+) -> Result<(), String> {
+    let network = &event_message.network;
+
+    if let Some(streams_clients) = params.streams_clients.as_ref() {
+        let stream_id = format!(
+            "{}-{}-{}-{}-{}",
+            params.contract_name, params.event_info.name, network, from_block, to_block
+        );
+
+        match streams_clients.stream(stream_id, &event_message, params.index_event_in_order).await {
+            Ok(streamed) => {
+                if streamed > 0 {
+                    info!(
+                        "{}::{} - {} - {} events {}",
+                        params.contract_name,
+                        params.event_info.name,
+                        "STREAMED".green(),
+                        streamed,
+                        format!("- blocks: {} - {} - network: {}", from_block, to_block, network)
+                    );
+                }
+            }
+            Err(e) => {
+                error!("Error streaming event: {}", e);
+                return Err(e.to_string());
+            }
+        }
+    }
+
+    if let Some(chat_clients) = params.chat_clients.as_ref() {
+        if !chat_clients.is_in_block_range_to_send(&from_block, &to_block) {
+            warn!(
+                "{}::{} - {} - messages has a max 10 block range due the rate limits - {}",
+                params.contract_name,
+                params.event_info.name,
+                "CHAT_MESSAGES_DISABLED".yellow(),
+                format!("- blocks: {} - {} - network: {}", from_block, to_block, network)
+            );
+        } else {
+            match chat_clients
+                .send_message(&event_message, params.index_event_in_order, &from_block, &to_block)
+                .await
+            {
+                Ok(messages_sent) => {
+                    if messages_sent > 0 {
+                        info!(
+                            "{}::{} - {} - {} events {}",
+                            params.contract_name,
+                            params.event_info.name,
+                            "CHAT_MESSAGES_SENT".green(),
+                            messages_sent,
+                            format!(
+                                "- blocks: {} - {} - network: {}",
+                                from_block, to_block, network
+                            )
+                        );
+                    }
+                }
+                Err(e) => {
+                    error!("Error sending chat messages: {}", e);
+                    return Err(e.to_string());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
 fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
     Arc::new(move |results| {
         let params = Arc::clone(&params);
@@ -174,6 +287,7 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
             let mut postgres_bulk_data: Vec<Vec<EthereumSqlTypeWrapper>> = Vec::new();
             let mut postgres_bulk_column_types: Vec<PgType> = Vec::new();
             let mut csv_bulk_data: Vec<Vec<String>> = Vec::new();
+            let mut entity_logs: Vec<(EntityLogKey, EntityLogValues)> = Vec::new();
 
             // stream and chat info
             let mut event_message_data: Vec<Value> = Vec::new();
@@ -191,19 +305,34 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
                     let network = result.tx_information.network.to_string();
                     let transaction_index = result.tx_information.transaction_index;
                     let log_index = result.tx_information.log_index;
+                    let block_timestamp = result.tx_information.block_timestamp;
+                    let block_date_time = result.tx_information.block_timestamp_to_datetime();
+                    let transaction_fields = result.tx_information.transaction_fields.clone();
 
                     let event_parameters: Vec<EthereumSqlTypeWrapper> =
                         map_log_params_to_ethereum_wrapper(&params.event_info.inputs, &log.params);
+                    let entity_log = (!params.entity_upserts.is_empty()).then(|| {
+                        (
+                            entity_log_key(&result.tx_information),
+                            EntityLogValues::from_log(&log.params, &result.tx_information),
+                        )
+                    });
 
                     let contract_address = EthereumSqlTypeWrapper::Address(address);
-                    let end_global_parameters = vec![
+                    let mut end_global_parameters = vec![
                         EthereumSqlTypeWrapper::H256(transaction_hash),
-                        EthereumSqlTypeWrapper::U64(block_number),
+                        EthereumSqlTypeWrapper::U64BigInt(block_number),
                         EthereumSqlTypeWrapper::H256(block_hash),
                         EthereumSqlTypeWrapper::String(network.to_string()),
-                        EthereumSqlTypeWrapper::U64(transaction_index),
-                        EthereumSqlTypeWrapper::U256(log_index),
+                        EthereumSqlTypeWrapper::U32(transaction_index.as_u32()),
+                        EthereumSqlTypeWrapper::U32(log_index.as_u32()),
+                        EthereumSqlTypeWrapper::DateTimeNullable(block_date_time),
                     ];
+                    if params.include_transaction_fields {
+                        end_global_parameters.extend(map_transaction_fields_to_ethereum_wrapper(
+                            transaction_fields.as_ref(),
+                        ));
+                    }
 
                     Some((
                         log.params,
@@ -213,10 +342,14 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
                         transaction_index,
                         block_number,
                         block_hash,
+                        block_timestamp,
+                        block_date_time,
+                        transaction_fields,
                         network,
                         contract_address,
                         event_parameters,
                         end_global_parameters,
+                        entity_log,
                     ))
                 })
                 .collect();
@@ -229,10 +362,14 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
                 transaction_index,
                 block_number,
                 block_hash,
+                block_timestamp,
+                block_date_time,
+                transaction_fields,
                 network,
                 contract_address,
                 event_parameters,
                 end_global_parameters,
+                entity_log,
             ) in owned_results
             {
                 if params.streams_clients.is_some() || params.chat_clients.is_some() {
@@ -245,9 +382,10 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
                             block_hash,
                             block_number,
                             transaction_hash,
-                            block_timestamp: None,
+                            block_timestamp,
                             log_index,
                             transaction_index,
+                            transaction_fields: transaction_fields.clone(),
                         },
                         false,
                     );
@@ -265,6 +403,9 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
                 }
 
                 postgres_bulk_data.push(all_params);
+                if let Some(entity_log) = entity_log {
+                    entity_logs.push(entity_log);
+                }
 
                 if params.csv.is_some() {
                     let mut csv_data: Vec<String> = vec![format!("{:?}", address)];
@@ -279,6 +420,14 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
                     csv_data.push(format!("{:?}", block_number));
                     csv_data.push(format!("{:?}", block_hash));
                     csv_data.push(network);
+                    csv_data.push(format!("{:?}", transaction_index));
+                    csv_data.push(format!("{:?}", log_index));
+                    csv_data.push(
+                        block_date_time.map(|date_time| date_time.to_rfc3339()).unwrap_or_default(),
+                    );
+                    if params.include_transaction_fields {
+                        csv_data.extend(transaction_fields.unwrap_or_default().csv_values());
+                    }
 
                     csv_bulk_data.push(csv_data);
                 }
@@ -286,9 +435,46 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
                 indexed_count += 1;
             }
 
+            // runs inside the transaction of the batch so the rows commit with the last synced
+            // block
             if let Some(postgres) = &params.postgres {
                 let bulk_data_length = postgres_bulk_data.len();
-                if bulk_data_length > 0 {
+                if !params.entity_upserts.is_empty() {
+                    // only the rows stored now update the entities, a replayed batch already
+                    // applied its changes when its rows were first stored
+                    let inserted: HashSet<EntityLogKey> = match postgres
+                        .insert_bulk_returning_log_keys(
+                            &params.postgres_event_table_name,
+                            &params.postgres_column_names,
+                            &postgres_bulk_data,
+                        )
+                        .await
+                    {
+                        Ok(inserted) => inserted.into_iter().collect(),
+                        Err(e) => {
+                            error!(
+                                "{}::{} - Error performing bulk insert: {}",
+                                params.contract_name, params.event_info.name, e
+                            );
+                            return Err(e);
+                        }
+                    };
+
+                    // applied in log order so the last `set` of a batch wins
+                    for (upsert, values) in entity_upserts_for_inserted_logs(
+                        &params.entity_upserts,
+                        &entity_logs,
+                        &inserted,
+                    ) {
+                        if let Err(e) = upsert.execute(postgres, &values).await {
+                            error!(
+                                "{}::{} - Error updating entity {}: {}",
+                                params.contract_name, params.event_info.name, upsert.entity_name, e
+                            );
+                            return Err(e.to_string());
+                        }
+                    }
+                } else if bulk_data_length > 0 {
                     // anything over 100 events is considered bulk and goes the COPY route
                     if bulk_data_length > 100 {
                         if let Err(e) = postgres
@@ -323,6 +509,24 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
                 }
             }
 
+            // sqlite tables have the same columns as the postgres ones
+            if let Some(sqlite) = &params.sqlite {
+                if let Err(e) = sqlite
+                    .insert_bulk(
+                        &params.sqlite_event_table_name,
+                        &params.postgres_column_names,
+                        postgres_bulk_data,
+                    )
+                    .await
+                {
+                    error!(
+                        "{}::{} - Error performing sqlite bulk insert: {}",
+                        params.contract_name, params.event_info.name, e
+                    );
+                    return Err(e.to_string());
+                }
+            }
+
             if let Some(csv) = &params.csv {
                 if !csv_bulk_data.is_empty() {
                     if let Err(e) = csv.append_bulk(csv_bulk_data).await {
@@ -337,77 +541,15 @@ fn no_code_callback(params: Arc<NoCodeCallbackParams>) -> EventCallbackType {
                 network: network.clone(),
             };
 
-            if let Some(streams_clients) = params.streams_clients.as_ref() {
-                let stream_id = format!(
-                    "{}-{}-{}-{}-{}",
-                    params.contract_name, params.event_info.name, network, from_block, to_block
-                );
-
-                match streams_clients
-                    .stream(stream_id, &event_message, params.index_event_in_order)
-                    .await
-                {
-                    Ok(streamed) => {
-                        if streamed > 0 {
-                            info!(
-                                "{}::{} - {} - {} events {}",
-                                params.contract_name,
-                                params.event_info.name,
-                                "STREAMED".green(),
-                                streamed,
-                                format!(
-                                    "- blocks: {} - {} - network: {}",
-                                    from_block, to_block, network
-                                )
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        error!("Error streaming event: {}", e);
-                        return Err(e.to_string());
-                    }
-                }
-            }
-
-            if let Some(chat_clients) = params.chat_clients.as_ref() {
-                if !chat_clients.is_in_block_range_to_send(&from_block, &to_block) {
-                    warn!(
-                        "{}::{} - {} - messages has a max 10 block range due the rate limits - {}",
-                        params.contract_name,
-                        params.event_info.name,
-                        "CHAT_MESSAGES_DISABLED".yellow(),
-                        format!("- blocks: {} - {} - network: {}", from_block, to_block, network)
-                    );
-                } else {
-                    match chat_clients
-                        .send_message(
-                            &event_message,
-                            params.index_event_in_order,
-                            &from_block,
-                            &to_block,
-                        )
-                        .await
-                    {
-                        Ok(messages_sent) => {
-                            if messages_sent > 0 {
-                                info!(
-                                    "{}::{} - {} - {} events {}",
-                                    params.contract_name,
-                                    params.event_info.name,
-                                    "CHAT_MESSAGES_SENT".green(),
-                                    messages_sent,
-                                    format!(
-                                        "- blocks: {} - {} - network: {}",
-                                        from_block, to_block, network
-                                    )
-                                );
-                            }
-                        }
-                        Err(e) => {
-                            error!("Error sending chat messages: {}", e);
-                            return Err(e.to_string());
-                        }
-                    }
+            // streams and chat can not be recalled so they are only published once the rows of
+            // the batch are committed, a failed publish retries the batch which skips the stored
+            // rows
+            if params.streams_clients.is_some() || params.chat_clients.is_some() {
+                let publish =
+                    publish_event_message(Arc::clone(&params), event_message, from_block, to_block);
+                match &params.postgres {
+                    Some(postgres) => postgres.after_commit(publish).await?,
+                    None => publish.await?,
                 }
             }
 
@@ -452,6 +594,9 @@ pub enum ProcessIndexersError {
     #[error("Event name not found in ABI for contract: {0} - event: {1}")]
     EventNameNotFoundInAbi(String, String),
 
+    #[error("Function not found in ABI for contract: {0} - function call: {1}")]
+    FunctionNameNotFoundInAbi(String, String),
+
     #[error("{0}")]
     ParseAbiError(#[from] ParseAbiError),
 }
@@ -460,9 +605,12 @@ pub async fn process_events(
     project_path: &Path,
     manifest: &mut Manifest,
     postgres: Option<Arc<PostgresClient>>,
+    sqlite: Option<Arc<SqliteClient>>,
     network_providers: &[CreateNetworkProvider],
 ) -> Result<Vec<EventCallbackRegistryInformation>, ProcessIndexersError> {
     let mut events: Vec<EventCallbackRegistryInformation> = vec![];
+    // built before a filter renames its contract below
+    let entities = IndexerEntities::new(project_path, manifest)?;
 
     for contract in &mut manifest.contracts {
         // TODO - this could be shared with `get_abi_items`
@@ -474,11 +622,12 @@ pub async fn process_events(
 
         let is_filter = contract.identify_and_modify_filter();
         let abi_items = ABIItem::get_abi_items(project_path, contract, is_filter)?;
-        let event_names = ABIItem::extract_event_names_and_signatures_from_abi(abi_items)?;
+        let event_names = ABIItem::extract_event_names_and_signatures_from_abi(abi_items.clone())?;
 
+        let mut indexed_events = vec![];
         for event_info in event_names {
             let event_name = event_info.name.clone();
-            let event = &abi_gen
+            let event = abi_gen
                 .events
                 .iter()
                 .find(|(name, _)| *name == &event_name)
@@ -498,6 +647,35 @@ pub async fn process_events(
                 })?
                 .clone();
 
+            indexed_events.push((event_info.topic_id(), event_info, event, None));
+        }
+
+        // function calls are indexed as an event built from the function inputs
+        if let Some(function_names) = &contract.include_function_calls {
+            let function_call_events =
+                ABIItem::extract_function_calls_as_events_from_abi(&abi_items, function_names)?;
+            for event_info in function_call_events {
+                let function = abi_gen
+                    .functions()
+                    .find(|function| function_call_event_name(&function.name) == event_info.name)
+                    .ok_or_else(|| {
+                        ProcessIndexersError::FunctionNameNotFoundInAbi(
+                            contract.name.clone(),
+                            event_info.name.clone(),
+                        )
+                    })?;
+                let event = function_call_event(function);
+
+                indexed_events.push((
+                    event.signature(),
+                    event_info,
+                    event,
+                    Some(function.short_signature()),
+                ));
+            }
+        }
+
+        for (topic_id, event_info, event, function_call_selector) in indexed_events {
             let contract_information =
                 ContractInformation::create(contract, network_providers, noop_decoder())?;
 
@@ -508,7 +686,8 @@ pub async fn process_events(
                         PathBuf::from(c.path.strip_prefix("./").unwrap())
                     });
 
-                let headers: Vec<String> = event_info.csv_headers_for_event();
+                let headers: Vec<String> = event_info
+                    .csv_headers_for_event(contract.include_transaction_fields.unwrap_or_default());
                 let csv_path_str = csv_path.to_str().expect("Failed to convert csv path to string");
                 let csv_path =
                     event_info.create_csv_file_for_event(project_path, contract, csv_path_str)?;
@@ -520,10 +699,18 @@ pub async fn process_events(
                 csv = Some(Arc::new(csv_appender));
             }
 
-            let postgres_column_names =
-                generate_column_names_only_with_base_properties(&event_info.inputs);
+            let include_transaction_fields =
+                contract.include_transaction_fields.unwrap_or_default();
+            let postgres_column_names = generate_column_names_only_with_base_properties(
+                &event_info.inputs,
+                include_transaction_fields,
+            );
             let postgres_event_table_name =
                 generate_event_table_full_name(&manifest.name, &contract.name, &event_info.name);
+            let sqlite_event_table_name =
+                generate_sqlite_event_table_name(&manifest.name, &contract.name, &event_info.name);
+
+            let entity_upserts = entities.upserts(&postgres_event_table_name);
 
             let streams_client = if let Some(streams) = &contract.streams {
                 Some(StreamsClients::new(streams.clone()).await)
@@ -547,7 +734,8 @@ pub async fn process_events(
                 indexer_name: manifest.name.clone(),
                 event_name: event_info.name.clone(),
                 index_event_in_order,
-                topic_id: event_info.topic_id(),
+                function_call_selector,
+                topic_id,
                 contract: contract_information,
                 callback: no_code_callback(Arc::new(NoCodeCallbackParams {
                     event_info,
@@ -559,6 +747,10 @@ pub async fn process_events(
                     postgres: postgres.clone(),
                     postgres_event_table_name,
                     postgres_column_names,
+                    entity_upserts,
+                    sqlite: sqlite.clone(),
+                    sqlite_event_table_name,
+                    include_transaction_fields,
                     streams_clients: Arc::new(streams_client),
                     chat_clients: Arc::new(chat_clients),
                 })),
@@ -0,0 +1,345 @@
+use std::{path::Path, sync::Arc};
+
+use ethers::types::U64;
+
+use crate::{
+    abi::{ABIItem, ParamTypeError, ReadAbiError},
+    database::{
+        postgres::{
+            client::{PostgresConnectionError, PostgresError},
+            generate::{
+                generate_indexer_contract_schema_name,
+                generate_internal_event_table_checkpoint_sql,
+                generate_internal_event_table_create_sql, generate_internal_event_table_name,
+            },
+            indexes::{
+                build_indexes, get_last_known_indexes_dropping_sql,
+                GetLastKnownIndexesDroppingSqlError, PrepareIndexesError,
+            },
+            migration::{plan_schema_migrations, SchemaMigrationError},
+            relationship::{
+                build_relationships, get_last_known_relationships_dropping_sql,
+                CreateRelationshipError, GetLastKnownRelationshipsDroppingSqlError,
+            },
+        },
+        sqlite::client::SqliteConnectionError,
+    },
+    helpers::camel_to_snake,
+    indexer::last_synced::{get_last_synced_block_number, SyncConfig},
+    manifest::{core::Manifest, storage::PostgresIndexes},
+    types::code::Code,
+    PostgresClient, SqliteClient,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum PlanError {
+    #[error("{0}")]
+    PostgresConnectionError(#[from] PostgresConnectionError),
+
+    #[error("{0}")]
+    PostgresError(#[from] PostgresError),
+
+    #[error("{0}")]
+    SqliteConnectionError(#[from] SqliteConnectionError),
+
+    #[error("Could not read ABI items: {0}")]
+    CouldNotReadAbiItems(#[from] ReadAbiError),
+
+    #[error("Could not get ABI param types: {0}")]
+    ParamTypeError(#[from] ParamTypeError),
+
+    #[error("Could not plan the table changes: {0}")]
+    SchemaMigrationError(#[from] SchemaMigrationError),
+
+    #[error("Could not plan the indexes: {0}")]
+    PrepareIndexesError(#[from] PrepareIndexesError),
+
+    #[error("Could not plan the relationships: {0}")]
+    CreateRelationshipError(#[from] CreateRelationshipError),
+
+    #[error("{0}")]
+    GetLastKnownIndexesDroppingSqlError(#[from] GetLastKnownIndexesDroppingSqlError),
+
+    #[error("{0}")]
+    GetLastKnownRelationshipsDroppingSqlError(#[from] GetLastKnownRelationshipsDroppingSqlError),
+}
+
+/// A statement `rindexer start` would run.
+#[derive(Debug, Clone)]
+pub struct PlannedStatement {
+    pub description: String,
+    pub sql: String,
+    pub destructive: bool,
+}
+
+impl PlannedStatement {
+    fn new(description: impl Into<String>, sql: &str) -> Self {
+        // the generated sql is indented for the source it lives in
+        let sql = sql.lines().map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<_>>();
+        PlannedStatement {
+            description: description.into(),
+            sql: sql.join("\n"),
+            destructive: false,
+        }
+    }
+}
+
+/// Where an event would resume from, `None` means the latest block of the network.
+#[derive(Debug, Clone)]
+pub struct EventResumeBlock {
+    pub contract_name: String,
+    pub event_name: String,
+    pub network: String,
+    pub last_synced_block: Option<U64>,
+    pub resume_from: Option<U64>,
+}
+
+/// What `rindexer start` would do to the database and where each event would resume from.
+#[derive(Debug, Default)]
+pub struct IndexerPlan {
+    pub tables: Vec<PlannedStatement>,
+    pub checkpoints: Vec<PlannedStatement>,
+    pub dropped_for_historic_sync: Vec<PlannedStatement>,
+    pub applied_after_historic_sync: Vec<PlannedStatement>,
+    pub resume_blocks: Vec<EventResumeBlock>,
+}
+
+async fn table_exists(client: &PostgresClient, table_name: &str) -> Result<bool, PostgresError> {
+    let row = client.query_one("SELECT to_regclass($1) IS NOT NULL", &[&table_name]).await?;
+    Ok(row.get(0))
+}
+
+async fn plan_tables(
+    client: &PostgresClient,
+    project_path: &Path,
+    manifest: &Manifest,
+    plan: &mut IndexerPlan,
+) -> Result<(), PlanError> {
+    if manifest.storage.postgres_disable_create_tables() {
+        return Ok(());
+    }
+
+    let changes = plan_schema_migrations(client, project_path, &manifest.to_indexer()).await?;
+    plan.tables = changes
+        .iter()
+        .map(|change| PlannedStatement {
+            destructive: change.is_destructive(),
+            ..PlannedStatement::new(change.to_string(), &change.sql())
+        })
+        .collect();
+
+    Ok(())
+}
+
+async fn plan_indexes_and_relationships(
+    client: &PostgresClient,
+    project_path: &Path,
+    manifest: &Manifest,
+    plan: &mut IndexerPlan,
+) -> Result<(), PlanError> {
+    let Some(postgres) = manifest.storage.postgres.as_ref() else {
+        return Ok(());
+    };
+    if manifest.storage.postgres_disable_create_tables() {
+        return Ok(());
+    }
+
+    let indexer_name = camel_to_snake(&manifest.name);
+    let relationships_table =
+        format!("rindexer_internal.{}_last_known_relationship_dropping_sql", indexer_name);
+    if table_exists(client, &relationships_table).await? {
+        for sql in get_last_known_relationships_dropping_sql(client, &manifest.name).await? {
+            plan.dropped_for_historic_sync
+                .push(PlannedStatement::new("drop last known relationship", sql.as_str()));
+        }
+    }
+
+    let indexes_table =
+        format!("rindexer_internal.{}_last_known_indexes_dropping_sql", indexer_name);
+    if table_exists(client, &indexes_table).await? {
+        for sql in get_last_known_indexes_dropping_sql(client, &manifest.name).await? {
+            plan.dropped_for_historic_sync
+                .push(PlannedStatement::new("drop last known index", sql.as_str()));
+        }
+    }
+
+    if let Some(indexes) =
+        PostgresIndexes::with_presets(postgres.indexes.as_ref(), &manifest.contracts)
+    {
+        for index in build_indexes(project_path, &manifest.name, &indexes, &manifest.contracts)? {
+            plan.applied_after_historic_sync.push(PlannedStatement::new(
+                format!("create index {}", index.index_name()),
+                index.apply_index_sql().as_str(),
+            ));
+        }
+    }
+
+    if let Some(foreign_keys) = &postgres.relationships {
+        let relationships =
+            build_relationships(project_path, &manifest.name, &manifest.contracts, foreign_keys)?;
+        for relationship in relationships {
+            let sql = relationship.apply_sql().iter().map(Code::to_string).collect::<String>();
+            plan.applied_after_historic_sync.push(PlannedStatement::new(
+                format!(
+                    "create relationship {}.{} -> {}.{}",
+                    relationship.db_table_name,
+                    relationship.db_table_column,
+                    relationship.linked_to.db_table_name,
+                    relationship.linked_to.db_table_column
+                ),
+                &sql,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+async fn plan_checkpoints_and_resume_blocks(
+    client: &Option<Arc<PostgresClient>>,
+    sqlite: &Option<Arc<SqliteClient>>,
+    project_path: &Path,
+    manifest: &Manifest,
+    plan: &mut IndexerPlan,
+) -> Result<(), PlanError> {
+    for contract in &manifest.contracts {
+        let abi_items = ABIItem::read_abi_items(project_path, contract)?;
+        let mut events = ABIItem::extract_event_names_and_signatures_from_abi(abi_items.clone())?;
+        if let Some(function_names) = &contract.include_function_calls {
+            events.extend(ABIItem::extract_function_calls_as_events_from_abi(
+                &abi_items,
+                function_names,
+            )?);
+        }
+        let contract_name = contract.before_modify_name_if_filter_readonly();
+        let schema_name = generate_indexer_contract_schema_name(&manifest.name, &contract_name);
+
+        for event in &events {
+            // a missing checkpoint table would abort the read only transaction when queried
+            let mut checkpoint_networks = None;
+            if let Some(client) = client {
+                let table_name = generate_internal_event_table_name(&schema_name, &event.name);
+                if table_exists(client, &table_name).await? {
+                    let rows =
+                        client.query(&format!("SELECT network FROM {}", table_name), &[]).await?;
+                    checkpoint_networks =
+                        Some(rows.iter().map(|row| row.get(0)).collect::<Vec<String>>());
+                } else {
+                    plan.checkpoints.push(PlannedStatement::new(
+                        format!("create checkpoint table {}", table_name),
+                        &generate_internal_event_table_create_sql(&table_name),
+                    ));
+                    checkpoint_networks = Some(vec![]);
+                }
+
+                for details in &contract.details {
+                    if checkpoint_networks
+                        .as_ref()
+                        .is_some_and(|networks| !networks.contains(&details.network))
+                    {
+                        plan.checkpoints.push(PlannedStatement::new(
+                            format!("add checkpoint {} - {}", table_name, details.network),
+                            &generate_internal_event_table_checkpoint_sql(
+                                &table_name,
+                                &details.network,
+                            ),
+                        ));
+                    }
+                }
+            }
+
+            let stream_details = contract.streams.as_ref();
+            for details in &contract.details {
+                let has_checkpoint = checkpoint_networks
+                    .as_ref()
+                    .is_none_or(|networks| networks.contains(&details.network));
+
+                // same as `rindexer start` a checkpoint is only used with a start block
+                let last_synced_block = if details.start_block.is_some() && has_checkpoint {
+                    get_last_synced_block_number(SyncConfig {
+                        project_path,
+                        database: client,
+                        sqlite,
+                        csv_details: &manifest.storage.csv,
+                        stream_details: &stream_details,
+                        contract_csv_enabled: manifest.contract_csv_enabled(&contract.name),
+                        indexer_name: &manifest.name,
+                        contract_name: &contract.name,
+                        event_name: &event.name,
+                        network: &details.network,
+                    })
+                    .await
+                } else {
+                    None
+                };
+
+                plan.resume_blocks.push(EventResumeBlock {
+                    contract_name: contract.name.clone(),
+                    event_name: event.name.clone(),
+                    network: details.network.clone(),
+                    last_synced_block,
+                    resume_from: last_synced_block.map(|block| block + 1).or(details.start_block),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Works out what `rindexer start` would do without changing anything, postgres is only
+/// queried inside a read only transaction.
+pub async fn plan(project_path: &Path, manifest: &Manifest) -> Result<IndexerPlan, PlanError> {
+    let mut plan = IndexerPlan::default();
+
+    if !manifest.storage.postgres_enabled() {
+        // a sqlite file which does not exist yet has no checkpoints to resume from
+        let sqlite = match manifest.storage.sqlite_path(project_path) {
+            Some(path) if path.exists() => Some(Arc::new(SqliteClient::new(&path)?)),
+            _ => None,
+        };
+        plan_checkpoints_and_resume_blocks(&None, &sqlite, project_path, manifest, &mut plan)
+            .await?;
+        return Ok(plan);
+    }
+
+    let client = Arc::new(PostgresClient::new().await?);
+    let database = Some(Arc::clone(&client));
+
+    client
+        .in_transaction(|_| async {
+            client.batch_execute("SET TRANSACTION READ ONLY").await?;
+
+            plan_tables(&client, project_path, manifest, &mut plan).await?;
+            plan_checkpoints_and_resume_blocks(&database, &None, project_path, manifest, &mut plan)
+                .await?;
+            plan_indexes_and_relationships(&client, project_path, manifest, &mut plan).await?;
+
+            Ok::<(), PlanError>(())
+        })
+        .await?;
+
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_planned_statement_trims_generated_sql() {
+        let statement = PlannedStatement::new(
+            "create index",
+            "
+                CREATE INDEX IF NOT EXISTS idx_from
+                    ON example.transfer (\"from\");
+            ",
+        );
+
+        assert_eq!(
+            statement.sql,
+            "CREATE INDEX IF NOT EXISTS idx_from\nON example.transfer (\"from\");"
+        );
+        assert!(!statement.destructive);
+    }
+}
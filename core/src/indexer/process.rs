@@ -5,7 +5,7 @@ use ethers::{
     prelude::ProviderError,
     types::{H256, U64},
 };
-use futures::future::join_all;
+use futures::future::{join_all, select_all};
 use tokio::{
     sync::{Mutex, MutexGuard},
     task::{JoinError, JoinHandle},
@@ -20,13 +20,19 @@ use crate::{
     },
     indexer::{
         dependency::{ContractEventsDependenciesConfig, EventDependencies},
-        fetch_logs::{fetch_logs_stream, FetchLogsResult},
+        fetch_logs::{
+            detect_reorg, fetch_contract_logs_streams, fetch_logs_stream,
+            get_logs_or_function_calls, FetchLogsResult,
+        },
         last_synced::update_progress_and_last_synced_task,
         log_helpers::is_relevant_block,
+        processed_ranges::record_processed_range,
         progress::IndexingEventProgressStatus,
+        reorg::{rollback_event_to_block, safe_block_number, BlockHashTracker},
         task_tracker::{indexing_event_processed, indexing_event_processing},
     },
     is_running,
+    provider::{NewBlockListener, WrappedLog},
 };
 
 #[derive(thiserror::Error, Debug)]
@@ -57,15 +63,74 @@ async fn process_event_logs(
     force_no_live_indexing: bool,
     block_until_indexed: bool,
 ) -> Result<(), Box<ProviderError>> {
-    let mut logs_stream = fetch_logs_stream(Arc::clone(&config), force_no_live_indexing);
+    let logs_stream = fetch_logs_stream(Arc::clone(&config), force_no_live_indexing);
+    process_logs_stream(config, logs_stream, block_until_indexed).await
+}
+
+/// Processes the events of a contract on a network which share one `eth_getLogs` per block range,
+/// each event still triggers its own handler and keeps its own last synced block.
+pub async fn process_contract_events(
+    configs: Vec<EventProcessingConfig>,
+) -> Result<(), ProcessEventError> {
+    let configs: Vec<Arc<EventProcessingConfig>> = configs.into_iter().map(Arc::new).collect();
+    for config in &configs {
+        debug!("{} - Processing events", config.info_log_name);
+    }
+
+    let logs_streams = fetch_contract_logs_streams(&configs, false);
+    let results = join_all(
+        configs
+            .into_iter()
+            .zip(logs_streams)
+            .map(|(config, logs_stream)| process_logs_stream(config, logs_stream, false)),
+    )
+    .await;
+
+    for result in results {
+        result?;
+    }
+
+    Ok(())
+}
+
+async fn process_logs_stream(
+    config: Arc<EventProcessingConfig>,
+    mut logs_stream: impl tokio_stream::Stream<Item = Result<FetchLogsResult, Box<dyn std::error::Error + Send>>>
+        + Unpin,
+    block_until_indexed: bool,
+) -> Result<(), Box<ProviderError>> {
     let mut tasks = Vec::new();
 
     while let Some(result) = logs_stream.next().await {
+        if let Ok(FetchLogsResult { rollback_to_block: Some(fork_block), .. }) = &result {
+            // anything still being indexed has to land before the reorged rows are removed
+            for task in tasks.drain(..) {
+                if let Err(e) = task.await {
+                    error!("{} - Error waiting for indexing task: {}", config.info_log_name, e);
+                }
+            }
+            rollback_event_to_block(&config, *fork_block).await;
+        }
+
         let task = handle_logs_result(Arc::clone(&config), result)
             .await
             .map_err(|e| Box::new(ProviderError::CustomError(e.to_string())))?;
 
         tasks.push(task);
+
+        // only a bounded number of batches index at once so the logs queue fills up and
+        // fetching pauses when decoding or storage falls behind
+        if tasks.len() >= config.prefetch_depth {
+            debug!(
+                "{} - {} batches indexing - waiting for the oldest to finish",
+                config.info_log_name,
+                tasks.len()
+            );
+            tasks
+                .remove(0)
+                .await
+                .map_err(|e| Box::new(ProviderError::CustomError(e.to_string())))?;
+        }
     }
 
     if block_until_indexed {
@@ -222,7 +287,7 @@ async fn process_contract_events_with_dependencies(
 }
 
 // TODO - this is a similar to live_indexing_stream but has to be a bit different we should merge
-// code
+// code, the chain reorg check is shared through `detect_reorg`
 #[allow(clippy::type_complexity)]
 async fn live_indexing_for_contract_event_dependencies<'a>(
     live_indexing_events: &'a MutexGuard<
@@ -252,11 +317,23 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
         );
     }
 
+    // remembers recent block hashes of each event so chain reorgs can be detected and rolled back
+    let mut block_hash_trackers: HashMap<H256, BlockHashTracker> = HashMap::new();
+
     // this is used for less busy chains to make sure they know rindexer is still alive
     let log_no_new_block_interval = Duration::from_secs(300);
 
+    // wakes up on new heads of any of the networks, networks without a websocket rpc are polled
+    let mut new_block_listeners: HashMap<String, NewBlockListener> = HashMap::new();
+    for (config, _) in live_indexing_events.iter() {
+        new_block_listeners
+            .entry(config.network_contract.network.clone())
+            .or_insert_with(|| config.network_contract.cached_provider.new_block_listener());
+    }
+
     loop {
-        tokio::time::sleep(Duration::from_millis(200)).await;
+        select_all(new_block_listeners.values_mut().map(|listener| Box::pin(listener.wait())))
+            .await;
 
         for (config, _) in live_indexing_events.iter() {
             let mut ordering_live_indexing_details = ordering_live_indexing_details_map
@@ -272,6 +349,35 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
                 Ok(latest_block) => {
                     if let Some(latest_block) = latest_block {
                         if let Some(latest_block_number) = latest_block.number {
+                            if let Some(fork_block) = detect_reorg(
+                                &config.network_contract.cached_provider,
+                                block_hash_trackers.entry(config.topic_id).or_default(),
+                                latest_block,
+                                &config.info_log_name,
+                            )
+                            .await
+                            {
+                                // only need to roll back if we already indexed blocks after the
+                                // fork, each batch is indexed before the next is fetched so
+                                // nothing is in flight
+                                if fork_block + 1 <
+                                    ordering_live_indexing_details.filter.get_from_block()
+                                {
+                                    rollback_event_to_block(config, fork_block).await;
+                                    ordering_live_indexing_details.filter =
+                                        ordering_live_indexing_details
+                                            .filter
+                                            .set_from_block(fork_block + 1);
+                                    ordering_live_indexing_details.last_seen_block_number =
+                                        fork_block;
+                                    *ordering_live_indexing_details_map
+                                        .get(&config.topic_id)
+                                        .expect("Failed to get ordering_live_indexing_details_map")
+                                        .lock()
+                                        .await = ordering_live_indexing_details.clone();
+                                }
+                            }
+
                             if ordering_live_indexing_details.last_seen_block_number ==
                                 latest_block_number
                             {
@@ -308,8 +414,25 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
                                 latest_block_number,
                                 ordering_live_indexing_details.last_seen_block_number
                             );
-                            let reorg_safe_distance = &config.indexing_distance_from_head;
-                            let safe_block_number = latest_block_number - reorg_safe_distance;
+                            let safe_block_number = match safe_block_number(
+                                &config.network_contract.cached_provider,
+                                &config.reorg_safe_distance,
+                                latest_block_number,
+                            )
+                            .await
+                            {
+                                Ok(safe_block_number) => safe_block_number,
+                                Err(e) => {
+                                    error!(
+                                        "{} - {} - Error getting the {} block to index up to: {}",
+                                        &config.info_log_name,
+                                        IndexingEventProgressStatus::Live.log(),
+                                        &config.reorg_safe_distance,
+                                        e
+                                    );
+                                    continue;
+                                }
+                            };
                             let from_block = ordering_live_indexing_details.filter.get_from_block();
                             // check reorg distance and skip if not safe
                             if from_block > safe_block_number {
@@ -326,9 +449,10 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
                             let to_block = safe_block_number;
                             if from_block == to_block &&
                                 !config.network_contract.disable_logs_bloom_checks &&
+                                config.function_call.is_none() &&
                                 !is_relevant_block(
                                     &ordering_live_indexing_details.filter.raw_filter().address,
-                                    &config.topic_id,
+                                    &[config.topic_id],
                                     latest_block,
                                 )
                             {
@@ -345,6 +469,8 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
                                     from_block
                                 );
 
+                                record_processed_range(config, from_block, to_block).await;
+
                                 ordering_live_indexing_details.filter =
                                     ordering_live_indexing_details
                                         .filter
@@ -373,12 +499,21 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
                             let permit = semaphore_client.acquire_owned().await;
 
                             if let Ok(permit) = permit {
-                                match config
-                                    .network_contract
-                                    .cached_provider
-                                    .get_logs(&ordering_live_indexing_details.filter)
-                                    .await
+                                let cached_provider = &config.network_contract.cached_provider;
+                                let logs = match get_logs_or_function_calls(
+                                    cached_provider,
+                                    &config.function_call,
+                                    &ordering_live_indexing_details.filter,
+                                )
+                                .await
                                 {
+                                    Ok(logs) => {
+                                        cached_provider.populate_block_timestamps(logs).await
+                                    }
+                                    Err(err) => Err(err),
+                                };
+
+                                match logs {
                                     Ok(logs) => {
                                         debug!(
                                             "{} - {} - Live topic_id {}, Logs: {} from {} to {}",
@@ -403,8 +538,13 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
                                         // clone here over the full logs way less overhead
                                         let last_log = logs.last().cloned();
 
-                                        let fetched_logs =
-                                            Ok(FetchLogsResult { logs, from_block, to_block });
+                                        let fetched_logs = Ok(FetchLogsResult {
+                                            logs,
+                                            from_block,
+                                            to_block,
+                                            // a reorg is rolled back before fetching
+                                            rollback_to_block: None,
+                                        });
 
                                         let result =
                                             handle_logs_result(Arc::clone(config), fetched_logs)
@@ -506,11 +646,51 @@ async fn live_indexing_for_contract_event_dependencies<'a>(
 async fn trigger_event(
     config: Arc<EventProcessingConfig>,
     fn_data: Vec<EventResult>,
+    from_block: U64,
     to_block: U64,
 ) {
     indexing_event_processing();
-    config.trigger_event(fn_data).await;
-    update_progress_and_last_synced_task(config, to_block, indexing_event_processed);
+    let progress_committed = config.trigger_event(fn_data, from_block, to_block).await;
+    update_progress_and_last_synced_task(
+        config,
+        from_block,
+        to_block,
+        progress_committed,
+        indexing_event_processed,
+    );
+}
+
+/// Decodes the logs into the results passed to the event callback.
+pub(crate) async fn event_results_for_logs(
+    config: &EventProcessingConfig,
+    logs: Vec<WrappedLog>,
+    from_block: U64,
+    to_block: U64,
+) -> Result<Vec<EventResult>, ProviderError> {
+    let mut transaction_fields = HashMap::new();
+    if config.include_transaction_fields && !logs.is_empty() {
+        let mut transaction_hashes: Vec<H256> =
+            logs.iter().filter_map(|log| log.inner.transaction_hash).collect();
+        transaction_hashes.sort_unstable();
+        transaction_hashes.dedup();
+
+        transaction_fields = config
+            .network_contract
+            .cached_provider
+            .get_transaction_fields(&transaction_hashes)
+            .await?;
+    }
+
+    Ok(logs
+        .into_iter()
+        .map(|log| {
+            let mut event_result =
+                EventResult::new(Arc::clone(&config.network_contract), log, from_block, to_block);
+            event_result.tx_information.transaction_fields =
+                transaction_fields.get(&event_result.tx_information.transaction_hash).cloned();
+            event_result
+        })
+        .collect())
 }
 
 async fn handle_logs_result(
@@ -521,18 +701,10 @@ async fn handle_logs_result(
         Ok(result) => {
             debug!("Processing logs {} - length {}", config.event_name, result.logs.len());
 
-            let fn_data = result
-                .logs
-                .into_iter()
-                .map(|log| {
-                    EventResult::new(
-                        Arc::clone(&config.network_contract),
-                        log,
-                        result.from_block,
-                        result.to_block,
-                    )
-                })
-                .collect::<Vec<_>>();
+            let fn_data =
+                event_results_for_logs(&config, result.logs, result.from_block, result.to_block)
+                    .await
+                    .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send>)?;
 
             // if shutting down so do not process anymore event
             while !is_running() {
@@ -541,17 +713,20 @@ async fn handle_logs_result(
 
             if !fn_data.is_empty() {
                 return if config.index_event_in_order {
-                    trigger_event(config, fn_data, result.to_block).await;
+                    trigger_event(config, fn_data, result.from_block, result.to_block).await;
                     Ok(tokio::spawn(async {}))
                 } else {
                     let task = tokio::spawn(async move {
-                        trigger_event(config, fn_data, result.to_block).await;
+                        trigger_event(config, fn_data, result.from_block, result.to_block).await;
                     });
                     Ok(task)
                 }
             }
 
-            Ok(tokio::spawn(async {})) // Return a completed task
+            // nothing to index but the range still counts as covered
+            Ok(tokio::spawn(async move {
+                record_processed_range(&config, result.from_block, result.to_block).await;
+            }))
         }
         Err(e) => {
             error!("Error fetching logs: {:?}", e);
@@ -0,0 +1,451 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ethers::types::U64;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use crate::{
+    database::postgres::{client::PostgresError, generate::generate_processed_ranges_table_name},
+    event::config::EventProcessingConfig,
+    helpers::camel_to_snake,
+    indexer::{
+        process::{process_event, ProcessEventError},
+        reorg::remove_indexed_rows,
+    },
+    EthereumSqlTypeWrapper, PostgresClient, ToSql,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ProcessedRangeError {
+    #[error("{0}")]
+    PostgresError(#[from] PostgresError),
+
+    #[error("Could not read or write the processed ranges file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Could not serialize the processed ranges: {0}")]
+    Serde(#[from] serde_json::Error),
+
+    #[error("Could not re-index the gap: {0}")]
+    ProcessEventError(#[from] ProcessEventError),
+}
+
+/// A block range of an event on a network which has been indexed.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProcessedRange {
+    pub contract_name: String,
+    pub event_name: String,
+    pub network: String,
+    pub from_block: U64,
+    pub to_block: U64,
+}
+
+impl ProcessedRange {
+    fn is_for(&self, contract_name: &str, event_name: &str, network: &str) -> bool {
+        self.contract_name == contract_name &&
+            self.event_name == event_name &&
+            self.network == network
+    }
+}
+
+/// Sorts the ranges and merges the ones which overlap or touch.
+pub fn merge_ranges(mut ranges: Vec<(U64, U64)>) -> Vec<(U64, U64)> {
+    ranges.sort();
+
+    let mut merged: Vec<(U64, U64)> = Vec::with_capacity(ranges.len());
+    for (from, to) in ranges {
+        match merged.last_mut() {
+            Some((_, last_to)) if from <= *last_to + 1 => *last_to = (*last_to).max(to),
+            _ => merged.push((from, to)),
+        }
+    }
+
+    merged
+}
+
+/// The block ranges between `from_block` and `to_block` which are not covered by the merged
+/// ranges.
+pub fn find_gaps(ranges: &[(U64, U64)], from_block: U64, to_block: U64) -> Vec<(U64, U64)> {
+    let mut gaps = vec![];
+    let mut next_block = from_block;
+
+    for (from, to) in ranges {
+        if *to < next_block {
+            continue;
+        }
+        if *from > to_block {
+            break;
+        }
+        if *from > next_block {
+            gaps.push((next_block, *from - 1));
+        }
+        next_block = *to + 1;
+    }
+
+    if next_block <= to_block {
+        gaps.push((next_block, to_block));
+    }
+
+    gaps
+}
+
+/// What has been indexed for an event on a network, see `rindexer verify`.
+#[derive(Debug)]
+pub struct EventCoverage {
+    pub start_block: Option<U64>,
+    pub ranges: Vec<(U64, U64)>,
+    pub gaps: Vec<(U64, U64)>,
+}
+
+impl EventCoverage {
+    pub fn indexed_to(&self) -> Option<U64> {
+        self.ranges.last().map(|(_, to)| *to)
+    }
+}
+
+enum ProcessedRangeStoreKind {
+    Postgres { database: Arc<PostgresClient>, table: String },
+    File { path: PathBuf, lock: Mutex<()> },
+}
+
+/// Processed ranges live in postgres when it is enabled otherwise in a JSONL file in the project.
+pub struct ProcessedRangeStore {
+    kind: ProcessedRangeStoreKind,
+}
+
+impl ProcessedRangeStore {
+    pub fn new(
+        project_path: &Path,
+        indexer_name: &str,
+        database: &Option<Arc<PostgresClient>>,
+    ) -> Self {
+        let kind = match database {
+            Some(database) => ProcessedRangeStoreKind::Postgres {
+                database: Arc::clone(database),
+                table: generate_processed_ranges_table_name(indexer_name),
+            },
+            None => ProcessedRangeStoreKind::File {
+                path: project_path
+                    .join(".rindexer")
+                    .join("processed-ranges")
+                    .join(format!("{}.jsonl", camel_to_snake(indexer_name))),
+                lock: Mutex::new(()),
+            },
+        };
+
+        ProcessedRangeStore { kind }
+    }
+
+    /// Records the range as indexed, merging it with the ranges it overlaps or touches.
+    pub async fn record(
+        &self,
+        contract_name: &str,
+        event_name: &str,
+        network: &str,
+        from_block: U64,
+        to_block: U64,
+    ) -> Result<(), ProcessedRangeError> {
+        match &self.kind {
+            ProcessedRangeStoreKind::Postgres { database, table } => {
+                database
+                    .execute(
+                        &format!(
+                            r#"WITH merged AS (
+                                DELETE FROM {table} WHERE "contract_name" = $1 AND "event_name" = $2 AND "network" = $3 AND "from_block" <= $5::NUMERIC + 1 AND "to_block" + 1 >= $4::NUMERIC
+                                RETURNING "from_block", "to_block"
+                            )
+                            INSERT INTO {table} ("contract_name", "event_name", "network", "from_block", "to_block")
+                            SELECT $1, $2, $3, LEAST($4::NUMERIC, MIN("from_block")), GREATEST($5::NUMERIC, MAX("to_block")) FROM merged"#,
+                            table = table
+                        ),
+                        &[
+                            &contract_name,
+                            &event_name,
+                            &network,
+                            &EthereumSqlTypeWrapper::U64(from_block),
+                            &EthereumSqlTypeWrapper::U64(to_block),
+                        ],
+                    )
+                    .await?;
+                Ok(())
+            }
+            ProcessedRangeStoreKind::File { path, lock } => {
+                let _guard = lock.lock().await;
+                let (mut event_ranges, mut ranges): (Vec<_>, Vec<_>) =
+                    read_processed_ranges_file(path)
+                        .await?
+                        .into_iter()
+                        .partition(|range| range.is_for(contract_name, event_name, network));
+                event_ranges.push(ProcessedRange {
+                    contract_name: contract_name.to_string(),
+                    event_name: event_name.to_string(),
+                    network: network.to_string(),
+                    from_block,
+                    to_block,
+                });
+
+                let merged = merge_ranges(
+                    event_ranges.iter().map(|range| (range.from_block, range.to_block)).collect(),
+                );
+                ranges.extend(merged.into_iter().map(|(from_block, to_block)| ProcessedRange {
+                    contract_name: contract_name.to_string(),
+                    event_name: event_name.to_string(),
+                    network: network.to_string(),
+                    from_block,
+                    to_block,
+                }));
+
+                write_processed_ranges_file(path, &ranges).await
+            }
+        }
+    }
+
+    /// The merged indexed ranges of the event on the network.
+    pub async fn ranges(
+        &self,
+        contract_name: &str,
+        event_name: &str,
+        network: &str,
+    ) -> Result<Vec<(U64, U64)>, ProcessedRangeError> {
+        let ranges = match &self.kind {
+            ProcessedRangeStoreKind::Postgres { database, table } => {
+                let rows = database
+                    .query(
+                        &format!(
+                            r#"SELECT "from_block", "to_block" FROM {} WHERE "contract_name" = $1 AND "event_name" = $2 AND "network" = $3"#,
+                            table
+                        ),
+                        &[&contract_name, &event_name, &network],
+                    )
+                    .await?;
+                rows.iter()
+                    .map(|row| {
+                        let from_block: Decimal = row.get("from_block");
+                        let to_block: Decimal = row.get("to_block");
+                        (
+                            U64::from_dec_str(&from_block.to_string()).unwrap_or_default(),
+                            U64::from_dec_str(&to_block.to_string()).unwrap_or_default(),
+                        )
+                    })
+                    .collect()
+            }
+            ProcessedRangeStoreKind::File { path, lock } => {
+                let _guard = lock.lock().await;
+                read_processed_ranges_file(path)
+                    .await?
+                    .iter()
+                    .filter(|range| range.is_for(contract_name, event_name, network))
+                    .map(|range| (range.from_block, range.to_block))
+                    .collect()
+            }
+        };
+
+        Ok(merge_ranges(ranges))
+    }
+
+    /// Forgets every indexed block after `block`, used when a reorg rolls the indexed data back.
+    pub async fn truncate_after(
+        &self,
+        contract_name: &str,
+        event_name: &str,
+        network: &str,
+        block: U64,
+    ) -> Result<(), ProcessedRangeError> {
+        match &self.kind {
+            ProcessedRangeStoreKind::Postgres { database, table } => {
+                let params: [&(dyn ToSql + Sync); 4] =
+                    [&contract_name, &event_name, &network, &EthereumSqlTypeWrapper::U64(block)];
+                database
+                    .execute(
+                        &format!(
+                            r#"DELETE FROM {} WHERE "contract_name" = $1 AND "event_name" = $2 AND "network" = $3 AND "from_block" > $4"#,
+                            table
+                        ),
+                        &params,
+                    )
+                    .await?;
+                database
+                    .execute(
+                        &format!(
+                            r#"UPDATE {} SET "to_block" = $4 WHERE "contract_name" = $1 AND "event_name" = $2 AND "network" = $3 AND "to_block" > $4"#,
+                            table
+                        ),
+                        &params,
+                    )
+                    .await?;
+                Ok(())
+            }
+            ProcessedRangeStoreKind::File { path, lock } => {
+                let _guard = lock.lock().await;
+                let mut ranges = read_processed_ranges_file(path).await?;
+                ranges.retain(|range| {
+                    !range.is_for(contract_name, event_name, network) || range.from_block <= block
+                });
+                for range in ranges
+                    .iter_mut()
+                    .filter(|range| range.is_for(contract_name, event_name, network))
+                {
+                    range.to_block = range.to_block.min(block);
+                }
+                write_processed_ranges_file(path, &ranges).await
+            }
+        }
+    }
+
+    /// The coverage of the event on the network from its start block, when there is no start
+    /// block it starts from the first indexed block.
+    pub async fn coverage(
+        &self,
+        contract_name: &str,
+        event_name: &str,
+        network: &str,
+        start_block: Option<U64>,
+    ) -> Result<EventCoverage, ProcessedRangeError> {
+        let ranges = self.ranges(contract_name, event_name, network).await?;
+        let gaps = match (start_block.or(ranges.first().map(|(from, _)| *from)), ranges.last()) {
+            (Some(from), Some((_, to))) => find_gaps(&ranges, from, *to),
+            _ => vec![],
+        };
+
+        Ok(EventCoverage { start_block, ranges, gaps })
+    }
+}
+
+async fn read_processed_ranges_file(
+    path: &Path,
+) -> Result<Vec<ProcessedRange>, ProcessedRangeError> {
+    let contents = match tokio::fs::read_to_string(path).await {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(ProcessedRangeError::from))
+        .collect()
+}
+
+async fn write_processed_ranges_file(
+    path: &Path,
+    ranges: &[ProcessedRange],
+) -> Result<(), ProcessedRangeError> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let mut contents = String::new();
+    for range in ranges {
+        contents.push_str(&serde_json::to_string(range)?);
+        contents.push('\n');
+    }
+
+    // write then rename so a crash never leaves a half written file behind
+    let temp_path = path.with_extension("jsonl.tmp");
+    tokio::fs::write(&temp_path, contents).await?;
+    tokio::fs::rename(&temp_path, path).await?;
+    Ok(())
+}
+
+/// Records the range as indexed for the event, a failure only means the range is re-indexed as
+/// a gap on the next start.
+pub async fn record_processed_range(
+    config: &EventProcessingConfig,
+    from_block: U64,
+    to_block: U64,
+) {
+    if let Err(e) = config
+        .processed_ranges
+        .record(
+            &config.contract_name,
+            &config.event_name,
+            &config.network_contract.network,
+            from_block,
+            to_block,
+        )
+        .await
+    {
+        error!(
+            "{} - Error recording processed blocks {} - {}: {}",
+            config.info_log_name, from_block, to_block, e
+        );
+    }
+}
+
+/// Re-indexes the ranges between the start block and the last synced block which were never
+/// recorded as indexed, for example batches which were still indexing when the process died.
+pub async fn heal_gaps(
+    config: &EventProcessingConfig,
+    start_block: U64,
+    last_synced_block: U64,
+) -> Result<(), ProcessedRangeError> {
+    let store = &config.processed_ranges;
+    let network = &config.network_contract.network;
+    let ranges = store.ranges(&config.contract_name, &config.event_name, network).await?;
+
+    if ranges.is_empty() {
+        // indexed before processed ranges were recorded so trust the last synced block
+        info!(
+            "{} - No processed ranges recorded yet - marking blocks {} - {} as indexed",
+            config.info_log_name, start_block, last_synced_block
+        );
+        return store
+            .record(
+                &config.contract_name,
+                &config.event_name,
+                network,
+                start_block,
+                last_synced_block,
+            )
+            .await;
+    }
+
+    for (from_block, to_block) in find_gaps(&ranges, start_block, last_synced_block) {
+        warn!(
+            "{} - Found a gap in the indexed blocks {} - {} - re-indexing it",
+            config.info_log_name, from_block, to_block
+        );
+
+        // anything written for the gap before it was lost would be indexed twice
+        remove_indexed_rows(config, from_block, Some(to_block)).await;
+
+        let gap_config = EventProcessingConfig {
+            start_block: from_block,
+            end_block: to_block,
+            live_indexing: false,
+            update_last_synced_block: false,
+            ..config.clone()
+        };
+        process_event(gap_config, true).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks(ranges: &[(u64, u64)]) -> Vec<(U64, U64)> {
+        ranges.iter().map(|(from, to)| (U64::from(*from), U64::from(*to))).collect()
+    }
+
+    #[test]
+    fn test_merge_ranges_and_find_gaps() {
+        let ranges = merge_ranges(blocks(&[(300, 399), (100, 199), (200, 249), (150, 180)]));
+        assert_eq!(ranges, blocks(&[(100, 249), (300, 399)]));
+
+        assert_eq!(find_gaps(&ranges, U64::from(100), U64::from(399)), blocks(&[(250, 299)]));
+        assert_eq!(
+            find_gaps(&ranges, U64::from(50), U64::from(450)),
+            blocks(&[(50, 99), (250, 299), (400, 450)])
+        );
+        assert!(find_gaps(&ranges, U64::from(120), U64::from(240)).is_empty());
+    }
+}
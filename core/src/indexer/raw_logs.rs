@@ -0,0 +1,549 @@
+use std::{path::Path, str::FromStr, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use ethers::{
+    abi::{Abi, Contract as EthersContract},
+    types::{Address, Bytes, Log, H256, U256, U64},
+};
+use serde_json::Value;
+use tokio_postgres::Row;
+use tracing::{info, warn};
+
+use crate::{
+    abi::{ABIItem, ParamTypeError, ReadAbiError},
+    database::postgres::{
+        client::{PostgresClient, PostgresError},
+        entity::IndexerEntities,
+        generate::{
+            generate_column_names_only_with_base_properties, generate_event_table_full_name,
+        },
+        setup::{setup_postgres, SetupPostgresError},
+        sql_type_wrapper::{
+            map_log_params_to_ethereum_wrapper, map_transaction_fields_to_ethereum_wrapper,
+            EthereumSqlTypeWrapper,
+        },
+    },
+    event::callback_registry::{EventResult, TransactionFields, TxInformation},
+    indexer::{
+        log_helpers::parse_log,
+        processed_ranges::{ProcessedRangeError, ProcessedRangeStore},
+    },
+    manifest::{contract::ParseAbiError, core::Manifest},
+};
+
+pub const RAW_LOGS_TABLE_NAME: &str = "rindexer_internal.raw_logs";
+
+const RAW_LOGS_COLUMNS: [&str; 16] = [
+    "indexer_name",
+    "contract_name",
+    "network",
+    "address",
+    "topic0",
+    "topic1",
+    "topic2",
+    "topic3",
+    "data",
+    "block_number",
+    "block_hash",
+    "block_timestamp",
+    "tx_hash",
+    "tx_index",
+    "log_index",
+    "transaction_fields",
+];
+
+pub fn generate_raw_logs_table_sql() -> String {
+    format!(
+        r#"
+        CREATE SCHEMA IF NOT EXISTS rindexer_internal;
+        CREATE TABLE IF NOT EXISTS {} (
+            indexer_name TEXT NOT NULL,
+            contract_name TEXT NOT NULL,
+            network TEXT NOT NULL,
+            address CHAR(42) NOT NULL,
+            topic0 CHAR(66),
+            topic1 CHAR(66),
+            topic2 CHAR(66),
+            topic3 CHAR(66),
+            data BYTEA NOT NULL,
+            block_number BIGINT NOT NULL,
+            block_hash CHAR(66) NOT NULL,
+            block_timestamp TIMESTAMPTZ,
+            tx_hash CHAR(66) NOT NULL,
+            tx_index INTEGER NOT NULL,
+            log_index INTEGER NOT NULL,
+            transaction_fields JSONB,
+            PRIMARY KEY (indexer_name, contract_name, network, tx_hash, log_index)
+        );
+    "#,
+        RAW_LOGS_TABLE_NAME
+    )
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RawLogsError {
+    #[error("Redecode needs postgres storage enabled")]
+    PostgresNotEnabled,
+
+    #[error("Redecode needs `raw_logs: true` on the postgres storage so logs are archived")]
+    RawLogsNotEnabled,
+
+    #[error("Contract {0} not found in the rindexer.yaml")]
+    ContractNotFound(String),
+
+    #[error("Event name not found in ABI for contract: {0} - event: {1}")]
+    EventNameNotFoundInAbi(String, String),
+
+    #[error("Could not setup postgres: {0}")]
+    SetupPostgresError(#[from] SetupPostgresError),
+
+    #[error("{0}")]
+    PostgresError(#[from] PostgresError),
+
+    #[error("Could not read ABI items: {0}")]
+    CouldNotReadAbiItems(#[from] ReadAbiError),
+
+    #[error("Could not read ABI JSON: {0}")]
+    CouldNotReadAbiJson(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    ParseAbiError(#[from] ParseAbiError),
+
+    #[error("{0}")]
+    ParamTypeError(#[from] ParamTypeError),
+
+    #[error("Invalid archived log: {0}")]
+    InvalidArchivedLog(String),
+
+    #[error("Could not insert the redecoded rows: {0}")]
+    BulkInsertError(String),
+
+    #[error("Could not read the processed ranges: {0}")]
+    ProcessedRangeError(#[from] ProcessedRangeError),
+
+    #[error("{0}::{1} - {2} - no logs of the event are archived from block {3}, it was not indexed with raw_logs enabled so use rindexer backfill for it instead")]
+    EventNotArchived(String, String, String, U64),
+}
+
+/// A log as it was fetched from the RPC with the coordinates needed to decode it again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchivedLog {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Bytes,
+    pub block_number: U64,
+    pub block_hash: H256,
+    pub block_timestamp: Option<U256>,
+    pub transaction_hash: H256,
+    pub transaction_index: U64,
+    pub log_index: U256,
+    pub transaction_fields: Option<TransactionFields>,
+}
+
+fn parse_hex<T: FromStr>(value: &str) -> Result<T, RawLogsError> {
+    T::from_str(value.trim()).map_err(|_| RawLogsError::InvalidArchivedLog(value.to_string()))
+}
+
+impl ArchivedLog {
+    pub fn from_event_result(result: &EventResult) -> Self {
+        ArchivedLog {
+            address: result.log.address,
+            topics: result.log.topics.clone(),
+            data: result.log.data.clone(),
+            block_number: result.tx_information.block_number,
+            block_hash: result.tx_information.block_hash,
+            block_timestamp: result.tx_information.block_timestamp,
+            transaction_hash: result.tx_information.transaction_hash,
+            transaction_index: result.tx_information.transaction_index,
+            log_index: result.tx_information.log_index,
+            transaction_fields: result.tx_information.transaction_fields.clone(),
+        }
+    }
+
+    fn from_row(row: &Row) -> Result<Self, RawLogsError> {
+        let mut topics = vec![];
+        for index in 0..4 {
+            if let Some(topic) = row.get::<_, Option<String>>(index + 1) {
+                topics.push(parse_hex(&topic)?);
+            }
+        }
+
+        let transaction_fields = match row.get::<_, Option<Value>>(12) {
+            Some(value) => serde_json::from_value(value)?,
+            None => None,
+        };
+
+        Ok(ArchivedLog {
+            address: parse_hex(&row.get::<_, String>(0))?,
+            topics,
+            data: Bytes::from(row.get::<_, Vec<u8>>(5)),
+            block_number: U64::from(row.get::<_, i64>(6) as u64),
+            block_hash: parse_hex(&row.get::<_, String>(7))?,
+            block_timestamp: row
+                .get::<_, Option<DateTime<Utc>>>(8)
+                .map(|timestamp| U256::from(timestamp.timestamp() as u64)),
+            transaction_hash: parse_hex(&row.get::<_, String>(9))?,
+            transaction_index: U64::from(row.get::<_, i32>(10) as u64),
+            log_index: U256::from(row.get::<_, i32>(11) as u64),
+            transaction_fields,
+        })
+    }
+
+    fn to_sql_row(
+        &self,
+        indexer_name: &str,
+        contract_name: &str,
+        network: &str,
+    ) -> Vec<EthereumSqlTypeWrapper> {
+        let topic = |index: usize| {
+            EthereumSqlTypeWrapper::StringCharNullable(
+                self.topics.get(index).map(|topic| format!("{:?}", topic)).unwrap_or_default(),
+            )
+        };
+
+        vec![
+            EthereumSqlTypeWrapper::String(indexer_name.to_string()),
+            EthereumSqlTypeWrapper::String(contract_name.to_string()),
+            EthereumSqlTypeWrapper::String(network.to_string()),
+            EthereumSqlTypeWrapper::Address(self.address),
+            topic(0),
+            topic(1),
+            topic(2),
+            topic(3),
+            EthereumSqlTypeWrapper::Bytes(self.data.clone()),
+            EthereumSqlTypeWrapper::U64BigInt(self.block_number),
+            EthereumSqlTypeWrapper::H256(self.block_hash),
+            EthereumSqlTypeWrapper::DateTimeNullable(
+                self.tx_information(network).block_timestamp_to_datetime(),
+            ),
+            EthereumSqlTypeWrapper::H256(self.transaction_hash),
+            EthereumSqlTypeWrapper::U32(self.transaction_index.as_u32()),
+            EthereumSqlTypeWrapper::U32(self.log_index.as_u32()),
+            EthereumSqlTypeWrapper::JSONB(
+                serde_json::to_value(&self.transaction_fields).unwrap_or_default(),
+            ),
+        ]
+    }
+
+    pub fn to_log(&self) -> Log {
+        Log {
+            address: self.address,
+            topics: self.topics.clone(),
+            data: self.data.clone(),
+            block_hash: Some(self.block_hash),
+            block_number: Some(self.block_number),
+            transaction_hash: Some(self.transaction_hash),
+            transaction_index: Some(self.transaction_index),
+            log_index: Some(self.log_index),
+            ..Default::default()
+        }
+    }
+
+    pub fn tx_information(&self, network: &str) -> TxInformation {
+        TxInformation {
+            network: network.to_string(),
+            address: self.address,
+            block_hash: self.block_hash,
+            block_number: self.block_number,
+            block_timestamp: self.block_timestamp,
+            transaction_hash: self.transaction_hash,
+            log_index: self.log_index,
+            transaction_index: self.transaction_index,
+            transaction_fields: self.transaction_fields.clone(),
+        }
+    }
+}
+
+/// Archives the logs of a batch, runs inside the transaction of the batch so the archive never
+/// holds logs the event tables are missing. Logs which are already archived are skipped.
+pub(crate) async fn store_raw_logs(
+    database: &PostgresClient,
+    indexer_name: &str,
+    contract_name: &str,
+    network: &str,
+    results: &[EventResult],
+) -> Result<(), String> {
+    let rows: Vec<Vec<EthereumSqlTypeWrapper>> = results
+        .iter()
+        .map(|result| {
+            ArchivedLog::from_event_result(result).to_sql_row(indexer_name, contract_name, network)
+        })
+        .collect();
+    let columns: Vec<String> = RAW_LOGS_COLUMNS.iter().map(|column| column.to_string()).collect();
+
+    database.insert_bulk(RAW_LOGS_TABLE_NAME, &columns, &rows).await
+}
+
+/// Removes the archived logs of an event from `from_block` after a chain reorg.
+pub(crate) async fn remove_raw_logs_from_block(
+    database: &PostgresClient,
+    indexer_name: &str,
+    contract_name: &str,
+    network: &str,
+    topic_id: &H256,
+    from_block: U64,
+) -> Result<u64, PostgresError> {
+    database
+        .execute(
+            &format!(
+                "DELETE FROM {} WHERE indexer_name = $1 AND contract_name = $2 AND network = $3 AND topic0 = $4 AND block_number >= $5",
+                RAW_LOGS_TABLE_NAME
+            ),
+            &[
+                &indexer_name,
+                &contract_name,
+                &network,
+                &format!("{:?}", topic_id),
+                &EthereumSqlTypeWrapper::U64BigInt(from_block),
+            ],
+        )
+        .await
+}
+
+/// How many archived logs of an event were decoded into its table on a network.
+#[derive(Debug, Clone)]
+pub struct RedecodedEvent {
+    pub event_name: String,
+    pub network: String,
+    pub from_block: U64,
+    pub decoded: usize,
+    pub undecodable: usize,
+}
+
+/// Rebuilds the event tables of a contract from the raw log archive without any network access.
+///
+/// The archive only starts when `raw_logs` is enabled, so rows of a network are replaced from the
+/// first archived block of the contract onwards and anything indexed before that is kept. Only the
+/// logs of the events being indexed are archived, so an event which was not indexed since that
+/// block has nothing to redecode and fails the redecode rather than emptying its table.
+pub async fn redecode(
+    project_path: &Path,
+    manifest: &Manifest,
+    contract_name: &str,
+) -> Result<Vec<RedecodedEvent>, RawLogsError> {
+    if !manifest.storage.postgres_enabled() {
+        return Err(RawLogsError::PostgresNotEnabled);
+    }
+    if !manifest.storage.postgres_raw_logs_enabled() {
+        return Err(RawLogsError::RawLogsNotEnabled);
+    }
+
+    let contract = manifest
+        .contracts
+        .iter()
+        .find(|contract| {
+            contract.name == contract_name ||
+                contract.before_modify_name_if_filter_readonly() == contract_name
+        })
+        .ok_or_else(|| RawLogsError::ContractNotFound(contract_name.to_string()))?;
+    // logs are archived under the name the contract is registered with
+    let contract_name = contract.before_modify_name_if_filter_readonly().to_string();
+
+    // creates the tables and migrates them to the current ABI
+    let client = Arc::new(setup_postgres(project_path, manifest).await?);
+    let processed_ranges =
+        ProcessedRangeStore::new(project_path, &manifest.name, &Some(Arc::clone(&client)));
+
+    let abi: Abi = serde_json::from_str(&contract.parse_abi(project_path)?)?;
+    #[allow(clippy::useless_conversion)]
+    let abi_gen = EthersContract::from(abi);
+    let abi_items = ABIItem::read_abi_items(project_path, contract)?;
+    let event_infos = ABIItem::extract_event_names_and_signatures_from_abi(abi_items)?;
+    let include_transaction_fields = contract.include_transaction_fields.unwrap_or(false);
+    let entities = IndexerEntities::new(project_path, manifest)?;
+
+    let mut redecoded = vec![];
+
+    client
+        .in_transaction(|_| async {
+            for details in &contract.details {
+                let first_block = client
+                    .query_one(
+                        &format!(
+                            "SELECT MIN(block_number) FROM {} WHERE indexer_name = $1 AND contract_name = $2 AND network = $3",
+                            RAW_LOGS_TABLE_NAME
+                        ),
+                        &[&manifest.name, &contract_name, &details.network],
+                    )
+                    .await?
+                    .get::<_, Option<i64>>(0);
+                let Some(first_block) = first_block.map(|block| U64::from(block as u64)) else {
+                    warn!("{} - {} - nothing archived to redecode", contract_name, details.network);
+                    continue;
+                };
+
+                let mut table_names = vec![];
+                for event_info in &event_infos {
+                    let event = abi_gen
+                        .events
+                        .get(&event_info.name)
+                        .and_then(|events| events.first())
+                        .ok_or_else(|| {
+                            RawLogsError::EventNameNotFoundInAbi(
+                                contract_name.clone(),
+                                event_info.name.clone(),
+                            )
+                        })?;
+                    let table_name = generate_event_table_full_name(
+                        &manifest.name,
+                        &contract_name,
+                        &event_info.name,
+                    );
+                    table_names.push(table_name.clone());
+
+                    let rows = client
+                        .query(
+                            &format!(
+                                "SELECT address, topic0, topic1, topic2, topic3, data, block_number, block_hash, block_timestamp, tx_hash, tx_index, log_index, transaction_fields FROM {} WHERE indexer_name = $1 AND contract_name = $2 AND network = $3 AND topic0 = $4 ORDER BY block_number, log_index",
+                                RAW_LOGS_TABLE_NAME
+                            ),
+                            &[
+                                &manifest.name,
+                                &contract_name,
+                                &details.network,
+                                &format!("{:?}", event_info.topic_id()),
+                            ],
+                        )
+                        .await?;
+
+                    // a quiet event has nothing archived either, it is only redecoded when it was
+                    // indexed over the archived blocks
+                    if rows.is_empty() &&
+                        !processed_ranges
+                            .ranges(&contract_name, &event_info.name, &details.network)
+                            .await?
+                            .iter()
+                            .any(|(from, to)| *from <= first_block && first_block <= *to)
+                    {
+                        return Err(RawLogsError::EventNotArchived(
+                            contract_name.clone(),
+                            event_info.name.clone(),
+                            details.network.clone(),
+                            first_block,
+                        ));
+                    }
+
+                    client
+                        .execute(
+                            &format!(
+                                "DELETE FROM {} WHERE network = $1 AND block_number >= $2",
+                                table_name
+                            ),
+                            &[&details.network, &EthereumSqlTypeWrapper::U64BigInt(first_block)],
+                        )
+                        .await?;
+
+                    let mut bulk_data: Vec<Vec<EthereumSqlTypeWrapper>> = vec![];
+                    let mut undecodable = 0;
+                    for row in &rows {
+                        let archived_log = ArchivedLog::from_row(row)?;
+                        let Some(log) = parse_log(event, &archived_log.to_log()) else {
+                            undecodable += 1;
+                            continue;
+                        };
+
+                        let mut all_params =
+                            vec![EthereumSqlTypeWrapper::Address(archived_log.address)];
+                        all_params.extend(map_log_params_to_ethereum_wrapper(
+                            &event_info.inputs,
+                            &log.params,
+                        ));
+                        all_params.extend(vec![
+                            EthereumSqlTypeWrapper::H256(archived_log.transaction_hash),
+                            EthereumSqlTypeWrapper::U64BigInt(archived_log.block_number),
+                            EthereumSqlTypeWrapper::H256(archived_log.block_hash),
+                            EthereumSqlTypeWrapper::String(details.network.clone()),
+                            EthereumSqlTypeWrapper::U32(archived_log.transaction_index.as_u32()),
+                            EthereumSqlTypeWrapper::U32(archived_log.log_index.as_u32()),
+                            EthereumSqlTypeWrapper::DateTimeNullable(
+                                archived_log
+                                    .tx_information(&details.network)
+                                    .block_timestamp_to_datetime(),
+                            ),
+                        ]);
+                        if include_transaction_fields {
+                            all_params.extend(map_transaction_fields_to_ethereum_wrapper(
+                                archived_log.transaction_fields.as_ref(),
+                            ));
+                        }
+                        bulk_data.push(all_params);
+                    }
+
+                    let column_names = generate_column_names_only_with_base_properties(
+                        &event_info.inputs,
+                        include_transaction_fields,
+                    );
+                    client
+                        .insert_bulk(&table_name, &column_names, &bulk_data)
+                        .await
+                        .map_err(RawLogsError::BulkInsertError)?;
+
+                    info!(
+                        "{}::{} - {} - redecoded {} logs from block {}",
+                        contract_name,
+                        event_info.name,
+                        details.network,
+                        bulk_data.len(),
+                        first_block
+                    );
+                    redecoded.push(RedecodedEvent {
+                        event_name: event_info.name.clone(),
+                        network: details.network.clone(),
+                        from_block: first_block,
+                        decoded: bulk_data.len(),
+                        undecodable,
+                    });
+                }
+
+                // the entities are built again from the redecoded rows
+                entities
+                    .rebuild_entities_for_event_tables(&client, &table_names, &details.network)
+                    .await?;
+            }
+
+            Ok::<(), RawLogsError>(())
+        })
+        .await?;
+
+    Ok(redecoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archived_log_round_trips_to_log() {
+        let archived_log = ArchivedLog {
+            address: Address::from_low_u64_be(1),
+            topics: vec![H256::from_low_u64_be(2), H256::from_low_u64_be(3)],
+            data: Bytes::from(vec![1, 2, 3]),
+            block_number: U64::from(100),
+            block_hash: H256::from_low_u64_be(4),
+            block_timestamp: Some(U256::from(1_700_000_000)),
+            transaction_hash: H256::from_low_u64_be(5),
+            transaction_index: U64::from(6),
+            log_index: U256::from(7),
+            transaction_fields: None,
+        };
+
+        let log = archived_log.to_log();
+        assert_eq!(log.topics, archived_log.topics);
+        assert_eq!(log.block_number, Some(U64::from(100)));
+        assert_eq!(log.log_index, Some(U256::from(7)));
+
+        let tx_information = archived_log.tx_information("ethereum");
+        assert_eq!(tx_information.network, "ethereum");
+        assert_eq!(tx_information.transaction_index, U64::from(6));
+        assert_eq!(
+            tx_information.block_timestamp_to_datetime().map(|timestamp| timestamp.timestamp()),
+            Some(1_700_000_000)
+        );
+
+        let row = archived_log.to_sql_row("indexer", "Contract", "ethereum");
+        assert_eq!(row.len(), RAW_LOGS_COLUMNS.len());
+        assert!(
+            matches!(&row[7], EthereumSqlTypeWrapper::StringCharNullable(topic) if topic.is_empty())
+        );
+    }
+}
@@ -1,4 +1,31 @@
-use ethers::types::{U256, U64};
+use std::{collections::VecDeque, path::Path, sync::Arc};
+
+use ethers::{
+    prelude::ProviderError,
+    types::{Block, H256, U256, U64},
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    database::{
+        postgres::generate::generate_event_table_full_name,
+        sqlite::generate::generate_sqlite_event_table_name,
+    },
+    event::config::EventProcessingConfig,
+    helpers::get_full_path,
+    indexer::{
+        last_synced::rewind_last_synced_block, raw_logs::remove_raw_logs_from_block,
+        IndexingEventProgressStatus,
+    },
+    manifest::network::ReorgSafeDistance,
+    provider::JsonRpcCachedProvider,
+    simple_file_formatters::csv::remove_csv_rows_in_block_range,
+    EthereumSqlTypeWrapper,
+};
+
+/// How many of the most recent block hashes are remembered to find a fork point, anything
+/// deeper than this can not be rolled back safely.
+const MAX_TRACKED_BLOCK_HASHES: usize = 256;
 
 pub fn reorg_safe_distance_for_chain(chain_id: &U256) -> U64 {
     match chain_id.as_u64() {
@@ -7,6 +34,295 @@ pub fn reorg_safe_distance_for_chain(chain_id: &U256) -> U64 {
     }
 }
 
+/// Resolves the highest block which is safe to index for the configured distance, block tags
+/// are looked up on the node and never go above the latest block we have seen.
+pub async fn safe_block_number(
+    cached_provider: &JsonRpcCachedProvider,
+    reorg_safe_distance: &ReorgSafeDistance,
+    latest_block_number: U64,
+) -> Result<U64, ProviderError> {
+    match reorg_safe_distance {
+        ReorgSafeDistance::Blocks(blocks) => Ok(latest_block_number.saturating_sub(*blocks)),
+        ReorgSafeDistance::Latest => Ok(latest_block_number),
+        ReorgSafeDistance::Safe | ReorgSafeDistance::Finalized => {
+            let tag = reorg_safe_distance.block_tag().expect("block tag should be set");
+            match cached_provider.get_block_number_by_tag(tag).await? {
+                Some(block_number) => Ok(block_number.min(latest_block_number)),
+                None => Err(ProviderError::CustomError(format!(
+                    "RPC did not return a block for the {} block tag",
+                    reorg_safe_distance
+                ))),
+            }
+        }
+    }
+}
+
+/// Remembers the hashes of the most recent blocks seen while live indexing so a chain
+/// reorganisation can be noticed when a new block no longer builds on top of them.
+#[derive(Debug, Default)]
+pub struct BlockHashTracker {
+    blocks: VecDeque<(U64, H256)>,
+}
+
+impl BlockHashTracker {
+    pub fn new() -> Self {
+        Self { blocks: VecDeque::new() }
+    }
+
+    pub fn latest(&self) -> Option<(U64, H256)> {
+        self.blocks.back().copied()
+    }
+
+    pub fn oldest(&self) -> Option<(U64, H256)> {
+        self.blocks.front().copied()
+    }
+
+    pub fn hash_at(&self, block_number: U64) -> Option<H256> {
+        self.blocks.iter().rev().find(|(number, _)| *number == block_number).map(|(_, hash)| *hash)
+    }
+
+    pub fn record(&mut self, block_number: U64, block_hash: H256) {
+        // a block at the same or lower height replaces anything we knew from that height upwards
+        while let Some((number, _)) = self.blocks.back() {
+            if *number >= block_number {
+                self.blocks.pop_back();
+            } else {
+                break;
+            }
+        }
+
+        self.blocks.push_back((block_number, block_hash));
+
+        while self.blocks.len() > MAX_TRACKED_BLOCK_HASHES {
+            self.blocks.pop_front();
+        }
+    }
+
+    /// Returns true if the block is known to not build on top of the tracked chain, if the
+    /// parent is not tracked (blocks were skipped between polls) this returns false and
+    /// `latest_is_canonical` should be used to verify the chain.
+    pub fn is_reorged(&self, block_number: U64, block_hash: H256, parent_hash: H256) -> bool {
+        if let Some(known_hash) = self.hash_at(block_number) {
+            return known_hash != block_hash;
+        }
+
+        if block_number.is_zero() {
+            return false;
+        }
+
+        match self.hash_at(block_number - 1) {
+            Some(known_parent_hash) => known_parent_hash != parent_hash,
+            None => false,
+        }
+    }
+
+    /// Checks the newest tracked block is still part of the canonical chain, used when blocks
+    /// have been skipped between polls, so the parent hash can not be compared.
+    pub async fn latest_is_canonical(
+        &self,
+        cached_provider: &Arc<JsonRpcCachedProvider>,
+    ) -> Result<bool, ProviderError> {
+        match self.latest() {
+            Some((number, hash)) => {
+                let block = cached_provider.get_block_by_number(number).await?;
+                Ok(block.and_then(|block| block.hash) == Some(hash))
+            }
+            None => Ok(true),
+        }
+    }
+
+    /// Walks back through the tracked blocks until one still matches the canonical chain and
+    /// returns its block number, tracked blocks above the fork point are forgotten. If none of
+    /// the tracked blocks match the reorg is deeper than we can see and the block before the
+    /// oldest tracked block is returned.
+    pub async fn find_fork_point(
+        &mut self,
+        cached_provider: &Arc<JsonRpcCachedProvider>,
+    ) -> Result<Option<U64>, ProviderError> {
+        let oldest = match self.oldest() {
+            Some((number, _)) => number,
+            None => return Ok(None),
+        };
+
+        while let Some((number, hash)) = self.latest() {
+            let canonical = cached_provider.get_block_by_number(number).await?;
+            if canonical.and_then(|block| block.hash) == Some(hash) {
+                return Ok(Some(number));
+            }
+            self.blocks.pop_back();
+        }
+
+        Ok(Some(oldest.saturating_sub(U64::from(1))))
+    }
+}
+
+pub fn block_hashes(block: &Block<H256>) -> Option<(U64, H256, H256)> {
+    match (block.number, block.hash) {
+        (Some(number), Some(hash)) => Some((number, hash, block.parent_hash)),
+        _ => None,
+    }
+}
+
+/// Removes everything indexed above the fork point for the event and rewinds the last synced
+/// block so the canonical blocks get indexed again.
+pub async fn rollback_event_to_block(config: &Arc<EventProcessingConfig>, fork_block: U64) {
+    warn!(
+        "{} - {} - Chain reorg detected on {} - rolling back indexed data after block {}",
+        config.info_log_name,
+        IndexingEventProgressStatus::Live.log(),
+        config.network_contract.network,
+        fork_block
+    );
+
+    remove_indexed_rows(config, fork_block + 1, None).await;
+
+    if let (true, Some(database)) = (config.archive_raw_logs, &config.database) {
+        if let Err(e) = remove_raw_logs_from_block(
+            database,
+            &config.indexer_name,
+            &config.contract_name,
+            &config.network_contract.network,
+            &config.topic_id,
+            fork_block + 1,
+        )
+        .await
+        {
+            error!("{} - Error removing the archived raw logs: {}", config.info_log_name, e);
+        }
+    }
+
+    if config.stream_last_synced_block_file_path.is_some() {
+        warn!(
+            "{} - {} - Events already streamed after block {} can not be recalled, consumers will receive the canonical events again",
+            config.info_log_name,
+            IndexingEventProgressStatus::Live.log(),
+            fork_block
+        );
+    }
+
+    rewind_last_synced_block(config, fork_block).await;
+
+    if let Err(e) = config
+        .processed_ranges
+        .truncate_after(
+            &config.contract_name,
+            &config.event_name,
+            &config.network_contract.network,
+            fork_block,
+        )
+        .await
+    {
+        error!("{} - Error rolling back the processed ranges: {}", config.info_log_name, e);
+    }
+
+    if let Some(factory_children) = &config.factory_children {
+        if let Err(e) = factory_children.rollback_to_block(fork_block).await {
+            error!("{} - Error rolling back the factory children: {}", config.info_log_name, e);
+        }
+    }
+}
+
+/// Removes the rows indexed for the event from `from_block` up to `to_block`, or every block
+/// after `from_block` when there is no `to_block`.
+pub(crate) async fn remove_indexed_rows(
+    config: &EventProcessingConfig,
+    from_block: U64,
+    to_block: Option<U64>,
+) {
+    if let Some(database) = &config.database {
+        let table_name = generate_event_table_full_name(
+            &config.indexer_name,
+            &config.contract_name,
+            &config.event_name,
+        );
+        // entities built from the rows are taken back to before the removed blocks
+        let result = config
+            .entities
+            .remove_event_rows(
+                database,
+                &table_name,
+                &config.network_contract.network,
+                from_block,
+                to_block,
+            )
+            .await;
+
+        match result {
+            Ok(deleted) => info!(
+                "{} - Removed {} rows from block {} in {}",
+                config.info_log_name, deleted, from_block, table_name
+            ),
+            Err(e) => error!(
+                "{} - Error removing rows from block {} in {}: {:?}",
+                config.info_log_name, from_block, table_name, e
+            ),
+        }
+    }
+
+    if let Some(sqlite) = &config.sqlite {
+        let table_name = generate_sqlite_event_table_name(
+            &config.indexer_name,
+            &config.contract_name,
+            &config.event_name,
+        );
+        let mut query =
+            format!("DELETE FROM \"{}\" WHERE network = ?1 AND block_number >= ?2", table_name);
+        let mut params = vec![
+            EthereumSqlTypeWrapper::String(config.network_contract.network.clone()),
+            EthereumSqlTypeWrapper::U64BigInt(from_block),
+        ];
+        if let Some(to_block) = to_block {
+            query.push_str(" AND block_number <= ?3");
+            params.push(EthereumSqlTypeWrapper::U64BigInt(to_block));
+        }
+
+        match sqlite.execute(&query, params).await {
+            Ok(deleted) => info!(
+                "{} - Removed {} rows from block {} in sqlite {}",
+                config.info_log_name, deleted, from_block, table_name
+            ),
+            Err(e) => error!(
+                "{} - Error removing rows from block {} in sqlite {}: {:?}",
+                config.info_log_name, from_block, table_name, e
+            ),
+        }
+    }
+
+    if let Some(csv_details) = &config.csv_details {
+        if let Ok(full_path) = get_full_path(&config.project_path, &csv_details.path) {
+            let csv_file =
+                csv_file_path_for_event(&full_path, &config.contract_name, &config.event_name);
+            if Path::new(&csv_file).exists() {
+                if let Err(e) = remove_csv_rows_in_block_range(
+                    &csv_file,
+                    &config.network_contract.network,
+                    from_block,
+                    to_block,
+                )
+                .await
+                {
+                    error!(
+                        "{} - Error removing rows from csv {}: {:?}",
+                        config.info_log_name, csv_file, e
+                    );
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn csv_file_path_for_event(
+    full_path: &Path,
+    contract_name: &str,
+    event_name: &str,
+) -> String {
+    full_path
+        .join(contract_name)
+        .join(format!("{}-{}.csv", contract_name, event_name).to_lowercase())
+        .to_string_lossy()
+        .into_owned()
+}
+
 #[cfg(test)]
 mod tests {
     use ethers::types::U256;
@@ -24,4 +340,56 @@ mod tests {
         let other_chain_id = U256::from(42);
         assert_eq!(reorg_safe_distance_for_chain(&other_chain_id), U64::from(64));
     }
+
+    #[test]
+    fn test_block_hash_tracker_detects_parent_mismatch() {
+        let mut tracker = BlockHashTracker::new();
+        tracker.record(U64::from(10), H256::from_low_u64_be(10));
+        tracker.record(U64::from(11), H256::from_low_u64_be(11));
+
+        assert!(!tracker.is_reorged(
+            U64::from(12),
+            H256::from_low_u64_be(12),
+            H256::from_low_u64_be(11)
+        ));
+        assert!(tracker.is_reorged(
+            U64::from(12),
+            H256::from_low_u64_be(12),
+            H256::from_low_u64_be(999)
+        ));
+        assert!(tracker.is_reorged(
+            U64::from(11),
+            H256::from_low_u64_be(111),
+            H256::from_low_u64_be(10)
+        ));
+        // parent not tracked so can not tell from the hashes alone
+        assert!(!tracker.is_reorged(
+            U64::from(20),
+            H256::from_low_u64_be(20),
+            H256::from_low_u64_be(19)
+        ));
+    }
+
+    #[test]
+    fn test_block_hash_tracker_record_replaces_higher_blocks() {
+        let mut tracker = BlockHashTracker::new();
+        tracker.record(U64::from(10), H256::from_low_u64_be(10));
+        tracker.record(U64::from(11), H256::from_low_u64_be(11));
+        tracker.record(U64::from(12), H256::from_low_u64_be(12));
+        tracker.record(U64::from(11), H256::from_low_u64_be(111));
+
+        assert_eq!(tracker.latest(), Some((U64::from(11), H256::from_low_u64_be(111))));
+        assert_eq!(tracker.hash_at(U64::from(12)), None);
+        assert_eq!(tracker.hash_at(U64::from(10)), Some(H256::from_low_u64_be(10)));
+    }
+
+    #[test]
+    fn test_block_hash_tracker_is_bounded() {
+        let mut tracker = BlockHashTracker::new();
+        for i in 0..(MAX_TRACKED_BLOCK_HASHES as u64 + 10) {
+            tracker.record(U64::from(i), H256::from_low_u64_be(i));
+        }
+
+        assert_eq!(tracker.oldest().map(|(number, _)| number), Some(U64::from(10)));
+    }
 }
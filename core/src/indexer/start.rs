@@ -1,4 +1,4 @@
-use std::{path::Path, sync::Arc};
+use std::{collections::HashMap, path::Path, sync::Arc};
 
 use ethers::{providers::ProviderError, types::U64};
 use futures::future::try_join_all;
@@ -10,24 +10,33 @@ use tokio::{
 use tracing::{error, info};
 
 use crate::{
-    database::postgres::client::PostgresConnectionError,
+    abi::ReadAbiError,
+    database::{
+        postgres::{client::PostgresConnectionError, entity::IndexerEntities},
+        sqlite::client::SqliteConnectionError,
+    },
     event::{
-        callback_registry::EventCallbackRegistry, config::EventProcessingConfig,
-        contract_setup::NetworkContract,
+        callback_registry::EventCallbackRegistry,
+        config::EventProcessingConfig,
+        contract_setup::{IndexingContractSetup, NetworkContract},
     },
     indexer::{
+        dead_letters::{redrive_dead_letters, DeadLetterError, DeadLetterPolicy, DeadLetterStore},
         dependency::ContractEventsDependenciesConfig,
+        factory::{FactoryChildren, FactoryChildrenError},
+        function_calls::FunctionCallTracer,
         last_synced::{get_last_synced_block_number, SyncConfig},
         process::{
-            process_contracts_events_with_dependencies, process_event,
+            process_contract_events, process_contracts_events_with_dependencies, process_event,
             ProcessContractsEventsWithDependenciesError, ProcessEventError,
         },
+        processed_ranges::{heal_gaps, ProcessedRangeError, ProcessedRangeStore},
         progress::IndexingEventsProgressState,
-        reorg::reorg_safe_distance_for_chain,
+        reorg::{reorg_safe_distance_for_chain, safe_block_number},
         ContractEventDependencies,
     },
-    manifest::core::Manifest,
-    PostgresClient,
+    manifest::{core::Manifest, network::ReorgSafeDistance},
+    PostgresClient, SqliteClient,
 };
 
 #[derive(thiserror::Error, Debug)]
@@ -51,6 +60,9 @@ pub enum StartIndexingError {
     #[error("{0}")]
     PostgresConnectionError(#[from] PostgresConnectionError),
 
+    #[error("{0}")]
+    SqliteConnectionError(#[from] SqliteConnectionError),
+
     #[error("Could not get block number from provider: {0}")]
     GetBlockNumberError(#[from] ProviderError),
 
@@ -68,6 +80,18 @@ pub enum StartIndexingError {
 
     #[error("The end block set for {0} is higher than the latest block: {1} - end block: {2}")]
     EndBlockIsHigherThanLatestBlockError(String, U64, U64),
+
+    #[error("{0}")]
+    FactoryChildrenError(#[from] FactoryChildrenError),
+
+    #[error("Could not re-drive dead letters: {0}")]
+    DeadLetterError(#[from] DeadLetterError),
+
+    #[error("Could not heal the gaps in the indexed blocks: {0}")]
+    ProcessedRangeError(#[from] ProcessedRangeError),
+
+    #[error("Could not read the ABIs of the entities: {0}")]
+    EntitiesAbiError(#[from] ReadAbiError),
 }
 
 pub struct ProcessedNetworkContract {
@@ -81,10 +105,33 @@ pub async fn start_indexing(
     dependencies: &[ContractEventDependencies],
     no_live_indexing_forced: bool,
     registry: Arc<EventCallbackRegistry>,
+) -> Result<Vec<ProcessedNetworkContract>, StartIndexingError> {
+    index_events(manifest, project_path, dependencies, no_live_indexing_forced, registry, false)
+        .await
+}
+
+/// Indexes the block ranges of the registry events as they are, without resuming from the last
+/// synced block or moving it, so a running indexer is not affected.
+pub(crate) async fn start_backfill_indexing(
+    manifest: &Manifest,
+    project_path: &Path,
+    registry: Arc<EventCallbackRegistry>,
+) -> Result<Vec<ProcessedNetworkContract>, StartIndexingError> {
+    index_events(manifest, project_path, &[], true, registry, true).await
+}
+
+async fn index_events(
+    manifest: &Manifest,
+    project_path: &Path,
+    dependencies: &[ContractEventDependencies],
+    no_live_indexing_forced: bool,
+    registry: Arc<EventCallbackRegistry>,
+    backfill: bool,
 ) -> Result<Vec<ProcessedNetworkContract>, StartIndexingError> {
     let start = Instant::now();
 
     let database = initialize_database(manifest).await?;
+    let sqlite = initialize_sqlite(manifest, project_path)?;
     let event_progress_state = IndexingEventsProgressState::monitor(&registry.events).await;
 
     // we can bring this into the yaml file later if required
@@ -101,6 +148,25 @@ pub async fn start_indexing(
 
     let mut processed_network_contracts: Vec<ProcessedNetworkContract> = Vec::new();
 
+    // factory children are shared by every event of the contract on the same network
+    let mut factory_children: HashMap<(String, String), Arc<FactoryChildren>> = HashMap::new();
+
+    let dead_letter_store = Arc::new(DeadLetterStore::new(project_path, &manifest.name, &database));
+    let dead_letter_policy =
+        manifest.indexing_settings().max_callback_attempts.map(|max_attempts| DeadLetterPolicy {
+            max_attempts,
+            store: Arc::clone(&dead_letter_store),
+        });
+
+    let processed_range_store =
+        Arc::new(ProcessedRangeStore::new(project_path, &manifest.name, &database));
+
+    let entities = Arc::new(IndexerEntities::new(project_path, manifest)?);
+
+    // non-blocking events of the same contract and network share one eth_getLogs per block range
+    let mut contract_events_configs: Vec<((String, usize), Vec<EventProcessingConfig>)> =
+        Vec::new();
+
     for event in registry.events.iter() {
         let stream_details = manifest
             .contracts
@@ -108,10 +174,12 @@ pub async fn start_indexing(
             .find(|c| c.name == event.contract.name)
             .and_then(|c| c.streams.as_ref());
 
-        for network_contract in event.contract.details.iter() {
+        for (network_contract_index, network_contract) in event.contract.details.iter().enumerate()
+        {
             let config = SyncConfig {
                 project_path,
                 database: &database,
+                sqlite: &sqlite,
                 csv_details: &manifest.storage.csv,
                 contract_csv_enabled: manifest.contract_csv_enabled(&event.contract.name),
                 stream_details: &stream_details,
@@ -145,25 +213,23 @@ pub async fn start_indexing(
                 }
             }
 
-            let last_known_start_block = if network_contract.start_block.is_some() {
-                let last_synced_block = get_last_synced_block_number(config).await;
-
-                if let Some(value) = last_synced_block {
-                    let start_from = value + 1;
-                    info!(
-                        "{} Found last synced block number - {:?} rindexer will start up from {:?}",
-                        event.info_log_name(),
-                        value,
-                        start_from
-                    );
-                    Some(start_from)
-                } else {
-                    None
-                }
+            let last_synced_block = if network_contract.start_block.is_some() && !backfill {
+                get_last_synced_block_number(config).await
             } else {
                 None
             };
 
+            let last_known_start_block = last_synced_block.map(|value| {
+                let start_from = value + 1;
+                info!(
+                    "{} Found last synced block number - {:?} rindexer will start up from {:?}",
+                    event.info_log_name(),
+                    value,
+                    start_from
+                );
+                start_from
+            });
+
             let start_block = last_known_start_block
                 .unwrap_or(network_contract.start_block.unwrap_or(latest_block));
             info!("{} start_block is {}", event.info_log_name(), start_block);
@@ -175,7 +241,7 @@ pub async fn start_indexing(
                 }
             }
 
-            let (end_block, indexing_distance_from_head) = calculate_safe_block_number(
+            let (end_block, reorg_safe_distance) = calculate_safe_block_number(
                 event.contract.reorg_safe_distance,
                 network_contract,
                 latest_block,
@@ -183,6 +249,41 @@ pub async fn start_indexing(
             )
             .await?;
 
+            let network_factory_children = match &network_contract.indexing_contract_setup {
+                IndexingContractSetup::Factory(factory) => {
+                    let key = (event.contract.name.clone(), network_contract.network.clone());
+                    let children = match factory_children.get(&key) {
+                        Some(children) => Arc::clone(children),
+                        None => {
+                            let children = FactoryChildren::load(
+                                project_path,
+                                &event.indexer_name,
+                                &event.contract.name,
+                                &network_contract.network,
+                                factory,
+                                network_contract.start_block.unwrap_or(latest_block),
+                                &database,
+                            )
+                            .await?;
+                            factory_children.insert(key, Arc::clone(&children));
+                            children
+                        }
+                    };
+                    Some(children)
+                }
+                _ => None,
+            };
+
+            let function_call = event.function_call_selector.map(|selector| {
+                let trace_method = manifest
+                    .networks
+                    .iter()
+                    .find(|network| network.name == network_contract.network)
+                    .and_then(|network| network.trace_method)
+                    .unwrap_or_default();
+                Arc::new(FunctionCallTracer::new(selector, event.topic_id, trace_method))
+            });
+
             // push status to the processed state
             processed_network_contracts.push(ProcessedNetworkContract {
                 id: network_contract.id.clone(),
@@ -204,6 +305,7 @@ pub async fn start_indexing(
                 registry: Arc::clone(&registry),
                 progress: Arc::clone(&event_progress_state),
                 database: database.clone(),
+                sqlite: sqlite.clone(),
                 csv_details: manifest.storage.csv.clone(),
                 stream_last_synced_block_file_path: stream_details
                     .as_ref()
@@ -214,9 +316,31 @@ pub async fn start_indexing(
                     network_contract.is_live_indexing()
                 },
                 index_event_in_order: event.index_event_in_order,
-                indexing_distance_from_head,
+                reorg_safe_distance,
+                factory_children: network_factory_children,
+                include_transaction_fields: event.contract.include_transaction_fields,
+                function_call,
+                prefetch_depth: manifest.indexing_settings().prefetch_depth,
+                dead_letters: dead_letter_policy.clone(),
+                update_last_synced_block: !backfill,
+                processed_ranges: Arc::clone(&processed_range_store),
+                archive_raw_logs: manifest.storage.postgres_raw_logs_enabled() &&
+                    event.function_call_selector.is_none(),
+                entities: Arc::clone(&entities),
             };
 
+            // anything marked with `rindexer dead-letters redrive` goes through the callback first
+            if !backfill {
+                redrive_dead_letters(&event_processing_config, &dead_letter_store).await?;
+            }
+
+            // batches lost before their range was recorded get indexed again before carrying on
+            if let (Some(start_block), Some(last_synced_block)) =
+                (network_contract.start_block, last_synced_block)
+            {
+                heal_gaps(&event_processing_config, start_block, last_synced_block).await?;
+            }
+
             let dependencies_status = ContractEventDependencies::dependencies_status(
                 &event_processing_config.contract_name,
                 &event_processing_config.event_name,
@@ -245,6 +369,12 @@ pub async fn start_indexing(
                     event_processing_config_arc,
                     dependencies,
                 );
+            } else if can_share_logs_request(&event_processing_config) {
+                let key = (event.contract.name.clone(), network_contract_index);
+                match contract_events_configs.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, configs)) => configs.push(event_processing_config),
+                    None => contract_events_configs.push((key, vec![event_processing_config])),
+                }
             } else {
                 let process_event = tokio::spawn(process_event(event_processing_config, false));
                 non_blocking_process_events.push(process_event);
@@ -252,6 +382,15 @@ pub async fn start_indexing(
         }
     }
 
+    for (_, mut configs) in contract_events_configs {
+        let process_event = if configs.len() == 1 {
+            tokio::spawn(process_event(configs.remove(0), false))
+        } else {
+            tokio::spawn(process_contract_events(configs))
+        };
+        non_blocking_process_events.push(process_event);
+    }
+
     // apply dependency events config after processing to avoid ordering issues
     for apply in apply_cross_contract_dependency_events_config_after_processing {
         let (dependency_in_other_contract, event_processing_config) = apply;
@@ -301,6 +440,15 @@ pub async fn start_indexing(
     Ok(processed_network_contracts)
 }
 
+/// Function calls come from traces and indexed filters narrow the topics so those events need their
+/// own request, every other event can share the contract request.
+fn can_share_logs_request(config: &EventProcessingConfig) -> bool {
+    config.function_call.is_none() &&
+        config.to_event_filter().is_ok_and(|filter| {
+            filter.raw_filter().topics[1..].iter().all(|topic| topic.is_none())
+        })
+}
+
 async fn initialize_database(
     manifest: &Manifest,
 ) -> Result<Option<Arc<PostgresClient>>, StartIndexingError> {
@@ -317,25 +465,46 @@ async fn initialize_database(
     }
 }
 
+fn initialize_sqlite(
+    manifest: &Manifest,
+    project_path: &Path,
+) -> Result<Option<Arc<SqliteClient>>, StartIndexingError> {
+    match manifest.storage.sqlite_path(project_path) {
+        Some(path) => match SqliteClient::new(&path) {
+            Ok(sqlite) => Ok(Some(Arc::new(sqlite))),
+            Err(e) => {
+                error!("Error opening sqlite database: {:?}", e);
+                Err(StartIndexingError::SqliteConnectionError(e))
+            }
+        },
+        None => Ok(None),
+    }
+}
+
+/// Works out the block historic indexing should stop at and how far from the head live indexing
+/// should stay, a network level `reorg_safe_distance` wins over the contract level flag.
 async fn calculate_safe_block_number(
     reorg_safe_distance: bool,
     network_contract: &NetworkContract,
     latest_block: U64,
-    mut end_block: U64,
-) -> Result<(U64, U64), StartIndexingError> {
-    let mut indexing_distance_from_head = U64::zero();
-    if reorg_safe_distance {
-        let chain_id = network_contract
-            .cached_provider
-            .get_chain_id()
-            .await
-            .map_err(StartIndexingError::GetChainIdError)?;
-        let reorg_safe_distance = reorg_safe_distance_for_chain(&chain_id);
-        let safe_block_number = latest_block - reorg_safe_distance;
-        if end_block > safe_block_number {
-            end_block = safe_block_number;
+    end_block: U64,
+) -> Result<(U64, ReorgSafeDistance), StartIndexingError> {
+    let reorg_safe_distance = match network_contract.reorg_safe_distance {
+        Some(reorg_safe_distance) => reorg_safe_distance,
+        None if reorg_safe_distance => {
+            let chain_id = network_contract
+                .cached_provider
+                .get_chain_id()
+                .await
+                .map_err(StartIndexingError::GetChainIdError)?;
+            ReorgSafeDistance::Blocks(reorg_safe_distance_for_chain(&chain_id))
         }
-        indexing_distance_from_head = reorg_safe_distance;
-    }
-    Ok((end_block, indexing_distance_from_head))
+        None => return Ok((end_block, ReorgSafeDistance::Latest)),
+    };
+
+    let safe_block_number =
+        safe_block_number(&network_contract.cached_provider, &reorg_safe_distance, latest_block)
+            .await?;
+
+    Ok((std::cmp::min(end_block, safe_block_number), reorg_safe_distance))
 }
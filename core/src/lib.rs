@@ -7,11 +7,14 @@ mod system_state;
 pub use system_state::{initiate_shutdown, is_running};
 
 mod database;
-pub use database::postgres::{
-    client::{PostgresClient, ToSql},
-    generate::drop_tables_for_indexer_sql,
-    setup::setup_postgres,
-    sql_type_wrapper::EthereumSqlTypeWrapper,
+pub use database::{
+    postgres::{
+        client::{PostgresClient, ToSql},
+        generate::drop_tables_for_indexer_sql,
+        setup::setup_postgres,
+        sql_type_wrapper::{map_transaction_fields_to_ethereum_wrapper, EthereumSqlTypeWrapper},
+    },
+    sqlite::{client::SqliteClient, generate::drop_sqlite_tables_for_indexer_sql},
 };
 
 mod simple_file_formatters;
@@ -31,6 +34,7 @@ mod abi;
 pub use abi::ABIItem;
 mod chat;
 pub mod event;
+pub mod log_cache;
 pub mod phantom;
 pub mod provider;
 mod start;
@@ -44,8 +48,8 @@ pub use futures::FutureExt;
 pub use lazy_static::lazy_static;
 pub use reqwest::header::HeaderMap;
 pub use start::{
-    start_rindexer, start_rindexer_no_code, IndexerNoCodeDetails, IndexingDetails, StartDetails,
-    StartNoCodeDetails,
+    start_backfill, start_backfill_no_code, start_rindexer, start_rindexer_no_code,
+    BackfillStartDetails, IndexerNoCodeDetails, IndexingDetails, StartDetails, StartNoCodeDetails,
 };
 pub use tokio::main as rindexer_main;
 pub use tokio_postgres::types::Type as PgType;
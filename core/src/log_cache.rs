@@ -0,0 +1,212 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use ethers::{
+    types::{Filter, ValueOrArray, H256, U256, U64},
+    utils::keccak256,
+};
+use tracing::{debug, info, warn};
+
+use crate::{event::RindexerEventFilter, provider::WrappedLog};
+
+static LOG_CACHE: OnceLock<LogCache> = OnceLock::new();
+
+/// Caches raw `eth_getLogs` responses on disk so re-indexing does not download the history again,
+/// only ranges behind the reorg safe distance of the chain are stored.
+#[derive(Debug)]
+pub struct LogCache {
+    path: PathBuf,
+}
+
+/// Turns the log cache on for every provider in the process.
+pub fn enable_log_cache(path: PathBuf) {
+    info!("Log cache enabled at {}", path.display());
+    let _ = LOG_CACHE.set(LogCache { path });
+}
+
+pub fn log_cache() -> Option<&'static LogCache> {
+    LOG_CACHE.get()
+}
+
+fn sorted_hex<T: AsRef<[u8]>>(values: impl Iterator<Item = T>) -> Vec<String> {
+    let mut values: Vec<String> = values.map(|value| hex::encode(value.as_ref())).collect();
+    values.sort();
+    values
+}
+
+/// The addresses and topics of a filter in a stable order, the block range is not part of it.
+fn filter_key(filter: &Filter) -> String {
+    let addresses = match &filter.address {
+        Some(ValueOrArray::Value(address)) => sorted_hex(std::iter::once(address)),
+        Some(ValueOrArray::Array(addresses)) => sorted_hex(addresses.iter()),
+        None => vec![],
+    };
+
+    let topics: Vec<String> = filter
+        .topics
+        .iter()
+        .map(|topic| {
+            let topic_ids: Vec<H256> = match topic {
+                Some(ValueOrArray::Value(Some(topic_id))) => vec![*topic_id],
+                Some(ValueOrArray::Array(topic_ids)) => {
+                    topic_ids.iter().flatten().copied().collect()
+                }
+                _ => vec![],
+            };
+            sorted_hex(topic_ids.iter()).join(",")
+        })
+        .collect();
+
+    let key = format!("addresses:{}|topics:{}", addresses.join(","), topics.join("|"));
+    hex::encode(&keccak256(key.as_bytes())[..16])
+}
+
+fn parse_range(file_name: &str) -> Option<(U64, U64)> {
+    let (from, to) = file_name.strip_suffix(".json")?.split_once('-')?;
+    Some((U64::from_dec_str(from).ok()?, U64::from_dec_str(to).ok()?))
+}
+
+impl LogCache {
+    fn filter_directory(&self, chain_id: &U256, filter: &RindexerEventFilter) -> PathBuf {
+        self.path.join(chain_id.to_string()).join(filter_key(filter.raw_filter()))
+    }
+
+    async fn cached_ranges(directory: &Path) -> Vec<(U64, U64)> {
+        let mut ranges = vec![];
+        let Ok(mut entries) = tokio::fs::read_dir(directory).await else {
+            return ranges;
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if let Some(range) = entry.file_name().to_str().and_then(parse_range) {
+                ranges.push(range);
+            }
+        }
+
+        ranges
+    }
+
+    /// Returns the cached logs from the start of the filter range and the first block which is
+    /// not cached, the rest of the range has to come from the network.
+    pub async fn get(
+        &self,
+        chain_id: &U256,
+        filter: &RindexerEventFilter,
+    ) -> (Vec<WrappedLog>, U64) {
+        let directory = self.filter_directory(chain_id, filter);
+        let ranges = Self::cached_ranges(&directory).await;
+        let to_block = filter.get_to_block();
+        let mut next_block = filter.get_from_block();
+        let mut logs = vec![];
+
+        while next_block <= to_block {
+            let Some((from, to)) = ranges
+                .iter()
+                .filter(|(from, to)| *from <= next_block && *to >= next_block)
+                .max_by_key(|(_, to)| *to)
+                .copied()
+            else {
+                break;
+            };
+
+            let path = directory.join(format!("{}-{}.json", from, to));
+            let cached: Vec<WrappedLog> = match tokio::fs::read(&path).await {
+                Ok(contents) => match serde_json::from_slice(&contents) {
+                    Ok(cached) => cached,
+                    Err(e) => {
+                        warn!("Ignoring unreadable log cache file {}: {}", path.display(), e);
+                        break;
+                    }
+                },
+                Err(e) => {
+                    warn!("Could not read log cache file {}: {}", path.display(), e);
+                    break;
+                }
+            };
+
+            let range_to_block = to.min(to_block);
+            logs.extend(cached.into_iter().filter(|log| {
+                log.inner
+                    .block_number
+                    .is_some_and(|block| block >= next_block && block <= range_to_block)
+            }));
+            next_block = range_to_block + 1;
+        }
+
+        if next_block > filter.get_from_block() {
+            debug!(
+                "Log cache hit for blocks {} - {} ({} logs)",
+                filter.get_from_block(),
+                next_block - 1,
+                logs.len()
+            );
+        }
+
+        (logs, next_block)
+    }
+
+    pub async fn store(&self, chain_id: &U256, filter: &RindexerEventFilter, logs: &[WrappedLog]) {
+        let directory = self.filter_directory(chain_id, filter);
+        let path =
+            directory.join(format!("{}-{}.json", filter.get_from_block(), filter.get_to_block()));
+
+        let result = async {
+            tokio::fs::create_dir_all(&directory).await?;
+            let contents = serde_json::to_vec(logs)?;
+            // write then rename so a crash never leaves a half written range behind
+            let temp_path = path.with_extension("json.tmp");
+            tokio::fs::write(&temp_path, contents).await?;
+            tokio::fs::rename(&temp_path, &path).await
+        };
+
+        if let Err(e) = result.await {
+            warn!("Could not write log cache file {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Removes the cached logs for a chain or the whole cache when no chain is passed.
+pub async fn prune_log_cache(path: &Path, chain_id: Option<U256>) -> Result<(), std::io::Error> {
+    let path = match chain_id {
+        Some(chain_id) => path.join(chain_id.to_string()),
+        None => path.to_path_buf(),
+    };
+
+    if path.exists() {
+        tokio::fs::remove_dir_all(&path).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::Address;
+
+    use super::*;
+
+    #[test]
+    fn test_filter_key_ignores_order_and_block_range() {
+        let first = Address::from_low_u64_be(1);
+        let second = Address::from_low_u64_be(2);
+        let transfer = H256::from_low_u64_be(3);
+        let approval = H256::from_low_u64_be(4);
+
+        let filter = Filter::new()
+            .address(vec![first, second])
+            .topic0(vec![transfer, approval])
+            .from_block(1)
+            .to_block(10);
+        let reordered = Filter::new()
+            .address(vec![second, first])
+            .topic0(vec![approval, transfer])
+            .from_block(20)
+            .to_block(30);
+        assert_eq!(filter_key(&filter), filter_key(&reordered));
+
+        let transfer_only = Filter::new().address(vec![first, second]).topic0(transfer);
+        assert_ne!(filter_key(&filter), filter_key(&transfer_only));
+    }
+}
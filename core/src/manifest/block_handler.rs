@@ -0,0 +1,76 @@
+use ethers::types::{Address, U64};
+use serde::{Deserialize, Serialize};
+
+use super::core::{deserialize_option_u64_from_string, serialize_option_u64_as_string};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BlockHandlerDetails {
+    pub network: String,
+
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_option_u64_from_string",
+        serialize_with = "serialize_option_u64_as_string"
+    )]
+    pub start_block: Option<U64>,
+
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_option_u64_from_string",
+        serialize_with = "serialize_option_u64_as_string"
+    )]
+    pub end_block: Option<U64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BlockHandler {
+    pub name: String,
+
+    pub details: Vec<BlockHandlerDetails>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_transactions: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reorg_safe_distance: Option<bool>,
+}
+
+impl BlockHandler {
+    /// The handler runs on blocks which are a multiple of the interval, every block if not set.
+    pub fn interval(&self) -> u64 {
+        self.interval.unwrap_or(1).max(1)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NativeTransfers {
+    pub details: Vec<BlockHandlerDetails>,
+
+    pub addresses: Vec<Address>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generate_csv: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reorg_safe_distance: Option<bool>,
+}
+
+impl NativeTransfers {
+    pub const HANDLER_NAME: &'static str = "NativeTransfers";
+
+    /// Native transfers are indexed by a built-in block handler which needs every transaction.
+    pub fn to_block_handler(&self) -> BlockHandler {
+        BlockHandler {
+            name: Self::HANDLER_NAME.to_string(),
+            details: self.details.clone(),
+            interval: None,
+            include_transactions: Some(true),
+            reorg_safe_distance: self.reorg_safe_distance,
+        }
+    }
+}
@@ -10,11 +10,11 @@ use serde_json::{json, Value};
 use super::core::{deserialize_option_u64_from_string, serialize_option_u64_as_string};
 use crate::{
     event::contract_setup::{
-        AddressDetails, ContractEventMapping, FilterDetails, IndexingContractSetup,
+        AddressDetails, ContractEventMapping, FactoryDetails, FilterDetails, IndexingContractSetup,
     },
     helpers::get_full_path,
     indexer::parse_topic,
-    manifest::{chat::ChatConfig, stream::StreamsConfig},
+    manifest::{chat::ChatConfig, entity::Entity, preset::ContractPreset, stream::StreamsConfig},
     types::single_or_array::StringOrArray,
 };
 
@@ -65,8 +65,9 @@ pub struct ContractDetails {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub indexed_filters: Option<Vec<EventInputIndexedFilters>>,
 
-    // #[serde(default, skip_serializing_if = "Option::is_none")]
-    // factory: Option<FactoryDetails>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub factory: Option<FactoryDetails>,
+
     #[serde(
         default,
         skip_serializing_if = "Option::is_none",
@@ -91,8 +92,8 @@ impl ContractDetails {
                 address: address.clone(),
                 indexed_filters: self.indexed_filters.clone(),
             })
-            // } else if let Some(factory) = &self.factory {
-            //     IndexingContractSetup::Factory(factory.clone())
+        } else if let Some(factory) = &self.factory {
+            IndexingContractSetup::Factory(factory.clone())
         } else if let Some(filter) = &self.filter {
             return match filter {
                 ValueOrArray::Value(filter) => IndexingContractSetup::Filter(FilterDetails {
@@ -115,9 +116,6 @@ impl ContractDetails {
         if let Some(address) = &self.address {
             return Some(address);
         }
-        // } else if let Some(factory) = &self.factory {
-        //     Some(&factory.address.parse::<Address>().into())
-        // } else {
         None
     }
 
@@ -133,27 +131,28 @@ impl ContractDetails {
             address: Some(address),
             filter: None,
             indexed_filters,
-            //factory: None,
+            factory: None,
             start_block,
             end_block,
         }
     }
 
-    // pub fn new_with_factory(
-    //     network: String,
-    //     factory: FactoryDetails,
-    //     start_block: Option<U64>,
-    //     end_block: Option<U64>,
-    // ) -> Self {
-    //     Self {
-    //         network,
-    //         address: None,
-    //         filter: None,
-    //         factory: Some(factory),
-    //         start_block,
-    //         end_block,
-    //     }
-    // }
+    pub fn new_with_factory(
+        network: String,
+        factory: FactoryDetails,
+        start_block: Option<U64>,
+        end_block: Option<U64>,
+    ) -> Self {
+        Self {
+            network,
+            address: None,
+            filter: None,
+            indexed_filters: None,
+            factory: Some(factory),
+            start_block,
+            end_block,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -193,14 +192,30 @@ impl DependencyEventTree {
     }
 }
 
+/// Contracts using a preset can leave out the ABI.
+fn default_abi() -> StringOrArray {
+    StringOrArray::Multiple(vec![])
+}
+
+fn is_empty_abi(abi: &StringOrArray) -> bool {
+    matches!(abi, StringOrArray::Multiple(abis) if abis.is_empty())
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Contract {
     pub name: String,
 
     pub details: Vec<ContractDetails>,
 
+    #[serde(default = "default_abi", skip_serializing_if = "is_empty_abi")]
     pub abi: StringOrArray,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<ContractPreset>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track_balances: Option<bool>,
+
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub include_events: Option<Vec<String>>,
 
@@ -221,6 +236,12 @@ pub struct Contract {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub chat: Option<ChatConfig>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_transaction_fields: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_function_calls: Option<Vec<String>>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -245,39 +266,56 @@ impl Contract {
     }
 
     pub fn parse_abi(&self, project_path: &Path) -> Result<String, ParseAbiError> {
-        match &self.abi {
-            StringOrArray::Single(abi_path) => {
+        let abi_paths = match &self.abi {
+            StringOrArray::Single(abi_path) if self.preset.is_none() => {
                 let full_path = get_full_path(project_path, abi_path)?;
                 let abi_str = fs::read_to_string(full_path)?;
-                Ok(abi_str)
+                return Ok(abi_str);
             }
-            StringOrArray::Multiple(abis) => {
-                let mut unique_entries = HashSet::new();
-                let mut merged_abi_value = Vec::new();
-
-                for abi_path in abis {
-                    let full_path = get_full_path(project_path, abi_path)?;
-                    let abi_str = fs::read_to_string(full_path)?;
-                    let abi_value: Value = serde_json::from_str(&abi_str)?;
-
-                    if let Value::Array(abi_arr) = abi_value {
-                        for entry in abi_arr {
-                            let entry_str = serde_json::to_string(&entry)?;
-                            if unique_entries.insert(entry_str) {
-                                merged_abi_value.push(entry);
-                            }
-                        }
-                    } else {
-                        return Err(ParseAbiError::InvalidAbiFormat(format!(
-                            "Expected an array but got a single value: {}",
-                            abi_value
-                        )));
+            StringOrArray::Single(abi_path) => vec![abi_path.clone()],
+            StringOrArray::Multiple(abis) => abis.clone(),
+        };
+
+        // the preset ABI comes first so a custom ABI can only add to it
+        let mut abi_strs: Vec<String> =
+            self.preset.iter().map(|preset| preset.abi().to_string()).collect();
+        for abi_path in abi_paths {
+            let full_path = get_full_path(project_path, &abi_path)?;
+            abi_strs.push(fs::read_to_string(full_path)?);
+        }
+
+        let mut unique_entries = HashSet::new();
+        let mut merged_abi_value = Vec::new();
+
+        for abi_str in abi_strs {
+            let abi_value: Value = serde_json::from_str(&abi_str)?;
+
+            if let Value::Array(abi_arr) = abi_value {
+                for entry in abi_arr {
+                    let entry_str = serde_json::to_string(&entry)?;
+                    if unique_entries.insert(entry_str) {
+                        merged_abi_value.push(entry);
                     }
                 }
+            } else {
+                return Err(ParseAbiError::InvalidAbiFormat(format!(
+                    "Expected an array but got a single value: {}",
+                    abi_value
+                )));
+            }
+        }
+
+        let merged_abi_str = serde_json::to_string(&json!(merged_abi_value))?;
+        Ok(merged_abi_str)
+    }
 
-                let merged_abi_str = serde_json::to_string(&json!(merged_abi_value))?;
-                Ok(merged_abi_str)
+    /// The balance entities of the preset when `track_balances` is enabled.
+    pub fn preset_entities(&self) -> Vec<Entity> {
+        match self.preset {
+            Some(preset) if self.track_balances.unwrap_or_default() => {
+                preset.balance_entities(&self.name)
             }
+            _ => vec![],
         }
     }
 
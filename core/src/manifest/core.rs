@@ -5,8 +5,15 @@ use serde_yaml::Value;
 use crate::{
     indexer::Indexer,
     manifest::{
-        contract::Contract, global::Global, graphql::GraphQLSettings, network::Network,
-        phantom::Phantom, storage::Storage,
+        block_handler::{BlockHandler, NativeTransfers},
+        contract::Contract,
+        entity::Entity,
+        global::Global,
+        graphql::GraphQLSettings,
+        indexing::IndexingSettings,
+        network::Network,
+        phantom::Phantom,
+        storage::Storage,
     },
 };
 
@@ -68,6 +75,15 @@ pub struct Manifest {
 
     pub contracts: Vec<Contract>,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_handlers: Option<Vec<BlockHandler>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub native_transfers: Option<NativeTransfers>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entities: Option<Vec<Entity>>,
+
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub phantom: Option<Phantom>,
 
@@ -76,11 +92,41 @@ pub struct Manifest {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub graphql: Option<GraphQLSettings>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indexing: Option<IndexingSettings>,
 }
 
 impl Manifest {
     pub fn to_indexer(&self) -> Indexer {
-        Indexer { name: self.name.clone(), contracts: self.contracts.clone() }
+        Indexer {
+            name: self.name.clone(),
+            contracts: self.contracts.clone(),
+            entities: self.all_entities(),
+            block_handlers: self.all_block_handlers(),
+        }
+    }
+
+    pub fn indexing_settings(&self) -> IndexingSettings {
+        self.indexing.clone().unwrap_or_default()
+    }
+
+    /// The block handlers to index including the built-in native transfers handler.
+    pub fn all_block_handlers(&self) -> Vec<BlockHandler> {
+        let mut block_handlers = self.block_handlers.clone().unwrap_or_default();
+        if let Some(native_transfers) = &self.native_transfers {
+            block_handlers.push(native_transfers.to_block_handler());
+        }
+        block_handlers
+    }
+
+    /// The entities to maintain including the balance tables of the contract presets.
+    pub fn all_entities(&self) -> Vec<Entity> {
+        let mut entities = self.entities.clone().unwrap_or_default();
+        for contract in &self.contracts {
+            entities.extend(contract.preset_entities());
+        }
+        entities
     }
 
     pub fn has_any_contracts_live_indexing(&self) -> bool {
@@ -0,0 +1,236 @@
+use std::{collections::HashMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{abi::ABIItem, manifest::contract::Contract};
+
+/// The values an entity can take from the log besides the event inputs.
+pub const ENTITY_BUILT_IN_VALUES: [&str; 3] = ["contract_address", "tx_hash", "block_number"];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EntityColumn {
+    pub name: String,
+
+    #[serde(rename = "type")]
+    pub type_: String,
+}
+
+/// The element type of a one dimensional array, other types are returned as is.
+pub fn entity_element_type(type_: &str) -> &str {
+    match type_.strip_suffix(']').and_then(|t| t.rsplit_once('[')) {
+        Some((element_type, _)) => element_type,
+        None => type_,
+    }
+}
+
+fn is_numeric_type(type_: &str) -> bool {
+    type_.starts_with("uint") || type_.starts_with("int")
+}
+
+impl EntityColumn {
+    pub fn is_numeric(&self) -> bool {
+        is_numeric_type(&self.type_)
+    }
+
+    /// The postgres type of the column, `None` for arrays and tuples which are not supported.
+    pub fn postgres_type(&self) -> Option<&'static str> {
+        if self.type_.contains('[') || self.type_.starts_with("tuple") {
+            return None;
+        }
+
+        match self.type_.as_str() {
+            _ if self.is_numeric() => Some("NUMERIC"),
+            "address" => Some("CHAR(42)"),
+            "bool" => Some("BOOLEAN"),
+            "string" => Some("TEXT"),
+            t if t.starts_with("bytes") => Some("TEXT"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EntityOperationType {
+    Set,
+    Add,
+    Subtract,
+    Increment,
+    Decrement,
+}
+
+impl EntityOperationType {
+    pub fn takes_value(&self) -> bool {
+        !matches!(self, EntityOperationType::Increment | EntityOperationType::Decrement)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EntityOperation {
+    pub field: String,
+
+    pub operation: EntityOperationType,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EntityEventMapping {
+    pub contract: String,
+
+    pub event: String,
+
+    pub keys: HashMap<String, String>,
+
+    #[serde(default)]
+    pub operations: Vec<EntityOperation>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_zero_address: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Entity {
+    pub name: String,
+
+    pub keys: Vec<EntityColumn>,
+
+    #[serde(default)]
+    pub fields: Vec<EntityColumn>,
+
+    pub events: Vec<EntityEventMapping>,
+}
+
+impl Entity {
+    pub fn columns(&self) -> impl Iterator<Item = &EntityColumn> {
+        self.keys.iter().chain(self.fields.iter())
+    }
+
+    pub fn field(&self, name: &str) -> Option<&EntityColumn> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// Checks the columns and that every event mapping matches the contract ABIs.
+    pub fn validate(&self, project_path: &Path, contracts: &[Contract]) -> Result<(), String> {
+        if self.keys.is_empty() {
+            return Err("must have at least one key".to_string());
+        }
+
+        let mut seen = vec!["network"];
+        for column in self.columns() {
+            if seen.contains(&column.name.as_str()) {
+                return Err(format!(
+                    "column {} is defined more than once or is reserved",
+                    column.name
+                ));
+            }
+            seen.push(&column.name);
+
+            if column.postgres_type().is_none() {
+                return Err(format!(
+                    "column {} has unsupported type {}, arrays and tuples can not be used",
+                    column.name, column.type_
+                ));
+            }
+        }
+
+        for mapping in &self.events {
+            let contract = contracts
+                .iter()
+                .find(|c| c.name == mapping.contract)
+                .ok_or_else(|| format!("contract {} not found", mapping.contract))?;
+            let abi_items = ABIItem::read_abi_items(project_path, contract)
+                .map_err(|e| format!("could not read ABI for {}: {}", contract.name, e))?;
+            let event = abi_items
+                .iter()
+                .find(|item| item.type_ == "event" && item.name == mapping.event)
+                .ok_or_else(|| {
+                    format!(
+                        "event {} not found in ABI for contract {}",
+                        mapping.event, contract.name
+                    )
+                })?;
+
+            let find_value = |value: &str| {
+                if ENTITY_BUILT_IN_VALUES.contains(&value) {
+                    return Ok(None);
+                }
+                let input =
+                    event.inputs.iter().find(|input| input.name == value).ok_or_else(|| {
+                        format!(
+                            "{} is not an input of event {} or a built-in value",
+                            value, event.name
+                        )
+                    })?;
+                // one dimensional arrays fan out into an upsert per element
+                let element_type = entity_element_type(&input.type_);
+                if element_type.contains('[') || element_type.starts_with("tuple") {
+                    return Err(format!(
+                        "input {} is a tuple or nested array which can not be used",
+                        value
+                    ));
+                }
+                Ok(Some(element_type))
+            };
+
+            for key in &self.keys {
+                let value = mapping.keys.get(&key.name).ok_or_else(|| {
+                    format!("event {} does not map key {}", mapping.event, key.name)
+                })?;
+                find_value(value)?;
+            }
+            if let Some(key) =
+                mapping.keys.keys().find(|key| !self.keys.iter().any(|k| k.name == **key))
+            {
+                return Err(format!("event {} maps unknown key {}", mapping.event, key));
+            }
+
+            let mut seen_fields = vec![];
+            for operation in &mapping.operations {
+                let field = self.field(&operation.field).ok_or_else(|| {
+                    format!("event {} operates on unknown field {}", mapping.event, operation.field)
+                })?;
+                if seen_fields.contains(&&operation.field) {
+                    return Err(format!(
+                        "event {} operates on field {} more than once",
+                        mapping.event, operation.field
+                    ));
+                }
+                seen_fields.push(&operation.field);
+
+                if operation.operation != EntityOperationType::Set && !field.is_numeric() {
+                    return Err(format!(
+                        "field {} must be numeric to add, subtract, increment or decrement",
+                        field.name
+                    ));
+                }
+
+                match (operation.operation.takes_value(), &operation.value) {
+                    (false, Some(_)) => {
+                        return Err(format!(
+                            "{:?} on field {} does not take a value",
+                            operation.operation, field.name
+                        ));
+                    }
+                    (false, None) => {}
+                    (true, None) => {
+                        return Err(format!("operation on field {} needs a value", field.name));
+                    }
+                    (true, Some(value)) => {
+                        let numeric_value =
+                            find_value(value)?.map_or(value == "block_number", is_numeric_type);
+                        if operation.operation != EntityOperationType::Set && !numeric_value {
+                            return Err(format!(
+                                "value {} for field {} must be numeric",
+                                value, field.name
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
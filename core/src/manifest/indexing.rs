@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+fn default_prefetch_depth() -> usize {
+    10
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndexingSettings {
+    /// How many fetched batches of logs can wait to be indexed before fetching pauses.
+    #[serde(default = "default_prefetch_depth")]
+    pub prefetch_depth: usize,
+
+    /// How many times a failing callback is retried before the batch is dead lettered, retried
+    /// forever when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_callback_attempts: Option<u32>,
+}
+
+impl Default for IndexingSettings {
+    fn default() -> Self {
+        Self { prefetch_depth: default_prefetch_depth(), max_callback_attempts: None }
+    }
+}
@@ -1,10 +1,14 @@
+pub mod block_handler;
 pub mod chat;
 pub mod contract;
 pub mod core;
+pub mod entity;
 pub mod global;
 pub mod graphql;
+pub mod indexing;
 pub mod network;
 pub mod phantom;
+pub mod preset;
 pub mod storage;
 pub mod stream;
 pub mod yaml;
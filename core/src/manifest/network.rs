@@ -1,15 +1,185 @@
-use ethers::prelude::U64;
-use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use ethers::{prelude::U64, types::BlockNumber};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use super::core::{deserialize_option_u64_from_string, serialize_option_u64_as_string};
 
+/// How far behind the head of the chain a network should index, either a fixed amount of
+/// blocks or a block tag the node resolves for us.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReorgSafeDistance {
+    Blocks(U64),
+    Latest,
+    Safe,
+    Finalized,
+}
+
+impl ReorgSafeDistance {
+    /// The block tag to ask the node for, `None` when the distance is a fixed amount of
+    /// blocks from the latest block.
+    pub fn block_tag(&self) -> Option<BlockNumber> {
+        match self {
+            ReorgSafeDistance::Blocks(_) | ReorgSafeDistance::Latest => None,
+            ReorgSafeDistance::Safe => Some(BlockNumber::Safe),
+            ReorgSafeDistance::Finalized => Some(BlockNumber::Finalized),
+        }
+    }
+}
+
+impl fmt::Display for ReorgSafeDistance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReorgSafeDistance::Blocks(blocks) => write!(f, "{}", blocks),
+            ReorgSafeDistance::Latest => write!(f, "latest"),
+            ReorgSafeDistance::Safe => write!(f, "safe"),
+            ReorgSafeDistance::Finalized => write!(f, "finalized"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ReorgSafeDistanceValue {
+    Number(u64),
+    Text(String),
+}
+
+impl<'de> Deserialize<'de> for ReorgSafeDistance {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match ReorgSafeDistanceValue::deserialize(deserializer)? {
+            ReorgSafeDistanceValue::Number(blocks) => Ok(ReorgSafeDistance::Blocks(U64::from(blocks))),
+            ReorgSafeDistanceValue::Text(value) => match value.to_lowercase().as_str() {
+                "latest" => Ok(ReorgSafeDistance::Latest),
+                "safe" => Ok(ReorgSafeDistance::Safe),
+                "finalized" => Ok(ReorgSafeDistance::Finalized),
+                other => U64::from_dec_str(other).map(ReorgSafeDistance::Blocks).map_err(|_| {
+                    serde::de::Error::custom(format!(
+                        "reorg_safe_distance must be a number of blocks or one of latest, safe, finalized - got {}",
+                        value
+                    ))
+                }),
+            },
+        }
+    }
+}
+
+impl Serialize for ReorgSafeDistance {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            ReorgSafeDistance::Blocks(blocks) => serializer.serialize_u64(blocks.as_u64()),
+            _ => serializer.serialize_str(&self.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RpcEndpointConfig {
+    pub url: String,
+
+    /// Lower priorities are used first, endpoints with a higher priority are only used when
+    /// all the lower ones are failing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+
+    /// How requests are shared between endpoints with the same priority.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compute_units_per_second: Option<u64>,
+
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_option_u64_from_string",
+        serialize_with = "serialize_option_u64_as_string"
+    )]
+    pub max_block_range: Option<U64>,
+}
+
+impl RpcEndpointConfig {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            priority: None,
+            weight: None,
+            compute_units_per_second: None,
+            max_block_range: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum NetworkRpc {
+    Single(String),
+    Multiple(Vec<RpcEndpointConfig>),
+}
+
+impl NetworkRpc {
+    pub fn endpoints(&self) -> Vec<RpcEndpointConfig> {
+        match self {
+            NetworkRpc::Single(url) => vec![RpcEndpointConfig::new(url.clone())],
+            NetworkRpc::Multiple(endpoints) => endpoints.clone(),
+        }
+    }
+
+    /// The url used for anything which only talks to a single endpoint, the first endpoint
+    /// with the lowest priority.
+    pub fn primary_url(&self) -> &str {
+        match self {
+            NetworkRpc::Single(url) => url,
+            NetworkRpc::Multiple(endpoints) => endpoints
+                .iter()
+                .min_by_key(|endpoint| endpoint.priority.unwrap_or_default())
+                .map_or("", |endpoint| endpoint.url.as_str()),
+        }
+    }
+
+    pub fn map_urls<F: Fn(&str) -> String>(&self, map: F) -> NetworkRpc {
+        match self {
+            NetworkRpc::Single(url) => NetworkRpc::Single(map(url)),
+            NetworkRpc::Multiple(endpoints) => NetworkRpc::Multiple(
+                endpoints
+                    .iter()
+                    .map(|endpoint| RpcEndpointConfig {
+                        url: map(&endpoint.url),
+                        ..endpoint.clone()
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// How the calls made to a contract are traced when indexing function calls.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TraceMethod {
+    #[default]
+    #[serde(rename = "trace_filter")]
+    TraceFilter,
+
+    #[serde(rename = "debug_traceBlockByNumber")]
+    DebugTraceBlockByNumber,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Network {
     pub name: String,
 
     pub chain_id: u64,
 
-    pub rpc: String,
+    pub rpc: NetworkRpc,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ws_rpc: Option<String>,
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub compute_units_per_second: Option<u64>,
@@ -24,4 +194,10 @@ pub struct Network {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub disable_logs_bloom_checks: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reorg_safe_distance: Option<ReorgSafeDistance>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_method: Option<TraceMethod>,
 }
@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::manifest::{
+    contract::Contract,
+    entity::{Entity, EntityColumn, EntityEventMapping, EntityOperation, EntityOperationType},
+    storage::{ContractEventsIndexes, EventIndex, EventIndexes},
+};
+
+const ERC20_ABI: &str = r#"[{"anonymous":false,"inputs":[{"indexed":true,"name":"owner","type":"address"},{"indexed":true,"name":"spender","type":"address"},{"indexed":false,"name":"value","type":"uint256"}],"name":"Approval","type":"event"},{"anonymous":false,"inputs":[{"indexed":true,"name":"from","type":"address"},{"indexed":true,"name":"to","type":"address"},{"indexed":false,"name":"value","type":"uint256"}],"name":"Transfer","type":"event"},{"inputs":[{"name":"owner","type":"address"},{"name":"spender","type":"address"}],"name":"allowance","outputs":[{"name":"","type":"uint256"}],"stateMutability":"view","type":"function"},{"inputs":[{"name":"spender","type":"address"},{"name":"value","type":"uint256"}],"name":"approve","outputs":[{"name":"","type":"bool"}],"stateMutability":"nonpayable","type":"function"},{"inputs":[{"name":"account","type":"address"}],"name":"balanceOf","outputs":[{"name":"","type":"uint256"}],"stateMutability":"view","type":"function"},{"inputs":[],"name":"decimals","outputs":[{"name":"","type":"uint8"}],"stateMutability":"view","type":"function"},{"inputs":[],"name":"name","outputs":[{"name":"","type":"string"}],"stateMutability":"view","type":"function"},{"inputs":[],"name":"symbol","outputs":[{"name":"","type":"string"}],"stateMutability":"view","type":"function"},{"inputs":[],"name":"totalSupply","outputs":[{"name":"","type":"uint256"}],"stateMutability":"view","type":"function"},{"inputs":[{"name":"to","type":"address"},{"name":"value","type":"uint256"}],"name":"transfer","outputs":[{"name":"","type":"bool"}],"stateMutability":"nonpayable","type":"function"},{"inputs":[{"name":"from","type":"address"},{"name":"to","type":"address"},{"name":"value","type":"uint256"}],"name":"transferFrom","outputs":[{"name":"","type":"bool"}],"stateMutability":"nonpayable","type":"function"}]"#;
+
+const ERC721_ABI: &str = r#"[{"anonymous":false,"inputs":[{"indexed":true,"name":"owner","type":"address"},{"indexed":true,"name":"approved","type":"address"},{"indexed":true,"name":"tokenId","type":"uint256"}],"name":"Approval","type":"event"},{"anonymous":false,"inputs":[{"indexed":true,"name":"owner","type":"address"},{"indexed":true,"name":"operator","type":"address"},{"indexed":false,"name":"approved","type":"bool"}],"name":"ApprovalForAll","type":"event"},{"anonymous":false,"inputs":[{"indexed":true,"name":"from","type":"address"},{"indexed":true,"name":"to","type":"address"},{"indexed":true,"name":"tokenId","type":"uint256"}],"name":"Transfer","type":"event"},{"inputs":[{"name":"to","type":"address"},{"name":"tokenId","type":"uint256"}],"name":"approve","outputs":[],"stateMutability":"nonpayable","type":"function"},{"inputs":[{"name":"owner","type":"address"}],"name":"balanceOf","outputs":[{"name":"","type":"uint256"}],"stateMutability":"view","type":"function"},{"inputs":[{"name":"tokenId","type":"uint256"}],"name":"getApproved","outputs":[{"name":"","type":"address"}],"stateMutability":"view","type":"function"},{"inputs":[{"name":"owner","type":"address"},{"name":"operator","type":"address"}],"name":"isApprovedForAll","outputs":[{"name":"","type":"bool"}],"stateMutability":"view","type":"function"},{"inputs":[],"name":"name","outputs":[{"name":"","type":"string"}],"stateMutability":"view","type":"function"},{"inputs":[{"name":"tokenId","type":"uint256"}],"name":"ownerOf","outputs":[{"name":"","type":"address"}],"stateMutability":"view","type":"function"},{"inputs":[{"name":"from","type":"address"},{"name":"to","type":"address"},{"name":"tokenId","type":"uint256"}],"name":"safeTransferFrom","outputs":[],"stateMutability":"nonpayable","type":"function"},{"inputs":[{"name":"operator","type":"address"},{"name":"approved","type":"bool"}],"name":"setApprovalForAll","outputs":[],"stateMutability":"nonpayable","type":"function"},{"inputs":[{"name":"interfaceId","type":"bytes4"}],"name":"supportsInterface","outputs":[{"name":"","type":"bool"}],"stateMutability":"view","type":"function"},{"inputs":[],"name":"symbol","outputs":[{"name":"","type":"string"}],"stateMutability":"view","type":"function"},{"inputs":[{"name":"tokenId","type":"uint256"}],"name":"tokenURI","outputs":[{"name":"","type":"string"}],"stateMutability":"view","type":"function"},{"inputs":[{"name":"from","type":"address"},{"name":"to","type":"address"},{"name":"tokenId","type":"uint256"}],"name":"transferFrom","outputs":[],"stateMutability":"nonpayable","type":"function"}]"#;
+
+const ERC1155_ABI: &str = r#"[{"anonymous":false,"inputs":[{"indexed":true,"name":"account","type":"address"},{"indexed":true,"name":"operator","type":"address"},{"indexed":false,"name":"approved","type":"bool"}],"name":"ApprovalForAll","type":"event"},{"anonymous":false,"inputs":[{"indexed":true,"name":"operator","type":"address"},{"indexed":true,"name":"from","type":"address"},{"indexed":true,"name":"to","type":"address"},{"indexed":false,"name":"ids","type":"uint256[]"},{"indexed":false,"name":"values","type":"uint256[]"}],"name":"TransferBatch","type":"event"},{"anonymous":false,"inputs":[{"indexed":true,"name":"operator","type":"address"},{"indexed":true,"name":"from","type":"address"},{"indexed":true,"name":"to","type":"address"},{"indexed":false,"name":"id","type":"uint256"},{"indexed":false,"name":"value","type":"uint256"}],"name":"TransferSingle","type":"event"},{"anonymous":false,"inputs":[{"indexed":false,"name":"value","type":"string"},{"indexed":true,"name":"id","type":"uint256"}],"name":"URI","type":"event"},{"inputs":[{"name":"account","type":"address"},{"name":"id","type":"uint256"}],"name":"balanceOf","outputs":[{"name":"","type":"uint256"}],"stateMutability":"view","type":"function"},{"inputs":[{"name":"accounts","type":"address[]"},{"name":"ids","type":"uint256[]"}],"name":"balanceOfBatch","outputs":[{"name":"","type":"uint256[]"}],"stateMutability":"view","type":"function"},{"inputs":[{"name":"account","type":"address"},{"name":"operator","type":"address"}],"name":"isApprovedForAll","outputs":[{"name":"","type":"bool"}],"stateMutability":"view","type":"function"},{"inputs":[{"name":"from","type":"address"},{"name":"to","type":"address"},{"name":"ids","type":"uint256[]"},{"name":"values","type":"uint256[]"},{"name":"data","type":"bytes"}],"name":"safeBatchTransferFrom","outputs":[],"stateMutability":"nonpayable","type":"function"},{"inputs":[{"name":"from","type":"address"},{"name":"to","type":"address"},{"name":"id","type":"uint256"},{"name":"value","type":"uint256"},{"name":"data","type":"bytes"}],"name":"safeTransferFrom","outputs":[],"stateMutability":"nonpayable","type":"function"},{"inputs":[{"name":"operator","type":"address"},{"name":"approved","type":"bool"}],"name":"setApprovalForAll","outputs":[],"stateMutability":"nonpayable","type":"function"},{"inputs":[{"name":"interfaceId","type":"bytes4"}],"name":"supportsInterface","outputs":[{"name":"","type":"bool"}],"stateMutability":"view","type":"function"},{"inputs":[{"name":"id","type":"uint256"}],"name":"uri","outputs":[{"name":"","type":"string"}],"stateMutability":"view","type":"function"}]"#;
+
+/// A token standard bundling its ABI, indexes and optionally holder balance entities.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContractPreset {
+    Erc20,
+    Erc721,
+    Erc1155,
+}
+
+fn column(name: &str, type_: &str) -> EntityColumn {
+    EntityColumn { name: name.to_string(), type_: type_.to_string() }
+}
+
+fn operation(field: &str, operation: EntityOperationType, value: Option<&str>) -> EntityOperation {
+    EntityOperation {
+        field: field.to_string(),
+        operation,
+        value: value.map(|value| value.to_string()),
+    }
+}
+
+/// Mints come from and burns go to the zero address which is not a holder so it is skipped.
+fn holder_mapping(
+    contract_name: &str,
+    event: &str,
+    keys: &[(&str, &str)],
+    operations: Vec<EntityOperation>,
+) -> EntityEventMapping {
+    EntityEventMapping {
+        contract: contract_name.to_string(),
+        event: event.to_string(),
+        keys: keys.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect(),
+        operations,
+        skip_zero_address: Some(true),
+    }
+}
+
+impl ContractPreset {
+    pub fn abi(&self) -> &'static str {
+        match self {
+            ContractPreset::Erc20 => ERC20_ABI,
+            ContractPreset::Erc721 => ERC721_ABI,
+            ContractPreset::Erc1155 => ERC1155_ABI,
+        }
+    }
+
+    fn event_indexes(&self) -> Vec<(&'static str, Vec<&'static str>)> {
+        match self {
+            ContractPreset::Erc20 => {
+                vec![("Transfer", vec!["from", "to"]), ("Approval", vec!["owner", "spender"])]
+            }
+            ContractPreset::Erc721 => vec![
+                ("Transfer", vec!["from", "to", "tokenId"]),
+                ("Approval", vec!["owner", "tokenId"]),
+                ("ApprovalForAll", vec!["owner", "operator"]),
+            ],
+            ContractPreset::Erc1155 => vec![
+                ("TransferSingle", vec!["from", "to", "id"]),
+                ("TransferBatch", vec!["from", "to"]),
+                ("ApprovalForAll", vec!["account", "operator"]),
+                ("URI", vec!["id"]),
+            ],
+        }
+    }
+
+    /// An index on each holder and token id input of the events the contract indexes.
+    pub fn indexes(&self, contract: &Contract) -> ContractEventsIndexes {
+        let events = self
+            .event_indexes()
+            .into_iter()
+            .filter(|(event, _)| {
+                contract
+                    .include_events
+                    .as_ref()
+                    .is_none_or(|events| events.iter().any(|e| e == event))
+            })
+            .map(|(event, inputs)| EventIndexes {
+                name: event.to_string(),
+                injected_parameters: None,
+                indexes: inputs
+                    .into_iter()
+                    .map(|input| EventIndex { event_input_names: vec![input.to_string()] })
+                    .collect(),
+            })
+            .collect();
+
+        ContractEventsIndexes { name: contract.name.clone(), injected_parameters: None, events }
+    }
+
+    /// The balance of each holder, for ERC721 also the owner of each token. Every table is keyed
+    /// by the token address so factory and multi address contracts are tracked per token.
+    pub fn balance_entities(&self, contract_name: &str) -> Vec<Entity> {
+        let token = ("token", "contract_address");
+        let mut balances = Entity {
+            name: format!("{}Balances", contract_name),
+            keys: vec![column("token", "address"), column("holder", "address")],
+            fields: vec![column("balance", "uint256")],
+            events: vec![],
+        };
+
+        match self {
+            ContractPreset::Erc20 => {
+                balances.events = vec![
+                    holder_mapping(
+                        contract_name,
+                        "Transfer",
+                        &[token, ("holder", "to")],
+                        vec![operation("balance", EntityOperationType::Add, Some("value"))],
+                    ),
+                    holder_mapping(
+                        contract_name,
+                        "Transfer",
+                        &[token, ("holder", "from")],
+                        vec![operation("balance", EntityOperationType::Subtract, Some("value"))],
+                    ),
+                ];
+                vec![balances]
+            }
+            ContractPreset::Erc721 => {
+                balances.events = vec![
+                    holder_mapping(
+                        contract_name,
+                        "Transfer",
+                        &[token, ("holder", "to")],
+                        vec![operation("balance", EntityOperationType::Increment, None)],
+                    ),
+                    holder_mapping(
+                        contract_name,
+                        "Transfer",
+                        &[token, ("holder", "from")],
+                        vec![operation("balance", EntityOperationType::Decrement, None)],
+                    ),
+                ];
+
+                // a burnt token is owned by the zero address
+                let owners = Entity {
+                    name: format!("{}Owners", contract_name),
+                    keys: vec![column("token", "address"), column("token_id", "uint256")],
+                    fields: vec![column("owner", "address")],
+                    events: vec![EntityEventMapping {
+                        contract: contract_name.to_string(),
+                        event: "Transfer".to_string(),
+                        keys: HashMap::from([
+                            ("token".to_string(), "contract_address".to_string()),
+                            ("token_id".to_string(), "tokenId".to_string()),
+                        ]),
+                        operations: vec![operation("owner", EntityOperationType::Set, Some("to"))],
+                        skip_zero_address: None,
+                    }],
+                };
+                vec![balances, owners]
+            }
+            ContractPreset::Erc1155 => {
+                balances.keys.insert(1, column("token_id", "uint256"));
+                // the ids and values of a batch fan out into an upsert per token id
+                for (event, id, value) in
+                    [("TransferSingle", "id", "value"), ("TransferBatch", "ids", "values")]
+                {
+                    balances.events.push(holder_mapping(
+                        contract_name,
+                        event,
+                        &[token, ("token_id", id), ("holder", "to")],
+                        vec![operation("balance", EntityOperationType::Add, Some(value))],
+                    ));
+                    balances.events.push(holder_mapping(
+                        contract_name,
+                        event,
+                        &[token, ("token_id", id), ("holder", "from")],
+                        vec![operation("balance", EntityOperationType::Subtract, Some(value))],
+                    ));
+                }
+                vec![balances]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, path::Path};
+
+    use ethers::{
+        abi::{LogParam, Token},
+        types::{Address, H256, U256, U64},
+    };
+
+    use super::*;
+    use crate::{
+        abi::ABIItem,
+        database::postgres::{
+            entity::{
+                entity_log_key, entity_upserts_for_inserted_logs, EntityLogKey, EntityLogValues,
+                EntityUpsert,
+            },
+            indexes::build_indexes,
+        },
+        event::callback_registry::TxInformation,
+        manifest::storage::PostgresIndexes,
+    };
+
+    #[test]
+    fn test_presets_match_their_abi() {
+        for preset in ["erc20", "erc721", "erc1155"] {
+            let contract: Contract = serde_yaml::from_str(&format!(
+                "name: Token\ndetails: []\npreset: {}\ntrack_balances: true",
+                preset
+            ))
+            .unwrap();
+            let contracts = [contract.clone()];
+
+            for entity in contract.preset_entities() {
+                entity.validate(Path::new("."), &contracts).unwrap();
+            }
+
+            let indexes = PostgresIndexes::with_presets(None, &contracts).unwrap();
+            assert!(!build_indexes(Path::new("."), "Test", &indexes, &contracts)
+                .unwrap()
+                .is_empty());
+        }
+    }
+
+    #[test]
+    fn test_erc20_balances_are_stable_when_transfers_are_replayed() {
+        let abi_items: Vec<ABIItem> = serde_json::from_str(ContractPreset::Erc20.abi()).unwrap();
+        let inputs = &abi_items
+            .iter()
+            .find(|item| item.type_ == "event" && item.name == "Transfer")
+            .unwrap()
+            .inputs;
+        let entity = &ContractPreset::Erc20.balance_entities("Token")[0];
+        let upserts: Vec<EntityUpsert> = entity
+            .events
+            .iter()
+            .map(|mapping| EntityUpsert::new("Test", entity, mapping, inputs))
+            .collect();
+
+        let token = Address::from_low_u64_be(100);
+        let transfer = |log_index: u64, from: Address, to: Address, value: u64| {
+            let tx_information = TxInformation {
+                network: "ethereum".to_string(),
+                address: token,
+                block_hash: H256::zero(),
+                block_number: U64::from(100),
+                block_timestamp: None,
+                transaction_hash: H256::from_low_u64_be(log_index + 1),
+                log_index: U256::from(log_index),
+                transaction_index: U64::zero(),
+                transaction_fields: None,
+            };
+            let log_params = vec![
+                LogParam { name: "from".to_string(), value: Token::Address(from) },
+                LogParam { name: "to".to_string(), value: Token::Address(to) },
+                LogParam { name: "value".to_string(), value: Token::Uint(U256::from(value)) },
+            ];
+            (
+                entity_log_key(&tx_information),
+                EntityLogValues::from_log(&log_params, &tx_information),
+            )
+        };
+        let alice = Address::from_low_u64_be(1);
+        let bob = Address::from_low_u64_be(2);
+        let batch = vec![
+            transfer(0, Address::zero(), alice, 100),
+            transfer(1, alice, bob, 30),
+            transfer(2, bob, Address::zero(), 5),
+        ];
+
+        // the event table only returns the keys of rows it did not already have
+        let mut event_table: HashSet<EntityLogKey> = HashSet::new();
+        let mut balances: HashMap<(String, String), i64> = HashMap::new();
+        for _ in 0..2 {
+            let inserted: HashSet<EntityLogKey> = batch
+                .iter()
+                .map(|(key, _)| key.clone())
+                .filter(|key| event_table.insert(key.clone()))
+                .collect();
+            for (upsert, params) in entity_upserts_for_inserted_logs(&upserts, &batch, &inserted) {
+                // the first mapping adds to the receiver, the second subtracts from the sender
+                let sign = if std::ptr::eq(upsert, &upserts[0]) { 1 } else { -1 };
+                *balances.entry((params[1].clone(), params[2].clone())).or_default() +=
+                    sign * params[3].parse::<i64>().unwrap();
+            }
+        }
+
+        let token = format!("{:?}", token);
+        assert_eq!(
+            balances,
+            HashMap::from([
+                ((token.clone(), format!("{:?}", alice)), 70),
+                ((token, format!("{:?}", bob)), 25),
+            ])
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 use tracing::info;
@@ -72,6 +72,30 @@ pub struct PostgresIndexes {
     pub contracts: Option<Vec<ContractEventsIndexes>>,
 }
 
+impl PostgresIndexes {
+    /// The configured indexes plus the preset indexes of contracts which do not configure any.
+    pub fn with_presets(
+        indexes: Option<&PostgresIndexes>,
+        contracts: &[Contract],
+    ) -> Option<PostgresIndexes> {
+        let mut indexes = indexes.cloned();
+        for contract in contracts {
+            let Some(preset) = contract.preset else {
+                continue;
+            };
+            let indexes = indexes.get_or_insert(PostgresIndexes {
+                global_injected_parameters: None,
+                contracts: None,
+            });
+            let contracts = indexes.contracts.get_or_insert_with(Vec::new);
+            if !contracts.iter().any(|c| c.name == contract.name) {
+                contracts.push(preset.indexes(contract));
+            }
+        }
+        indexes
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PostgresDetails {
     pub enabled: bool,
@@ -87,6 +111,12 @@ pub struct PostgresDetails {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub disable_create_tables: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_destructive_migrations: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_logs: Option<bool>,
 }
 
 fn default_csv_path() -> String {
@@ -104,6 +134,30 @@ pub struct CsvDetails {
     pub disable_create_headers: Option<bool>,
 }
 
+fn default_sqlite_path() -> String {
+    "./rindexer.db".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SqliteDetails {
+    pub enabled: bool,
+
+    #[serde(default = "default_sqlite_path")]
+    pub path: String,
+}
+
+fn default_log_cache_path() -> String {
+    "./.rindexer/log-cache".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LogCacheDetails {
+    pub enabled: bool,
+
+    #[serde(default = "default_log_cache_path")]
+    pub path: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct Storage {
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -111,6 +165,12 @@ pub struct Storage {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub csv: Option<CsvDetails>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sqlite: Option<SqliteDetails>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_cache: Option<LogCacheDetails>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -147,6 +207,17 @@ impl Storage {
             .map_or(false, |details| details.disable_create_tables.unwrap_or_default())
     }
 
+    pub fn postgres_allow_destructive_migrations(&self) -> bool {
+        self.postgres
+            .as_ref()
+            .is_some_and(|details| details.allow_destructive_migrations.unwrap_or_default())
+    }
+
+    pub fn postgres_raw_logs_enabled(&self) -> bool {
+        self.postgres_enabled() &&
+            self.postgres.as_ref().is_some_and(|details| details.raw_logs.unwrap_or_default())
+    }
+
     pub fn postgres_drop_each_run(&self) -> bool {
         let enabled = self.postgres_enabled();
         if !enabled {
@@ -174,6 +245,23 @@ impl Storage {
             .map_or(false, |details| details.disable_create_headers.unwrap_or_default())
     }
 
+    pub fn sqlite_enabled(&self) -> bool {
+        self.sqlite.as_ref().is_some_and(|details| details.enabled)
+    }
+
+    /// The sqlite database file when sqlite is enabled, a relative path is relative to the
+    /// project.
+    pub fn sqlite_path(&self, project_path: &Path) -> Option<PathBuf> {
+        self.sqlite
+            .as_ref()
+            .filter(|details| details.enabled)
+            .map(|details| project_path.join(&details.path))
+    }
+
+    pub fn log_cache_enabled(&self) -> bool {
+        self.log_cache.as_ref().is_some_and(|details| details.enabled)
+    }
+
     pub async fn create_relationships_and_indexes(
         &self,
         project_path: &Path,
@@ -212,9 +300,11 @@ impl Storage {
                 info!("Temp dropping indexes from the database for historic indexing for speed reasons");
                 drop_last_known_indexes(manifest_name).await?;
 
-                if let Some(indexes) = &storage.indexes {
+                if let Some(indexes) =
+                    PostgresIndexes::with_presets(storage.indexes.as_ref(), contracts)
+                {
                     let indexes_result =
-                        prepare_indexes(project_path, manifest_name, indexes, contracts).await;
+                        prepare_indexes(project_path, manifest_name, &indexes, contracts).await;
 
                     match indexes_result {
                         Ok(result) => {
@@ -13,7 +13,8 @@ use tracing::error;
 
 use crate::{
     abi::ABIItem,
-    helpers::{load_env_from_full_path, replace_env_variable_to_raw_name},
+    helpers::{camel_to_snake, load_env_from_full_path, replace_env_variable_to_raw_name},
+    indexer::factory::read_factory_event,
     manifest::{
         core::{Manifest, ProjectType},
         network::Network,
@@ -52,6 +53,12 @@ pub enum ValidateManifestError {
     #[error("Invalid filter event name {0} for contract {1} does not exist in ABI")]
     InvalidFilterEventNameDoesntExistInABI(String, String),
 
+    #[error("Contract {0} must have an abi or a preset")]
+    ContractHasNoAbi(String),
+
+    #[error("track_balances on contract {0} needs a preset")]
+    TrackBalancesWithoutPreset(String),
+
     #[error("Could not read or parse ABI for contract {0} with path {1}")]
     InvalidABI(String, String),
 
@@ -75,6 +82,56 @@ pub enum ValidateManifestError {
 
     #[error("Global ABI can only be a single string")]
     GlobalAbiCanOnlyBeASingleString(String),
+
+    #[error("ws_rpc for network {0} must be a ws:// or wss:// url")]
+    InvalidWsRpcUrl(String),
+
+    #[error("Network {0} must have at least one rpc")]
+    NetworkHasNoRpc(String),
+
+    #[error("Factory for contract {0} is invalid: {1}")]
+    InvalidFactory(String, String),
+
+    #[error("Function {0} included in include_function_calls for contract {1} but not found in ABI - it must be a function type and match the name exactly")]
+    FunctionCallIncludedNotFoundInABI(String, String),
+
+    #[error("include_function_calls for contract {0} is only supported in no-code projects and contracts with an address")]
+    FunctionCallsNotSupported(String),
+
+    #[error("Block handler names {0} must be unique and can not be NativeTransfers")]
+    BlockHandlerNameMustBeUnique(String),
+
+    #[error("Invalid network mapped to block handler: network - {0} block handler - {1}")]
+    InvalidNetworkMappedToBlockHandler(String, String),
+
+    #[error("native_transfers must have at least one address")]
+    NativeTransfersHasNoAddresses,
+
+    #[error(
+        "block_handlers are only supported in rust projects, use native_transfers for no-code"
+    )]
+    BlockHandlersNotSupported,
+
+    #[error("sqlite storage is only supported in no-code projects")]
+    SqliteNotSupported,
+
+    #[error("entities and track_balances are only supported in no-code projects with postgres storage enabled")]
+    EntitiesNotSupported,
+
+    #[error("entities and track_balances are only stored in postgres and can not be used with sqlite storage")]
+    EntitiesNotSupportedWithSqlite,
+
+    #[error("Entity names {0} must be unique")]
+    EntityNameMustBeUnique(String),
+
+    #[error("Entity {0} is invalid: {1}")]
+    InvalidEntity(String, String),
+
+    #[error("indexing prefetch_depth must be at least 1")]
+    InvalidPrefetchDepth,
+
+    #[error("indexing max_callback_attempts must be at least 1")]
+    InvalidMaxCallbackAttempts,
 }
 
 fn validate_manifest(
@@ -94,6 +151,22 @@ fn validate_manifest(
         ));
     }
 
+    for network in &manifest.networks {
+        if network.rpc.endpoints().is_empty() {
+            return Err(ValidateManifestError::NetworkHasNoRpc(network.name.clone()));
+        }
+
+        if let Some(ws_rpc) = &network.ws_rpc {
+            // rust projects keep the raw env variable name so only check actual urls
+            if ws_rpc.contains("://") &&
+                !ws_rpc.starts_with("ws://") &&
+                !ws_rpc.starts_with("wss://")
+            {
+                return Err(ValidateManifestError::InvalidWsRpcUrl(network.name.clone()));
+            }
+        }
+    }
+
     for contract in &manifest.contracts {
         if contract.name.to_lowercase().contains("filter") {
             return Err(ValidateManifestError::ContractNameCanNotIncludeFilter(
@@ -101,10 +174,26 @@ fn validate_manifest(
             ));
         }
 
+        if contract.preset.is_none() &&
+            matches!(&contract.abi, StringOrArray::Multiple(abis) if abis.is_empty())
+        {
+            return Err(ValidateManifestError::ContractHasNoAbi(contract.name.clone()));
+        }
+
+        if contract.track_balances.unwrap_or_default() && contract.preset.is_none() {
+            return Err(ValidateManifestError::TrackBalancesWithoutPreset(contract.name.clone()));
+        }
+
         let events = ABIItem::read_abi_items(project_path, contract)
             .map_err(|e| ValidateManifestError::InvalidABI(contract.name.clone(), e.to_string()))?;
 
         for detail in &contract.details {
+            if let Some(factory) = &detail.factory {
+                read_factory_event(project_path, factory).map_err(|e| {
+                    ValidateManifestError::InvalidFactory(contract.name.clone(), e.to_string())
+                })?;
+            }
+
             let has_network = manifest.networks.iter().any(|n| n.name == detail.network);
             if !has_network {
                 return Err(ValidateManifestError::InvalidNetworkMappedToContract(
@@ -180,6 +269,23 @@ fn validate_manifest(
             }
         }
 
+        if let Some(include_function_calls) = &contract.include_function_calls {
+            if manifest.project_type == ProjectType::Rust ||
+                contract.details.iter().any(|detail| detail.filter.is_some())
+            {
+                return Err(ValidateManifestError::FunctionCallsNotSupported(contract.name.clone()));
+            }
+
+            for function in include_function_calls {
+                if !events.iter().any(|e| e.name == *function && e.type_ == "function") {
+                    return Err(ValidateManifestError::FunctionCallIncludedNotFoundInABI(
+                        function.clone(),
+                        contract.name.clone(),
+                    ));
+                }
+            }
+        }
+
         if let Some(_dependency_events) = &contract.dependency_events {
             // TODO - validate the events all exist in the contract ABIs
         }
@@ -213,6 +319,86 @@ fn validate_manifest(
         }
     }
 
+    if manifest.project_type != ProjectType::Rust &&
+        manifest.block_handlers.as_ref().is_some_and(|b| !b.is_empty())
+    {
+        return Err(ValidateManifestError::BlockHandlersNotSupported);
+    }
+
+    let mut seen = HashSet::new();
+    let duplicates_block_handler_names: Vec<String> = manifest
+        .all_block_handlers()
+        .into_iter()
+        .filter_map(|b| if seen.insert(b.name.clone()) { None } else { Some(b.name) })
+        .collect();
+
+    if !duplicates_block_handler_names.is_empty() {
+        return Err(ValidateManifestError::BlockHandlerNameMustBeUnique(
+            duplicates_block_handler_names.join(", "),
+        ));
+    }
+
+    for block_handler in manifest.all_block_handlers() {
+        for detail in &block_handler.details {
+            if !manifest.networks.iter().any(|n| n.name == detail.network) {
+                return Err(ValidateManifestError::InvalidNetworkMappedToBlockHandler(
+                    detail.network.clone(),
+                    block_handler.name.clone(),
+                ));
+            }
+        }
+    }
+
+    if manifest.native_transfers.as_ref().is_some_and(|n| n.addresses.is_empty()) {
+        return Err(ValidateManifestError::NativeTransfersHasNoAddresses);
+    }
+
+    if manifest.storage.sqlite_enabled() && manifest.project_type != ProjectType::NoCode {
+        return Err(ValidateManifestError::SqliteNotSupported);
+    }
+
+    let entities = manifest.all_entities();
+    if !entities.is_empty() {
+        if manifest.project_type != ProjectType::NoCode || !manifest.storage.postgres_enabled() {
+            return Err(ValidateManifestError::EntitiesNotSupported);
+        }
+
+        if manifest.storage.sqlite_enabled() {
+            return Err(ValidateManifestError::EntitiesNotSupportedWithSqlite);
+        }
+
+        let mut seen = HashSet::new();
+        let duplicates_entity_names: Vec<String> = entities
+            .iter()
+            .filter_map(|e| {
+                if seen.insert(camel_to_snake(&e.name)) {
+                    None
+                } else {
+                    Some(e.name.clone())
+                }
+            })
+            .collect();
+        if !duplicates_entity_names.is_empty() {
+            return Err(ValidateManifestError::EntityNameMustBeUnique(
+                duplicates_entity_names.join(", "),
+            ));
+        }
+
+        for entity in &entities {
+            entity
+                .validate(project_path, &manifest.contracts)
+                .map_err(|e| ValidateManifestError::InvalidEntity(entity.name.clone(), e))?;
+        }
+    }
+
+    if manifest.indexing.as_ref().is_some_and(|i| i.prefetch_depth == 0) {
+        return Err(ValidateManifestError::InvalidPrefetchDepth);
+    }
+
+    if manifest.indexing.as_ref().is_some_and(|i| i.max_callback_attempts == Some(0)) {
+        return Err(ValidateManifestError::InvalidMaxCallbackAttempts);
+    }
+
     if let Some(global) = &manifest.global {
         if let Some(contracts) = &global.contracts {
             for contract in contracts {
@@ -313,9 +499,17 @@ pub fn read_manifest(file_path: &PathBuf) -> Result<Manifest, ReadManifestError>
                 .iter()
                 .find(|n| n.name == network.name)
                 .map_or_else(
-                    || replace_env_variable_to_raw_name(&network.rpc),
-                    |n| replace_env_variable_to_raw_name(&n.rpc),
+                    || network.rpc.map_urls(replace_env_variable_to_raw_name),
+                    |n| n.rpc.map_urls(replace_env_variable_to_raw_name),
                 );
+            if let Some(ws_rpc) = manifest_networks_only
+                .networks
+                .iter()
+                .find(|n| n.name == network.name)
+                .and_then(|n| n.ws_rpc.as_ref())
+            {
+                network.ws_rpc = Some(replace_env_variable_to_raw_name(ws_rpc));
+            }
         }
     }
 
@@ -1,27 +1,238 @@
 use std::{
-    sync::Arc,
+    collections::{HashMap, HashSet, VecDeque},
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, OnceLock, Weak,
+    },
     time::{Duration, Instant},
 };
 
 use ethers::{
-    middleware::Middleware,
+    middleware::{Middleware, MiddlewareError},
     prelude::Log,
-    providers::{Http, Provider, ProviderError, RetryClient, RetryClientBuilder},
-    types::{Block, BlockNumber, H256, U256, U64},
+    providers::{Http, Provider, ProviderError, RetryClient, RetryClientBuilder, Ws},
+    types::{
+        Address, Block, BlockId, BlockNumber, Bytes, Trace, TraceFilter, Transaction,
+        TransactionReceipt, H256, U256, U64,
+    },
 };
+use futures::{future::try_join_all, StreamExt};
+use rand::Rng;
 use reqwest::header::HeaderMap;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tokio::sync::Mutex;
+use tokio::sync::{watch, Mutex};
+use tracing::{error, info, warn};
 use url::Url;
 
-use crate::{event::RindexerEventFilter, manifest::core::Manifest};
+use crate::{
+    event::{callback_registry::TransactionFields, RindexerEventFilter},
+    indexer::{
+        fetch_logs::provider_block_range_limit_from_error,
+        reorg::{reorg_safe_distance_for_chain, safe_block_number},
+    },
+    log_cache::{log_cache, LogCache},
+    manifest::{
+        core::Manifest,
+        network::{ReorgSafeDistance, RpcEndpointConfig},
+    },
+};
+
+/// How often live indexing polls for new blocks when no websocket is connected.
+const NEW_BLOCK_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How long live indexing waits for a websocket head before checking the rpc anyway.
+const NEW_HEAD_WAIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A head pushed over the websocket is only trusted for this long, after that the rpc is polled
+/// again in case the socket silently stopped sending.
+const NEW_HEAD_STALE_AFTER: Duration = Duration::from_secs(30);
+
+const NEW_HEADS_RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+type NewHead = Option<(Instant, Arc<Block<H256>>)>;
 
 #[derive(Debug)]
-pub struct JsonRpcCachedProvider {
+struct NewHeadsSubscription {
+    ws_rpc_url: String,
+    receiver: OnceLock<watch::Receiver<NewHead>>,
+}
+
+/// How many failures in a row before an endpoint is taken out of rotation.
+const CIRCUIT_BREAKER_FAILURE_THRESHOLD: u32 = 3;
+
+/// How long an unhealthy endpoint is skipped before it is tried again.
+const CIRCUIT_BREAKER_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// When a network has more than one endpoint a request which takes longer than this fails over
+/// to the next endpoint.
+const FAILOVER_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How many successful requests before a learnt block range limit is doubled again, providers
+/// lift limits and a limit learnt from a busy moment should not stick forever.
+const LEARNT_BLOCK_RANGE_RELAX_AFTER_SUCCESSES: u32 = 100;
+
+#[derive(Debug, Default)]
+struct RpcEndpointHealth {
+    consecutive_failures: u32,
+    unhealthy_until: Option<Instant>,
+    // learnt from eth_getLogs errors, only used to route big ranges to endpoints which can take
+    // them
+    learnt_max_block_range: Option<U64>,
+    successes_since_block_range_rejected: u32,
+}
+
+#[derive(Debug)]
+struct RpcEndpoint {
     provider: Arc<Provider<RetryClient<Http>>>,
+    host: String,
+    priority: u32,
+    weight: u32,
+    max_block_range: Option<U64>,
+    health: std::sync::Mutex<RpcEndpointHealth>,
+}
+
+impl RpcEndpoint {
+    fn new(
+        provider: Provider<RetryClient<Http>>,
+        host: String,
+        priority: u32,
+        weight: u32,
+        max_block_range: Option<U64>,
+    ) -> Self {
+        RpcEndpoint {
+            provider: Arc::new(provider),
+            host,
+            priority,
+            weight: weight.max(1),
+            max_block_range,
+            health: std::sync::Mutex::new(RpcEndpointHealth::default()),
+        }
+    }
+
+    fn health(&self) -> std::sync::MutexGuard<'_, RpcEndpointHealth> {
+        self.health.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.health().unhealthy_until.is_none_or(|until| Instant::now() >= until)
+    }
+
+    fn can_serve_block_range(&self, block_range: U64) -> bool {
+        let learnt_max_block_range = self.health().learnt_max_block_range;
+        [self.max_block_range, learnt_max_block_range]
+            .iter()
+            .flatten()
+            .all(|max_block_range| block_range <= *max_block_range)
+    }
+
+    fn record_success(&self) {
+        let mut health = self.health();
+        health.consecutive_failures = 0;
+        health.unhealthy_until = None;
+
+        if let Some(learnt_max_block_range) = health.learnt_max_block_range {
+            health.successes_since_block_range_rejected += 1;
+            if health.successes_since_block_range_rejected >=
+                LEARNT_BLOCK_RANGE_RELAX_AFTER_SUCCESSES
+            {
+                health.learnt_max_block_range = Some(learnt_max_block_range * 2);
+                health.successes_since_block_range_rejected = 0;
+            }
+        }
+    }
+
+    fn record_failure(&self) {
+        let mut health = self.health();
+        health.consecutive_failures += 1;
+        if health.consecutive_failures >= CIRCUIT_BREAKER_FAILURE_THRESHOLD {
+            warn!(
+                "RPC endpoint {} failed {} times in a row - skipping it for {:?}",
+                self.host, health.consecutive_failures, CIRCUIT_BREAKER_COOLDOWN
+            );
+            health.unhealthy_until = Some(Instant::now() + CIRCUIT_BREAKER_COOLDOWN);
+        }
+    }
+
+    /// Remembers the range the endpoint rejected, the limit the provider gave in the error is
+    /// used when there is one otherwise half of the rejected range.
+    fn record_block_range_rejected(&self, block_range: U64, provider_max_block_range: Option<U64>) {
+        let mut health = self.health();
+        let max_block_range = provider_max_block_range.unwrap_or(block_range / 2);
+        if health.learnt_max_block_range.is_none_or(|learnt| max_block_range < learnt) {
+            health.learnt_max_block_range = Some(max_block_range);
+        }
+        health.successes_since_block_range_rejected = 0;
+    }
+}
+
+#[derive(Debug)]
+pub struct JsonRpcCachedProvider {
+    endpoints: Vec<RpcEndpoint>,
     cache: Mutex<Option<(Instant, Arc<Block<H256>>)>>,
-    pub max_block_range: Option<U64>,
+    tag_cache: Mutex<Option<(Instant, BlockNumber, U64)>>,
+    block_timestamp_cache: std::sync::Mutex<BlockTimestampCache>,
+    chain_id: tokio::sync::OnceCell<U256>,
+    new_heads: Option<NewHeadsSubscription>,
+    // the highest block number handed out by `get_latest_block`
+    latest_seen_block_number: AtomicU64,
+    // the configured distance of the network, logs are only cached once they are behind it
+    reorg_safe_distance: Option<ReorgSafeDistance>,
+}
+
+/// Lets a live indexing loop sleep until there is likely a new block to process.
+pub struct NewBlockListener {
+    receiver: Option<watch::Receiver<NewHead>>,
+}
+
+impl NewBlockListener {
+    /// Waits for the next head pushed over the websocket, if no websocket is configured or it
+    /// is disconnected this falls back to the polling interval.
+    pub async fn wait(&mut self) {
+        if let Some(receiver) = &mut self.receiver {
+            let connected = receiver.borrow().is_some();
+            if connected {
+                match tokio::time::timeout(NEW_HEAD_WAIT_TIMEOUT, receiver.changed()).await {
+                    Ok(Err(_)) => self.receiver = None,
+                    Ok(Ok(())) | Err(_) => return,
+                }
+            }
+        }
+
+        tokio::time::sleep(NEW_BLOCK_POLL_INTERVAL).await;
+    }
+}
+
+/// How many block timestamps are remembered, logs are mostly emitted in the same few blocks.
+const BLOCK_TIMESTAMP_CACHE_SIZE: usize = 10_000;
+
+/// How many blocks or transactions are requested at the same time when enriching logs.
+const LOOKUP_BATCH_SIZE: usize = 50;
+
+/// Block timestamps keyed by block hash so a reorged block never resolves to a stale time.
+#[derive(Debug, Default)]
+struct BlockTimestampCache {
+    timestamps: HashMap<H256, U256>,
+    insertion_order: VecDeque<H256>,
+}
+
+impl BlockTimestampCache {
+    fn get(&self, block_hash: &H256) -> Option<U256> {
+        self.timestamps.get(block_hash).copied()
+    }
+
+    fn insert(&mut self, block_hash: H256, timestamp: U256) {
+        if self.timestamps.insert(block_hash, timestamp).is_none() {
+            self.insertion_order.push_back(block_hash);
+        }
+
+        while self.insertion_order.len() > BLOCK_TIMESTAMP_CACHE_SIZE {
+            if let Some(oldest) = self.insertion_order.pop_front() {
+                self.timestamps.remove(&oldest);
+            }
+        }
+    }
 }
 
 /// TODO: This is a temporary type until we migrate to alloy
@@ -34,16 +245,269 @@ pub struct WrappedLog {
     pub block_timestamp: Option<U256>,
 }
 
+/// A call frame returned by the geth `callTracer`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallTracerFrame {
+    #[serde(rename = "type")]
+    pub call_type: String,
+    pub from: Address,
+    #[serde(default)]
+    pub to: Option<Address>,
+    #[serde(default)]
+    pub value: Option<U256>,
+    #[serde(default)]
+    pub input: Bytes,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub calls: Option<Vec<CallTracerFrame>>,
+}
+
+/// The trace of a transaction from `debug_traceBlockByNumber`, newer geth versions wrap the
+/// call frame with the transaction hash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CallTracerResult {
+    Wrapped {
+        #[serde(rename = "txHash", default)]
+        tx_hash: Option<H256>,
+        result: CallTracerFrame,
+    },
+    Frame(CallTracerFrame),
+}
+
+impl CallTracerResult {
+    pub fn transaction_hash(&self) -> Option<H256> {
+        match self {
+            CallTracerResult::Wrapped { tx_hash, .. } => *tx_hash,
+            CallTracerResult::Frame(_) => None,
+        }
+    }
+
+    pub fn frame(&self) -> &CallTracerFrame {
+        match self {
+            CallTracerResult::Wrapped { result, .. } => result,
+            CallTracerResult::Frame(frame) => frame,
+        }
+    }
+}
+
 impl JsonRpcCachedProvider {
-    pub fn new(provider: Provider<RetryClient<Http>>, max_block_range: Option<U64>) -> Self {
+    pub fn new(
+        provider: Provider<RetryClient<Http>>,
+        ws_rpc_url: Option<String>,
+        max_block_range: Option<U64>,
+    ) -> Self {
+        // a single endpoint never fails over so the host is never logged
+        Self::new_with_endpoints(
+            vec![RpcEndpoint::new(provider, String::new(), 0, 1, max_block_range)],
+            ws_rpc_url,
+            None,
+        )
+    }
+
+    fn new_with_endpoints(
+        mut endpoints: Vec<RpcEndpoint>,
+        ws_rpc_url: Option<String>,
+        reorg_safe_distance: Option<ReorgSafeDistance>,
+    ) -> Self {
+        // the primary endpoint is always first so anything not routed uses it
+        endpoints.sort_by_key(|endpoint| (endpoint.priority, std::cmp::Reverse(endpoint.weight)));
+
         JsonRpcCachedProvider {
-            provider: Arc::new(provider),
+            endpoints,
             cache: Mutex::new(None),
-            max_block_range,
+            tag_cache: Mutex::new(None),
+            block_timestamp_cache: std::sync::Mutex::new(BlockTimestampCache::default()),
+            chain_id: tokio::sync::OnceCell::new(),
+            new_heads: ws_rpc_url
+                .map(|ws_rpc_url| NewHeadsSubscription { ws_rpc_url, receiver: OnceLock::new() }),
+            latest_seen_block_number: AtomicU64::new(0),
+            reorg_safe_distance,
+        }
+    }
+
+    fn primary_provider(&self) -> &Arc<Provider<RetryClient<Http>>> {
+        &self.endpoints[0].provider
+    }
+
+    /// The lowest max block range of the healthy endpoints so a range fits whichever endpoint
+    /// the request fails over to, all endpoints are used when none are healthy.
+    pub fn max_block_range(&self) -> Option<U64> {
+        let healthy: Vec<&RpcEndpoint> =
+            self.endpoints.iter().filter(|endpoint| endpoint.is_healthy()).collect();
+        let endpoints = if healthy.is_empty() { self.endpoints.iter().collect() } else { healthy };
+
+        endpoints.iter().filter_map(|endpoint| endpoint.max_block_range).min()
+    }
+
+    /// Orders the endpoints for a request, healthy endpoints with the lowest priority first and
+    /// endpoints with the same priority shuffled by their weight. When a block range is passed
+    /// the endpoints which can serve it are moved to the front.
+    fn ordered_endpoints(&self, block_range: Option<U64>) -> Vec<&RpcEndpoint> {
+        if self.endpoints.len() == 1 {
+            return self.endpoints.iter().collect();
+        }
+
+        let (mut healthy, unhealthy): (Vec<&RpcEndpoint>, Vec<&RpcEndpoint>) =
+            self.endpoints.iter().partition(|endpoint| endpoint.is_healthy());
+
+        let mut ordered = Vec::with_capacity(self.endpoints.len());
+        let mut rng = rand::thread_rng();
+        while !healthy.is_empty() {
+            let priority = healthy.iter().map(|endpoint| endpoint.priority).min().unwrap_or(0);
+            let mut same_priority: Vec<&RpcEndpoint> = vec![];
+            healthy.retain(|endpoint| {
+                if endpoint.priority == priority {
+                    same_priority.push(endpoint);
+                    false
+                } else {
+                    true
+                }
+            });
+
+            while !same_priority.is_empty() {
+                let total_weight: u32 = same_priority.iter().map(|endpoint| endpoint.weight).sum();
+                let mut pick = rng.gen_range(0..total_weight);
+                let index = same_priority
+                    .iter()
+                    .position(|endpoint| {
+                        if pick < endpoint.weight {
+                            true
+                        } else {
+                            pick -= endpoint.weight;
+                            false
+                        }
+                    })
+                    .unwrap_or(0);
+                ordered.push(same_priority.remove(index));
+            }
+        }
+
+        // unhealthy endpoints are still tried last as it is better than failing the request
+        ordered.extend(unhealthy);
+
+        if let Some(block_range) = block_range {
+            ordered.sort_by_key(|endpoint| !endpoint.can_serve_block_range(block_range));
+        }
+
+        ordered
+    }
+
+    /// Sends the request to the endpoints in order until one succeeds, failures are recorded
+    /// against the endpoint so unhealthy ones are skipped for a while.
+    async fn request_with_failover<T, F, Fut>(
+        &self,
+        block_range: Option<U64>,
+        request: F,
+    ) -> Result<T, ProviderError>
+    where
+        F: Fn(Arc<Provider<RetryClient<Http>>>) -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        if self.endpoints.len() == 1 {
+            return request(Arc::clone(self.primary_provider())).await;
+        }
+
+        let mut last_error = None;
+        for endpoint in self.ordered_endpoints(block_range) {
+            let result = tokio::time::timeout(
+                FAILOVER_REQUEST_TIMEOUT,
+                request(Arc::clone(&endpoint.provider)),
+            )
+            .await;
+
+            match result {
+                Ok(Ok(response)) => {
+                    endpoint.record_success();
+                    return Ok(response);
+                }
+                Ok(Err(e)) => {
+                    // a json rpc error means the endpoint is up but did not like the request,
+                    // only the known range and result size errors teach it a block range limit
+                    match e.as_error_response() {
+                        Some(error) => {
+                            if let (Some(block_range), Some(provider_max_block_range)) =
+                                (block_range, provider_block_range_limit_from_error(error))
+                            {
+                                endpoint.record_block_range_rejected(
+                                    block_range,
+                                    provider_max_block_range,
+                                );
+                            }
+                        }
+                        None => endpoint.record_failure(),
+                    }
+                    warn!(
+                        "RPC endpoint {} failed - trying the next endpoint: {}",
+                        endpoint.host, e
+                    );
+                    last_error = Some(e);
+                }
+                Err(_) => {
+                    endpoint.record_failure();
+                    warn!(
+                        "RPC endpoint {} timed out after {:?} - trying the next endpoint",
+                        endpoint.host, FAILOVER_REQUEST_TIMEOUT
+                    );
+                    last_error = Some(ProviderError::CustomError(format!(
+                        "RPC endpoint {} timed out",
+                        endpoint.host
+                    )));
+                }
+            }
+        }
+
+        Err(last_error
+            .unwrap_or_else(|| ProviderError::CustomError("No RPC endpoints".to_string())))
+    }
+
+    /// Creates a listener for new blocks, the first call starts the `newHeads` websocket
+    /// subscription for the network which all listeners then share.
+    pub fn new_block_listener(self: &Arc<Self>) -> NewBlockListener {
+        let receiver = self.new_heads.as_ref().map(|subscription| {
+            subscription
+                .receiver
+                .get_or_init(|| {
+                    let (sender, receiver) = watch::channel(None);
+                    tokio::spawn(subscribe_new_heads(
+                        subscription.ws_rpc_url.clone(),
+                        Arc::downgrade(self),
+                        sender,
+                    ));
+                    receiver
+                })
+                .clone()
+        });
+
+        NewBlockListener { receiver }
+    }
+
+    fn latest_new_head(&self) -> Option<Arc<Block<H256>>> {
+        let receiver = self.new_heads.as_ref()?.receiver.get()?;
+        let new_head = receiver.borrow();
+        match &*new_head {
+            Some((received_at, block)) if received_at.elapsed() < NEW_HEAD_STALE_AFTER => {
+                Some(Arc::clone(block))
+            }
+            _ => None,
         }
     }
 
     pub async fn get_latest_block(&self) -> Result<Option<Arc<Block<H256>>>, ProviderError> {
+        let latest_block = self.fetch_latest_block().await?;
+        if let Some(block_number) = latest_block.as_ref().and_then(|block| block.number) {
+            self.latest_seen_block_number.fetch_max(block_number.as_u64(), Ordering::Relaxed);
+        }
+
+        Ok(latest_block)
+    }
+
+    async fn fetch_latest_block(&self) -> Result<Option<Arc<Block<H256>>>, ProviderError> {
+        if let Some(block) = self.latest_new_head() {
+            return Ok(Some(block));
+        }
+
         let mut cache_guard = self.cache.lock().await;
 
         if let Some((timestamp, block)) = &*cache_guard {
@@ -52,7 +516,11 @@ impl JsonRpcCachedProvider {
             }
         }
 
-        let latest_block = self.provider.get_block(BlockNumber::Latest).await?;
+        let latest_block = self
+            .request_with_failover(None, |provider| async move {
+                provider.get_block(BlockNumber::Latest).await
+            })
+            .await?;
 
         if let Some(block) = latest_block {
             let arc_block = Arc::new(block);
@@ -66,12 +534,98 @@ impl JsonRpcCachedProvider {
     }
 
     pub async fn get_block_number(&self) -> Result<U64, ProviderError> {
-        self.provider.get_block_number().await
+        self.request_with_failover(
+            None,
+            |provider| async move { provider.get_block_number().await },
+        )
+        .await
+    }
+
+    /// Resolves a block tag like `safe` or `finalized` to its block number, cached for a short
+    /// time as every event on the network polls this while live indexing.
+    pub async fn get_block_number_by_tag(
+        &self,
+        tag: BlockNumber,
+    ) -> Result<Option<U64>, ProviderError> {
+        let mut cache_guard = self.tag_cache.lock().await;
+
+        if let Some((timestamp, cached_tag, block_number)) = &*cache_guard {
+            if *cached_tag == tag && timestamp.elapsed() < Duration::from_millis(300) {
+                return Ok(Some(*block_number));
+            }
+        }
+
+        let block_number = self
+            .request_with_failover(None, |provider| async move { provider.get_block(tag).await })
+            .await?
+            .and_then(|block| block.number);
+        *cache_guard = block_number.map(|number| (Instant::now(), tag, number));
+
+        Ok(block_number)
+    }
+
+    pub async fn get_block_by_number(
+        &self,
+        block_number: U64,
+    ) -> Result<Option<Block<H256>>, ProviderError> {
+        self.request_with_failover(None, |provider| async move {
+            provider.get_block(BlockNumber::Number(block_number)).await
+        })
+        .await
     }
 
     pub async fn get_logs(
         &self,
         filter: &RindexerEventFilter,
+    ) -> Result<Vec<WrappedLog>, ProviderError> {
+        match log_cache() {
+            Some(log_cache) => self.get_logs_with_cache(log_cache, filter).await,
+            None => self.get_logs_from_rpc(filter).await,
+        }
+    }
+
+    /// Serves the start of the range from the log cache and only asks the rpc for the rest, which
+    /// is cached once it is behind the reorg safe distance of the network.
+    async fn get_logs_with_cache(
+        &self,
+        log_cache: &LogCache,
+        filter: &RindexerEventFilter,
+    ) -> Result<Vec<WrappedLog>, ProviderError> {
+        let chain_id = *self.chain_id.get_or_try_init(|| self.get_chain_id()).await?;
+        let (mut logs, next_block) = log_cache.get(&chain_id, filter).await;
+        if next_block > filter.get_to_block() {
+            return Ok(logs);
+        }
+
+        let remaining_filter = filter.clone().set_from_block(next_block);
+        let fetched_logs = self.get_logs_from_rpc(&remaining_filter).await?;
+
+        if remaining_filter.get_to_block() <= self.log_cache_safe_block_number(&chain_id).await? {
+            log_cache.store(&chain_id, &remaining_filter, &fetched_logs).await;
+        }
+
+        logs.extend(fetched_logs);
+        Ok(logs)
+    }
+
+    /// The highest block whose logs can be cached, measured from the latest block indexing has
+    /// already seen so it never costs a request. Without a configured `reorg_safe_distance` the
+    /// default distance of the chain is used as anything cached can not be rolled back.
+    async fn log_cache_safe_block_number(&self, chain_id: &U256) -> Result<U64, ProviderError> {
+        let latest_block_number = match self.latest_seen_block_number.load(Ordering::Relaxed) {
+            0 => self.get_latest_block().await?.and_then(|block| block.number).unwrap_or_default(),
+            block_number => U64::from(block_number),
+        };
+        let reorg_safe_distance = self
+            .reorg_safe_distance
+            .unwrap_or_else(|| ReorgSafeDistance::Blocks(reorg_safe_distance_for_chain(chain_id)));
+
+        safe_block_number(self, &reorg_safe_distance, latest_block_number).await
+    }
+
+    async fn get_logs_from_rpc(
+        &self,
+        filter: &RindexerEventFilter,
     ) -> Result<Vec<WrappedLog>, ProviderError> {
         // rindexer_info!("get_logs DEBUG [{:?}]", filter.raw_filter());
         // LEAVING FOR NOW CONTEXT: TEMP FIX TO MAKE SURE FROM BLOCK IS ALWAYS SET
@@ -80,17 +634,243 @@ impl JsonRpcCachedProvider {
         //     filter = filter.from_block(BlockNumber::Earliest);
         // }
         // rindexer_info!("get_logs DEBUG AFTER [{:?}]", filter);
-        let result = self.provider.request("eth_getLogs", [filter.raw_filter()]).await?;
+        let block_range = filter.get_to_block().saturating_sub(filter.get_from_block());
+        let result = self
+            .request_with_failover(Some(block_range), |provider| async move {
+                provider.request("eth_getLogs", [filter.raw_filter()]).await
+            })
+            .await?;
         // rindexer_info!("get_logs RESULT [{:?}]", result);
         Ok(result)
     }
 
+    /// Fills in the block timestamp for logs the node did not return `blockTimestamp` for, the
+    /// missing block headers are looked up in batches and cached as logs share blocks.
+    pub async fn populate_block_timestamps(
+        &self,
+        mut logs: Vec<WrappedLog>,
+    ) -> Result<Vec<WrappedLog>, ProviderError> {
+        if logs.iter().all(|log| log.block_timestamp.is_some()) {
+            return Ok(logs);
+        }
+
+        let mut resolved: HashMap<H256, U256> = HashMap::new();
+        let mut missing: HashSet<H256> = HashSet::new();
+        {
+            let mut cache = self.block_timestamp_cache.lock().expect("lock poisoned");
+            for log in &logs {
+                if let (Some(block_hash), Some(timestamp)) =
+                    (log.inner.block_hash, log.block_timestamp)
+                {
+                    cache.insert(block_hash, timestamp);
+                    resolved.insert(block_hash, timestamp);
+                }
+            }
+
+            for block_hash in logs
+                .iter()
+                .filter(|log| log.block_timestamp.is_none())
+                .filter_map(|log| log.inner.block_hash)
+            {
+                if resolved.contains_key(&block_hash) {
+                    continue;
+                }
+                match cache.get(&block_hash) {
+                    Some(timestamp) => {
+                        resolved.insert(block_hash, timestamp);
+                    }
+                    None => {
+                        missing.insert(block_hash);
+                    }
+                }
+            }
+        }
+
+        let missing: Vec<H256> = missing.into_iter().collect();
+        for batch in missing.chunks(LOOKUP_BATCH_SIZE) {
+            let blocks = try_join_all(batch.iter().map(|block_hash| {
+                let block_hash = *block_hash;
+                self.request_with_failover(None, move |provider| async move {
+                    provider.get_block(BlockId::Hash(block_hash)).await
+                })
+            }))
+            .await?;
+
+            let mut cache = self.block_timestamp_cache.lock().expect("lock poisoned");
+            for (block_hash, block) in batch.iter().zip(blocks) {
+                match block {
+                    Some(block) => {
+                        cache.insert(*block_hash, block.timestamp);
+                        resolved.insert(*block_hash, block.timestamp);
+                    }
+                    None => {
+                        return Err(ProviderError::CustomError(format!(
+                            "RPC did not return block {:?} to resolve its timestamp",
+                            block_hash
+                        )));
+                    }
+                }
+            }
+        }
+
+        for log in logs.iter_mut().filter(|log| log.block_timestamp.is_none()) {
+            log.block_timestamp =
+                log.inner.block_hash.and_then(|block_hash| resolved.get(&block_hash).copied());
+        }
+
+        Ok(logs)
+    }
+
+    /// Looks up the transaction and receipt for each hash in batches, the hashes should be
+    /// unique as a transaction with many logs only needs to be fetched once.
+    pub async fn get_transaction_fields(
+        &self,
+        transaction_hashes: &[H256],
+    ) -> Result<HashMap<H256, TransactionFields>, ProviderError> {
+        let mut transaction_fields = HashMap::with_capacity(transaction_hashes.len());
+
+        for batch in transaction_hashes.chunks(LOOKUP_BATCH_SIZE) {
+            let results = try_join_all(batch.iter().map(|transaction_hash| {
+                let transaction_hash = *transaction_hash;
+                async move {
+                    let (transaction, receipt) = futures::try_join!(
+                        self.request_with_failover(None, move |provider| async move {
+                            provider.get_transaction(transaction_hash).await
+                        }),
+                        self.request_with_failover(None, move |provider| async move {
+                            provider.get_transaction_receipt(transaction_hash).await
+                        })
+                    )?;
+
+                    match (transaction, receipt) {
+                        (Some(transaction), Some(receipt)) => Ok((
+                            transaction_hash,
+                            TransactionFields {
+                                from: transaction.from,
+                                to: transaction.to,
+                                value: transaction.value,
+                                gas_used: receipt.gas_used.unwrap_or_default(),
+                                // receipts before EIP-1559 do not include the effective gas price
+                                effective_gas_price: receipt
+                                    .effective_gas_price
+                                    .or(transaction.gas_price)
+                                    .unwrap_or_default(),
+                            },
+                        )),
+                        _ => Err(ProviderError::CustomError(format!(
+                            "RPC did not return transaction {:?} or its receipt",
+                            transaction_hash
+                        ))),
+                    }
+                }
+            }))
+            .await?;
+
+            transaction_fields.extend(results);
+        }
+
+        Ok(transaction_fields)
+    }
+
+    /// Looks up the receipt for each hash in batches.
+    pub async fn get_transaction_receipts(
+        &self,
+        transaction_hashes: &[H256],
+    ) -> Result<HashMap<H256, TransactionReceipt>, ProviderError> {
+        let mut receipts = HashMap::with_capacity(transaction_hashes.len());
+
+        for batch in transaction_hashes.chunks(LOOKUP_BATCH_SIZE) {
+            let results = try_join_all(batch.iter().map(|transaction_hash| {
+                let transaction_hash = *transaction_hash;
+                async move {
+                    let receipt = self
+                        .request_with_failover(None, move |provider| async move {
+                            provider.get_transaction_receipt(transaction_hash).await
+                        })
+                        .await?;
+
+                    receipt.map(|receipt| (transaction_hash, receipt)).ok_or_else(|| {
+                        ProviderError::CustomError(format!(
+                            "RPC did not return the receipt for transaction {:?}",
+                            transaction_hash
+                        ))
+                    })
+                }
+            }))
+            .await?;
+
+            receipts.extend(results);
+        }
+
+        Ok(receipts)
+    }
+
+    /// Gets the block and its transactions in a single request, the transactions are only
+    /// returned when asked for otherwise the block only holds the transaction hashes.
+    pub async fn get_block_with_transactions(
+        &self,
+        block_number: U64,
+        include_transactions: bool,
+    ) -> Result<Option<(Block<H256>, Vec<Transaction>)>, ProviderError> {
+        if !include_transactions {
+            return Ok(self.get_block_by_number(block_number).await?.map(|block| (block, vec![])));
+        }
+
+        let block = self
+            .request_with_failover(None, |provider| async move {
+                provider.get_block_with_txs(BlockNumber::Number(block_number)).await
+            })
+            .await?;
+
+        match block {
+            Some(mut block) => {
+                let transactions = std::mem::take(&mut block.transactions);
+                let mut header: Block<H256> = serde_json::to_value(&block)
+                    .and_then(serde_json::from_value)
+                    .map_err(ProviderError::SerdeJson)?;
+                header.transactions =
+                    transactions.iter().map(|transaction| transaction.hash).collect();
+
+                Ok(Some((header, transactions)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub async fn trace_filter(&self, filter: &TraceFilter) -> Result<Vec<Trace>, ProviderError> {
+        self.request_with_failover(None, |provider| async move {
+            provider.request("trace_filter", [filter]).await
+        })
+        .await
+    }
+
+    /// Traces every transaction in the block with the geth `callTracer`.
+    pub async fn debug_trace_block_calls(
+        &self,
+        block_number: U64,
+    ) -> Result<Vec<CallTracerResult>, ProviderError> {
+        self.request_with_failover(None, |provider| async move {
+            provider
+                .request(
+                    "debug_traceBlockByNumber",
+                    (
+                        BlockNumber::Number(block_number),
+                        serde_json::json!({ "tracer": "callTracer" }),
+                    ),
+                )
+                .await
+        })
+        .await
+    }
+
     pub async fn get_chain_id(&self) -> Result<U256, ProviderError> {
-        self.provider.get_chainid().await
+        self.request_with_failover(None, |provider| async move { provider.get_chainid().await })
+            .await
     }
 
+    /// The provider of the primary endpoint, requests sent through it do not fail over.
     pub fn get_inner_provider(&self) -> Arc<Provider<RetryClient<Http>>> {
-        Arc::clone(&self.provider)
+        Arc::clone(self.primary_provider())
     }
 }
 #[derive(Error, Debug)]
@@ -104,26 +884,133 @@ pub enum RetryClientError {
 
 pub fn create_client(
     rpc_url: &str,
+    ws_rpc_url: Option<&str>,
     compute_units_per_second: Option<u64>,
     max_block_range: Option<U64>,
+    reorg_safe_distance: Option<ReorgSafeDistance>,
     custom_headers: HeaderMap,
 ) -> Result<Arc<JsonRpcCachedProvider>, RetryClientError> {
-    let url = Url::parse(rpc_url).map_err(|e| {
-        RetryClientError::HttpProviderCantBeCreated(rpc_url.to_string(), e.to_string())
-    })?;
-    let client = reqwest::Client::builder().default_headers(custom_headers).build()?;
-
-    let provider = Http::new_with_client(url, client);
-    let instance = Provider::new(
-        RetryClientBuilder::default()
-            // assume minimum compute units per second if not provided as growth plan standard
-            .compute_units_per_second(compute_units_per_second.unwrap_or(660))
-            .rate_limit_retries(5000)
-            .timeout_retries(1000)
-            .initial_backoff(Duration::from_millis(500))
-            .build(provider, Box::<ethers::providers::HttpRateLimitRetryPolicy>::default()),
-    );
-    Ok(Arc::new(JsonRpcCachedProvider::new(instance, max_block_range)))
+    create_client_with_endpoints(
+        &[RpcEndpointConfig::new(rpc_url.to_string())],
+        ws_rpc_url,
+        compute_units_per_second,
+        max_block_range,
+        reorg_safe_distance,
+        custom_headers,
+    )
+}
+
+/// Creates a client which routes requests across all the endpoints, `compute_units_per_second`
+/// and `max_block_range` are used for any endpoint which does not set its own.
+pub fn create_client_with_endpoints(
+    endpoints: &[RpcEndpointConfig],
+    ws_rpc_url: Option<&str>,
+    compute_units_per_second: Option<u64>,
+    max_block_range: Option<U64>,
+    reorg_safe_distance: Option<ReorgSafeDistance>,
+    custom_headers: HeaderMap,
+) -> Result<Arc<JsonRpcCachedProvider>, RetryClientError> {
+    // a single endpoint keeps retrying, with more we would rather fail over than sit in retries
+    let failover = endpoints.len() > 1;
+
+    let endpoints = endpoints
+        .iter()
+        .map(|endpoint| {
+            let url = Url::parse(&endpoint.url).map_err(|e| {
+                RetryClientError::HttpProviderCantBeCreated(endpoint.url.clone(), e.to_string())
+            })?;
+            let host = url.host_str().unwrap_or_default().to_string();
+            let client =
+                reqwest::Client::builder().default_headers(custom_headers.clone()).build()?;
+
+            let provider = Http::new_with_client(url, client);
+            let instance = Provider::new(
+                RetryClientBuilder::default()
+                    // assume minimum compute units per second if not provided as growth plan
+                    // standard
+                    .compute_units_per_second(
+                        endpoint
+                            .compute_units_per_second
+                            .or(compute_units_per_second)
+                            .unwrap_or(660),
+                    )
+                    .rate_limit_retries(if failover { 10 } else { 5000 })
+                    .timeout_retries(if failover { 3 } else { 1000 })
+                    .initial_backoff(Duration::from_millis(500))
+                    .build(provider, Box::<ethers::providers::HttpRateLimitRetryPolicy>::default()),
+            );
+
+            Ok(RpcEndpoint::new(
+                instance,
+                host,
+                endpoint.priority.unwrap_or_default(),
+                endpoint.weight.unwrap_or(1),
+                endpoint.max_block_range.or(max_block_range),
+            ))
+        })
+        .collect::<Result<Vec<_>, RetryClientError>>()?;
+
+    Ok(Arc::new(JsonRpcCachedProvider::new_with_endpoints(
+        endpoints,
+        ws_rpc_url.map(|ws_rpc_url| ws_rpc_url.to_string()),
+        reorg_safe_distance,
+    )))
+}
+
+/// Keeps a `newHeads` subscription open and pushes every head to the listeners, if the socket
+/// drops the listeners go back to polling until it reconnects.
+async fn subscribe_new_heads(
+    ws_rpc_url: String,
+    cached_provider: Weak<JsonRpcCachedProvider>,
+    sender: watch::Sender<NewHead>,
+) {
+    loop {
+        match Provider::<Ws>::connect(&ws_rpc_url).await {
+            Ok(ws_provider) => match ws_provider.subscribe_blocks().await {
+                Ok(mut stream) => {
+                    info!("Subscribed to new heads over websocket");
+                    while let Some(block) = stream.next().await {
+                        if let Some(block_number) = block.number {
+                            // the websocket node can be ahead of the rpc used for eth_getLogs so
+                            // only hand the head out once the rpc has it
+                            let Some(cached_provider) = cached_provider.upgrade() else {
+                                return;
+                            };
+                            if !wait_for_block_on_rpc(&cached_provider, block_number).await {
+                                continue;
+                            }
+                        }
+
+                        if sender.send(Some((Instant::now(), Arc::new(block)))).is_err() {
+                            return;
+                        }
+                    }
+                }
+                Err(e) => error!("Error subscribing to new heads over websocket: {}", e),
+            },
+            Err(e) => error!("Error connecting to websocket rpc: {}", e),
+        }
+
+        let _ = sender.send(None);
+        warn!(
+            "New heads websocket disconnected - falling back to polling and reconnecting in {:?}",
+            NEW_HEADS_RECONNECT_INTERVAL
+        );
+        tokio::time::sleep(NEW_HEADS_RECONNECT_INTERVAL).await;
+    }
+}
+
+async fn wait_for_block_on_rpc(cached_provider: &JsonRpcCachedProvider, block_number: U64) -> bool {
+    for _ in 0..10 {
+        if let Ok(rpc_block_number) = cached_provider.get_block_number().await {
+            if rpc_block_number >= block_number {
+                return true;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    false
 }
 
 pub async fn get_chain_id(rpc_url: &str) -> Result<U256, ProviderError> {
@@ -137,6 +1024,7 @@ pub async fn get_chain_id(rpc_url: &str) -> Result<U256, ProviderError> {
 pub struct CreateNetworkProvider {
     pub network_name: String,
     pub disable_logs_bloom_checks: bool,
+    pub reorg_safe_distance: Option<ReorgSafeDistance>,
     pub client: Arc<JsonRpcCachedProvider>,
 }
 
@@ -144,15 +1032,18 @@ impl CreateNetworkProvider {
     pub fn create(manifest: &Manifest) -> Result<Vec<CreateNetworkProvider>, RetryClientError> {
         let mut result: Vec<CreateNetworkProvider> = vec![];
         for network in &manifest.networks {
-            let provider = create_client(
-                &network.rpc,
+            let provider = create_client_with_endpoints(
+                &network.rpc.endpoints(),
+                network.ws_rpc.as_deref(),
                 network.compute_units_per_second,
                 network.max_block_range,
+                network.reorg_safe_distance,
                 manifest.get_custom_headers(),
             )?;
             result.push(CreateNetworkProvider {
                 network_name: network.name.clone(),
                 disable_logs_bloom_checks: network.disable_logs_bloom_checks.unwrap_or_default(),
+                reorg_safe_distance: network.reorg_safe_distance,
                 client: provider,
             });
         }
@@ -168,14 +1059,14 @@ mod tests {
     #[test]
     fn test_create_retry_client() {
         let rpc_url = "http://localhost:8545";
-        let result = create_client(rpc_url, Some(660), None, HeaderMap::new());
+        let result = create_client(rpc_url, None, Some(660), None, None, HeaderMap::new());
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_create_retry_client_invalid_url() {
         let rpc_url = "invalid_url";
-        let result = create_client(rpc_url, Some(660), None, HeaderMap::new());
+        let result = create_client(rpc_url, None, Some(660), None, None, HeaderMap::new());
         assert!(result.is_err());
         if let Err(RetryClientError::HttpProviderCantBeCreated(url, _)) = result {
             assert_eq!(url, rpc_url);
@@ -183,4 +1074,75 @@ mod tests {
             panic!("Expected HttpProviderCantBeCreated error");
         }
     }
+
+    fn endpoint(url: &str, priority: u32, max_block_range: Option<u64>) -> RpcEndpointConfig {
+        RpcEndpointConfig {
+            priority: Some(priority),
+            max_block_range: max_block_range.map(U64::from),
+            ..RpcEndpointConfig::new(url.to_string())
+        }
+    }
+
+    #[test]
+    fn test_endpoints_ordered_by_priority_and_health() {
+        let client = create_client_with_endpoints(
+            &[
+                endpoint("http://localhost:8546", 1, None),
+                endpoint("http://localhost:8545", 0, None),
+            ],
+            None,
+            None,
+            None,
+            None,
+            HeaderMap::new(),
+        )
+        .unwrap();
+
+        let hosts: Vec<u32> = client.ordered_endpoints(None).iter().map(|e| e.priority).collect();
+        assert_eq!(hosts, vec![0, 1]);
+
+        for _ in 0..CIRCUIT_BREAKER_FAILURE_THRESHOLD {
+            client.endpoints[0].record_failure();
+        }
+        let hosts: Vec<u32> = client.ordered_endpoints(None).iter().map(|e| e.priority).collect();
+        assert_eq!(hosts, vec![1, 0]);
+
+        client.endpoints[0].record_success();
+        let hosts: Vec<u32> = client.ordered_endpoints(None).iter().map(|e| e.priority).collect();
+        assert_eq!(hosts, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_endpoints_ordered_by_block_range_limits() {
+        let client = create_client_with_endpoints(
+            &[
+                endpoint("http://localhost:8545", 0, Some(1000)),
+                endpoint("http://localhost:8546", 1, None),
+            ],
+            None,
+            None,
+            None,
+            None,
+            HeaderMap::new(),
+        )
+        .unwrap();
+
+        assert_eq!(client.max_block_range(), Some(U64::from(1000)));
+
+        let ordered = client.ordered_endpoints(Some(U64::from(5000)));
+        assert_eq!(ordered[0].priority, 1);
+
+        client.endpoints[1].record_block_range_rejected(U64::from(5000), None);
+        assert!(!client.endpoints[1].can_serve_block_range(U64::from(5000)));
+        assert!(client.endpoints[1].can_serve_block_range(U64::from(2500)));
+
+        // the learnt limit grows back once the endpoint keeps answering
+        for _ in 0..LEARNT_BLOCK_RANGE_RELAX_AFTER_SUCCESSES {
+            client.endpoints[1].record_success();
+        }
+        assert!(client.endpoints[1].can_serve_block_range(U64::from(5000)));
+
+        client.endpoints[1].record_block_range_rejected(U64::from(5000), Some(U64::from(2000)));
+        assert!(!client.endpoints[1].can_serve_block_range(U64::from(2500)));
+    }
 }
@@ -4,7 +4,8 @@ use std::{
     sync::Arc,
 };
 
-use csv::Writer;
+use csv::{ReaderBuilder, Writer};
+use ethers::types::U64;
 use tokio::sync::Mutex;
 
 pub struct AsyncCsvAppender {
@@ -73,3 +74,59 @@ impl AsyncCsvAppender {
         .expect("Failed to run CSV write operation")
     }
 }
+
+/// Rewrites the csv file without the rows for the network from `from_block` up to and including
+/// `to_block`, or every block after `from_block` when there is no `to_block`. Used to roll back
+/// data after a chain reorganisation and to clear a range before it is backfilled.
+pub async fn remove_csv_rows_in_block_range(
+    file_path: &str,
+    network: &str,
+    from_block: U64,
+    to_block: Option<U64>,
+) -> Result<(), csv::Error> {
+    let path = PathBuf::from(file_path);
+    let network = network.to_string();
+
+    tokio::task::spawn_blocking(move || {
+        let mut reader = ReaderBuilder::new().has_headers(true).from_path(&path)?;
+        let headers = reader.headers()?.clone();
+        let block_number_index = headers.iter().position(|h| h == "block_number");
+        let network_index = headers.iter().position(|h| h == "network");
+
+        let (block_number_index, network_index) = match (block_number_index, network_index) {
+            (Some(block_number_index), Some(network_index)) => (block_number_index, network_index),
+            _ => return Ok(()),
+        };
+
+        let mut kept_records = vec![];
+        for record in reader.records() {
+            let record = record?;
+            let is_in_range = record.get(network_index) == Some(network.as_str()) &&
+                record
+                    .get(block_number_index)
+                    .and_then(|value| U64::from_dec_str(value).ok())
+                    .is_some_and(|value| {
+                        value >= from_block && to_block.is_none_or(|to_block| value <= to_block)
+                    });
+
+            if !is_in_range {
+                kept_records.push(record);
+            }
+        }
+
+        let temp_path = path.with_extension("csv.tmp");
+        {
+            let mut writer = Writer::from_path(&temp_path)?;
+            writer.write_record(&headers)?;
+            for record in kept_records {
+                writer.write_record(&record)?;
+            }
+            writer.flush()?;
+        }
+        std::fs::rename(&temp_path, &path)?;
+
+        Ok(())
+    })
+    .await
+    .expect("Failed to run CSV remove rows operation")
+}
@@ -11,13 +11,19 @@ use crate::{
         relationship::{ApplyAllRelationships, Relationship},
         setup::{setup_postgres, SetupPostgresError},
     },
-    event::callback_registry::EventCallbackRegistry,
+    event::{
+        block_callback_registry::BlockCallbackRegistry, callback_registry::EventCallbackRegistry,
+    },
     indexer::{
+        backfill::{backfill, BackfillDetails, BackfillError},
+        block_handlers::{start_block_handlers, BlockHandlerError},
+        native_transfers::{native_transfers_block_handler, NativeTransfersError},
         no_code::{setup_no_code, SetupNoCodeError},
         start::{start_indexing, StartIndexingError},
         ContractEventDependencies, ContractEventDependenciesMapFromRelationshipsError,
     },
     initiate_shutdown,
+    log_cache::enable_log_cache,
     logger::mark_shutdown_started,
     manifest::{
         core::ProjectType,
@@ -29,6 +35,7 @@ use crate::{
 
 pub struct IndexingDetails {
     pub registry: EventCallbackRegistry,
+    pub block_registry: BlockCallbackRegistry,
 }
 
 pub struct StartDetails<'a> {
@@ -74,8 +81,17 @@ pub enum StartRindexerError {
     #[error("{0}")]
     RelationshipsAndIndexersError(#[from] RelationshipsAndIndexersError),
 
+    #[error("Could not start block handlers: {0}")]
+    BlockHandlerError(#[from] BlockHandlerError),
+
+    #[error("Could not setup native transfers: {0}")]
+    NativeTransfersError(#[from] NativeTransfersError),
+
     #[error("Shutdown handler failed with error: {0}")]
     ShutdownHandlerFailed(String),
+
+    #[error("Could not backfill: {0}")]
+    BackfillError(#[from] BackfillError),
 }
 
 async fn handle_shutdown(signal: &str) {
@@ -152,6 +168,12 @@ pub async fn start_rindexer(details: StartDetails<'_>) -> Result<(), StartRindex
             if let Some(mut indexing_details) = details.indexing_details {
                 let postgres_enabled = &manifest.storage.postgres_enabled();
 
+                if let Some(log_cache) =
+                    manifest.storage.log_cache.as_ref().filter(|log_cache| log_cache.enabled)
+                {
+                    enable_log_cache(project_path.join(&log_cache.path));
+                }
+
                 // setup postgres is already called in no-code startup
                 if manifest.project_type != ProjectType::NoCode && *postgres_enabled {
                     setup_postgres(project_path, &manifest).await?;
@@ -169,6 +191,21 @@ pub async fn start_rindexer(details: StartDetails<'_>) -> Result<(), StartRindex
                 let mut dependencies: Vec<ContractEventDependencies> =
                     ContractEventDependencies::parse(&manifest);
 
+                if let Some(native_transfers) = &manifest.native_transfers {
+                    indexing_details.block_registry.register_block_handler(
+                        native_transfers_block_handler(project_path, &manifest, native_transfers)
+                            .await?,
+                    );
+                }
+
+                // block handlers are not tied to events so run alongside the event indexing
+                let block_handler_handles = start_block_handlers(
+                    &manifest,
+                    project_path,
+                    indexing_details.block_registry.complete(),
+                )
+                .await?;
+
                 let processed_network_contracts = start_indexing(
                     &manifest,
                     project_path,
@@ -216,6 +253,12 @@ pub async fn start_rindexer(details: StartDetails<'_>) -> Result<(), StartRindex
                     }
                 }
 
+                for handle in block_handler_handles {
+                    if let Err(e) = handle.await {
+                        error!("Block handler task failed: {:?}", e);
+                    }
+                }
+
                 // Do not need now with the main shutdown keeping around in-case
                 // if details.graphql_details.enabled {
                 //     signal::ctrl_c()
@@ -268,3 +311,58 @@ pub async fn start_rindexer_no_code(
 
     start_rindexer(start_details).await.map_err(StartRindexerNoCode::StartRindexerError)
 }
+
+pub struct BackfillStartDetails<'a> {
+    pub manifest_path: &'a PathBuf,
+    pub registry: EventCallbackRegistry,
+    pub backfill: BackfillDetails,
+}
+
+/// Re-indexes a block range of a contract and exits, it can run alongside a live indexer.
+pub async fn start_backfill(details: BackfillStartDetails<'_>) -> Result<(), StartRindexerError> {
+    let project_path = details
+        .manifest_path
+        .parent()
+        .ok_or(StartRindexerError::NoProjectPathFoundUsingParentOfManifestPath)?;
+    let manifest = read_manifest(details.manifest_path)?;
+
+    if manifest.project_type != ProjectType::NoCode {
+        setup_info_logger();
+        info!("Starting rindexer rust project backfill");
+
+        if manifest.storage.postgres_enabled() {
+            setup_postgres(project_path, &manifest).await?;
+        }
+    }
+
+    if let Some(log_cache) =
+        manifest.storage.log_cache.as_ref().filter(|log_cache| log_cache.enabled)
+    {
+        enable_log_cache(project_path.join(&log_cache.path));
+    }
+
+    backfill(&manifest, project_path, details.registry, &details.backfill).await?;
+
+    Ok(())
+}
+
+pub async fn start_backfill_no_code(
+    manifest_path: &PathBuf,
+    backfill: BackfillDetails,
+) -> Result<(), StartRindexerNoCode> {
+    let start_details = setup_no_code(StartNoCodeDetails {
+        manifest_path,
+        indexing_details: IndexerNoCodeDetails { enabled: true },
+        graphql_details: GraphqlOverrideSettings { enabled: false, override_port: None },
+    })
+    .await?;
+
+    let registry = start_details
+        .indexing_details
+        .map(|indexing_details| indexing_details.registry)
+        .unwrap_or_default();
+
+    start_backfill(BackfillStartDetails { manifest_path, registry, backfill })
+        .await
+        .map_err(StartRindexerNoCode::StartRindexerError)
+}
@@ -50,7 +50,10 @@ export default defineConfig({
             { text: 'Networks', link: '/docs/start-building/yaml-config/networks' },
             { text: 'Storage', link: '/docs/start-building/yaml-config/storage' },
             { text: 'Contracts', link: '/docs/start-building/yaml-config/contracts' },
+            { text: 'Block Handlers', link: '/docs/start-building/yaml-config/block-handlers' },
+            { text: 'Entities', link: '/docs/start-building/yaml-config/entities' },
             { text: 'GraphQL', link: '/docs/start-building/yaml-config/graphql' },
+            { text: 'Indexing', link: '/docs/start-building/yaml-config/indexing' },
             { text: 'Global', link: '/docs/start-building/yaml-config/global' },
           ],
         },
@@ -70,6 +73,30 @@ export default defineConfig({
           text: 'Delete',
           link: '/docs/start-building/delete',
         },
+        {
+          text: 'Cache',
+          link: '/docs/start-building/cache',
+        },
+        {
+          text: 'Dead Letters',
+          link: '/docs/start-building/dead-letters',
+        },
+        {
+          text: 'Backfill',
+          link: '/docs/start-building/backfill',
+        },
+        {
+          text: 'Verify',
+          link: '/docs/start-building/verify',
+        },
+        {
+          text: 'Plan',
+          link: '/docs/start-building/plan',
+        },
+        {
+          text: 'Redecode',
+          link: '/docs/start-building/redecode',
+        },
         {
           text: 'Chatbots',
           link: '/docs/start-building/chatbots',
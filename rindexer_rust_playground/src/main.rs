@@ -1,11 +1,13 @@
 use std::{env, path::PathBuf, str::FromStr};
 
 use rindexer::{
-    manifest::yaml::read_manifest, start_rindexer, GraphqlOverrideSettings, IndexingDetails,
-    StartDetails,
+    indexer::backfill::BackfillDetails, manifest::yaml::read_manifest, start_backfill,
+    start_rindexer, BackfillStartDetails, GraphqlOverrideSettings, IndexingDetails, StartDetails,
 };
 
-use self::rindexer_lib::indexers::all_handlers::register_all_handlers;
+use self::rindexer_lib::indexers::all_handlers::{
+    register_all_block_handlers, register_all_handlers,
+};
 
 mod rindexer_lib;
 
@@ -13,6 +15,29 @@ mod rindexer_lib;
 async fn main() {
     let args: Vec<String> = env::args().collect();
 
+    if let Some(backfill) = BackfillDetails::from_args(&args) {
+        let result = match (backfill, env::current_dir()) {
+            (Ok(backfill), Ok(path)) => {
+                let manifest_path = path.join("rindexer.yaml");
+                start_backfill(BackfillStartDetails {
+                    manifest_path: &manifest_path,
+                    registry: register_all_handlers(&manifest_path).await,
+                    backfill,
+                })
+                .await
+                .map_err(|e| e.to_string())
+            }
+            (Err(e), _) => Err(e.to_string()),
+            (_, Err(e)) => Err(e.to_string()),
+        };
+
+        if let Err(e) = result {
+            eprintln!("Error running backfill: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let mut enable_graphql = false;
     let mut enable_indexer = false;
 
@@ -57,7 +82,10 @@ async fn main() {
                 manifest_path: &manifest_path,
                 indexing_details: if enable_indexer {
                     // EventCallbackRegistry { events: vec![] }
-                    Some(IndexingDetails { registry: register_all_handlers(&manifest_path).await })
+                    Some(IndexingDetails {
+                        registry: register_all_handlers(&manifest_path).await,
+                        block_registry: register_all_block_handlers().await,
+                    })
                 } else {
                     None
                 },
@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 
-use rindexer::event::callback_registry::EventCallbackRegistry;
+use rindexer::event::{
+    block_callback_registry::BlockCallbackRegistry, callback_registry::EventCallbackRegistry,
+};
 
 use super::rindexer_playground::{
     erc_20_filter::erc_20_filter_handlers,
@@ -17,3 +19,7 @@ pub async fn register_all_handlers(manifest_path: &PathBuf) -> EventCallbackRegi
     playground_types_filter_handlers(manifest_path, &mut registry).await;
     registry
 }
+
+pub async fn register_all_block_handlers() -> BlockCallbackRegistry {
+    BlockCallbackRegistry::new()
+}
@@ -40,7 +40,12 @@ async fn swap_handler(manifest_path: &PathBuf, registry: &mut EventCallbackRegis
                     result.tx_information.block_hash.to_string(),
                     result.tx_information.network.to_string(),
                     result.tx_information.transaction_index.to_string(),
-                    result.tx_information.log_index.to_string()
+                    result.tx_information.log_index.to_string(),
+                    result
+                        .tx_information
+                        .block_timestamp_to_datetime()
+                        .map(|date_time| date_time.to_rfc3339())
+                        .unwrap_or_default(),
                 ]);
                 let data = vec![
                     EthereumSqlTypeWrapper::Address(result.tx_information.address),
@@ -58,11 +63,16 @@ async fn swap_handler(manifest_path: &PathBuf, registry: &mut EventCallbackRegis
                     EthereumSqlTypeWrapper::I128(result.event_data.tick_6),
                     EthereumSqlTypeWrapper::I256(result.event_data.tick_7),
                     EthereumSqlTypeWrapper::H256(result.tx_information.transaction_hash),
-                    EthereumSqlTypeWrapper::U64(result.tx_information.block_number),
+                    EthereumSqlTypeWrapper::U64BigInt(result.tx_information.block_number),
                     EthereumSqlTypeWrapper::H256(result.tx_information.block_hash),
                     EthereumSqlTypeWrapper::String(result.tx_information.network.to_string()),
-                    EthereumSqlTypeWrapper::U64(result.tx_information.transaction_index),
-                    EthereumSqlTypeWrapper::U256(result.tx_information.log_index)
+                    EthereumSqlTypeWrapper::U32(
+                            result.tx_information.transaction_index.as_u32(),
+                        ),
+                    EthereumSqlTypeWrapper::U32(result.tx_information.log_index.as_u32()),
+                    EthereumSqlTypeWrapper::DateTimeNullable(
+                        result.tx_information.block_timestamp_to_datetime(),
+                    ),
                 ];
                 postgres_bulk_data.push(data);
             }
@@ -104,7 +114,8 @@ async fn swap_handler(manifest_path: &PathBuf, registry: &mut EventCallbackRegis
                             "block_hash".to_string(),
                             "network".to_string(),
                             "tx_index".to_string(),
-                            "log_index".to_string()
+                            "log_index".to_string(),
+                            "block_timestamp".to_string(),
                         ],
                         &postgres_bulk_data
                             .first()
@@ -145,7 +156,8 @@ async fn swap_handler(manifest_path: &PathBuf, registry: &mut EventCallbackRegis
                                 "block_hash".to_string(),
                                 "network".to_string(),
                                 "tx_index".to_string(),
-                                "log_index".to_string()
+                                "log_index".to_string(),
+                                "block_timestamp".to_string(),
                             ],
                             &postgres_bulk_data,
                         )
@@ -32,6 +32,11 @@ async fn approval_handler(manifest_path: &PathBuf, registry: &mut EventCallbackR
                         result.tx_information.network.to_string(),
                         result.tx_information.transaction_index.to_string(),
                         result.tx_information.log_index.to_string(),
+                        result
+                            .tx_information
+                            .block_timestamp_to_datetime()
+                            .map(|date_time| date_time.to_rfc3339())
+                            .unwrap_or_default(),
                     ]);
                     let data = vec![
                         EthereumSqlTypeWrapper::Address(result.tx_information.address),
@@ -39,11 +44,16 @@ async fn approval_handler(manifest_path: &PathBuf, registry: &mut EventCallbackR
                         EthereumSqlTypeWrapper::Address(result.event_data.spender),
                         EthereumSqlTypeWrapper::U256(result.event_data.value),
                         EthereumSqlTypeWrapper::H256(result.tx_information.transaction_hash),
-                        EthereumSqlTypeWrapper::U64(result.tx_information.block_number),
+                        EthereumSqlTypeWrapper::U64BigInt(result.tx_information.block_number),
                         EthereumSqlTypeWrapper::H256(result.tx_information.block_hash),
                         EthereumSqlTypeWrapper::String(result.tx_information.network.to_string()),
-                        EthereumSqlTypeWrapper::U64(result.tx_information.transaction_index),
-                        EthereumSqlTypeWrapper::U256(result.tx_information.log_index),
+                        EthereumSqlTypeWrapper::U32(
+                            result.tx_information.transaction_index.as_u32(),
+                        ),
+                        EthereumSqlTypeWrapper::U32(result.tx_information.log_index.as_u32()),
+                        EthereumSqlTypeWrapper::DateTimeNullable(
+                            result.tx_information.block_timestamp_to_datetime(),
+                        ),
                     ];
                     postgres_bulk_data.push(data);
                 }
@@ -79,6 +89,7 @@ async fn approval_handler(manifest_path: &PathBuf, registry: &mut EventCallbackR
                                 "network".to_string(),
                                 "tx_index".to_string(),
                                 "log_index".to_string(),
+                                "block_timestamp".to_string(),
                             ],
                             &postgres_bulk_data
                                 .first()
@@ -113,6 +124,7 @@ async fn approval_handler(manifest_path: &PathBuf, registry: &mut EventCallbackR
                                 "network".to_string(),
                                 "tx_index".to_string(),
                                 "log_index".to_string(),
+                                "block_timestamp".to_string(),
                             ],
                             &postgres_bulk_data,
                         )
@@ -164,6 +176,11 @@ async fn transfer_handler(manifest_path: &PathBuf, registry: &mut EventCallbackR
                         result.tx_information.network.to_string(),
                         result.tx_information.transaction_index.to_string(),
                         result.tx_information.log_index.to_string(),
+                        result
+                            .tx_information
+                            .block_timestamp_to_datetime()
+                            .map(|date_time| date_time.to_rfc3339())
+                            .unwrap_or_default(),
                     ]);
                     let data = vec![
                         EthereumSqlTypeWrapper::Address(result.tx_information.address),
@@ -171,11 +188,16 @@ async fn transfer_handler(manifest_path: &PathBuf, registry: &mut EventCallbackR
                         EthereumSqlTypeWrapper::Address(result.event_data.to),
                         EthereumSqlTypeWrapper::U256(result.event_data.value),
                         EthereumSqlTypeWrapper::H256(result.tx_information.transaction_hash),
-                        EthereumSqlTypeWrapper::U64(result.tx_information.block_number),
+                        EthereumSqlTypeWrapper::U64BigInt(result.tx_information.block_number),
                         EthereumSqlTypeWrapper::H256(result.tx_information.block_hash),
                         EthereumSqlTypeWrapper::String(result.tx_information.network.to_string()),
-                        EthereumSqlTypeWrapper::U64(result.tx_information.transaction_index),
-                        EthereumSqlTypeWrapper::U256(result.tx_information.log_index),
+                        EthereumSqlTypeWrapper::U32(
+                            result.tx_information.transaction_index.as_u32(),
+                        ),
+                        EthereumSqlTypeWrapper::U32(result.tx_information.log_index.as_u32()),
+                        EthereumSqlTypeWrapper::DateTimeNullable(
+                            result.tx_information.block_timestamp_to_datetime(),
+                        ),
                     ];
                     postgres_bulk_data.push(data);
                 }
@@ -211,6 +233,7 @@ async fn transfer_handler(manifest_path: &PathBuf, registry: &mut EventCallbackR
                                 "network".to_string(),
                                 "tx_index".to_string(),
                                 "log_index".to_string(),
+                                "block_timestamp".to_string(),
                             ],
                             &postgres_bulk_data
                                 .first()
@@ -245,6 +268,7 @@ async fn transfer_handler(manifest_path: &PathBuf, registry: &mut EventCallbackR
                                 "network".to_string(),
                                 "tx_index".to_string(),
                                 "log_index".to_string(),
+                                "block_timestamp".to_string(),
                             ],
                             &postgres_bulk_data,
                         )
@@ -8,6 +8,7 @@ use ethers::providers::{Http, Provider, RetryClient};
 use ethers::types::U64;
 use rindexer::{
     lazy_static,
+    manifest::network::ReorgSafeDistance,
     provider::{create_client, JsonRpcCachedProvider, RetryClientError},
     public_read_env_value, HeaderMap,
 };
@@ -15,15 +16,24 @@ use rindexer::{
 #[allow(dead_code)]
 fn create_shadow_client(
     rpc_url: &str,
+    ws_rpc_url: Option<&str>,
     compute_units_per_second: Option<u64>,
     max_block_range: Option<U64>,
+    reorg_safe_distance: Option<ReorgSafeDistance>,
 ) -> Result<Arc<JsonRpcCachedProvider>, RetryClientError> {
     let mut header = HeaderMap::new();
     header.insert(
         "X-SHADOW-API-KEY",
         public_read_env_value("RINDEXER_PHANTOM_API_KEY").unwrap().parse().unwrap(),
     );
-    create_client(rpc_url, compute_units_per_second, max_block_range, header)
+    create_client(
+        rpc_url,
+        ws_rpc_url,
+        compute_units_per_second,
+        max_block_range,
+        reorg_safe_distance,
+        header,
+    )
 }
 
 lazy_static! {
@@ -32,6 +42,8 @@ lazy_static! {
             .unwrap_or("https://mainnet.gateway.tenderly.co".to_string()),
         None,
         None,
+        None,
+        None,
         HeaderMap::new()
     )
     .expect("Error creating provider");
@@ -40,6 +52,8 @@ lazy_static! {
             .unwrap_or("https://mainnet.base.org".to_string()),
         None,
         None,
+        None,
+        None,
         HeaderMap::new()
     )
     .expect("Error creating provider");
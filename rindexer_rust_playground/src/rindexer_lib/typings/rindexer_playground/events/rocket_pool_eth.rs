@@ -76,6 +76,7 @@ pub struct EventContext<TExtensions>
 where
     TExtensions: Send + Sync,
 {
+    /// Writes made while a handler runs join the transaction of the batch.
     pub database: Arc<PostgresClient>,
     pub csv: Arc<AsyncCsvAppender>,
     pub extensions: Arc<TExtensions>,
@@ -142,7 +143,7 @@ where
     {
         let csv = AsyncCsvAppender::new("/Users/joshstevens/code/rindexer/rindexer_rust_playground/./generated_csv/RocketPoolETH/rocketpooleth-approval.csv");
         if !Path::new("/Users/joshstevens/code/rindexer/rindexer_rust_playground/./generated_csv/RocketPoolETH/rocketpooleth-approval.csv").exists() {
-            csv.append_header(vec!["contract_address".into(), "owner".into(), "spender".into(), "value".into(), "tx_hash".into(), "block_number".into(), "block_hash".into(), "network".into(), "tx_index".into(), "log_index".into()])
+            csv.append_header(vec!["contract_address".into(), "owner".into(), "spender".into(), "value".into(), "tx_hash".into(), "block_number".into(), "block_hash".into(), "network".into(), "tx_index".into(), "log_index".into(), "block_timestamp".into()])
                 .await
                 .expect("Failed to write CSV header");
         }
@@ -243,7 +244,7 @@ where
     {
         let csv = AsyncCsvAppender::new("/Users/joshstevens/code/rindexer/rindexer_rust_playground/./generated_csv/RocketPoolETH/rocketpooleth-transfer.csv");
         if !Path::new("/Users/joshstevens/code/rindexer/rindexer_rust_playground/./generated_csv/RocketPoolETH/rocketpooleth-transfer.csv").exists() {
-            csv.append_header(vec!["contract_address".into(), "from".into(), "to".into(), "value".into(), "tx_hash".into(), "block_number".into(), "block_hash".into(), "network".into(), "tx_index".into(), "log_index".into()])
+            csv.append_header(vec!["contract_address".into(), "from".into(), "to".into(), "value".into(), "tx_hash".into(), "block_number".into(), "block_hash".into(), "network".into(), "tx_index".into(), "log_index".into(), "block_timestamp".into()])
                 .await
                 .expect("Failed to write CSV header");
         }
@@ -420,10 +421,18 @@ where
                         .iter()
                         .find(|n| n.name == c.network)
                         .map_or(false, |n| n.disable_logs_bloom_checks.unwrap_or_default()),
+                    reorg_safe_distance: rindexer_yaml
+                        .networks
+                        .iter()
+                        .find(|n| n.name == c.network)
+                        .and_then(|n| n.reorg_safe_distance),
                 })
                 .collect(),
             abi: contract_details.abi,
             reorg_safe_distance: contract_details.reorg_safe_distance.unwrap_or_default(),
+            include_transaction_fields: contract_details
+                .include_transaction_fields
+                .unwrap_or_default(),
         };
 
         let callback: Arc<
@@ -451,6 +460,7 @@ where
             indexer_name: "RindexerPlayground".to_string(),
             event_name: event_name.to_string(),
             index_event_in_order,
+            function_call_selector: None,
             topic_id: topic_id.parse::<H256>().unwrap(),
             contract,
             callback,